@@ -1,17 +1,34 @@
-use aws_sdk_dynamodb::Client;
-use axum::routing::{delete, get, post};
+use std::sync::Arc;
+
+use axum::middleware;
+use axum::routing::{delete, get, post, put};
 use axum::Router;
+use ed25519_dalek::SigningKey;
 use rand::prelude::SmallRng;
 
 use crate::controller;
+use crate::controller::auth::AuthConfig;
+use crate::gateway::Table;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
-    pub dynamo_client: Client,
+    pub table: Table,
     pub random_number_generator: SmallRng,
+    /// Signs the balance attestations. They are disabled without it.
+    pub attestation_key: Option<Arc<SigningKey>>,
 }
 
-pub fn build_app(client: Client, rng: SmallRng) -> Router {
+pub fn build_app(
+    table: Table,
+    rng: SmallRng,
+    auth_config: AuthConfig,
+    attestation_key: Option<SigningKey>,
+) -> Router {
+    let app_state = AppState {
+        table,
+        random_number_generator: rng,
+        attestation_key: attestation_key.map(Arc::new),
+    };
     Router::new()
         .route("/", get(root))
         .nest(
@@ -22,23 +39,158 @@ pub fn build_app(client: Client, rng: SmallRng) -> Router {
                     "/balance",
                     delete(controller::delete_entries::delete_entries),
                 )
+                .route("/balance", put(controller::amend_entries::amend_entries))
+                .route(
+                    "/balance/post",
+                    post(controller::pending_entries::post_entries),
+                )
+                .route(
+                    "/balance/void",
+                    post(controller::pending_entries::void_entries),
+                )
                 .route(
                     "/balance/:account_id",
                     get(controller::get_balance::get_balance),
                 )
+                .route(
+                    "/balances/batchGet",
+                    post(controller::get_balance::get_balances),
+                )
+                .route(
+                    "/balance/:account_id/attestation",
+                    get(controller::attestation::get_attestation),
+                )
                 .route(
                     "/balance/:account_id/entry",
                     get(controller::get_entries::get_entries),
                 )
+                .route(
+                    "/balance/:account_id/export",
+                    get(controller::export::export_entries),
+                )
+                .route(
+                    "/balance/:account_id/summary",
+                    get(controller::get_summary::get_summary),
+                )
+                .route(
+                    "/balance/:account_id/statement",
+                    get(controller::get_statement::get_statement),
+                )
+                .route(
+                    "/balance/:account_id/parent",
+                    put(controller::hierarchy::set_parent),
+                )
+                .route(
+                    "/balance/:account_id/children",
+                    get(controller::hierarchy::get_children),
+                )
+                .route(
+                    "/balance/:account_id/rollup",
+                    get(controller::hierarchy::get_roll_up),
+                )
+                .route(
+                    "/balance/:account_id/reconciliations",
+                    post(controller::reconciliation::reconcile),
+                )
+                .route(
+                    "/balance/:account_id/reconciliations/:run_id",
+                    get(controller::reconciliation::get_reconciliation),
+                )
+                .route(
+                    "/balance/:account_id/accruals",
+                    get(controller::accruals::get_accrual_schedules),
+                )
+                .route(
+                    "/balance/:account_id/accruals/:ledger_field",
+                    put(controller::accruals::set_accrual_schedule)
+                        .delete(controller::accruals::delete_accrual_schedule),
+                )
+                .route(
+                    "/balance/:account_id/accruals/:ledger_field/:period",
+                    get(controller::accruals::get_accrual),
+                )
+                .route(
+                    "/balance/:account_id/limits",
+                    get(controller::velocity_limits::get_velocity_usage),
+                )
+                .route(
+                    "/balance/:account_id/limits/:limit_id",
+                    put(controller::velocity_limits::set_account_velocity_limit)
+                        .delete(controller::velocity_limits::delete_account_velocity_limit),
+                )
+                .route(
+                    "/balance/:account_id/tags",
+                    put(controller::accounts::set_account_tags),
+                )
+                .route(
+                    "/balance/:account_id/verify",
+                    get(controller::verify::verify_account),
+                )
                 .route(
                     "/balance/:account_id/entry/:entry_id",
                     get(controller::get_entry::get_entry),
-                ),
+                )
+                .route(
+                    "/periods/:period/close",
+                    post(controller::close_period::close_period),
+                )
+                .route("/accounts", get(controller::accounts::list_accounts))
+                .route(
+                    "/accruals",
+                    get(controller::accruals::list_accrual_schedules),
+                )
+                .route("/accruals/post", post(controller::accruals::post_accruals))
+                .route("/rules", get(controller::fee_rules::get_fee_rules))
+                .route(
+                    "/rules/:rule_id",
+                    put(controller::fee_rules::set_fee_rule)
+                        .delete(controller::fee_rules::delete_fee_rule),
+                )
+                .route(
+                    "/limits",
+                    get(controller::velocity_limits::get_velocity_limits),
+                )
+                .route(
+                    "/limits/:limit_id",
+                    put(controller::velocity_limits::set_default_velocity_limit)
+                        .delete(controller::velocity_limits::delete_default_velocity_limit),
+                )
+                .route(
+                    "/recurring",
+                    get(controller::recurring::get_recurring_schedules),
+                )
+                .route(
+                    "/recurring/post",
+                    post(controller::recurring::post_recurring_entries),
+                )
+                .route(
+                    "/recurring/:schedule_id",
+                    get(controller::recurring::get_recurring_schedule)
+                        .put(controller::recurring::set_recurring_schedule)
+                        .delete(controller::recurring::delete_recurring_schedule),
+                )
+                .route(
+                    "/recurring/:schedule_id/pause",
+                    post(controller::recurring::pause_recurring_schedule),
+                )
+                .route(
+                    "/recurring/:schedule_id/resume",
+                    post(controller::recurring::resume_recurring_schedule),
+                )
+                .route(
+                    "/audit",
+                    get(controller::get_audit_records::get_audit_records),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    app_state.clone(),
+                    controller::ledger::ledger_middleware,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    Arc::new(auth_config),
+                    controller::auth::auth_middleware,
+                )),
         )
-        .with_state(AppState {
-            dynamo_client: client,
-            random_number_generator: rng,
-        })
+        .with_state(app_state)
 }
 
 async fn root() -> &'static str {
@@ -47,36 +199,47 @@ async fn root() -> &'static str {
 
 #[cfg(test)]
 pub mod test {
-    use aws_sdk_dynamodb::Client;
     use lazy_static::lazy_static;
     use rand::SeedableRng;
     use rand::{rngs::SmallRng, Rng};
     use tokio::sync::Mutex;
 
     use crate::{
-        domain::gateway::LedgerEntryRepository,
-        gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+        domain::{
+            entity::{Actor, LedgerId},
+            gateway::{ApiKeyRepository, LedgerEntryRepository, TenantRepository},
+        },
+        gateway::{
+            api_key_repository::DynamoDbApiKeyRepository,
+            ledger_entry_repository::DynamoDbLedgerEntryRepository,
+            table_config::{SnapshotPolicy, TableConfig},
+            tenant_repository::DynamoDbTenantRepository,
+            Table,
+        },
     };
 
     lazy_static! {
-        static ref CLIENT: Mutex<Option<Client>> = Mutex::new(None);
+        static ref TABLE: Mutex<Option<Table>> = Mutex::new(None);
         static ref RNG: Mutex<Option<SmallRng>> = Mutex::new(None);
     }
 
-    pub async fn set_up_dynamo_db_for_test() -> Client {
-        let mut client = CLIENT.lock().await;
-        match client.as_ref() {
-            Some(client) => client.clone(),
+    pub async fn set_up_dynamo_db_for_test() -> Table {
+        let mut table = TABLE.lock().await;
+        match table.as_ref() {
+            Some(table) => table.clone(),
             None => {
                 dotenv::dotenv().expect("Error loading env for test");
-                let new_client = crate::dynamo_db_client().await;
-                *client = Some(new_client.clone());
-                new_client
+                let new_table = Table::new(
+                    crate::dynamo_db_client().await,
+                    &TableConfig::load().expect("Error loading table config for test"),
+                );
+                *table = Some(new_table.clone());
+                new_table
             }
         }
     }
 
-    pub async fn get_rng() -> impl Rng {
+    pub async fn get_rng() -> impl Rng + Clone {
         let mut rng = RNG.lock().await;
         match rng.as_ref() {
             Some(rng) => rng.clone(),
@@ -88,7 +251,35 @@ pub mod test {
         }
     }
 
+    pub fn get_actor() -> Actor {
+        Actor::new("test".into()).expect("A valid actor")
+    }
+
+    pub async fn get_api_key_repository() -> impl ApiKeyRepository {
+        DynamoDbApiKeyRepository::from(set_up_dynamo_db_for_test().await)
+    }
+
     pub async fn get_repository() -> impl LedgerEntryRepository {
         DynamoDbLedgerEntryRepository::from(set_up_dynamo_db_for_test().await)
     }
+
+    /// A repository that snapshots the balances every `every_entries` entries.
+    pub async fn get_repository_with_snapshots(every_entries: u64) -> impl LedgerEntryRepository {
+        let table = set_up_dynamo_db_for_test().await;
+        DynamoDbLedgerEntryRepository::from(Table {
+            snapshot_policy: SnapshotPolicy {
+                every_entries,
+                daily: false,
+            },
+            ..table
+        })
+    }
+
+    pub async fn get_repository_for_ledger(ledger_id: &LedgerId) -> impl LedgerEntryRepository {
+        DynamoDbLedgerEntryRepository::new(set_up_dynamo_db_for_test().await, Some(ledger_id))
+    }
+
+    pub async fn get_tenant_repository() -> impl TenantRepository {
+        DynamoDbTenantRepository::from(set_up_dynamo_db_for_test().await)
+    }
 }
@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{
+    Account, AccountCursor, AccountId, AccountQuery, AccountTag, BalanceFilter, LedgerId,
+};
+use crate::domain::gateway::GetBalanceError;
+use crate::domain::use_case::{
+    list_accounts_from_cursor_use_case, list_accounts_use_case, set_account_tags_use_case,
+};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn list_accounts(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Query(params): Query<ListAccountsParams>,
+) -> Result<Json<ListAccountsResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let limit = params.limit.unwrap_or(100);
+    if limit > 100 {
+        return Err(JsonError::unprocessable_entity(
+            "Limit must be lower or equal to 100".into(),
+        ));
+    }
+    let has_filters = params.balances.is_some()
+        || params.active_after.is_some()
+        || params.active_before.is_some()
+        || params.tags.is_some();
+    let result = match params.cursor {
+        Some(_) if has_filters => {
+            return Err(JsonError::unprocessable_entity(
+                "You can't provide a cursor and other filters".into(),
+            ))
+        }
+        Some(cursor) => {
+            list_accounts_from_cursor_use_case(&repository, AccountCursor::decode(cursor)?, limit)
+                .await
+        }
+        None => {
+            let query = AccountQuery {
+                balances: comma_separated(params.balances.as_deref(), |filter| {
+                    filter.parse::<BalanceFilter>()
+                })?,
+                active_after: params.active_after,
+                active_before: params.active_before,
+                tags: comma_separated(params.tags.as_deref(), |tag| AccountTag::new(tag.into()))?,
+            };
+            list_accounts_use_case(&repository, query, limit).await
+        }
+    };
+    let (accounts, cursor) = result.map_err(anyhow::Error::from)?;
+    Ok(Json(ListAccountsResponse {
+        accounts,
+        cursor: cursor.map(|cursor| cursor.encode()).transpose()?,
+    }))
+}
+
+pub async fn set_account_tags(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Json(request): Json<SetAccountTagsRequest>,
+) -> Result<Json<SetAccountTagsResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match set_account_tags_use_case(&repository, &account_id, &request.tags).await {
+        Ok(()) => Ok(Json(SetAccountTagsResponse {
+            account_id,
+            tags: request.tags,
+        })),
+        Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+fn comma_separated<T, C: FromIterator<T>>(
+    value: Option<&str>,
+    parse: impl Fn(&str) -> anyhow::Result<T>,
+) -> Result<C, JsonError<'static>> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .filter(|value| !value.is_empty())
+        .map(parse)
+        .collect::<anyhow::Result<C>>()
+        .map_err(|err| JsonError::unprocessable_entity(err.to_string().into()))
+}
+
+#[derive(Deserialize)]
+pub struct ListAccountsParams {
+    /// Comma separated balance ranges, like `balance_usd_amount<0`.
+    balances: Option<String>,
+    active_after: Option<DateTime<Utc>>,
+    active_before: Option<DateTime<Utc>>,
+    /// Comma separated tags, all of them required.
+    tags: Option<String>,
+    limit: Option<u8>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ListAccountsResponse {
+    accounts: Vec<Account>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SetAccountTagsRequest {
+    tags: BTreeSet<AccountTag>,
+}
+
+#[derive(Serialize)]
+pub struct SetAccountTagsResponse {
+    account_id: AccountId,
+    tags: BTreeSet<AccountTag>,
+}
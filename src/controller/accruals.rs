@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+use crate::controller::ledger::run_per_ledger;
+use crate::controller::push_entries::PushEntryResponse;
+use crate::domain::entity::{
+    AccountId, Accrual, AccrualRate, AccrualSchedule, Actor, DayCount, LedgerFieldName, LedgerId,
+    Period,
+};
+use crate::domain::gateway::AccrualError;
+use crate::domain::use_case::{
+    delete_accrual_schedule_use_case, get_accrual_schedules_use_case, get_accrual_use_case,
+    post_accruals_use_case, set_accrual_schedule_use_case,
+};
+use crate::gateway::Table;
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn set_accrual_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path((account_id, ledger_field)): Path<(AccountId, LedgerFieldName)>,
+    Json(request): Json<SetAccrualScheduleRequest>,
+) -> Result<Json<AccrualSchedule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match set_accrual_schedule_use_case(
+        &repository,
+        &actor,
+        &account_id,
+        &ledger_field,
+        request.day_count,
+        request.rates,
+    )
+    .await
+    {
+        Ok(schedule) => Ok(Json(schedule)),
+        Err(err @ AccrualError::InvalidSchedule(_)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_accrual_schedules(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+) -> Result<Json<Vec<AccrualSchedule>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let schedules = get_accrual_schedules_use_case(&repository, Some(&account_id))
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(schedules))
+}
+
+pub async fn list_accrual_schedules(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+) -> Result<Json<Vec<AccrualSchedule>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let schedules = get_accrual_schedules_use_case(&repository, None)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(schedules))
+}
+
+pub async fn delete_accrual_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path((account_id, ledger_field)): Path<(AccountId, LedgerFieldName)>,
+) -> Result<(), JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match delete_accrual_schedule_use_case(&repository, &account_id, &ledger_field).await {
+        Ok(()) => Ok(()),
+        Err(err @ AccrualError::NotFound(_, _)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_accrual(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path((account_id, ledger_field, period)): Path<(AccountId, LedgerFieldName, Period)>,
+) -> Result<Json<Accrual>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_accrual_use_case(&repository, &account_id, &ledger_field, &period).await {
+        Ok(accrual) => Ok(Json(accrual)),
+        Err(err @ AccrualError::NotFound(_, _)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn post_accruals(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+) -> Result<Json<PushEntryResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let result = post_accruals_use_case(&repository, app_state.random_number_generator, &actor)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(result.into()))
+}
+
+/// Posts the accruals of the default ledger and of every other ledger each `interval`, as
+/// the `accruals` actor. Several servers can run it at the same time, as a period is posted
+/// only once.
+pub async fn run_accruals(table: Table, interval: Duration) {
+    let actor = Actor::new("accruals".into()).expect("A valid actor");
+    run_per_ledger(
+        table,
+        interval,
+        actor,
+        "accruals",
+        |repository, actor| async move {
+            post_accruals_use_case(&repository, SmallRng::from_entropy(), &actor).await
+        },
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct SetAccrualScheduleRequest {
+    day_count: DayCount,
+    rates: Vec<AccrualRate>,
+}
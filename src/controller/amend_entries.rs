@@ -0,0 +1,24 @@
+use axum::{extract::State, Extension, Json};
+
+use crate::domain::entity::{Actor, LedgerId};
+
+use crate::domain::use_case::amend_entries_use_case;
+use crate::{app::AppState, gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository};
+
+use super::push_entries::{PushEntryRequest, PushEntryResponse};
+
+pub async fn amend_entries(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Json(amend_entries): Json<Vec<PushEntryRequest>>,
+) -> Json<PushEntryResponse> {
+    let (applied, non_applied) = amend_entries_use_case(
+        &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
+        app_state.random_number_generator,
+        &actor,
+        amend_entries.into_iter().map(|entry| entry.into()),
+    )
+    .await;
+    Json((applied, non_applied).into())
+}
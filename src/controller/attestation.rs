@@ -0,0 +1,74 @@
+use std::path::Path as FilePath;
+
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use dotenv::var;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use serde::Deserialize;
+
+use crate::domain::entity::{AccountId, LedgerId, SignedAttestation};
+use crate::domain::gateway::GetBalanceError;
+use crate::domain::use_case::attest_balance_use_case;
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn get_attestation(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<GetAttestationParams>,
+) -> Result<Json<SignedAttestation>, JsonError<'static>> {
+    let Some(signing_key) = app_state.attestation_key.as_deref() else {
+        return Err(JsonError::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "Attestations are not configured in this server".into(),
+        ));
+    };
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match attest_balance_use_case(
+        &repository,
+        signing_key,
+        &account_id,
+        ledger_id.as_deref(),
+        params.as_of,
+    )
+    .await
+    {
+        Ok(attestation) => Ok(Json(attestation)),
+        Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GetAttestationParams {
+    as_of: Option<DateTime<Utc>>,
+}
+
+/// The key attestations are signed with, read from the PKCS#8 PEM file in the
+/// `ATTESTATION_KEY_FILE` env. Without it, the server doesn't sign attestations.
+pub fn attestation_key_from_env() -> anyhow::Result<Option<SigningKey>> {
+    let Ok(path) = var("ATTESTATION_KEY_FILE") else {
+        return Ok(None);
+    };
+    SigningKey::read_pkcs8_pem_file(&path)
+        .map(Some)
+        .map_err(|err| anyhow::anyhow!("Error reading the attestation key {path}: {err}"))
+}
+
+/// Reads a public key from a SPKI PEM file, the format of `openssl pkey -pubout`.
+pub fn read_verifying_key(path: &FilePath) -> anyhow::Result<VerifyingKey> {
+    let pem = std::fs::read_to_string(path).with_context(|| format!("Error opening {path:?}"))?;
+    VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|err| anyhow::anyhow!("Error reading the public key {path:?}: {err}"))
+}
@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::bail;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{FromRequestParts, MatchedPath, Query, RawPathParams, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::DateTime;
+use dotenv::var;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::controller::JsonError;
+use crate::domain::entity::{
+    AccountId, AccountScope, Actor, ApiKeyCredential, LedgerId, Principal, Scope,
+};
+use crate::domain::gateway::ApiKeyError;
+use crate::domain::use_case::authenticate_api_key_use_case;
+use crate::gateway::api_key_repository::DynamoDbApiKeyRepository;
+use crate::gateway::Table;
+
+/// Requests are buffered to check signatures and account ids, so they need a limit.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// How the principal behind each request is authenticated. It is loaded from the `AUTH_MODE` env.
+pub enum AuthConfig {
+    /// Every request is made by the anonymous actor, with every scope.
+    Disabled,
+    /// API keys stored in the ledger table. Requests are authenticated with
+    /// `Authorization: ApiKey {key_id}:{secret}` or signed with
+    /// `Authorization: HMAC-SHA256 {key_id}:{signature}` and the `x-timestamp` header.
+    ApiKeys(DynamoDbApiKeyRepository),
+    /// A HS256 JWT signed with the `JWT_SECRET` env is sent as `Authorization: Bearer {token}`.
+    /// The actor is the `sub` claim, with the scopes of the `scopes` claim, none without it, and
+    /// the optional `accounts` claim restricts it.
+    Jwt(DecodingKey),
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    scopes: Option<HashSet<Scope>>,
+    accounts: Option<AccountScope>,
+    ledger_id: Option<LedgerId>,
+}
+
+impl AuthConfig {
+    pub fn from_env(table: &Table) -> anyhow::Result<Self> {
+        match var("AUTH_MODE").as_deref() {
+            Err(_) | Ok("none") => Ok(Self::Disabled),
+            Ok("api_key") => Ok(Self::ApiKeys(DynamoDbApiKeyRepository::from(table.clone()))),
+            Ok("jwt") => Ok(Self::Jwt(DecodingKey::from_secret(
+                var("JWT_SECRET")?.as_bytes(),
+            ))),
+            Ok(auth_mode) => bail!("Unknown AUTH_MODE {auth_mode}"),
+        }
+    }
+
+    async fn principal(
+        &self,
+        parts: &Parts,
+        body: &Bytes,
+    ) -> Result<Option<Principal>, JsonError<'static>> {
+        let authorization = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|authorization| authorization.to_str().ok());
+        match self {
+            Self::Disabled => Ok(Some(Principal::anonymous())),
+            Self::ApiKeys(repository) => {
+                let Some((key_id, credential)) = authorization
+                    .and_then(|authorization| api_key_credential(authorization, parts, body))
+                else {
+                    return Ok(None);
+                };
+                match authenticate_api_key_use_case(repository, key_id, credential).await {
+                    Ok(principal) => Ok(Some(principal)),
+                    Err(ApiKeyError::NotFound(_)) | Err(ApiKeyError::InvalidCredential(_)) => {
+                        Ok(None)
+                    }
+                    Err(err) => Err(anyhow::Error::from(err).into()),
+                }
+            }
+            Self::Jwt(decoding_key) => {
+                let Some(token) =
+                    authorization.and_then(|authorization| authorization.strip_prefix("Bearer "))
+                else {
+                    return Ok(None);
+                };
+                let Ok(token) =
+                    decode::<Claims>(token, decoding_key, &Validation::new(Algorithm::HS256))
+                else {
+                    return Ok(None);
+                };
+                let Ok(actor) = Actor::new(token.claims.sub) else {
+                    return Ok(None);
+                };
+                Ok(Some(Principal {
+                    actor,
+                    scopes: token.claims.scopes.unwrap_or_default(),
+                    accounts: token.claims.accounts.unwrap_or_default(),
+                    ledger_id: token.claims.ledger_id,
+                }))
+            }
+        }
+    }
+}
+
+fn api_key_credential<'a>(
+    authorization: &'a str,
+    parts: &Parts,
+    body: &Bytes,
+) -> Option<(&'a str, ApiKeyCredential)> {
+    if let Some(key_id_and_secret) = authorization.strip_prefix("ApiKey ") {
+        let (key_id, secret) = key_id_and_secret.split_once(':')?;
+        return Some((key_id, ApiKeyCredential::Secret(secret.into())));
+    }
+    let (key_id, signature) = authorization
+        .strip_prefix("HMAC-SHA256 ")?
+        .split_once(':')?;
+    let timestamp = parts.headers.get("x-timestamp")?.to_str().ok()?;
+    let path_and_query = parts.uri.path_and_query()?.as_str();
+    Some((
+        key_id,
+        ApiKeyCredential::Signature {
+            signature: signature.into(),
+            string_to_sign: format!(
+                "{}\n{}\n{}\n{}",
+                timestamp,
+                parts.method,
+                path_and_query,
+                hex::encode(Sha256::digest(body))
+            ),
+            signed_at: DateTime::from_timestamp(timestamp.parse().ok()?, 0)?,
+        },
+    ))
+}
+
+fn required_scopes(method: &Method, path: &str) -> Vec<Scope> {
+    match (method, path) {
+        (&Method::POST, "/api/v1/balance") => vec![Scope::Push],
+        (&Method::DELETE, "/api/v1/balance") => vec![Scope::Delete],
+        (&Method::PUT, "/api/v1/balance") => vec![Scope::Push, Scope::Delete],
+        (&Method::POST, "/api/v1/balance/post") => vec![Scope::Push],
+        (&Method::POST, "/api/v1/balance/void") => vec![Scope::Delete],
+        (&Method::POST, "/api/v1/balances/batchGet") => vec![Scope::Read],
+        (_, "/api/v1/audit") => vec![Scope::Admin],
+        (_, "/api/v1/balance/:account_id/verify") => vec![Scope::Admin],
+        (&Method::GET, _) => vec![Scope::Read],
+        _ => vec![Scope::Admin],
+    }
+}
+
+/// Every account id found in the path, the query and the top level of the JSON body, including
+/// the parent set for an account.
+async fn requested_account_ids(parts: &mut Parts, body: &Bytes) -> Vec<AccountId> {
+    let mut account_ids = Vec::new();
+    if let Ok(path_params) = RawPathParams::from_request_parts(parts, &()).await {
+        account_ids.extend(
+            path_params
+                .iter()
+                .filter(|(key, _)| *key == "account_id")
+                .map(|(_, value)| value.to_string()),
+        );
+    }
+    if let Ok(Query(query)) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri) {
+        account_ids.extend(query.get("account_id").cloned());
+    }
+    if let Ok(body) = serde_json::from_slice::<Value>(body) {
+        let objects = match body {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        for object in objects {
+            for key in ["account_id", "parent_id"] {
+                if let Some(Value::String(account_id)) = object.get(key) {
+                    account_ids.push(account_id.clone());
+                }
+            }
+            if let Some(Value::Array(ids)) = object.get("account_ids") {
+                account_ids.extend(ids.iter().filter_map(|id| id.as_str().map(String::from)));
+            }
+        }
+    }
+    account_ids
+        .into_iter()
+        .filter_map(|account_id| Uuid::parse_str(&account_id).ok().map(AccountId::new))
+        .collect()
+}
+
+/// Authenticates the request and checks that its principal can perform it. The principal and
+/// its actor are made available to the handlers as extensions.
+pub async fn auth_middleware(
+    State(auth_config): State<Arc<AuthConfig>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, JsonError<'static>> {
+    let (mut parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| JsonError::new(StatusCode::PAYLOAD_TOO_LARGE, "Body too large".into()))?;
+    let principal = auth_config
+        .principal(&parts, &body)
+        .await?
+        .ok_or(JsonError::unauthorized())?;
+
+    let path = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| parts.uri.path().to_string());
+    if !required_scopes(&parts.method, &path)
+        .into_iter()
+        .all(|scope| principal.has_scope(scope))
+    {
+        return Err(JsonError::forbidden());
+    }
+    if !principal.accounts.is_unrestricted() {
+        let account_ids = requested_account_ids(&mut parts, &body).await;
+        if account_ids.is_empty()
+            || !account_ids
+                .iter()
+                .all(|account_id| principal.accounts.allows(account_id))
+        {
+            return Err(JsonError::forbidden());
+        }
+    }
+
+    parts.extensions.insert(principal.actor.clone());
+    parts.extensions.insert(principal);
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::Request;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+
+    async fn jwt_principal(claims: Value) -> Option<Principal> {
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("A valid token");
+        let (parts, _) = Request::builder()
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(())
+            .expect("A valid request")
+            .into_parts();
+        AuthConfig::Jwt(DecodingKey::from_secret(b"secret"))
+            .principal(&parts, &Bytes::new())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn jwt_scopes() {
+        let expires_at = chrono::Utc::now().timestamp() + 60;
+        let principal = jwt_principal(json!({
+            "sub": "payments",
+            "exp": expires_at,
+            "scopes": ["read", "push"],
+        }))
+        .await
+        .expect("An authenticated principal");
+        assert_eq!(principal.scopes, HashSet::from([Scope::Read, Scope::Push]));
+
+        // A token without the claim has no scope, instead of every one.
+        let principal = jwt_principal(json!({"sub": "payments", "exp": expires_at}))
+            .await
+            .expect("An authenticated principal");
+        assert!(principal.scopes.is_empty());
+        assert!(!principal.has_scope(Scope::Read));
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn parent_id_is_requested() {
+        let account_id = Uuid::new_v4();
+        let parent_id = Uuid::new_v4();
+        let (mut parts, _) = Request::builder()
+            .uri(format!("/api/v1/balance/{account_id}/parent"))
+            .body(())
+            .expect("A valid request")
+            .into_parts();
+        let body = Bytes::from(json!({"parent_id": parent_id}).to_string());
+        assert_eq!(
+            requested_account_ids(&mut parts, &body).await,
+            vec![AccountId::new(parent_id)]
+        );
+    }
+
+    #[test]
+    fn batch_get_balances_scopes() {
+        assert_eq!(
+            required_scopes(&Method::POST, "/api/v1/balances/batchGet"),
+            vec![Scope::Read]
+        );
+    }
+}
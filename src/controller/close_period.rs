@@ -0,0 +1,67 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, Actor, LedgerId, Period};
+use crate::domain::gateway::ClosePeriodError;
+use crate::domain::use_case::close_period_use_case;
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+use super::PeriodClosingResponse;
+
+pub async fn close_period(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(period): Path<Period>,
+    Json(close_period): Json<ClosePeriodRequest>,
+) -> Result<Json<ClosePeriodResponse>, JsonError<'static>> {
+    match close_period_use_case(
+        &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
+        app_state.random_number_generator,
+        &actor,
+        &period,
+        close_period.account_ids.into_iter(),
+    )
+    .await
+    {
+        Ok((closed, non_closed)) => Ok(Json(ClosePeriodResponse {
+            closed_accounts: closed.into_iter().map(|v| v.into()).collect(),
+            non_closed_accounts: non_closed
+                .into_iter()
+                .map(|(reason, account_id)| NonClosedAccount {
+                    error: reason.message(),
+                    error_code: reason.reason_code(),
+                    account_id,
+                })
+                .collect(),
+        })),
+        Err(ClosePeriodError::PeriodNotFinished(period)) => Err(JsonError::unprocessable_entity(
+            format!("Period {} has not finished yet", period).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ClosePeriodRequest {
+    account_ids: Vec<AccountId>,
+}
+
+#[derive(Serialize)]
+pub struct ClosePeriodResponse {
+    closed_accounts: Vec<PeriodClosingResponse>,
+    non_closed_accounts: Vec<NonClosedAccount>,
+}
+
+#[derive(Serialize)]
+struct NonClosedAccount {
+    error: String,
+    error_code: u16,
+    account_id: AccountId,
+}
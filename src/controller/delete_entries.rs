@@ -1,9 +1,10 @@
-use axum::{extract::State, Json};
+use axum::{extract::State, Extension, Json};
 use serde::Serialize;
 
 use crate::domain::use_case::delete_entries_use_case;
 use crate::{
-    app::AppState, domain::entity::DeleteEntryRequest,
+    app::AppState,
+    domain::entity::{Actor, DeleteEntryRequest, LedgerId},
     gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
 };
 
@@ -11,11 +12,14 @@ use super::LedgerResponse;
 
 pub async fn delete_entries(
     State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
     Json(delete_entries): Json<Vec<DeleteEntryRequest>>,
 ) -> Json<DeleteEntryResponse> {
     let (applied, non_applied) = delete_entries_use_case(
-        &DynamoDbLedgerEntryRepository::from(app_state.dynamo_client),
+        &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
         app_state.random_number_generator,
+        &actor,
         delete_entries.into_iter(),
     )
     .await;
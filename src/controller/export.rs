@@ -0,0 +1,409 @@
+use std::future::Future;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::bail;
+use arrow_array::{
+    builder::{Decimal128Builder, StringBuilder, TimestampMicrosecondBuilder, UInt64Builder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, StreamExt};
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use serde::Deserialize;
+use tokio::io::{duplex, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::io::ReaderStream;
+
+use crate::app::AppState;
+use crate::controller::{JsonError, LedgerResponse, Status};
+use crate::domain::entity::{
+    AccountId, EntryWithBalance, LedgerBalanceName, LedgerFieldName, LedgerId,
+};
+use crate::domain::gateway::GetBalanceError;
+use crate::domain::use_case::export_entries_use_case;
+use crate::gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository;
+
+/// Rows buffered by the Parquet writer before a row group is written to the output.
+const PARQUET_ROW_GROUP_SIZE: usize = 10_000;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/jsonl",
+            Self::Csv => "text/csv",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            _ => bail!("Format must be one of jsonl, csv or parquet"),
+        }
+    }
+}
+
+/// Encodes batches of entries as they are exported. JSON Lines have the same entries as the
+/// API. CSV and Parquet have a column for each of the ledger fields and their balances.
+pub enum ExportEncoder {
+    Jsonl,
+    Csv {
+        /// The header line, until it is written with the first batch.
+        header: Vec<u8>,
+        fields: Vec<LedgerFieldName>,
+    },
+    Parquet {
+        writer: Box<ArrowWriter<Vec<u8>>>,
+        schema: SchemaRef,
+        fields: Vec<LedgerFieldName>,
+    },
+}
+
+impl ExportEncoder {
+    /// CSV and Parquet need the ledger fields upfront, because their columns can't change
+    /// once the first rows are written.
+    pub fn new(format: ExportFormat, fields: Vec<LedgerFieldName>) -> anyhow::Result<Self> {
+        if format != ExportFormat::Jsonl && fields.is_empty() {
+            bail!("The ledger fields to export are required for the csv and parquet formats");
+        }
+        let columns = columns(&fields);
+        Ok(match format {
+            ExportFormat::Jsonl => Self::Jsonl,
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(&columns)?;
+                Self::Csv {
+                    header: writer.into_inner()?,
+                    fields,
+                }
+            }
+            ExportFormat::Parquet => {
+                let schema = Arc::new(Schema::new(
+                    columns
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, column)| {
+                            let (data_type, nullable) = match index {
+                                2 => (DataType::UInt64, false),
+                                4 => (
+                                    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                                    false,
+                                ),
+                                5 | 6 => (DataType::Utf8, true),
+                                index if index >= FIXED_COLUMNS.len() => {
+                                    (DataType::Decimal128(38, 0), true)
+                                }
+                                _ => (DataType::Utf8, false),
+                            };
+                            Field::new(column, data_type, nullable)
+                        })
+                        .collect::<Vec<Field>>(),
+                ));
+                let properties = WriterProperties::builder()
+                    .set_max_row_group_size(PARQUET_ROW_GROUP_SIZE)
+                    .build();
+                Self::Parquet {
+                    writer: Box::new(ArrowWriter::try_new(
+                        Vec::new(),
+                        schema.clone(),
+                        Some(properties),
+                    )?),
+                    schema,
+                    fields,
+                }
+            }
+        })
+    }
+
+    /// Encodes the entries, returning the bytes ready to be written.
+    pub fn encode(&mut self, entries: Vec<EntryWithBalance>) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Jsonl => {
+                let mut bytes = Vec::new();
+                for entry in entries {
+                    serde_json::to_writer(&mut bytes, &LedgerResponse::from(entry))?;
+                    bytes.push(b'\n');
+                }
+                Ok(bytes)
+            }
+            Self::Csv { header, fields } => {
+                let mut writer = csv::Writer::from_writer(std::mem::take(header));
+                for entry in entries {
+                    let mut record = vec![
+                        entry.account_id.to_string(),
+                        entry.entry_id.to_string(),
+                        entry.sequence.to_string(),
+                        Status::from(entry.status).to_string(),
+                        entry.created_at.to_rfc3339(),
+                        entry
+                            .actor
+                            .map(|actor| actor.to_string())
+                            .unwrap_or_default(),
+                        entry
+                            .reversal
+                            .map(|reversal| serde_json::to_string(&reversal))
+                            .transpose()?
+                            .unwrap_or_default(),
+                        serde_json::to_string(&entry.additional_fields)?,
+                    ];
+                    for field in fields.iter() {
+                        record.push(
+                            entry
+                                .ledger_fields
+                                .get(field)
+                                .map(|value| value.to_string())
+                                .unwrap_or_default(),
+                        );
+                    }
+                    for field in fields.iter() {
+                        record.push(
+                            entry
+                                .ledger_balances
+                                .get(&LedgerBalanceName::from(field.clone()))
+                                .map(|value| value.to_string())
+                                .unwrap_or_default(),
+                        );
+                    }
+                    writer.write_record(&record)?;
+                }
+                Ok(writer.into_inner()?)
+            }
+            Self::Parquet {
+                writer,
+                schema,
+                fields,
+            } => {
+                writer.write(&record_batch(schema, fields, entries)?)?;
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+        }
+    }
+
+    /// The remaining bytes, like the Parquet footer. Only valid once every entry was encoded.
+    pub fn finish(self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Jsonl => Ok(Vec::new()),
+            Self::Csv { header, .. } => Ok(header),
+            Self::Parquet { writer, .. } => Ok(writer.into_inner()?),
+        }
+    }
+}
+
+const FIXED_COLUMNS: [&str; 8] = [
+    "account_id",
+    "entry_id",
+    "sequence",
+    "status",
+    "created_at",
+    "actor",
+    "reversal",
+    "additional_fields",
+];
+
+fn columns(fields: &[LedgerFieldName]) -> Vec<String> {
+    FIXED_COLUMNS
+        .iter()
+        .map(|column| column.to_string())
+        .chain(
+            fields
+                .iter()
+                .map(|field| format!("ledger_fields.{}", String::from(field.clone()))),
+        )
+        .chain(fields.iter().map(|field| {
+            format!(
+                "ledger_balances.{}",
+                String::from(LedgerBalanceName::from(field.clone()))
+            )
+        }))
+        .collect()
+}
+
+fn record_batch(
+    schema: &SchemaRef,
+    fields: &[LedgerFieldName],
+    entries: Vec<EntryWithBalance>,
+) -> anyhow::Result<RecordBatch> {
+    let mut account_ids = StringBuilder::new();
+    let mut entry_ids = StringBuilder::new();
+    let mut sequences = UInt64Builder::new();
+    let mut statuses = StringBuilder::new();
+    let mut created_ats = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+    let mut actors = StringBuilder::new();
+    let mut reversals = StringBuilder::new();
+    let mut additional_fields = StringBuilder::new();
+    let mut ledger_fields = fields
+        .iter()
+        .map(|_| Decimal128Builder::new().with_precision_and_scale(38, 0))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut ledger_balances = fields
+        .iter()
+        .map(|_| Decimal128Builder::new().with_precision_and_scale(38, 0))
+        .collect::<Result<Vec<_>, _>>()?;
+    for entry in entries {
+        account_ids.append_value(entry.account_id.to_string());
+        entry_ids.append_value(entry.entry_id.to_string());
+        sequences.append_value(entry.sequence);
+        statuses.append_value(Status::from(entry.status).to_string());
+        created_ats.append_value(entry.created_at.timestamp_micros());
+        actors.append_option(entry.actor.map(|actor| actor.to_string()));
+        reversals.append_option(
+            entry
+                .reversal
+                .map(|reversal| serde_json::to_string(&reversal))
+                .transpose()?,
+        );
+        additional_fields.append_value(serde_json::to_string(&entry.additional_fields)?);
+        for (field, builder) in fields.iter().zip(ledger_fields.iter_mut()) {
+            builder.append_option(entry.ledger_fields.get(field).copied());
+        }
+        for (field, builder) in fields.iter().zip(ledger_balances.iter_mut()) {
+            builder.append_option(
+                entry
+                    .ledger_balances
+                    .get(&LedgerBalanceName::from(field.clone()))
+                    .copied(),
+            );
+        }
+    }
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(account_ids.finish()),
+        Arc::new(entry_ids.finish()),
+        Arc::new(sequences.finish()),
+        Arc::new(statuses.finish()),
+        Arc::new(created_ats.finish()),
+        Arc::new(actors.finish()),
+        Arc::new(reversals.finish()),
+        Arc::new(additional_fields.finish()),
+    ];
+    for mut builder in ledger_fields.into_iter().chain(ledger_balances) {
+        columns.push(Arc::new(builder.finish()));
+    }
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Runs the export, encoding the batches it sends as they arrive and writing them to the
+/// output. It returns how many entries were exported.
+pub async fn write_export(
+    mut encoder: ExportEncoder,
+    receiver: mpsc::Receiver<Vec<EntryWithBalance>>,
+    export: impl Future<Output = Result<(), GetBalanceError>>,
+    output: &mut (impl AsyncWrite + Unpin),
+) -> anyhow::Result<u64> {
+    let write = async {
+        // The receiver is dropped if writing fails, which stops the export.
+        let mut receiver = receiver;
+        let mut exported = 0;
+        while let Some(entries) = receiver.recv().await {
+            exported += entries.len() as u64;
+            output.write_all(&encoder.encode(entries)?).await?;
+        }
+        anyhow::Ok(exported)
+    };
+    let (export_result, write_result) = tokio::join!(export, write);
+    let exported = write_result?;
+    export_result?;
+    output.write_all(&encoder.finish()?).await?;
+    output.flush().await?;
+    Ok(exported)
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    format: ExportFormat,
+    /// Comma separated ledger fields, required by csv and parquet.
+    fields: Option<String>,
+}
+
+/// Pipe buffer between the export and the response body.
+const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+
+pub async fn export_entries(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, JsonError<'static>> {
+    let fields = params
+        .fields
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|field| !field.is_empty())
+        .map(|field| LedgerFieldName::new(field.into()))
+        .collect::<anyhow::Result<Vec<LedgerFieldName>>>()
+        .map_err(|err| JsonError::unprocessable_entity(err.to_string().into()))?;
+    let encoder = ExportEncoder::new(params.format, fields)
+        .map_err(|err| JsonError::unprocessable_entity(err.to_string().into()))?;
+
+    let content_disposition = format!(
+        "attachment; filename=\"{}.{}\"",
+        account_id,
+        params.format.extension()
+    );
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let (mut writer, reader) = duplex(EXPORT_BUFFER_SIZE);
+    let (result_sender, result_receiver) = oneshot::channel();
+    tokio::spawn(async move {
+        let (sender, receiver) = mpsc::channel(4);
+        let export = export_entries_use_case(
+            &repository,
+            Some(&account_id),
+            &params.start_date,
+            &params.end_date,
+            1,
+            sender,
+        );
+        let result = write_export(encoder, receiver, export, &mut writer).await;
+        if let Err(err) = &result {
+            tracing::error!("Error exporting account {}: {err}", account_id);
+        }
+        let _ = result_sender.send(result.is_ok());
+    });
+    // A failed export ends the body with an error, so the client doesn't take it as complete.
+    let failure = stream::once(result_receiver).filter_map(|succeeded| async move {
+        (!succeeded.unwrap_or(false)).then(|| Err(io::Error::other("The export failed")))
+    });
+    let body = Body::from_stream(ReaderStream::new(reader).chain(failure));
+    Ok((
+        [
+            (CONTENT_TYPE, params.format.content_type().to_string()),
+            (CONTENT_DISPOSITION, content_disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
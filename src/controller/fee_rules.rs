@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::domain::entity::{
+    Actor, FeeAmount, FeeEntry, FeeRule, FeeRuleId, LedgerFieldName, LedgerId, RulePredicate,
+};
+use crate::domain::gateway::FeeRuleError;
+use crate::domain::use_case::{
+    delete_fee_rule_use_case, get_fee_rules_use_case, set_fee_rule_use_case,
+};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn set_fee_rule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(rule_id): Path<FeeRuleId>,
+    Json(request): Json<SetFeeRuleRequest>,
+) -> Result<Json<FeeRule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match set_fee_rule_use_case(
+        &repository,
+        &actor,
+        &rule_id,
+        request.when,
+        request.ledger_field,
+        request.fee,
+        request.entries,
+    )
+    .await
+    {
+        Ok(rule) => Ok(Json(rule)),
+        Err(err @ FeeRuleError::InvalidRule(_)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_fee_rules(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+) -> Result<Json<Vec<FeeRule>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let rules = get_fee_rules_use_case(&repository)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(rules))
+}
+
+pub async fn delete_fee_rule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(rule_id): Path<FeeRuleId>,
+) -> Result<(), JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match delete_fee_rule_use_case(&repository, &rule_id).await {
+        Ok(()) => Ok(()),
+        Err(err @ FeeRuleError::NotFound(_)) => Err(JsonError::not_found(err.to_string().into())),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetFeeRuleRequest {
+    #[serde(default)]
+    when: Vec<RulePredicate>,
+    ledger_field: LedgerFieldName,
+    fee: FeeAmount,
+    entries: Vec<FeeEntry>,
+}
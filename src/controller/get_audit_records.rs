@@ -0,0 +1,104 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{
+    AccountId, Actor, AuditAction, AuditCursor, AuditRecord, EntryId, LedgerId,
+};
+use crate::domain::use_case::{get_audit_records_from_cursor_use_case, get_audit_records_use_case};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn get_audit_records(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Query(params): Query<GetAuditRecordsParams>,
+) -> Result<Json<GetAuditRecordsResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let limit = params.limit.unwrap_or(100);
+    if limit > 100 {
+        return Err(JsonError::unprocessable_entity(
+            "Limit must be lower or equal to 100".into(),
+        ));
+    }
+    let result = match (
+        params.cursor,
+        params.account_id,
+        params.actor,
+        params.from,
+        params.to,
+    ) {
+        (Some(cursor), None, None, None, None) => {
+            get_audit_records_from_cursor_use_case(&repository, AuditCursor::decode(cursor)?, limit)
+                .await
+        }
+        (Some(_), _, _, _, _) => {
+            return Err(JsonError::unprocessable_entity(
+                "You can't provide a cursor and other filters".into(),
+            ))
+        }
+        (None, None, None, _, _) => {
+            return Err(JsonError::unprocessable_entity(
+                "You need to provide an `account_id` or an `actor`".into(),
+            ))
+        }
+        (None, account_id, actor, Some(from), Some(to)) => {
+            get_audit_records_use_case(&repository, account_id, actor, &from, &to, limit).await
+        }
+        (None, _, _, _, _) => {
+            return Err(JsonError::unprocessable_entity(
+                "You need to provide both the `from` and the `to` dates".into(),
+            ))
+        }
+    };
+    let (records, cursor) = result.map_err(anyhow::Error::from)?;
+    Ok(Json(GetAuditRecordsResponse {
+        records: records.into_iter().map(|record| record.into()).collect(),
+        cursor: cursor.map(|cursor| cursor.encode()).transpose()?,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct GetAuditRecordsParams {
+    account_id: Option<AccountId>,
+    actor: Option<Actor>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: Option<u8>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct GetAuditRecordsResponse {
+    records: Vec<AuditRecordResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AuditRecordResponse {
+    account_id: AccountId,
+    actor: Actor,
+    action: AuditAction,
+    entry_ids: Vec<EntryId>,
+    sequence: u64,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditRecord> for AuditRecordResponse {
+    fn from(value: AuditRecord) -> Self {
+        Self {
+            account_id: value.account_id,
+            actor: value.actor,
+            action: value.action,
+            entry_ids: value.entry_ids,
+            sequence: value.sequence,
+            created_at: value.created_at,
+        }
+    }
+}
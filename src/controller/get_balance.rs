@@ -1,31 +1,85 @@
 use axum::{
-    extract::{Path, State},
-    Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::domain::entity::AccountId;
-use crate::domain::use_case::get_balance_use_case;
+use crate::domain::entity::{AccountId, LedgerId, Period};
+use crate::domain::use_case::{
+    get_balance_use_case, get_balances_use_case, get_period_closing_use_case,
+};
 use crate::{
     app::AppState, controller::JsonError, domain::gateway::GetBalanceError,
     gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
 };
 
-use super::LedgerResponse;
+use super::{LedgerResponse, PeriodClosingResponse};
 
 pub async fn get_balance(
     State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
     Path(account_id): Path<AccountId>,
-) -> Result<Json<LedgerResponse>, JsonError<'static>> {
-    match get_balance_use_case(
-        &DynamoDbLedgerEntryRepository::from(app_state.dynamo_client),
-        &account_id,
-    )
-    .await
-    {
-        Ok(balance) => Ok(Json(balance.into())),
+    Query(params): Query<GetBalanceParams>,
+) -> Result<Response, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    if let Some(period) = params.period {
+        return match get_period_closing_use_case(&repository, &account_id, &period).await {
+            Ok(closing) => Ok(Json(PeriodClosingResponse::from(closing)).into_response()),
+            Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+                format!("Period {} is not closed for account {}", period, account_id).into(),
+            )),
+            Err(e) => Err(anyhow::Error::from(e).into()),
+        };
+    }
+    match get_balance_use_case(&repository, &account_id).await {
+        Ok(balance) => Ok(Json(LedgerResponse::from(balance)).into_response()),
         Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
             format!("Account {} not found", account_id).into(),
         )),
         Err(e) => Err(anyhow::Error::from(e).into()),
     }
 }
+
+#[derive(Deserialize)]
+pub struct GetBalanceParams {
+    period: Option<Period>,
+}
+
+/// Accounts that can be looked up in one request.
+const MAX_BATCH_GET_ACCOUNTS: usize = 1_000;
+
+pub async fn get_balances(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Json(request): Json<GetBalancesRequest>,
+) -> Result<Json<GetBalancesResponse>, JsonError<'static>> {
+    if request.account_ids.is_empty() || request.account_ids.len() > MAX_BATCH_GET_ACCOUNTS {
+        return Err(JsonError::unprocessable_entity(
+            format!(
+                "Between 1 and {} account ids must be requested",
+                MAX_BATCH_GET_ACCOUNTS
+            )
+            .into(),
+        ));
+    }
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_balances_use_case(&repository, &request.account_ids).await {
+        Ok((balances, missing_account_ids)) => Ok(Json(GetBalancesResponse {
+            balances: balances.into_iter().map(LedgerResponse::from).collect(),
+            missing_account_ids,
+        })),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GetBalancesRequest {
+    account_ids: Vec<AccountId>,
+}
+
+#[derive(Serialize)]
+pub struct GetBalancesResponse {
+    balances: Vec<LedgerResponse>,
+    missing_account_ids: Vec<AccountId>,
+}
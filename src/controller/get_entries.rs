@@ -1,11 +1,11 @@
 use axum::{
     extract::{Path, Query, State},
-    Json,
+    Extension, Json,
 };
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
-use crate::domain::entity::Cursor;
+use crate::domain::entity::{Cursor, LedgerId};
 use crate::domain::use_case::{get_entries_from_cursor_use_case, get_entries_use_case};
 use crate::{
     app::AppState,
@@ -17,6 +17,7 @@ use crate::{controller::GetEntriesLedgerResponse, domain::entity::AccountId};
 
 pub async fn get_entries(
     State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
     Path(account_id): Path<AccountId>,
     Query(query_params): Query<GetEntriesParams>,
 ) -> Result<Json<GetEntriesLedgerResponse>, JsonError<'static>> {
@@ -37,7 +38,7 @@ pub async fn get_entries(
                 return Err(JsonError::unprocessable_entity("Invalid cursor".into()));
             }
             get_entries_from_cursor_use_case(
-                &DynamoDbLedgerEntryRepository::from(app_state.dynamo_client),
+                &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
                 cursor,
                 query_params.limit,
             )
@@ -50,7 +51,7 @@ pub async fn get_entries(
         }
         (None, Some(start_date), Some(end_date), order) => {
             get_entries_use_case(
-                &DynamoDbLedgerEntryRepository::from(app_state.dynamo_client),
+                &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
                 &account_id,
                 &start_date,
                 &end_date,
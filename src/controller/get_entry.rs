@@ -1,10 +1,10 @@
 use axum::{
     extract::{Path, Query, State},
-    Json,
+    Extension, Json,
 };
 use serde::Deserialize;
 
-use crate::domain::entity::{Cursor, EntryId};
+use crate::domain::entity::{Cursor, EntryId, LedgerId};
 use crate::domain::use_case::{get_entry_from_cursor_use_case, get_entry_use_case};
 use crate::{
     app::AppState, controller::JsonError, domain::gateway::GetBalanceError,
@@ -14,10 +14,11 @@ use crate::{controller::GetEntriesLedgerResponse, domain::entity::AccountId};
 
 pub async fn get_entry(
     State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
     Path((account_id, entry_id)): Path<(AccountId, EntryId)>,
     Query(params): Query<GetEntryParams>,
 ) -> Result<Json<GetEntriesLedgerResponse>, JsonError<'static>> {
-    let repository = DynamoDbLedgerEntryRepository::from(app_state.dynamo_client);
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
     let limit = params.limit.unwrap_or(100);
     let result = match params.cursor {
         Some(cursor) => {
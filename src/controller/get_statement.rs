@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Deserialize;
+
+use crate::app::AppState;
+use crate::controller::JsonError;
+use crate::domain::entity::{AccountId, LedgerBalanceName, LedgerId, Period, Statement};
+use crate::domain::gateway::GetBalanceError;
+use crate::domain::use_case::get_statement_use_case;
+use crate::gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFormat {
+    #[default]
+    Json,
+    Csv,
+    Html,
+    Text,
+}
+
+impl StatementFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv",
+            Self::Html => "text/html; charset=utf-8",
+            Self::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Html => "html",
+            Self::Text => "txt",
+        }
+    }
+
+    fn render(&self, statement: &Statement) -> anyhow::Result<String> {
+        match self {
+            Self::Json => Ok(serde_json::to_string(statement)?),
+            Self::Csv => render_csv(statement),
+            Self::Html => Ok(render_html(statement)),
+            Self::Text => Ok(render_text(statement)),
+        }
+    }
+}
+
+/// The statement as a table. The opening and closing balances are its first and last rows,
+/// and every ledger field has a column for its amount and another for its balance.
+fn table(statement: &Statement) -> (Vec<String>, Vec<Vec<String>>) {
+    let fields = statement.ledger_fields();
+    let mut header = vec![
+        "sequence".to_string(),
+        "created_at".to_string(),
+        "entry_id".to_string(),
+        "description".to_string(),
+    ];
+    for field in fields.iter() {
+        header.push(String::from(field.clone()));
+        header.push(String::from(LedgerBalanceName::from(field.clone())));
+    }
+    let balances_row = |description: &str, ledger_balances: &HashMap<LedgerBalanceName, i128>| {
+        let mut row = vec![
+            String::new(),
+            String::new(),
+            String::new(),
+            description.to_string(),
+        ];
+        for field in fields.iter() {
+            row.push(String::new());
+            row.push(
+                ledger_balances
+                    .get(&LedgerBalanceName::from(field.clone()))
+                    .map(i128::to_string)
+                    .unwrap_or_default(),
+            );
+        }
+        row
+    };
+    let mut rows = vec![balances_row("Opening balance", &statement.opening_balances)];
+    for line in statement.lines.iter() {
+        let mut row = vec![
+            line.sequence.to_string(),
+            line.created_at.to_rfc3339(),
+            line.entry_id.to_string(),
+            line.note().unwrap_or_default(),
+        ];
+        for field in fields.iter() {
+            row.push(
+                line.ledger_fields
+                    .get(field)
+                    .map(i128::to_string)
+                    .unwrap_or_default(),
+            );
+            row.push(
+                line.ledger_balances
+                    .get(&LedgerBalanceName::from(field.clone()))
+                    .map(i128::to_string)
+                    .unwrap_or_default(),
+            );
+        }
+        rows.push(row);
+    }
+    rows.push(balances_row("Closing balance", &statement.closing_balances));
+    (header, rows)
+}
+
+fn render_csv(statement: &Statement) -> anyhow::Result<String> {
+    let (header, rows) = table(statement);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&header)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn render_html(statement: &Statement) -> String {
+    let (header, rows) = table(statement);
+    let title = escape_html(&format!(
+        "Statement of {} for {}",
+        statement.account_id, statement.period
+    ));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n<thead>\n<tr>"
+    );
+    for column in header.iter() {
+        let _ = write!(html, "<th>{}</th>", escape_html(column));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in rows.iter() {
+        html.push_str("<tr>");
+        for cell in row.iter() {
+            let _ = write!(html, "<td>{}</td>", escape_html(cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+fn render_text(statement: &Statement) -> String {
+    let (header, rows) = table(statement);
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            rows.iter()
+                .map(|row| row[index].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<usize>>();
+    let mut text = format!(
+        "Statement of {} for {}\n\n",
+        statement.account_id, statement.period
+    );
+    for row in [&header].into_iter().chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<String>>()
+            .join("  ");
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+#[derive(Deserialize)]
+pub struct GetStatementParams {
+    period: Period,
+    #[serde(default)]
+    format: StatementFormat,
+}
+
+pub async fn get_statement(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<GetStatementParams>,
+) -> Result<Response, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let statement = match get_statement_use_case(&repository, &account_id, &params.period).await {
+        Ok(statement) => statement,
+        Err(GetBalanceError::NotFound(account_id)) => {
+            return Err(JsonError::not_found(
+                format!("Account {} not found", account_id).into(),
+            ))
+        }
+        Err(e) => return Err(anyhow::Error::from(e).into()),
+    };
+    let content_disposition = format!(
+        "inline; filename=\"{}-{}.{}\"",
+        account_id,
+        params.period,
+        params.format.extension()
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, params.format.content_type().to_string()),
+            (CONTENT_DISPOSITION, content_disposition),
+        ],
+        params.format.render(&statement)?,
+    )
+        .into_response())
+}
@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::domain::entity::{AccountId, AccountSummary, LedgerId};
+use crate::domain::use_case::get_summary_use_case;
+use crate::{
+    app::AppState, controller::JsonError, domain::gateway::GetBalanceError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn get_summary(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<GetSummaryParams>,
+) -> Result<Json<AccountSummary>, JsonError<'static>> {
+    if params.start_date > params.end_date {
+        return Err(JsonError::unprocessable_entity(
+            "The `start_date` must be before the `end_date`".into(),
+        ));
+    }
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_summary_use_case(
+        &repository,
+        &account_id,
+        &params.start_date,
+        &params.end_date,
+    )
+    .await
+    {
+        Ok(summary) => Ok(Json(summary)),
+        Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GetSummaryParams {
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+}
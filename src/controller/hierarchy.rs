@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, Actor, LedgerId, Principal, RollUp};
+use crate::domain::gateway::{GetBalanceError, RollUpError, SetParentError};
+use crate::domain::use_case::{get_children_use_case, get_roll_up_use_case, set_parent_use_case};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn set_parent(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(account_id): Path<AccountId>,
+    Json(request): Json<SetParentRequest>,
+) -> Result<Json<SetParentResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match set_parent_use_case(&repository, &actor, &account_id, request.parent_id.as_ref()).await {
+        Ok(()) => Ok(Json(SetParentResponse {
+            account_id,
+            parent_id: request.parent_id,
+        })),
+        Err(SetParentError::OptimisticLockError(account_id)) => Err(JsonError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "The parent of account {} was changed at the same time",
+                account_id
+            )
+            .into(),
+        )),
+        Err(err @ (SetParentError::Cycle(_, _) | SetParentError::TooDeep(_, _))) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_children(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+) -> Result<Json<GetChildrenResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_children_use_case(&repository, &account_id).await {
+        Ok(account_ids) => Ok(Json(GetChildrenResponse {
+            account_id,
+            children: account_ids,
+        })),
+        Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_roll_up(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(principal): Extension<Principal>,
+    Path(account_id): Path<AccountId>,
+) -> Result<Json<RollUp>, JsonError<'static>> {
+    // The subtree can hold accounts the principal is not allowed to read.
+    if !principal.accounts.is_unrestricted() {
+        return Err(JsonError::forbidden());
+    }
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_roll_up_use_case(&repository, &account_id).await {
+        Ok(roll_up) => Ok(Json(roll_up)),
+        Err(RollUpError::AccountNotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(err @ RollUpError::TooManyAccounts(_, _)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetParentRequest {
+    /// Without it the account becomes a root account.
+    parent_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+pub struct SetParentResponse {
+    account_id: AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+pub struct GetChildrenResponse {
+    account_id: AccountId,
+    children: Vec<AccountId>,
+}
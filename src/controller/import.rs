@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::controller::push_entries::{NonAppliedEntry, PushEntryRequest};
+use crate::domain::entity::{
+    AccountId, Actor, Entry, EntryId, EntryStatus, EntryWithConditionals, LedgerFieldName,
+};
+use crate::domain::gateway::LedgerEntryRepository;
+use crate::domain::use_case::import_entries_use_case;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            _ => bail!("Format must be one of jsonl or csv"),
+        }
+    }
+}
+
+/// Reads the rows of an import file, one at a time. JSON Lines have the same entries as the
+/// push endpoint. CSV has the `account_id`, `entry_id`, `additional_fields` and `conditionals`
+/// columns, the last two JSON encoded, and a `ledger_fields.{field}` column for each ledger
+/// field. Other columns are ignored, so files from the export can be imported.
+pub enum ImportReader {
+    Jsonl(Lines<BufReader<File>>),
+    Csv {
+        headers: csv::StringRecord,
+        records: csv::StringRecordsIntoIter<File>,
+    },
+}
+
+impl ImportReader {
+    pub fn open(path: &Path, format: ImportFormat) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("Error opening {path:?}"))?;
+        Ok(match format {
+            ImportFormat::Jsonl => Self::Jsonl(BufReader::new(file).lines()),
+            ImportFormat::Csv => {
+                let mut reader = csv::Reader::from_reader(file);
+                Self::Csv {
+                    headers: reader.headers()?.clone(),
+                    records: reader.into_records(),
+                }
+            }
+        })
+    }
+
+    /// Skips the rows already imported without parsing them.
+    fn skip_rows(&mut self, rows: u64) -> anyhow::Result<()> {
+        for row in 1..=rows {
+            let skipped = match self {
+                Self::Jsonl(lines) => next_line(lines)?.is_some(),
+                Self::Csv { records, .. } => records.next().transpose()?.is_some(),
+            };
+            if !skipped {
+                bail!(
+                    "The checkpoint is past the end of the file, which only has {} rows",
+                    row - 1
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for ImportReader {
+    type Item = anyhow::Result<EntryWithConditionals>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Jsonl(lines) => next_line(lines)
+                .transpose()
+                .map(|line| Ok(serde_json::from_str::<PushEntryRequest>(&line?)?.into())),
+            Self::Csv { headers, records } => records
+                .next()
+                .map(|record| entry_from_record(headers, &record?)),
+        }
+    }
+}
+
+/// The next line that is not blank.
+fn next_line(lines: &mut Lines<BufReader<File>>) -> anyhow::Result<Option<String>> {
+    for line in lines {
+        let line = line?;
+        if !line.trim().is_empty() {
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+fn entry_from_record(
+    headers: &csv::StringRecord,
+    record: &csv::StringRecord,
+) -> anyhow::Result<EntryWithConditionals> {
+    let mut account_id = None;
+    let mut entry_id = None;
+    let mut ledger_fields = HashMap::new();
+    let mut additional_fields = Value::Null;
+    let mut conditionals = Vec::new();
+    for (header, value) in headers.iter().zip(record.iter()) {
+        if value.is_empty() {
+            continue;
+        }
+        match header {
+            "account_id" => account_id = Some(AccountId::new(value.parse()?)),
+            "entry_id" => entry_id = Some(EntryId::new(value.into())?),
+            "additional_fields" => additional_fields = serde_json::from_str(value)?,
+            "conditionals" => conditionals = serde_json::from_str(value)?,
+            header => {
+                if let Some(field) = header.strip_prefix("ledger_fields.") {
+                    ledger_fields.insert(
+                        LedgerFieldName::new(field.into())?,
+                        value
+                            .parse()
+                            .with_context(|| format!("Invalid value for {header}"))?,
+                    );
+                }
+            }
+        }
+    }
+    Ok(EntryWithConditionals {
+        entry: Entry {
+            account_id: account_id.ok_or(anyhow!("Missing account_id"))?,
+            entry_id: entry_id.ok_or(anyhow!("Missing entry_id"))?,
+            ledger_fields,
+            additional_fields,
+            status: EntryStatus::Applied,
+            reversal: None,
+        },
+        conditionals,
+    })
+}
+
+pub struct ImportOptions {
+    /// Rows read and pushed at a time. An account keeps the order of its rows in the file.
+    pub batch_size: usize,
+    /// Accounts pushed at the same time.
+    pub concurrency: usize,
+    /// Only validate the rows, without pushing them.
+    pub dry_run: bool,
+    /// File where the report is saved after every batch, to resume the import from it.
+    pub checkpoint: Option<PathBuf>,
+    /// File where the entries that were not applied are written as JSON Lines.
+    pub rejected: Option<PathBuf>,
+}
+
+/// How many rows were imported, and what happened to them.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub rows: u64,
+    pub applied: u64,
+    /// The number of entries that were not applied by their error code.
+    pub non_applied: BTreeMap<u16, u64>,
+    /// Rows that could not be read. Only a dry run goes past the first one.
+    pub invalid: u64,
+}
+
+/// Pushes the rows of the file in batches. After each batch the report is saved to the
+/// checkpoint, so an interrupted import resumes from the first batch that wasn't finished.
+/// Entries of that batch that were already applied are reported as already existing.
+pub async fn run_import(
+    repository: &impl LedgerEntryRepository,
+    random_number_generator: impl Rng + Clone,
+    actor: &Actor,
+    mut reader: ImportReader,
+    options: &ImportOptions,
+) -> anyhow::Result<ImportReport> {
+    let checkpoint = options.checkpoint.as_deref().filter(|_| !options.dry_run);
+    let mut report = match checkpoint {
+        Some(path) if path.exists() => {
+            let report: ImportReport = serde_json::from_slice(&fs::read(path)?)
+                .with_context(|| format!("Error reading the checkpoint {path:?}"))?;
+            reader.skip_rows(report.rows)?;
+            tracing::info!("Resuming the import after {} rows", report.rows);
+            report
+        }
+        _ => ImportReport::default(),
+    };
+    let mut rejected = match options.rejected.as_deref().filter(|_| !options.dry_run) {
+        Some(path) => Some(BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(report.rows > 0)
+                .truncate(report.rows == 0)
+                .open(path)?,
+        )),
+        None => None,
+    };
+
+    loop {
+        let mut batch = Vec::with_capacity(options.batch_size);
+        let mut read = 0;
+        for entry in reader.by_ref().take(options.batch_size) {
+            read += 1;
+            let row = report.rows + report.invalid + read;
+            match entry {
+                Ok(entry) => batch.push(entry),
+                Err(err) if options.dry_run => {
+                    tracing::error!("Invalid row {row}: {err:#}");
+                    report.invalid += 1;
+                }
+                Err(err) => return Err(err.context(format!("Invalid row {row}"))),
+            }
+        }
+        if read == 0 {
+            break;
+        }
+        let rows = batch.len() as u64;
+        if options.dry_run {
+            report.rows += rows;
+            continue;
+        }
+
+        let (applied, non_applied) = import_entries_use_case(
+            repository,
+            random_number_generator.clone(),
+            actor,
+            batch,
+            options.concurrency,
+        )
+        .await;
+        report.rows += rows;
+        report.applied += applied.len() as u64;
+        for (reason, entry) in non_applied {
+            *report.non_applied.entry(reason.reason_code()).or_default() += 1;
+            if let Some(rejected) = rejected.as_mut() {
+                serde_json::to_writer(&mut *rejected, &NonAppliedEntry::from((reason, entry)))?;
+                rejected.write_all(b"\n")?;
+            }
+        }
+        if let Some(rejected) = rejected.as_mut() {
+            rejected.flush()?;
+        }
+        if let Some(path) = checkpoint {
+            save_checkpoint(path, &report)?;
+        }
+        tracing::info!("{} rows imported, {} applied", report.rows, report.applied);
+    }
+    Ok(report)
+}
+
+/// Replaces the checkpoint at once, so an interruption never leaves it half written.
+fn save_checkpoint(path: &Path, report: &ImportReport) -> anyhow::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, serde_json::to_vec(report)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
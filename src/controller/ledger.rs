@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::app::AppState;
+use crate::controller::JsonError;
+use crate::domain::entity::{Actor, Entry, EntryWithBalance, LedgerId, Principal};
+use crate::domain::gateway::TenantError;
+use crate::domain::use_case::{get_tenant_use_case, list_tenants_use_case, NonAppliedReason};
+use crate::gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository;
+use crate::gateway::tenant_repository::DynamoDbTenantRepository;
+use crate::gateway::Table;
+
+/// Header with the ledger of the request. Without it the request uses the default ledger.
+pub const LEDGER_ID_HEADER: &str = "x-ledger-id";
+
+/// Resolves the ledger of the request and checks that the principal can access it. The ledger
+/// is made available to the handlers as an extension.
+pub async fn ledger_middleware(
+    State(app_state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, JsonError<'static>> {
+    let ledger_id = request
+        .headers()
+        .get(LEDGER_ID_HEADER)
+        .map(|ledger_id| {
+            ledger_id
+                .to_str()
+                .ok()
+                .and_then(|ledger_id| LedgerId::new(ledger_id.into()).ok())
+                .ok_or(JsonError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid {} header", LEDGER_ID_HEADER).into(),
+                ))
+        })
+        .transpose()?;
+    let principal = request
+        .extensions()
+        .get::<Principal>()
+        .ok_or(JsonError::unauthorized())?;
+    if !principal.can_access_ledger(ledger_id.as_ref()) {
+        return Err(JsonError::forbidden());
+    }
+    if let Some(ledger_id) = ledger_id {
+        let repository = DynamoDbTenantRepository::from(app_state.table);
+        match get_tenant_use_case(&repository, &ledger_id).await {
+            Ok(_) => {}
+            Err(TenantError::NotFound(ledger_id)) => {
+                return Err(JsonError::not_found(
+                    format!("Ledger {} not found", ledger_id).into(),
+                ))
+            }
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        }
+        request.extensions_mut().insert(ledger_id);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Runs `post` for the default ledger and for every other ledger each `interval`, as `actor`,
+/// logging the entries it doesn't post and its errors. `name` is what the entries are called
+/// in the logs.
+pub async fn run_per_ledger<F, Fut, E>(
+    table: Table,
+    interval: Duration,
+    actor: Actor,
+    name: &str,
+    post: F,
+) where
+    F: Fn(DynamoDbLedgerEntryRepository, Actor) -> Fut,
+    Fut: Future<Output = Result<(Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>), E>>,
+    E: Display,
+{
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let ledger_ids = match all_ledger_ids(&table).await {
+            Ok(ledger_ids) => ledger_ids,
+            Err(err) => {
+                tracing::error!("Error listing the ledgers to post {}: {}", name, err);
+                continue;
+            }
+        };
+        for ledger_id in ledger_ids {
+            let repository = DynamoDbLedgerEntryRepository::new(table.clone(), ledger_id.as_ref());
+            match post(repository, actor.clone()).await {
+                Ok((applied, non_applied)) => {
+                    if !applied.is_empty() {
+                        tracing::info!("{} {} posted", applied.len(), name);
+                    }
+                    for (reason, entry) in non_applied {
+                        tracing::warn!(
+                            "Entry {} of account {} in {} not posted: {}",
+                            entry.entry_id,
+                            entry.account_id,
+                            name,
+                            reason.message()
+                        );
+                    }
+                }
+                Err(err) => tracing::error!("Error posting {}: {}", name, err),
+            }
+        }
+    }
+}
+
+/// The default ledger, as `None`, and every other ledger.
+async fn all_ledger_ids(table: &Table) -> anyhow::Result<Vec<Option<LedgerId>>> {
+    let tenants = list_tenants_use_case(&DynamoDbTenantRepository::from(table.clone())).await?;
+    Ok(std::iter::once(None)
+        .chain(tenants.into_iter().map(|tenant| Some(tenant.ledger_id)))
+        .collect())
+}
@@ -1,30 +1,60 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, fmt::Display};
 
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::entity::AccountId;
 use crate::domain::entity::LedgerBalanceName;
 use crate::domain::entity::LedgerFieldName;
-use crate::domain::entity::{EntryId, EntryStatus, EntryWithBalance};
-
+use crate::domain::entity::{AccountId, Actor};
+use crate::domain::entity::{EntryHash, EntryId, EntryStatus, EntryWithBalance, Reversal};
+use crate::domain::entity::{Period, PeriodClosing};
+
+pub mod accounts;
+pub mod accruals;
+pub mod amend_entries;
+pub mod attestation;
+pub mod auth;
+pub mod close_period;
 pub mod delete_entries;
+pub mod export;
+pub mod fee_rules;
+pub mod get_audit_records;
 pub mod get_balance;
 pub mod get_entries;
 pub mod get_entry;
+pub mod get_statement;
+pub mod get_summary;
+pub mod hierarchy;
+pub mod import;
+pub mod ledger;
+pub mod pending_entries;
 pub mod push_entries;
+pub mod reconciliation;
+pub mod recurring;
+pub mod velocity_limits;
+pub mod verify;
 
 #[derive(Serialize, Deserialize)]
 pub struct LedgerResponse {
     account_id: AccountId,
     entry_id: EntryId,
     ledger_balances: HashMap<LedgerBalanceName, i128>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pending_balances: HashMap<LedgerBalanceName, i128>,
     ledger_fields: HashMap<LedgerFieldName, i128>,
     additional_fields: Value,
     status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reversal: Option<Reversal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actor: Option<Actor>,
     created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev_hash: Option<EntryHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<EntryHash>,
 }
 
 impl From<EntryWithBalance> for LedgerResponse {
@@ -33,10 +63,34 @@ impl From<EntryWithBalance> for LedgerResponse {
             account_id: value.account_id,
             entry_id: value.entry_id,
             ledger_balances: value.ledger_balances,
+            pending_balances: value.pending_balances,
             ledger_fields: value.ledger_fields,
             additional_fields: value.additional_fields,
             status: value.status.into(),
+            reversal: value.reversal,
+            actor: value.actor,
             created_at: value.created_at,
+            prev_hash: value.prev_hash,
+            hash: value.hash,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PeriodClosingResponse {
+    account_id: AccountId,
+    period: Period,
+    ledger_balances: HashMap<LedgerBalanceName, i128>,
+    closed_at: DateTime<Utc>,
+}
+
+impl From<PeriodClosing> for PeriodClosingResponse {
+    fn from(value: PeriodClosing) -> Self {
+        PeriodClosingResponse {
+            account_id: value.account_id,
+            period: value.period,
+            ledger_balances: value.ledger_balances,
+            closed_at: value.closed_at,
         }
     }
 }
@@ -46,6 +100,24 @@ pub enum Status {
     Applied,
     Reverted,
     Revert,
+    Pending,
+    Posted,
+    Void,
+    Voided,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Applied => write!(f, "Applied"),
+            Self::Reverted => write!(f, "Reverted"),
+            Self::Revert => write!(f, "Revert"),
+            Self::Pending => write!(f, "Pending"),
+            Self::Posted => write!(f, "Posted"),
+            Self::Void => write!(f, "Void"),
+            Self::Voided => write!(f, "Voided"),
+        }
+    }
 }
 
 impl From<EntryStatus> for Status {
@@ -54,6 +126,10 @@ impl From<EntryStatus> for Status {
             EntryStatus::Applied => Status::Applied,
             EntryStatus::Reverted(_) => Status::Reverted,
             EntryStatus::Revert(_) => Status::Revert,
+            EntryStatus::Pending => Status::Pending,
+            EntryStatus::Posted(_) => Status::Posted,
+            EntryStatus::Void(_) => Status::Void,
+            EntryStatus::Voided(_) => Status::Voided,
         }
     }
 }
@@ -86,6 +162,14 @@ impl<'a> JsonError<'a> {
         Self::new(StatusCode::UNPROCESSABLE_ENTITY, message)
     }
 
+    pub fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "Unauthorized".into())
+    }
+
+    pub fn forbidden() -> Self {
+        Self::new(StatusCode::FORBIDDEN, "Forbidden".into())
+    }
+
     pub fn internal_server_error() -> Self {
         Self::new(
             StatusCode::INTERNAL_SERVER_ERROR,
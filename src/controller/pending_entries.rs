@@ -0,0 +1,85 @@
+use axum::{extract::State, Extension, Json};
+use serde::Serialize;
+
+use crate::domain::use_case::resolve_pending_entries_use_case;
+use crate::{
+    app::AppState,
+    domain::entity::{Actor, LedgerId, PendingEntryRequest, PendingResolution},
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+use super::LedgerResponse;
+
+pub async fn post_entries(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Json(pending_entries): Json<Vec<PendingEntryRequest>>,
+) -> Json<PendingEntryResponse> {
+    resolve_pending_entries(
+        app_state,
+        ledger_id,
+        actor,
+        PendingResolution::Post,
+        pending_entries,
+    )
+    .await
+}
+
+pub async fn void_entries(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Json(pending_entries): Json<Vec<PendingEntryRequest>>,
+) -> Json<PendingEntryResponse> {
+    resolve_pending_entries(
+        app_state,
+        ledger_id,
+        actor,
+        PendingResolution::Void,
+        pending_entries,
+    )
+    .await
+}
+
+async fn resolve_pending_entries(
+    app_state: AppState,
+    ledger_id: Option<Extension<LedgerId>>,
+    actor: Actor,
+    resolution: PendingResolution,
+    pending_entries: Vec<PendingEntryRequest>,
+) -> Json<PendingEntryResponse> {
+    let (applied, non_applied) = resolve_pending_entries_use_case(
+        &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
+        app_state.random_number_generator,
+        &actor,
+        resolution,
+        pending_entries.into_iter(),
+    )
+    .await;
+    let response = PendingEntryResponse {
+        applied_entries: applied.into_iter().map(|v| v.into()).collect(),
+        non_applied_entries: non_applied
+            .into_iter()
+            .map(|(reason, pending_entry_request)| NonAppliedPendingEntry {
+                error: reason.message(),
+                error_code: reason.reason_code(),
+                pending_entry_request,
+            })
+            .collect(),
+    };
+    Json(response)
+}
+
+#[derive(Serialize)]
+pub struct PendingEntryResponse {
+    applied_entries: Vec<LedgerResponse>,
+    non_applied_entries: Vec<NonAppliedPendingEntry>,
+}
+
+#[derive(Serialize)]
+struct NonAppliedPendingEntry {
+    error: String,
+    error_code: u16,
+    pending_entry_request: PendingEntryRequest,
+}
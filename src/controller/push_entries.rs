@@ -1,39 +1,31 @@
 use std::collections::HashMap;
 
-use axum::{extract::State, Json};
+use axum::{extract::State, Extension, Json};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::entity::LedgerFieldName;
-use crate::domain::entity::{AccountId, Conditional, EntryWithConditionals};
-use crate::domain::entity::{Entry, EntryId, EntryStatus};
-use crate::domain::use_case::push_entries_use_case;
+use crate::domain::entity::{AccountId, Actor, Conditional, EntryWithConditionals};
+use crate::domain::entity::{Entry, EntryId, EntryStatus, EntryWithBalance};
+use crate::domain::entity::{LedgerFieldName, LedgerId};
+use crate::domain::use_case::{push_entries_use_case, NonAppliedReason};
 use crate::{app::AppState, gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository};
 
 use super::LedgerResponse;
 
 pub async fn push_entries(
     State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
     Json(push_entries): Json<Vec<PushEntryRequest>>,
 ) -> Json<PushEntryResponse> {
     let (applied, non_applied) = push_entries_use_case(
-        &DynamoDbLedgerEntryRepository::from(app_state.dynamo_client),
+        &DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref()),
         app_state.random_number_generator,
+        &actor,
         push_entries.into_iter().map(|entry| entry.into()),
     )
     .await;
-    let response = PushEntryResponse {
-        applied_entries: applied.into_iter().map(|v| v.into()).collect(),
-        non_applied_entries: non_applied
-            .into_iter()
-            .map(|(reason, entry)| NonAppliedEntry {
-                error: reason.message(),
-                error_code: reason.reason_code(),
-                entry: entry.into(),
-            })
-            .collect(),
-    };
-    Json(response)
+    Json((applied, non_applied).into())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -43,6 +35,8 @@ pub struct PushEntryRequest {
     ledger_fields: HashMap<LedgerFieldName, i128>,
     additional_fields: Option<Value>,
     conditionals: Option<Vec<Conditional>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pending: bool,
 }
 
 #[derive(Serialize)]
@@ -50,13 +44,35 @@ pub struct PushEntryResponse {
     applied_entries: Vec<LedgerResponse>,
     non_applied_entries: Vec<NonAppliedEntry>,
 }
+
+impl From<(Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>)> for PushEntryResponse {
+    fn from(
+        (applied, non_applied): (Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>),
+    ) -> Self {
+        Self {
+            applied_entries: applied.into_iter().map(|v| v.into()).collect(),
+            non_applied_entries: non_applied.into_iter().map(|v| v.into()).collect(),
+        }
+    }
+}
+
 #[derive(Serialize)]
-struct NonAppliedEntry {
+pub(super) struct NonAppliedEntry {
     error: String,
     error_code: u16,
     entry: PushEntryRequest,
 }
 
+impl From<(NonAppliedReason, Entry)> for NonAppliedEntry {
+    fn from((reason, entry): (NonAppliedReason, Entry)) -> Self {
+        Self {
+            error: reason.message(),
+            error_code: reason.reason_code(),
+            entry: entry.into(),
+        }
+    }
+}
+
 impl From<PushEntryRequest> for EntryWithConditionals {
     fn from(value: PushEntryRequest) -> Self {
         Self {
@@ -65,7 +81,12 @@ impl From<PushEntryRequest> for EntryWithConditionals {
                 entry_id: value.entry_id,
                 ledger_fields: value.ledger_fields,
                 additional_fields: value.additional_fields.unwrap_or(Value::Null),
-                status: EntryStatus::Applied,
+                status: if value.pending {
+                    EntryStatus::Pending
+                } else {
+                    EntryStatus::Applied
+                },
+                reversal: None,
             },
             conditionals: value.conditionals.unwrap_or_default(),
         }
@@ -80,6 +101,7 @@ impl From<Entry> for PushEntryRequest {
             ledger_fields: value.ledger_fields,
             additional_fields: Some(value.additional_fields),
             conditionals: None,
+            pending: value.status == EntryStatus::Pending,
         }
     }
 }
@@ -0,0 +1,126 @@
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::domain::entity::{
+    AccountId, Actor, ExpectedEntry, LedgerFieldName, LedgerId, MatchKey, Reconciliation,
+    ReconciliationOptions,
+};
+use crate::domain::gateway::ReconciliationError;
+use crate::domain::use_case::{get_reconciliation_use_case, reconcile_use_case};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFileFormat {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+impl StatementFileFormat {
+    /// Reads the lines of a statement. JSON Lines have an object with the `reference`, `amount`
+    /// and `date` of each line, and CSV has a column for each of them. Other columns and keys
+    /// are ignored, and blank lines are skipped.
+    fn parse(&self, body: &[u8]) -> anyhow::Result<Vec<ExpectedEntry>> {
+        match self {
+            Self::Jsonl => std::str::from_utf8(body)?
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    serde_json::from_str(line)
+                        .with_context(|| format!("Invalid line {}", index + 1))
+                })
+                .collect(),
+            Self::Csv => {
+                let mut reader = csv::Reader::from_reader(body);
+                reader
+                    .deserialize()
+                    .enumerate()
+                    .map(|(index, line)| line.with_context(|| format!("Invalid row {}", index + 1)))
+                    .collect()
+            }
+        }
+    }
+}
+
+pub async fn reconcile(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<ReconcileParams>,
+    body: Bytes,
+) -> Result<Json<Reconciliation>, JsonError<'static>> {
+    if params.start_date >= params.end_date {
+        return Err(JsonError::unprocessable_entity(
+            "The `start_date` must be before the `end_date`".into(),
+        ));
+    }
+    let expected_entries = params
+        .format
+        .unwrap_or_default()
+        .parse(&body)
+        .map_err(|err| JsonError::unprocessable_entity(format!("{err:#}").into()))?;
+    let options = ReconciliationOptions {
+        ledger_field: params.ledger_field,
+        match_by: params.match_by.unwrap_or(MatchKey::EntryId),
+        amount_tolerance: params.amount_tolerance.unwrap_or_default(),
+        date_tolerance: params.date_tolerance.unwrap_or_default(),
+    };
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match reconcile_use_case(
+        &repository,
+        &actor,
+        &account_id,
+        &params.start_date,
+        &params.end_date,
+        options,
+        expected_entries,
+    )
+    .await
+    {
+        Ok(reconciliation) => Ok(Json(reconciliation)),
+        Err(err @ ReconciliationError::TooManyEntries(_)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_reconciliation(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path((account_id, run_id)): Path<(AccountId, String)>,
+) -> Result<Json<Reconciliation>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_reconciliation_use_case(&repository, &account_id, &run_id).await {
+        Ok(reconciliation) => Ok(Json(reconciliation)),
+        Err(err @ ReconciliationError::NotFound(_, _)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReconcileParams {
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    ledger_field: LedgerFieldName,
+    /// `entry_id` (default) or `additional_fields.{key}`.
+    match_by: Option<MatchKey>,
+    amount_tolerance: Option<u64>,
+    /// In days.
+    date_tolerance: Option<u32>,
+    format: Option<StatementFileFormat>,
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+use crate::controller::ledger::run_per_ledger;
+use crate::controller::push_entries::PushEntryResponse;
+use crate::domain::entity::{
+    Actor, LedgerId, Recurrence, RecurringEntry, RecurringSchedule, RecurringScheduleId,
+};
+use crate::domain::gateway::RecurringScheduleError;
+use crate::domain::use_case::{
+    delete_recurring_schedule_use_case, get_recurring_schedule_use_case,
+    get_recurring_schedules_use_case, pause_recurring_schedule_use_case,
+    post_recurring_entries_use_case, resume_recurring_schedule_use_case,
+    set_recurring_schedule_use_case,
+};
+use crate::gateway::Table;
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn set_recurring_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(schedule_id): Path<RecurringScheduleId>,
+    Json(request): Json<SetRecurringScheduleRequest>,
+) -> Result<Json<RecurringSchedule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match set_recurring_schedule_use_case(
+        &repository,
+        &actor,
+        &schedule_id,
+        request.recurrence,
+        request.starts_at,
+        request.ends_at,
+        request.max_occurrences,
+        request.entries,
+    )
+    .await
+    {
+        Ok(schedule) => Ok(Json(schedule)),
+        Err(err @ RecurringScheduleError::InvalidSchedule(_)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_recurring_schedules(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+) -> Result<Json<Vec<RecurringSchedule>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let schedules = get_recurring_schedules_use_case(&repository)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(schedules))
+}
+
+pub async fn get_recurring_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(schedule_id): Path<RecurringScheduleId>,
+) -> Result<Json<RecurringSchedule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match get_recurring_schedule_use_case(&repository, &schedule_id).await {
+        Ok(schedule) => Ok(Json(schedule)),
+        Err(err @ RecurringScheduleError::NotFound(_)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn pause_recurring_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(schedule_id): Path<RecurringScheduleId>,
+) -> Result<Json<RecurringSchedule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match pause_recurring_schedule_use_case(&repository, &schedule_id).await {
+        Ok(schedule) => Ok(Json(schedule)),
+        Err(err @ RecurringScheduleError::NotFound(_)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn resume_recurring_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(schedule_id): Path<RecurringScheduleId>,
+) -> Result<Json<RecurringSchedule>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match resume_recurring_schedule_use_case(&repository, &schedule_id).await {
+        Ok(schedule) => Ok(Json(schedule)),
+        Err(err @ RecurringScheduleError::NotFound(_)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn delete_recurring_schedule(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(schedule_id): Path<RecurringScheduleId>,
+) -> Result<(), JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    match delete_recurring_schedule_use_case(&repository, &schedule_id).await {
+        Ok(()) => Ok(()),
+        Err(err @ RecurringScheduleError::NotFound(_)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn post_recurring_entries(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+) -> Result<Json<PushEntryResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let result =
+        post_recurring_entries_use_case(&repository, app_state.random_number_generator, &actor)
+            .await
+            .map_err(anyhow::Error::from)?;
+    Ok(Json(result.into()))
+}
+
+/// Posts the due occurrences of the recurring schedules of the default ledger and of every
+/// other ledger each `interval`, as the `recurring` actor. Several servers can run it at the
+/// same time, as an occurrence is posted only once.
+pub async fn run_recurring_entries(table: Table, interval: Duration) {
+    let actor = Actor::new("recurring".into()).expect("A valid actor");
+    run_per_ledger(
+        table,
+        interval,
+        actor,
+        "recurring entries",
+        |repository, actor| async move {
+            post_recurring_entries_use_case(&repository, SmallRng::from_entropy(), &actor).await
+        },
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct SetRecurringScheduleRequest {
+    recurrence: Recurrence,
+    starts_at: DateTime<Utc>,
+    ends_at: Option<DateTime<Utc>>,
+    max_occurrences: Option<u32>,
+    entries: Vec<RecurringEntry>,
+}
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::domain::entity::{
+    AccountId, Actor, LedgerFieldName, LedgerId, VelocityDirection, VelocityLimit, VelocityLimitId,
+    VelocityUsage, VelocityWindow,
+};
+use crate::domain::gateway::VelocityLimitError;
+use crate::domain::use_case::{
+    delete_velocity_limit_use_case, get_velocity_limits_use_case, get_velocity_usage_use_case,
+    set_velocity_limit_use_case,
+};
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn set_default_velocity_limit(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path(limit_id): Path<VelocityLimitId>,
+    Json(request): Json<SetVelocityLimitRequest>,
+) -> Result<Json<VelocityLimit>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    set_velocity_limit(&repository, &actor, None, &limit_id, request).await
+}
+
+pub async fn set_account_velocity_limit(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Extension(actor): Extension<Actor>,
+    Path((account_id, limit_id)): Path<(AccountId, VelocityLimitId)>,
+    Json(request): Json<SetVelocityLimitRequest>,
+) -> Result<Json<VelocityLimit>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    set_velocity_limit(&repository, &actor, Some(&account_id), &limit_id, request).await
+}
+
+async fn set_velocity_limit(
+    repository: &DynamoDbLedgerEntryRepository,
+    actor: &Actor,
+    account_id: Option<&AccountId>,
+    limit_id: &VelocityLimitId,
+    request: SetVelocityLimitRequest,
+) -> Result<Json<VelocityLimit>, JsonError<'static>> {
+    match set_velocity_limit_use_case(
+        repository,
+        actor,
+        account_id,
+        limit_id,
+        request.ledger_field,
+        request.direction,
+        request.max_amount,
+        request.window,
+    )
+    .await
+    {
+        Ok(limit) => Ok(Json(limit)),
+        Err(err @ VelocityLimitError::InvalidLimit(_)) => {
+            Err(JsonError::unprocessable_entity(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+pub async fn get_velocity_limits(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+) -> Result<Json<Vec<VelocityLimit>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let limits = get_velocity_limits_use_case(&repository)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(limits))
+}
+
+pub async fn get_velocity_usage(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+) -> Result<Json<Vec<VelocityUsage>>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let usages = get_velocity_usage_use_case(&repository, &account_id)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(usages))
+}
+
+pub async fn delete_default_velocity_limit(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(limit_id): Path<VelocityLimitId>,
+) -> Result<(), JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    delete_velocity_limit(&repository, None, &limit_id).await
+}
+
+pub async fn delete_account_velocity_limit(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path((account_id, limit_id)): Path<(AccountId, VelocityLimitId)>,
+) -> Result<(), JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    delete_velocity_limit(&repository, Some(&account_id), &limit_id).await
+}
+
+async fn delete_velocity_limit(
+    repository: &DynamoDbLedgerEntryRepository,
+    account_id: Option<&AccountId>,
+    limit_id: &VelocityLimitId,
+) -> Result<(), JsonError<'static>> {
+    match delete_velocity_limit_use_case(repository, account_id, limit_id).await {
+        Ok(()) => Ok(()),
+        Err(err @ VelocityLimitError::NotFound(_)) => {
+            Err(JsonError::not_found(err.to_string().into()))
+        }
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetVelocityLimitRequest {
+    ledger_field: LedgerFieldName,
+    direction: VelocityDirection,
+    max_amount: i128,
+    window: VelocityWindow,
+}
@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, LedgerId, VerificationReport};
+use crate::domain::gateway::GetBalanceError;
+use crate::domain::use_case::verify_account_use_case;
+use crate::{
+    app::AppState, controller::JsonError,
+    gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository,
+};
+
+pub async fn verify_account(
+    State(app_state): State<AppState>,
+    ledger_id: Option<Extension<LedgerId>>,
+    Path(account_id): Path<AccountId>,
+    Query(params): Query<VerifyAccountParams>,
+) -> Result<Json<VerifyAccountResponse>, JsonError<'static>> {
+    let repository = DynamoDbLedgerEntryRepository::new(app_state.table, ledger_id.as_deref());
+    let segments = params.segments.unwrap_or(4);
+    if !(1..=64).contains(&segments) {
+        return Err(JsonError::unprocessable_entity(
+            "Segments must be between 1 and 64".into(),
+        ));
+    }
+    match verify_account_use_case(
+        &repository,
+        &account_id,
+        segments,
+        params.since_snapshot.unwrap_or(false),
+    )
+    .await
+    {
+        Ok(report) => Ok(Json(report.into())),
+        Err(GetBalanceError::NotFound(account_id)) => Err(JsonError::not_found(
+            format!("Account {} not found", account_id).into(),
+        )),
+        Err(e) => Err(anyhow::Error::from(e).into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VerifyAccountParams {
+    segments: Option<i32>,
+    since_snapshot: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct VerifyAccountResponse {
+    valid: bool,
+    #[serde(flatten)]
+    report: VerificationReport,
+}
+
+impl From<VerificationReport> for VerifyAccountResponse {
+    fn from(report: VerificationReport) -> Self {
+        Self {
+            valid: report.is_valid(),
+            report,
+        }
+    }
+}
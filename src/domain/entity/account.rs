@@ -0,0 +1,196 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, LedgerBalanceName};
+
+/// An account with entries, as listed from the HEAD of its balance.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Account {
+    pub account_id: AccountId,
+    pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    /// When its last entry was created.
+    pub last_activity_at: DateTime<Utc>,
+    pub tags: BTreeSet<AccountTag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct AccountTag(String);
+
+impl AccountTag {
+    pub fn new(value: String) -> anyhow::Result<Self> {
+        if value.is_empty() || value.len() > 64 {
+            bail!("Account tag must have between 1 and 64 characters");
+        }
+        if value.contains(',') {
+            bail!("Account tag cannot contain commas");
+        }
+        Ok(Self(value))
+    }
+}
+
+impl TryFrom<String> for AccountTag {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<AccountTag> for String {
+    fn from(value: AccountTag) -> String {
+        value.0
+    }
+}
+
+impl Display for AccountTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Filters of an account listing. An account is listed when it matches all of them.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct AccountQuery {
+    pub balances: Vec<BalanceFilter>,
+    pub active_after: Option<DateTime<Utc>>,
+    pub active_before: Option<DateTime<Utc>>,
+    pub tags: BTreeSet<AccountTag>,
+}
+
+/// A range of a balance, like `balance_usd_amount<0`. Accounts without the balance don't
+/// match it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct BalanceFilter {
+    pub ledger_balance_name: LedgerBalanceName,
+    pub operator: BalanceOperator,
+    pub value: i128,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BalanceOperator {
+    LessThan,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    GreaterThan,
+}
+
+impl BalanceOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LessThan => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterOrEqual => ">=",
+            Self::GreaterThan => ">",
+        }
+    }
+
+    pub fn compare(&self, left: i128, right: i128) -> bool {
+        match self {
+            Self::LessThan => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Equal => left == right,
+            Self::GreaterOrEqual => left >= right,
+            Self::GreaterThan => left > right,
+        }
+    }
+
+    /// Splits a comparison like `usd_amount<=0` in its left side, operator and right side.
+    pub fn split(value: &str) -> Option<(&str, Self, &str)> {
+        let (left, rest) = value.split_at(value.find(['<', '=', '>'])?);
+        // The longest operators first, so `<=` is not taken as `<`.
+        [
+            Self::LessOrEqual,
+            Self::GreaterOrEqual,
+            Self::LessThan,
+            Self::GreaterThan,
+            Self::Equal,
+        ]
+        .into_iter()
+        .find_map(|operator| {
+            rest.strip_prefix(operator.as_str())
+                .map(|right| (left, operator, right))
+        })
+    }
+}
+
+impl FromStr for BalanceFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (ledger_balance_name, operator, amount) = BalanceOperator::split(value)
+            .ok_or(anyhow!("Balance filter must be like balance_usd_amount<0"))?;
+        Ok(Self {
+            ledger_balance_name: LedgerBalanceName::new(ledger_balance_name.into())?,
+            operator,
+            value: amount
+                .parse()
+                .map_err(|_| anyhow!("Invalid amount in balance filter `{value}`"))?,
+        })
+    }
+}
+
+/// Where an account listing continues, after the last account returned.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccountCursor {
+    pub query: AccountQuery,
+    pub account_id: AccountId,
+}
+
+impl AccountCursor {
+    pub fn encode(&self) -> anyhow::Result<String> {
+        Ok(BASE64_STANDARD.encode(serde_json::to_string(&self)?))
+    }
+
+    pub fn decode(value: String) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&BASE64_STANDARD.decode(value)?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_balance_filters() -> anyhow::Result<()> {
+        let balance = LedgerBalanceName::new("balance_usd_amount".into())?;
+        for (value, operator, amount) in [
+            ("balance_usd_amount<0", BalanceOperator::LessThan, 0),
+            ("balance_usd_amount<=-10", BalanceOperator::LessOrEqual, -10),
+            ("balance_usd_amount=5", BalanceOperator::Equal, 5),
+            (
+                "balance_usd_amount>=100",
+                BalanceOperator::GreaterOrEqual,
+                100,
+            ),
+            ("balance_usd_amount>7", BalanceOperator::GreaterThan, 7),
+        ] {
+            assert_eq!(
+                value.parse::<BalanceFilter>()?,
+                BalanceFilter {
+                    ledger_balance_name: balance.clone(),
+                    operator,
+                    value: amount,
+                }
+            );
+        }
+        for value in [
+            "balance_usd_amount",
+            "usd_amount<0",
+            "balance_usd_amount<",
+            "balance_usd_amount=<0",
+        ] {
+            assert!(value.parse::<BalanceFilter>().is_err(), "{value}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::bail;
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::domain::entity::{
+    divide_rounded, AccountId, Actor, Entry, EntryId, EntryStatus, EntryWithBalance,
+    LedgerBalanceName, LedgerFieldName, Period,
+};
+
+/// The rates are in millionths, so `50_000` is 5% a year.
+const RATE_SCALE: i128 = 1_000_000;
+
+/// How the days of a period count towards a year.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DayCount {
+    #[serde(rename = "ACT/360")]
+    Act360,
+    #[serde(rename = "ACT/365")]
+    Act365,
+    #[serde(rename = "30/360")]
+    Thirty360,
+}
+
+impl DayCount {
+    /// The days `date` counts for. With 30/360 every month has 30 days, so the 31st counts
+    /// none and the last day of February counts the days left to the 30th.
+    fn days(&self, date: NaiveDate) -> i128 {
+        match self {
+            Self::Act360 | Self::Act365 => 1,
+            Self::Thirty360 => {
+                let is_last_day = date
+                    .succ_opt()
+                    .map(|next| next.month() != date.month())
+                    .unwrap_or(true);
+                match date.day() {
+                    31 => 0,
+                    day if date.month() == 2 && is_last_day => 30 - day as i128 + 1,
+                    _ => 1,
+                }
+            }
+        }
+    }
+
+    fn year_days(&self) -> i128 {
+        match self {
+            Self::Act360 | Self::Thirty360 => 360,
+            Self::Act365 => 365,
+        }
+    }
+}
+
+/// An annual rate that applies from `effective_from` until the next rate of the schedule.
+/// Negative rates accrue fees on positive balances.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccrualRate {
+    pub effective_from: NaiveDate,
+    /// In millionths, so `50_000` is 5%.
+    pub annual_rate_ppm: i64,
+}
+
+/// Accrues the balance of a ledger field of an account, and posts it monthly to the same
+/// field.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccrualSchedule {
+    pub account_id: AccountId,
+    pub ledger_field: LedgerFieldName,
+    pub day_count: DayCount,
+    /// Sorted by `effective_from`. Nothing accrues before the first one.
+    pub rates: Vec<AccrualRate>,
+    pub actor: Actor,
+    pub updated_at: DateTime<Utc>,
+    /// The last period whose accrual was posted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posted_until: Option<Period>,
+}
+
+impl AccrualSchedule {
+    pub fn new(
+        account_id: AccountId,
+        ledger_field: LedgerFieldName,
+        day_count: DayCount,
+        mut rates: Vec<AccrualRate>,
+        actor: Actor,
+        updated_at: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        if rates.is_empty() {
+            bail!("An accrual schedule needs at least one rate");
+        }
+        rates.sort_by_key(|rate| rate.effective_from);
+        if rates
+            .windows(2)
+            .any(|pair| pair[0].effective_from == pair[1].effective_from)
+        {
+            bail!("Two rates cannot be effective from the same date");
+        }
+        let schedule = Self {
+            account_id,
+            ledger_field,
+            day_count,
+            rates,
+            actor,
+            updated_at,
+            posted_until: None,
+        };
+        // The entry ids of the postings must be valid for every period.
+        EntryId::new(schedule.entry_id(&Period::new(2000, 1)?).to_string())?;
+        Ok(schedule)
+    }
+
+    /// The id of the entry that posts the accrual of the period. Posting it again fails
+    /// because the entry already exists, so a period is never posted twice.
+    pub fn entry_id(&self, period: &Period) -> EntryId {
+        EntryId::new_unchecked(format!(
+            "accrual-{}-{}",
+            period,
+            String::from(self.ledger_field.clone())
+        ))
+    }
+
+    fn annual_rate_ppm(&self, date: NaiveDate) -> i64 {
+        self.rates
+            .iter()
+            .take_while(|rate| rate.effective_from <= date)
+            .last()
+            .map(|rate| rate.annual_rate_ppm)
+            .unwrap_or_default()
+    }
+
+    /// The periods after `posted_until`, or from the first rate, that finished by `now`.
+    pub fn periods_to_post(&self, now: &DateTime<Utc>) -> Vec<Period> {
+        let mut period = match self.posted_until {
+            Some(period) => period.next(),
+            None => Period::from(
+                &self.rates[0]
+                    .effective_from
+                    .and_time(Default::default())
+                    .and_utc(),
+            ),
+        };
+        let mut periods = Vec::new();
+        while period.end() <= *now {
+            periods.push(period);
+            period = period.next();
+        }
+        periods
+    }
+}
+
+/// The balance of a day and the rate it accrued at.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccrualDay {
+    pub date: NaiveDate,
+    /// The balance at the end of the day.
+    pub balance: i128,
+    pub annual_rate_ppm: i64,
+}
+
+/// What a schedule accrued in a period.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Accrual {
+    pub account_id: AccountId,
+    pub ledger_field: LedgerFieldName,
+    pub period: Period,
+    pub day_count: DayCount,
+    pub entry_id: EntryId,
+    /// The sum of the daily accruals, rounded half away from zero once for the period.
+    pub amount: i128,
+    pub days: Vec<AccrualDay>,
+}
+
+impl Accrual {
+    /// Accrues the end of day balances of the period. The `opening_balance` is the balance of
+    /// the field at the start of the period, and the `entries` the ones created in it in
+    /// sequence order.
+    pub fn new(
+        schedule: &AccrualSchedule,
+        period: &Period,
+        opening_balance: i128,
+        entries: &[EntryWithBalance],
+    ) -> Self {
+        let balance_name = LedgerBalanceName::from(schedule.ledger_field.clone());
+        let mut entries = entries
+            .iter()
+            .filter(|entry| entry.ledger_fields.contains_key(&schedule.ledger_field))
+            .peekable();
+        let mut balance = opening_balance;
+        let mut days = Vec::new();
+        let mut accrued: i128 = 0;
+        let mut date = period.start().date_naive();
+        let end = period.end().date_naive();
+        while date < end {
+            let next = date + Days::new(1);
+            let end_of_day = next.and_time(Default::default()).and_utc();
+            while let Some(entry) = entries.next_if(|entry| entry.created_at < end_of_day) {
+                if let Some(entry_balance) = entry.ledger_balances.get(&balance_name) {
+                    balance = *entry_balance;
+                }
+            }
+            let annual_rate_ppm = schedule.annual_rate_ppm(date);
+            accrued += balance * annual_rate_ppm as i128 * schedule.day_count.days(date);
+            days.push(AccrualDay {
+                date,
+                balance,
+                annual_rate_ppm,
+            });
+            date = next;
+        }
+        Self {
+            account_id: schedule.account_id.clone(),
+            ledger_field: schedule.ledger_field.clone(),
+            period: *period,
+            day_count: schedule.day_count,
+            entry_id: schedule.entry_id(period),
+            amount: divide_rounded(accrued, RATE_SCALE * schedule.day_count.year_days()),
+            days,
+        }
+    }
+
+    /// The entry that posts the accrued amount.
+    pub fn entry(&self) -> Entry {
+        Entry {
+            account_id: self.account_id.clone(),
+            entry_id: self.entry_id.clone(),
+            ledger_fields: [(self.ledger_field.clone(), self.amount)].into(),
+            additional_fields: json!({
+                "accrual": {
+                    "period": self.period,
+                    "day_count": self.day_count,
+                }
+            }),
+            status: EntryStatus::Applied,
+            reversal: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::entity::{EntryBuilder, EntryWithBalanceBuilder};
+
+    use super::*;
+
+    fn new_schedule(day_count: DayCount, rates: &[(&str, i64)]) -> AccrualSchedule {
+        AccrualSchedule::new(
+            AccountId::new(Default::default()),
+            LedgerFieldName::new("amount".into()).expect("A valid field"),
+            day_count,
+            rates
+                .iter()
+                .map(|(effective_from, annual_rate_ppm)| AccrualRate {
+                    effective_from: effective_from.parse().expect("A valid date"),
+                    annual_rate_ppm: *annual_rate_ppm,
+                })
+                .collect(),
+            Actor::new("test".into()).expect("A valid actor"),
+            Utc::now(),
+        )
+        .expect("A valid schedule")
+    }
+
+    fn entry(amount: i128, balance: i128, created_at: &str) -> EntryWithBalance {
+        let mut entry = EntryWithBalanceBuilder::from_entry(
+            EntryBuilder::new()
+                .with_ledger_field("amount", amount)
+                .build(),
+        )
+        .with_ledger_balance("balance_amount", balance)
+        .build();
+        entry.created_at = created_at.parse().expect("A valid date");
+        entry
+    }
+
+    #[test]
+    fn thirty_360_counts_30_days_a_month() -> anyhow::Result<()> {
+        for (year, month) in [(2023, 2), (2024, 2), (2024, 3), (2024, 4)] {
+            let period = Period::new(year, month)?;
+            let mut date = period.start().date_naive();
+            let mut days = 0;
+            while date < period.end().date_naive() {
+                days += DayCount::Thirty360.days(date);
+                date = date + Days::new(1);
+            }
+            assert_eq!(days, 30, "{period}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn accrue_end_of_day_balances() -> anyhow::Result<()> {
+        // 1_000_000 at 3.6% for 10 days, then 2_000_000 for 20 days.
+        let schedule = new_schedule(DayCount::Act360, &[("2024-01-01", 36_000)]);
+        let accrual = Accrual::new(
+            &schedule,
+            &"2024-06".parse()?,
+            1_000_000,
+            &[entry(1_000_000, 2_000_000, "2024-06-11 08:00:00 UTC")],
+        );
+        assert_eq!(accrual.days.len(), 30);
+        assert_eq!(accrual.days[9].balance, 1_000_000);
+        assert_eq!(accrual.days[10].balance, 2_000_000);
+        assert_eq!(accrual.amount, 1_000 + 4_000);
+        assert_eq!(accrual.entry_id.to_string(), "accrual-2024-06-amount");
+        assert_eq!(accrual.entry().ledger_fields.values().sum::<i128>(), 5_000);
+
+        // The rate changes on the 16th, and nothing accrued before the first rate.
+        let schedule = new_schedule(
+            DayCount::Act365,
+            &[("2024-06-06", 73_000), ("2024-06-16", -36_500)],
+        );
+        let accrual = Accrual::new(&schedule, &"2024-06".parse()?, 1_000_000, &[]);
+        assert_eq!(accrual.days[4].annual_rate_ppm, 0);
+        assert_eq!(accrual.days[15].annual_rate_ppm, -36_500);
+        assert_eq!(accrual.amount, 2_000 - 1_500);
+
+        let schedule = new_schedule(DayCount::Thirty360, &[("2024-01-01", 120_000)]);
+        let accrual = Accrual::new(&schedule, &"2024-02".parse()?, 100, &[]);
+        assert_eq!(accrual.amount, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn periods_to_post() -> anyhow::Result<()> {
+        let mut schedule = new_schedule(DayCount::Act360, &[("2024-05-20", 10_000)]);
+        let now = "2024-08-01 00:00:00 UTC".parse()?;
+        assert_eq!(
+            schedule.periods_to_post(&now),
+            vec!["2024-05".parse()?, "2024-06".parse()?, "2024-07".parse()?]
+        );
+        schedule.posted_until = Some("2024-06".parse()?);
+        assert_eq!(schedule.periods_to_post(&now), vec!["2024-07".parse()?]);
+        schedule.posted_until = Some("2024-07".parse()?);
+        assert!(schedule.periods_to_post(&now).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        let schedule_with = |rates: Vec<AccrualRate>| {
+            AccrualSchedule::new(
+                AccountId::new(Default::default()),
+                LedgerFieldName::new("amount".into()).expect("A valid field"),
+                DayCount::Act360,
+                rates,
+                Actor::new("test".into()).expect("A valid actor"),
+                Utc::now(),
+            )
+        };
+        let rate = AccrualRate {
+            effective_from: "2024-01-01".parse().expect("A valid date"),
+            annual_rate_ppm: 10_000,
+        };
+        assert!(schedule_with(vec![]).is_err());
+        assert!(schedule_with(vec![rate.clone(), rate.clone()]).is_err());
+        assert!(schedule_with(vec![rate]).is_ok());
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+/// The authenticated principal behind a request.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String")]
+pub struct Actor(String);
+
+impl Actor {
+    pub fn new(actor: String) -> anyhow::Result<Self> {
+        if actor.is_empty() {
+            bail!("Actor cannot be empty")
+        }
+        if actor.contains('|') {
+            bail!("Actor cannot contains the `|` char")
+        }
+        if actor.len() > 128 {
+            bail!("Actor cannot be longer the 128 characters")
+        }
+        Ok(Self(actor))
+    }
+
+    /// Used when the server runs without authentication.
+    pub fn anonymous() -> Self {
+        Self("anonymous".into())
+    }
+}
+
+impl Display for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<String> for Actor {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Actor::new(value)
+    }
+}
+
+impl From<Actor> for String {
+    fn from(value: Actor) -> String {
+        value.0
+    }
+}
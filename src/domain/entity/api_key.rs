@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, Actor, LedgerId};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Push,
+    Delete,
+    /// Closing periods, the audit log and everything else.
+    Admin,
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Push => write!(f, "push"),
+            Self::Delete => write!(f, "delete"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "read" => Ok(Self::Read),
+            "push" => Ok(Self::Push),
+            "delete" => Ok(Self::Delete),
+            "admin" => Ok(Self::Admin),
+            _ => bail!("Scope must be one of read, push, delete or admin"),
+        }
+    }
+}
+
+/// The accounts a principal can access. Without ids and prefixes every account is allowed.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct AccountScope {
+    #[serde(default)]
+    pub account_ids: HashSet<AccountId>,
+    #[serde(default)]
+    pub account_prefixes: Vec<String>,
+}
+
+impl AccountScope {
+    pub fn is_unrestricted(&self) -> bool {
+        self.account_ids.is_empty() && self.account_prefixes.is_empty()
+    }
+
+    pub fn allows(&self, account_id: &AccountId) -> bool {
+        if self.is_unrestricted() || self.account_ids.contains(account_id) {
+            return true;
+        }
+        let account_id = account_id.to_string();
+        self.account_prefixes
+            .iter()
+            .any(|prefix| account_id.starts_with(prefix))
+    }
+}
+
+/// Who is making a request and what they are allowed to do.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Principal {
+    pub actor: Actor,
+    pub scopes: HashSet<Scope>,
+    pub accounts: AccountScope,
+    /// The only ledger the principal can access. Without it every ledger is allowed.
+    pub ledger_id: Option<LedgerId>,
+}
+
+impl Principal {
+    /// Used when the server runs without authentication.
+    pub fn anonymous() -> Self {
+        Self {
+            actor: Actor::anonymous(),
+            scopes: HashSet::from([Scope::Admin]),
+            accounts: AccountScope::default(),
+            ledger_id: None,
+        }
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+
+    /// `None` is the default ledger.
+    pub fn can_access_ledger(&self, ledger_id: Option<&LedgerId>) -> bool {
+        self.ledger_id.is_none() || self.ledger_id.as_ref() == ledger_id
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ApiKey {
+    pub key_id: String,
+    pub secret: String,
+    pub actor: Actor,
+    pub scopes: HashSet<Scope>,
+    pub accounts: AccountScope,
+    pub ledger_id: Option<LedgerId>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for Principal {
+    fn from(value: ApiKey) -> Self {
+        Self {
+            actor: value.actor,
+            scopes: value.scopes,
+            accounts: value.accounts,
+            ledger_id: value.ledger_id,
+        }
+    }
+}
+
+/// How a request proves that it knows the secret of an API key.
+pub enum ApiKeyCredential {
+    Secret(String),
+    /// HEX encoded HMAC-SHA256 of `string_to_sign`, signed at `signed_at`.
+    Signature {
+        signature: String,
+        string_to_sign: String,
+        signed_at: DateTime<Utc>,
+    },
+}
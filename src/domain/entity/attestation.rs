@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, EntryHash, LedgerBalanceName, LedgerId};
+
+/// The balance of an account after one of its entries, with the hash of that entry, so it
+/// can be checked against the hash chain of the account.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Attestation {
+    pub account_id: AccountId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_id: Option<LedgerId>,
+    pub ledger_balances: BTreeMap<LedgerBalanceName, i128>,
+    pub sequence: u64,
+    /// None when the entry was appended before entries were hashed.
+    pub hash: Option<EntryHash>,
+    /// The balance is the one after the last entry created up to this moment.
+    pub as_of: DateTime<Utc>,
+    pub attested_at: DateTime<Utc>,
+}
+
+/// An attestation signed with Ed25519. The signature is over the exact bytes of `payload`,
+/// the attestation as JSON, so it can be verified without serializing it again.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct SignedAttestation {
+    pub payload: String,
+    /// Hex encoded.
+    pub signature: String,
+}
+
+impl SignedAttestation {
+    pub fn sign(attestation: &Attestation, signing_key: &SigningKey) -> anyhow::Result<Self> {
+        let payload = serde_json::to_string(attestation)?;
+        let signature = signing_key.sign(payload.as_bytes());
+        Ok(Self {
+            payload,
+            signature: hex::encode(signature.to_bytes()),
+        })
+    }
+
+    /// The attestation, if it was signed by the key.
+    pub fn verify(&self, verifying_key: &VerifyingKey) -> anyhow::Result<Attestation> {
+        let signature: [u8; Signature::BYTE_SIZE] = hex::decode(&self.signature)
+            .context("The signature is not hex encoded")?
+            .try_into()
+            .map_err(|_| anyhow!("The signature must have {} bytes", Signature::BYTE_SIZE))?;
+        verifying_key
+            .verify(self.payload.as_bytes(), &Signature::from_bytes(&signature))
+            .context("Invalid signature")?;
+        Ok(serde_json::from_str(&self.payload)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::OsRng;
+
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    fn attestation() -> Attestation {
+        Attestation {
+            account_id: AccountId::new(Default::default()),
+            ledger_id: None,
+            ledger_balances: BTreeMap::from([(
+                LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                i128::MAX,
+            )]),
+            sequence: 3,
+            hash: Some(EntryHash::new_unchecked("abc".into())),
+            as_of: utc_now(),
+            attested_at: utc_now(),
+        }
+    }
+
+    #[test]
+    fn verify_signed_attestation() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let signed = SignedAttestation::sign(&attestation(), &signing_key).expect("Signed");
+        assert_eq!(
+            signed
+                .verify(&signing_key.verifying_key())
+                .expect("A valid signature"),
+            attestation()
+        );
+    }
+
+    #[test]
+    fn verify_tampered_attestation() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let mut signed = SignedAttestation::sign(&attestation(), &signing_key).expect("Signed");
+        signed.payload = signed.payload.replace("\"sequence\":3", "\"sequence\":4");
+        assert!(signed.verify(&signing_key.verifying_key()).is_err());
+        let other_key = SigningKey::generate(&mut OsRng);
+        let signed = SignedAttestation::sign(&attestation(), &signing_key).expect("Signed");
+        assert!(signed.verify(&other_key.verifying_key()).is_err());
+    }
+}
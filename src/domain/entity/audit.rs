@@ -0,0 +1,58 @@
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, Actor, EntryId};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    PushEntries,
+    RevertEntries,
+    AmendEntries,
+    ClosePeriod,
+    PostEntries,
+    VoidEntries,
+}
+
+/// One append-only record per change made to an account, written in the same transaction as the
+/// change itself.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AuditRecord {
+    pub account_id: AccountId,
+    pub actor: Actor,
+    pub action: AuditAction,
+    pub entry_ids: Vec<EntryId>,
+    pub sequence: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AuditQuery {
+    pub account_id: Option<AccountId>,
+    pub actor: Option<Actor>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Where to continue an audit query. The next page starts right after the record of `account_id`
+/// with this `created_at` and `sequence`, so records of other accounts with the same `created_at`
+/// are not skipped when querying by actor.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AuditCursor {
+    pub query: AuditQuery,
+    pub account_id: AccountId,
+    pub created_at: DateTime<Utc>,
+    pub sequence: u64,
+}
+
+impl AuditCursor {
+    pub fn encode(&self) -> anyhow::Result<String> {
+        Ok(BASE64_STANDARD.encode(serde_json::to_string(&self)?))
+    }
+
+    pub fn decode(value: String) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&BASE64_STANDARD.decode(value)?)?)
+    }
+}
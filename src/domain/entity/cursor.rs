@@ -56,3 +56,10 @@ impl Cursor {
         }
     }
 }
+
+/// Where a segment of a parallel scan continues, the keys of the last row read.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ScanCursor {
+    pub pk: String,
+    pub sk: String,
+}
@@ -1,13 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 
 use anyhow::bail;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 
 use crate::domain::entity::conditional::Conditional;
-use crate::domain::entity::{AccountId, LedgerBalanceName, LedgerFieldName};
+use crate::domain::entity::{AccountId, Actor, LedgerBalanceName, LedgerFieldName};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
 #[serde(try_from = "String")]
@@ -50,6 +51,7 @@ pub struct Entry {
     pub ledger_fields: HashMap<LedgerFieldName, i128>,
     pub additional_fields: Value,
     pub status: EntryStatus,
+    pub reversal: Option<Reversal>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -82,6 +84,58 @@ pub enum EntryStatus {
     Applied,
     Reverted(u64),
     Revert(u64),
+    /// Counted in the pending balances, and not in the ledger balances, until it is posted or
+    /// voided.
+    Pending,
+    /// A pending entry that was posted by the Applied entry with this sequence.
+    Posted(u64),
+    /// Takes the pending entry with this sequence out of the pending balances.
+    Void(u64),
+    /// A pending entry that was voided by the Void entry with this sequence.
+    Voided(u64),
+}
+
+impl EntryStatus {
+    /// Whether the entry is counted in the pending balances instead of the ledger balances.
+    pub fn is_pending(&self) -> bool {
+        match self {
+            Self::Applied | Self::Reverted(_) | Self::Revert(_) => false,
+            Self::Pending | Self::Posted(_) | Self::Void(_) | Self::Voided(_) => true,
+        }
+    }
+}
+
+/// What is done with a pending entry: posting moves it to the ledger balances at the time it
+/// is posted, and voiding drops it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingResolution {
+    Post,
+    Void,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReversalReason {
+    #[default]
+    Unspecified,
+    Duplicate,
+    WrongAmount,
+    Fraud,
+    Chargeback,
+    CustomerRequest,
+    Amendment,
+    Other,
+}
+
+/// Why an entry was reverted. It is kept on the Revert and Reverted rows, whose `actor` is who
+/// reverted it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Reversal {
+    #[serde(default)]
+    pub reason: ReversalReason,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub metadata: Value,
 }
 
 impl From<EntryWithBalance> for Entry {
@@ -92,6 +146,7 @@ impl From<EntryWithBalance> for Entry {
             ledger_fields: value.ledger_fields,
             additional_fields: value.additional_fields,
             status: value.status,
+            reversal: value.reversal,
         }
     }
 }
@@ -101,11 +156,79 @@ pub struct EntryWithBalance {
     pub account_id: AccountId,
     pub entry_id: EntryId,
     pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    /// The balances of the pending entries that were not posted or voided yet, without the
+    /// ones that are zero.
+    pub pending_balances: HashMap<LedgerBalanceName, i128>,
     pub ledger_fields: HashMap<LedgerFieldName, i128>,
     pub additional_fields: Value,
     pub status: EntryStatus,
+    pub reversal: Option<Reversal>,
+    pub actor: Option<Actor>,
     pub sequence: u64,
     pub created_at: DateTime<Utc>,
+    /// Hash of the previous entry of the account. None for the first entry, or when the
+    /// previous entry was appended before entries were hashed.
+    pub prev_hash: Option<EntryHash>,
+    /// None for entries appended before entries were hashed.
+    pub hash: Option<EntryHash>,
+}
+
+/// Hex encoded SHA-256 that chains the entries of an account, so a row edited out-of-band
+/// doesn't match its hash or the `prev_hash` of the next entry.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+#[serde(transparent)]
+pub struct EntryHash(String);
+
+impl Display for EntryHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl EntryHash {
+    pub fn new_unchecked(hash: String) -> Self {
+        Self(hash)
+    }
+}
+
+#[derive(Serialize)]
+struct CanonicalEntry<'a> {
+    account_id: &'a AccountId,
+    entry_id: &'a EntryId,
+    sequence: u64,
+    ledger_fields: BTreeMap<&'a LedgerFieldName, i128>,
+    ledger_balances: BTreeMap<&'a LedgerBalanceName, i128>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pending_balances: BTreeMap<&'a LedgerBalanceName, i128>,
+    status: &'a EntryStatus,
+    created_at: &'a DateTime<Utc>,
+    prev_hash: Option<&'a EntryHash>,
+}
+
+impl EntryWithBalance {
+    /// Hash of the entry as it was appended. A Reverted entry is hashed as Applied, and a
+    /// Posted or Voided one as Pending, because what happened to them is recorded by the entry
+    /// that comes later in the chain.
+    pub fn compute_hash(&self) -> EntryHash {
+        let status = match self.status {
+            EntryStatus::Reverted(_) => &EntryStatus::Applied,
+            EntryStatus::Posted(_) | EntryStatus::Voided(_) => &EntryStatus::Pending,
+            ref status => status,
+        };
+        let canonical = CanonicalEntry {
+            account_id: &self.account_id,
+            entry_id: &self.entry_id,
+            sequence: self.sequence,
+            ledger_fields: self.ledger_fields.iter().map(|(k, v)| (k, *v)).collect(),
+            ledger_balances: self.ledger_balances.iter().map(|(k, v)| (k, *v)).collect(),
+            pending_balances: self.pending_balances.iter().map(|(k, v)| (k, *v)).collect(),
+            status,
+            created_at: &self.created_at,
+            prev_hash: self.prev_hash.as_ref(),
+        };
+        let bytes = serde_json::to_vec(&canonical).expect("The canonical entry is always valid");
+        EntryHash(hex::encode(Sha256::digest(bytes)))
+    }
 }
 
 #[cfg(test)]
@@ -117,8 +240,9 @@ pub mod test {
     use serde_json::Value::Null;
     use uuid::Uuid;
 
+    use crate::app::test::get_actor;
     use crate::domain::entity::{
-        AccountId, Entry, EntryId, EntryStatus, EntryWithBalance, LedgerBalanceName,
+        AccountId, Entry, EntryHash, EntryId, EntryStatus, EntryWithBalance, LedgerBalanceName,
         LedgerFieldName,
     };
     use crate::utils::utc_now;
@@ -136,6 +260,7 @@ pub mod test {
                     ledger_fields: HashMap::new(),
                     additional_fields: Null,
                     status: EntryStatus::Applied,
+                    reversal: None,
                 },
             }
         }
@@ -145,6 +270,11 @@ pub mod test {
             self
         }
 
+        pub fn with_entry_id(mut self, entry_id: EntryId) -> Self {
+            self.entry.entry_id = entry_id;
+            self
+        }
+
         pub fn with_ledger_field(mut self, key: impl Into<String>, value: i128) -> Self {
             self.entry.ledger_fields.insert(
                 LedgerFieldName::new(key.into()).expect("Error with ledger field name"),
@@ -153,6 +283,11 @@ pub mod test {
             self
         }
 
+        pub fn with_additional_fields(mut self, additional_fields: serde_json::Value) -> Self {
+            self.entry.additional_fields = additional_fields;
+            self
+        }
+
         pub fn build(self) -> Entry {
             self.entry
         }
@@ -160,6 +295,7 @@ pub mod test {
 
     thread_local! {
         pub static SEQUENCE_FAKE: RefCell<HashMap<AccountId, u64>> = RefCell::new(HashMap::new()) ;
+        pub static HASH_FAKE: RefCell<HashMap<AccountId, EntryHash>> = RefCell::new(HashMap::new()) ;
     }
 
     pub struct EntryWithBalanceBuilder {
@@ -169,10 +305,9 @@ pub mod test {
     impl EntryWithBalanceBuilder {
         pub fn from_entry(entry: Entry) -> Self {
             let sequence = SEQUENCE_FAKE.with_borrow_mut(|v| {
-                v.entry(entry.account_id.clone())
+                *v.entry(entry.account_id.clone())
                     .and_modify(|sequence| *sequence += 1)
                     .or_insert(0)
-                    .clone()
             });
             Self {
                 entry: EntryWithBalance {
@@ -181,9 +316,14 @@ pub mod test {
                     ledger_fields: entry.ledger_fields,
                     additional_fields: entry.additional_fields,
                     ledger_balances: HashMap::new(),
+                    pending_balances: HashMap::new(),
                     status: entry.status,
+                    reversal: entry.reversal,
+                    actor: Some(get_actor()),
                     sequence,
                     created_at: utc_now(),
+                    prev_hash: None,
+                    hash: None,
                 },
             }
         }
@@ -196,7 +336,13 @@ pub mod test {
             self
         }
 
-        pub fn build(self) -> EntryWithBalance {
+        pub fn build(mut self) -> EntryWithBalance {
+            HASH_FAKE.with_borrow_mut(|v| {
+                self.entry.prev_hash = v.get(&self.entry.account_id).cloned();
+                let hash = self.entry.compute_hash();
+                v.insert(self.entry.account_id.clone(), hash.clone());
+                self.entry.hash = Some(hash);
+            });
             self.entry
         }
     }
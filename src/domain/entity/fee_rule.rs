@@ -0,0 +1,520 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::domain::entity::{
+    divide_rounded, AccountId, Actor, BalanceOperator, Entry, EntryId, EntryStatus,
+    EntryWithConditionals, LedgerFieldName,
+};
+
+/// Entries a rule can derive from each entry it matches.
+pub const MAX_FEE_RULE_ENTRIES: usize = 4;
+
+/// Entries all the rules of a ledger can derive from each entry, so that an entry fits in a
+/// transaction with the entries derived from it, and the HEADs and velocity counters of their
+/// accounts.
+pub const MAX_DERIVED_ENTRIES: usize = 7;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct FeeRuleId(String);
+
+impl FeeRuleId {
+    /// It is part of the `entry_id` of the derived entries, so it is kept short.
+    pub fn new(value: String) -> anyhow::Result<Self> {
+        if value.is_empty() || value.len() > 24 {
+            bail!("Rule id must have between 1 and 24 characters");
+        }
+        if !value
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+        {
+            bail!("Rule id can only have letters, digits, `-` and `_`");
+        }
+        Ok(Self(value))
+    }
+}
+
+impl TryFrom<String> for FeeRuleId {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<FeeRuleId> for String {
+    fn from(value: FeeRuleId) -> String {
+        value.0
+    }
+}
+
+impl Display for FeeRuleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A condition on a pushed entry, like `usd_amount<0` on a ledger field, or
+/// `additional_fields.type=card_purchase` on a top level key of the `additional_fields`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub enum RulePredicate {
+    LedgerField {
+        ledger_field: LedgerFieldName,
+        operator: BalanceOperator,
+        value: i128,
+    },
+    /// Numbers are compared by their text.
+    AdditionalField { key: String, value: String },
+}
+
+impl RulePredicate {
+    /// Entries without the ledger field or the key don't match.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Self::LedgerField {
+                ledger_field,
+                operator,
+                value,
+            } => entry
+                .ledger_fields
+                .get(ledger_field)
+                .is_some_and(|amount| operator.compare(*amount, *value)),
+            Self::AdditionalField { key, value } => match entry.additional_fields.get(key) {
+                Some(Value::String(field)) => field == value,
+                Some(Value::Number(field)) => field.to_string() == *value,
+                Some(Value::Bool(field)) => field.to_string() == *value,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl FromStr for RulePredicate {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (left, operator, right) = BalanceOperator::split(value).ok_or(anyhow!(
+            "Rule predicate must be like usd_amount<0 or additional_fields.type=card_purchase"
+        ))?;
+        if let Some(key) = left.strip_prefix("additional_fields.") {
+            if key.is_empty() || operator != BalanceOperator::Equal {
+                bail!("Additional fields can only be compared with `=`, like additional_fields.type=card_purchase");
+            }
+            return Ok(Self::AdditionalField {
+                key: key.into(),
+                value: right.into(),
+            });
+        }
+        Ok(Self::LedgerField {
+            ledger_field: LedgerFieldName::new(left.into())?,
+            operator,
+            value: right
+                .parse()
+                .map_err(|_| anyhow!("Invalid amount in rule predicate `{value}`"))?,
+        })
+    }
+}
+
+impl TryFrom<String> for RulePredicate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RulePredicate> for String {
+    fn from(value: RulePredicate) -> String {
+        value.to_string()
+    }
+}
+
+impl Display for RulePredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LedgerField {
+                ledger_field,
+                operator,
+                value,
+            } => write!(
+                f,
+                "{}{}{}",
+                String::from(ledger_field.clone()),
+                operator.as_str(),
+                value
+            ),
+            Self::AdditionalField { key, value } => {
+                write!(f, "additional_fields.{}={}", key, value)
+            }
+        }
+    }
+}
+
+/// A band of a tiered fee, for amounts up to `up_to`. The last one has no limit.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct FeeTier {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_to: Option<i128>,
+    #[serde(default)]
+    pub fixed: i128,
+    /// In millionths of the amount, so `15_000` is 1.5%.
+    #[serde(default)]
+    pub rate_ppm: i64,
+}
+
+/// How the fee is computed from the absolute amount of the ledger field of the entry.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeAmount {
+    Fixed {
+        amount: i128,
+    },
+    /// Rounded half away from zero, and then kept between `min` and `max`.
+    Percentage {
+        rate_ppm: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<i128>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<i128>,
+    },
+    /// The first tier the amount is up to applies its fixed amount and rate.
+    Tiered {
+        tiers: Vec<FeeTier>,
+    },
+}
+
+impl FeeAmount {
+    pub fn fee(&self, amount: i128) -> i128 {
+        let amount = amount.abs();
+        match self {
+            Self::Fixed { amount } => *amount,
+            Self::Percentage { rate_ppm, min, max } => {
+                let fee = percentage(amount, *rate_ppm);
+                let fee = min.map_or(fee, |min| fee.max(min));
+                max.map_or(fee, |max| fee.min(max))
+            }
+            Self::Tiered { tiers } => tiers
+                .iter()
+                .find(|tier| tier.up_to.is_none_or(|up_to| amount <= up_to))
+                .map(|tier| tier.fixed + percentage(amount, tier.rate_ppm))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn percentage(amount: i128, rate_ppm: i64) -> i128 {
+    divide_rounded(amount * rate_ppm as i128, 1_000_000)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeDirection {
+    /// Subtracts the fee from the ledger field.
+    Debit,
+    /// Adds the fee to the ledger field.
+    Credit,
+}
+
+/// An entry derived from each entry matched by a rule.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct FeeEntry {
+    /// The account of the matched entry when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<AccountId>,
+    pub ledger_field: LedgerFieldName,
+    pub direction: FeeDirection,
+}
+
+/// Derives entries from the pushed entries that match all of its predicates.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct FeeRule {
+    pub rule_id: FeeRuleId,
+    pub when: Vec<RulePredicate>,
+    /// The ledger field of the matched entry the fee is computed from.
+    pub ledger_field: LedgerFieldName,
+    pub fee: FeeAmount,
+    pub entries: Vec<FeeEntry>,
+    pub actor: Actor,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeeRule {
+    pub fn new(
+        rule_id: FeeRuleId,
+        when: Vec<RulePredicate>,
+        ledger_field: LedgerFieldName,
+        fee: FeeAmount,
+        entries: Vec<FeeEntry>,
+        actor: Actor,
+        updated_at: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        if entries.is_empty() || entries.len() > MAX_FEE_RULE_ENTRIES {
+            bail!("A rule must derive between 1 and {MAX_FEE_RULE_ENTRIES} entries");
+        }
+        if let FeeAmount::Tiered { tiers } = &fee {
+            let Some((last, tiers)) = tiers.split_last() else {
+                bail!("A tiered fee needs at least one tier");
+            };
+            if last.up_to.is_some() {
+                bail!("The last tier can't have an `up_to`");
+            }
+            if tiers.iter().any(|tier| tier.up_to.is_none())
+                || !tiers.windows(2).all(|pair| pair[0].up_to < pair[1].up_to)
+            {
+                bail!("The tiers must be sorted by their `up_to`");
+            }
+        }
+        Ok(Self {
+            rule_id,
+            when,
+            ledger_field,
+            fee,
+            entries,
+            actor,
+            updated_at,
+        })
+    }
+
+    /// The entries derived from `entry`, or none when it doesn't match, is pending or the fee
+    /// is zero. A pending entry is matched when it is posted. They are linked to it with the `derived_from` key of their `additional_fields`, and
+    /// their `entry_id` is derived from the rule, the account and the `entry_id` of the matched
+    /// entry, so pushing it again doesn't apply them twice.
+    pub fn derive(&self, entry: &Entry) -> Vec<EntryWithConditionals> {
+        if entry.status != EntryStatus::Applied {
+            return Vec::new();
+        }
+        if !self.when.iter().all(|predicate| predicate.matches(entry)) {
+            return Vec::new();
+        }
+        let Some(amount) = entry.ledger_fields.get(&self.ledger_field) else {
+            return Vec::new();
+        };
+        let fee = self.fee.fee(*amount);
+        if fee == 0 {
+            return Vec::new();
+        }
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, fee_entry)| {
+                let digest =
+                    Sha256::digest(format!("{}|{}|{}", entry.account_id, entry.entry_id, index));
+                Entry {
+                    account_id: fee_entry
+                        .account_id
+                        .clone()
+                        .unwrap_or(entry.account_id.clone()),
+                    entry_id: EntryId::new_unchecked(format!(
+                        "{}-{}",
+                        self.rule_id,
+                        &hex::encode(digest)[..32]
+                    )),
+                    ledger_fields: [(
+                        fee_entry.ledger_field.clone(),
+                        match fee_entry.direction {
+                            FeeDirection::Debit => -fee,
+                            FeeDirection::Credit => fee,
+                        },
+                    )]
+                    .into(),
+                    additional_fields: json!({
+                        "derived_from": {
+                            "account_id": entry.account_id,
+                            "entry_id": entry.entry_id,
+                            "rule_id": self.rule_id,
+                        }
+                    }),
+                    status: EntryStatus::Applied,
+                    reversal: None,
+                }
+                .into()
+            })
+            .collect()
+    }
+
+    /// The entries of the account, each followed by the entries the rules derive from it in
+    /// the same account, and the derived entries of other accounts grouped by account. Derived
+    /// entries don't trigger rules themselves.
+    pub fn with_derived_entries(
+        account_id: &AccountId,
+        entries: &[EntryWithConditionals],
+        rules: &[FeeRule],
+    ) -> (
+        Vec<EntryWithConditionals>,
+        Vec<(AccountId, Vec<EntryWithConditionals>)>,
+    ) {
+        let mut account_entries = Vec::new();
+        let mut linked_entries: Vec<(AccountId, Vec<EntryWithConditionals>)> = Vec::new();
+        for entry in entries {
+            account_entries.push(entry.clone());
+            for derived in rules.iter().flat_map(|rule| rule.derive(&entry.entry)) {
+                if derived.entry.account_id == *account_id {
+                    account_entries.push(derived);
+                } else if let Some((_, linked)) = linked_entries
+                    .iter_mut()
+                    .find(|(linked_account_id, _)| *linked_account_id == derived.entry.account_id)
+                {
+                    linked.push(derived);
+                } else {
+                    linked_entries.push((derived.entry.account_id.clone(), vec![derived]));
+                }
+            }
+        }
+        (account_entries, linked_entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::domain::entity::EntryBuilder;
+
+    use super::*;
+
+    fn rule(when: &[&str], fee: FeeAmount, fee_account: &AccountId) -> FeeRule {
+        FeeRule::new(
+            FeeRuleId::new("card_fee".into()).expect("A valid rule id"),
+            when.iter()
+                .map(|predicate| predicate.parse().expect("A valid predicate"))
+                .collect(),
+            LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+            fee,
+            vec![
+                FeeEntry {
+                    account_id: None,
+                    ledger_field: LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+                    direction: FeeDirection::Debit,
+                },
+                FeeEntry {
+                    account_id: Some(fee_account.clone()),
+                    ledger_field: LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+                    direction: FeeDirection::Credit,
+                },
+            ],
+            Actor::new("test".into()).expect("A valid actor"),
+            Utc::now(),
+        )
+        .expect("A valid rule")
+    }
+
+    fn purchase(amount: i128, kind: &str) -> Entry {
+        EntryBuilder::new()
+            .with_ledger_field("usd_amount", amount)
+            .with_additional_fields(json!({"type": kind}))
+            .build()
+    }
+
+    #[test]
+    fn derive_fee_entries() {
+        let fee_account = AccountId::new(Default::default());
+        let rule = rule(
+            &["usd_amount<0", "additional_fields.type=card_purchase"],
+            FeeAmount::Percentage {
+                rate_ppm: 15_000,
+                min: Some(10),
+                max: None,
+            },
+            &fee_account,
+        );
+        let entry = purchase(-10_000, "card_purchase");
+        let derived = rule.derive(&entry);
+        assert_eq!(derived.len(), 2);
+        assert_eq!(derived[0].entry.account_id, entry.account_id);
+        assert_eq!(
+            derived[0].entry.ledger_fields.values().collect::<Vec<_>>(),
+            vec![&-150]
+        );
+        assert_eq!(derived[1].entry.account_id, fee_account);
+        assert_eq!(
+            derived[1].entry.ledger_fields.values().collect::<Vec<_>>(),
+            vec![&150]
+        );
+        assert_eq!(
+            derived[0].entry.additional_fields["derived_from"]["entry_id"],
+            json!(entry.entry_id)
+        );
+        assert_ne!(derived[0].entry.entry_id, derived[1].entry.entry_id);
+        assert_eq!(rule.derive(&entry), derived);
+
+        assert!(rule.derive(&purchase(-10_000, "transfer")).is_empty());
+        assert!(rule.derive(&purchase(10_000, "card_purchase")).is_empty());
+        let derived = rule.derive(&purchase(-100, "card_purchase"));
+        assert_eq!(
+            derived[0].entry.ledger_fields.values().collect::<Vec<_>>(),
+            vec![&-10]
+        );
+    }
+
+    #[test]
+    fn tiered_fees() {
+        let fee = FeeAmount::Tiered {
+            tiers: vec![
+                FeeTier {
+                    up_to: Some(1_000),
+                    fixed: 50,
+                    rate_ppm: 0,
+                },
+                FeeTier {
+                    up_to: Some(10_000),
+                    fixed: 0,
+                    rate_ppm: 10_000,
+                },
+                FeeTier {
+                    up_to: None,
+                    fixed: 25,
+                    rate_ppm: 5_000,
+                },
+            ],
+        };
+        assert_eq!(fee.fee(-1_000), 50);
+        assert_eq!(fee.fee(5_000), 50);
+        assert_eq!(fee.fee(20_000), 125);
+        assert_eq!(FeeAmount::Fixed { amount: 30 }.fee(-1), 30);
+        assert_eq!(
+            FeeAmount::Percentage {
+                rate_ppm: 15_000,
+                min: None,
+                max: Some(100),
+            }
+            .fee(1_000_000),
+            100
+        );
+    }
+
+    #[test]
+    fn parse_predicates() {
+        assert_eq!(
+            "usd_amount<=-5".parse::<RulePredicate>().ok(),
+            Some(RulePredicate::LedgerField {
+                ledger_field: LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+                operator: BalanceOperator::LessOrEqual,
+                value: -5,
+            })
+        );
+        assert_eq!(
+            "additional_fields.type=card_purchase"
+                .parse::<RulePredicate>()
+                .ok(),
+            Some(RulePredicate::AdditionalField {
+                key: "type".into(),
+                value: "card_purchase".into(),
+            })
+        );
+        for value in [
+            "usd_amount",
+            "usd_amount<x",
+            "additional_fields.type>1",
+            "additional_fields.=a",
+        ] {
+            assert!(value.parse::<RulePredicate>().is_err(), "{value}");
+        }
+    }
+}
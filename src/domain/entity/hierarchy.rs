@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::domain::entity::{AccountId, EntryWithBalance, LedgerBalanceName};
+
+/// The balances of an account and every account under it, added together.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct RollUp {
+    pub account_id: AccountId,
+    pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    /// Number of accounts in the subtree, including the ones without entries.
+    pub accounts: u64,
+    /// Levels below the account. Zero when it has no children.
+    pub depth: u32,
+}
+
+impl RollUp {
+    pub fn new(account_id: AccountId) -> Self {
+        Self {
+            account_id,
+            ledger_balances: HashMap::new(),
+            accounts: 0,
+            depth: 0,
+        }
+    }
+
+    /// Adds the balance of one account of the subtree.
+    pub fn add(&mut self, balance: &EntryWithBalance) {
+        for (ledger_balance_name, value) in balance.ledger_balances.iter() {
+            *self
+                .ledger_balances
+                .entry(ledger_balance_name.clone())
+                .or_insert(0) += value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::entity::{EntryBuilder, EntryWithBalanceBuilder};
+
+    use super::*;
+
+    #[test]
+    fn roll_up_adds_balances_by_name() {
+        let balance = |amount: i128, fee: Option<i128>| {
+            let builder = EntryWithBalanceBuilder::from_entry(EntryBuilder::new().build())
+                .with_ledger_balance("balance_amount", amount);
+            match fee {
+                Some(fee) => builder.with_ledger_balance("balance_fee", fee),
+                None => builder,
+            }
+            .build()
+        };
+        let mut roll_up = RollUp::new(AccountId::new(Default::default()));
+        roll_up.add(&balance(100, Some(3)));
+        roll_up.add(&balance(-40, None));
+        assert_eq!(
+            roll_up.ledger_balances,
+            HashMap::from([
+                (
+                    LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                    60
+                ),
+                (
+                    LedgerBalanceName::new("balance_fee".into()).expect("A valid balance"),
+                    3
+                ),
+            ])
+        );
+    }
+}
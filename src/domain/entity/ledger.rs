@@ -0,0 +1,71 @@
+use std::fmt::Display;
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::entity::Actor;
+
+/// Identifies an isolated ledger (tenant) inside the deployment.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String")]
+pub struct LedgerId(String);
+
+impl LedgerId {
+    pub fn new(ledger_id: String) -> anyhow::Result<Self> {
+        if ledger_id.is_empty() {
+            bail!("Ledger id cannot be empty")
+        }
+        if ledger_id.len() > 64 {
+            bail!("Ledger id cannot be longer the 64 characters")
+        }
+        if !ledger_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Ledger id can only contain ascii letters, numbers, `-` and `_`")
+        }
+        Ok(Self(ledger_id))
+    }
+}
+
+impl Display for LedgerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<String> for LedgerId {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        LedgerId::new(value)
+    }
+}
+
+impl std::str::FromStr for LedgerId {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        LedgerId::new(value.into())
+    }
+}
+
+impl From<LedgerId> for String {
+    fn from(value: LedgerId) -> String {
+        value.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Tenant {
+    pub ledger_id: LedgerId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Free form configuration of the tenant.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub metadata: Value,
+    pub actor: Actor,
+    pub created_at: DateTime<Utc>,
+}
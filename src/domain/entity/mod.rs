@@ -1,20 +1,66 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+pub use account::{
+    Account, AccountCursor, AccountQuery, AccountTag, BalanceFilter, BalanceOperator,
+};
 pub use account_id::AccountId;
+pub use accrual::{Accrual, AccrualRate, AccrualSchedule, DayCount};
+pub use actor::Actor;
+pub use api_key::{AccountScope, ApiKey, ApiKeyCredential, Principal, Scope};
+pub use attestation::{Attestation, SignedAttestation};
+pub use audit::{AuditAction, AuditCursor, AuditQuery, AuditRecord};
 pub use conditional::Conditional;
-pub use cursor::{Cursor, EntryToContinue};
+pub use cursor::{Cursor, EntryToContinue, ScanCursor};
 #[cfg(test)]
 pub use entry::test::{EntryBuilder, EntryWithBalanceBuilder};
-pub use entry::{Entry, EntryId, EntryStatus, EntryWithBalance, EntryWithConditionals};
+pub use entry::{
+    Entry, EntryHash, EntryId, EntryStatus, EntryWithBalance, EntryWithConditionals,
+    PendingResolution, Reversal, ReversalReason,
+};
+pub use fee_rule::{FeeAmount, FeeEntry, FeeRule, FeeRuleId, RulePredicate, MAX_DERIVED_ENTRIES};
+pub use hierarchy::RollUp;
+pub use ledger::{LedgerId, Tenant};
 pub use ledger_balance_name::LedgerBalanceName;
 pub use ledger_field_name::LedgerFieldName;
+pub use period::{Period, PeriodClosing};
+pub use reconciliation::{
+    reconcile, ExpectedEntry, MatchKey, Reconciliation, ReconciliationOptions,
+};
+pub use recurring::{Recurrence, RecurringEntry, RecurringSchedule, RecurringScheduleId};
+pub use snapshot::Snapshot;
+pub use statement::Statement;
+pub use summary::AccountSummary;
+pub use velocity_limit::{
+    VelocityDirection, VelocityLimit, VelocityLimitId, VelocityUsage, VelocityWindow,
+    MAX_VELOCITY_LIMITS,
+};
+pub use verification::{AccountRows, Discrepancy, StoredEntry, VerificationReport};
 
+mod account;
 mod account_id;
+mod accrual;
+mod actor;
+mod api_key;
+mod attestation;
+mod audit;
 mod conditional;
 mod cursor;
 mod entry;
+mod fee_rule;
+mod hierarchy;
+mod ledger;
 mod ledger_balance_name;
 mod ledger_field_name;
+mod period;
+mod reconciliation;
+mod recurring;
+mod snapshot;
+mod statement;
+mod summary;
+mod velocity_limit;
+mod verification;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Clone)]
 pub enum Order {
@@ -26,4 +72,57 @@ pub enum Order {
 pub struct DeleteEntryRequest {
     pub account_id: AccountId,
     pub entry_id: EntryId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_fields: Option<HashMap<LedgerFieldName, i128>>,
+    #[serde(flatten)]
+    pub reversal: Reversal,
+}
+
+/// A pending entry to post or void.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PendingEntryRequest {
+    pub account_id: AccountId,
+    pub entry_id: EntryId,
+}
+
+/// Reversal of an entry. Without `ledger_fields` everything that was not reverted yet is
+/// reverted, otherwise only the given amounts are.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EntryReversal {
+    pub entry_id: EntryId,
+    pub ledger_fields: Option<HashMap<LedgerFieldName, i128>>,
+    pub reversal: Reversal,
+}
+
+/// Divides rounding half away from zero. The `denominator` must be positive.
+pub(crate) fn divide_rounded(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+    if (numerator % denominator).abs() * 2 >= denominator {
+        quotient + numerator.signum()
+    } else {
+        quotient
+    }
+}
+
+impl From<DeleteEntryRequest> for EntryReversal {
+    fn from(value: DeleteEntryRequest) -> Self {
+        Self {
+            entry_id: value.entry_id,
+            ledger_fields: value.ledger_fields,
+            reversal: value.reversal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_half_away_from_zero() {
+        assert_eq!(divide_rounded(5, 10), 1);
+        assert_eq!(divide_rounded(4, 10), 0);
+        assert_eq!(divide_rounded(-5, 10), -1);
+        assert_eq!(divide_rounded(-14, 10), -1);
+    }
 }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, LedgerBalanceName};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct Period {
+    year: i32,
+    month: u32,
+}
+
+impl Period {
+    pub fn new(year: i32, month: u32) -> anyhow::Result<Self> {
+        if !(1..=12).contains(&month) {
+            bail!("Period month must be between 1 and 12")
+        }
+        Ok(Self { year, month })
+    }
+
+    pub fn start(&self) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("A period always has a valid first day")
+            .and_utc()
+    }
+
+    /// The first instant that is not part of this period.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.next().start()
+    }
+
+    pub fn next(&self) -> Self {
+        match self.month {
+            12 => Self {
+                year: self.year + 1,
+                month: 1,
+            },
+            month => Self {
+                year: self.year,
+                month: month + 1,
+            },
+        }
+    }
+}
+
+impl From<&DateTime<Utc>> for Period {
+    fn from(value: &DateTime<Utc>) -> Self {
+        Self {
+            year: value.year(),
+            month: value.month(),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl FromStr for Period {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (year, month) = value
+            .split_once('-')
+            .ok_or(anyhow!("Period must be in the format YYYY-MM"))?;
+        if year.len() != 4 || month.len() != 2 {
+            bail!("Period must be in the format YYYY-MM")
+        }
+        Self::new(year.parse()?, month.parse()?)
+    }
+}
+
+impl TryFrom<String> for Period {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Period::from_str(&value)
+    }
+}
+
+impl From<Period> for String {
+    fn from(value: Period) -> String {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PeriodClosing {
+    pub account_id: AccountId,
+    pub period: Period,
+    pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    pub closed_at: DateTime<Utc>,
+}
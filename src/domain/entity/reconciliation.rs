@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::bail;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::entity::{
+    AccountId, Actor, EntryId, EntryStatus, EntryWithBalance, LedgerFieldName,
+};
+
+/// A line of an external statement, like a bank statement, that should be in the ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ExpectedEntry {
+    /// The `entry_id` or the `additional_fields` value the entry is matched by.
+    pub reference: String,
+    pub amount: i128,
+    pub date: NaiveDate,
+}
+
+/// What the reference of an expected entry is compared to.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub enum MatchKey {
+    EntryId,
+    /// A top level key of the `additional_fields`, like `additional_fields.bank_reference`.
+    AdditionalField(String),
+}
+
+impl MatchKey {
+    fn reference(&self, entry: &EntryWithBalance) -> Option<String> {
+        match self {
+            Self::EntryId => Some(entry.entry_id.to_string()),
+            Self::AdditionalField(key) => match entry.additional_fields.get(key)? {
+                Value::String(value) => Some(value.clone()),
+                Value::Number(value) => Some(value.to_string()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl FromStr for MatchKey {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "entry_id" => Ok(Self::EntryId),
+            value => match value.strip_prefix("additional_fields.") {
+                Some(key) if !key.is_empty() => Ok(Self::AdditionalField(key.into())),
+                _ => bail!("Match key must be entry_id or additional_fields.{{key}}"),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for MatchKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<MatchKey> for String {
+    fn from(value: MatchKey) -> String {
+        value.to_string()
+    }
+}
+
+impl Display for MatchKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntryId => write!(f, "entry_id"),
+            Self::AdditionalField(key) => write!(f, "additional_fields.{}", key),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ReconciliationOptions {
+    /// The ledger field compared to the amounts of the statement.
+    pub ledger_field: LedgerFieldName,
+    pub match_by: MatchKey,
+    /// Largest difference between the amounts for them to match.
+    pub amount_tolerance: u64,
+    /// Largest number of days between the statement date and the entry creation.
+    pub date_tolerance: u32,
+}
+
+/// An expected entry and the entry of the ledger with the same reference.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ReconciliationMatch {
+    pub expected: ExpectedEntry,
+    pub entry_id: EntryId,
+    pub amount: i128,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An entry of the ledger that is not in the statement.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UnmatchedEntry {
+    pub entry_id: EntryId,
+    pub reference: Option<String>,
+    pub amount: i128,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A reconciliation run of an account between `start_date` and `end_date`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Reconciliation {
+    pub run_id: String,
+    pub account_id: AccountId,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub options: ReconciliationOptions,
+    pub actor: Actor,
+    pub created_at: DateTime<Utc>,
+    pub matched: Vec<ReconciliationMatch>,
+    pub amount_mismatches: Vec<ReconciliationMatch>,
+    pub missing_in_ledger: Vec<ExpectedEntry>,
+    pub missing_in_statement: Vec<UnmatchedEntry>,
+}
+
+/// The sets of a reconciliation, with the same order of the statement and of the entries.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct ReconciliationSets {
+    pub matched: Vec<ReconciliationMatch>,
+    pub amount_mismatches: Vec<ReconciliationMatch>,
+    pub missing_in_ledger: Vec<ExpectedEntry>,
+    pub missing_in_statement: Vec<UnmatchedEntry>,
+}
+
+/// Matches each expected entry with an entry of the same reference created within the date
+/// tolerance, preferring one within the amount tolerance and then the closest date. An entry
+/// is matched only once. Reversals, the entries they fully reverted and the entries without
+/// the ledger field are left out, as they don't move the balance of the field. Entries
+/// outside of `start_date` and `end_date` can be matched, but are never missing in the
+/// statement.
+pub fn reconcile(
+    options: &ReconciliationOptions,
+    expected_entries: Vec<ExpectedEntry>,
+    entries: &[EntryWithBalance],
+    start_date: &DateTime<Utc>,
+    end_date: &DateTime<Utc>,
+) -> ReconciliationSets {
+    let entries: Vec<(&EntryWithBalance, i128, Option<String>)> = entries
+        .iter()
+        .filter(|entry| entry.status == EntryStatus::Applied)
+        .filter_map(|entry| {
+            entry
+                .ledger_fields
+                .get(&options.ledger_field)
+                .map(|amount| (entry, *amount, options.match_by.reference(entry)))
+        })
+        .collect();
+    let mut by_reference: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, (_, _, reference)) in entries.iter().enumerate() {
+        if let Some(reference) = reference {
+            by_reference.entry(reference).or_default().push(index);
+        }
+    }
+
+    let mut sets = ReconciliationSets::default();
+    let mut used = HashSet::new();
+    for expected in expected_entries {
+        let candidate = by_reference
+            .get(expected.reference.as_str())
+            .into_iter()
+            .flatten()
+            .filter(|index| !used.contains(*index))
+            .map(|index| {
+                let (entry, amount, _) = &entries[*index];
+                let days = (entry.created_at.date_naive() - expected.date)
+                    .num_days()
+                    .unsigned_abs();
+                let amount_matches =
+                    (amount - expected.amount).unsigned_abs() <= options.amount_tolerance as u128;
+                (*index, days, amount_matches)
+            })
+            .filter(|(_, days, _)| *days <= options.date_tolerance as u64)
+            .min_by_key(|(index, days, amount_matches)| (!amount_matches, *days, *index));
+        let Some((index, _, amount_matches)) = candidate else {
+            sets.missing_in_ledger.push(expected);
+            continue;
+        };
+        used.insert(index);
+        let (entry, amount, _) = &entries[index];
+        let reconciliation_match = ReconciliationMatch {
+            expected,
+            entry_id: entry.entry_id.clone(),
+            amount: *amount,
+            created_at: entry.created_at,
+        };
+        if amount_matches {
+            sets.matched.push(reconciliation_match);
+        } else {
+            sets.amount_mismatches.push(reconciliation_match);
+        }
+    }
+    sets.missing_in_statement = entries
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (entry, _, _))| {
+            !used.contains(index) && entry.created_at >= *start_date && entry.created_at < *end_date
+        })
+        .map(|(_, (entry, amount, reference))| UnmatchedEntry {
+            entry_id: entry.entry_id.clone(),
+            reference,
+            amount,
+            created_at: entry.created_at,
+        })
+        .collect();
+    sets
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::domain::entity::{EntryBuilder, EntryWithBalanceBuilder};
+
+    use super::*;
+
+    fn entry(reference: &str, amount: i128, created_at: &str) -> EntryWithBalance {
+        let mut entry = EntryWithBalanceBuilder::from_entry(
+            EntryBuilder::new()
+                .with_ledger_field("amount", amount)
+                .with_additional_fields(json!({"bank_reference": reference}))
+                .build(),
+        )
+        .build();
+        entry.created_at = created_at.parse().expect("A valid date");
+        entry
+    }
+
+    fn expected(reference: &str, amount: i128, date: &str) -> ExpectedEntry {
+        ExpectedEntry {
+            reference: reference.into(),
+            amount,
+            date: date.parse().expect("A valid date"),
+        }
+    }
+
+    #[test]
+    fn reconcile_with_tolerances() {
+        let options = ReconciliationOptions {
+            ledger_field: LedgerFieldName::new("amount".into()).expect("A valid field"),
+            match_by: "additional_fields.bank_reference"
+                .parse()
+                .expect("A valid match key"),
+            amount_tolerance: 1,
+            date_tolerance: 2,
+        };
+        let entries = vec![
+            entry("a", 100, "2024-07-01 10:00:00 UTC"),
+            entry("b", 250, "2024-07-03 10:00:00 UTC"),
+            entry("c", 75, "2024-07-05 10:00:00 UTC"),
+            entry("d", 40, "2024-07-10 10:00:00 UTC"),
+            entry("e", 10, "2024-08-01 10:00:00 UTC"),
+        ];
+        let sets = reconcile(
+            &options,
+            vec![
+                expected("a", 101, "2024-07-02"),
+                expected("b", 200, "2024-07-03"),
+                expected("c", 75, "2024-07-09"),
+                expected("x", 5, "2024-07-04"),
+            ],
+            &entries,
+            &"2024-07-01 00:00:00 UTC".parse().expect("A valid date"),
+            &"2024-08-01 00:00:00 UTC".parse().expect("A valid date"),
+        );
+        let entry_ids = |matches: &[ReconciliationMatch]| {
+            matches
+                .iter()
+                .map(|reconciliation_match| reconciliation_match.entry_id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entry_ids(&sets.matched), vec![entries[0].entry_id.clone()]);
+        assert_eq!(
+            entry_ids(&sets.amount_mismatches),
+            vec![entries[1].entry_id.clone()]
+        );
+        assert_eq!(
+            sets.missing_in_ledger,
+            vec![
+                expected("c", 75, "2024-07-09"),
+                expected("x", 5, "2024-07-04")
+            ]
+        );
+        assert_eq!(
+            sets.missing_in_statement
+                .iter()
+                .map(|entry| entry.reference.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("c"), Some("d")]
+        );
+    }
+
+    #[test]
+    fn parse_match_keys() {
+        assert_eq!("entry_id".parse::<MatchKey>().ok(), Some(MatchKey::EntryId));
+        assert_eq!(
+            "additional_fields.bank_reference".parse::<MatchKey>().ok(),
+            Some(MatchKey::AdditionalField("bank_reference".into()))
+        );
+        assert!("additional_fields.".parse::<MatchKey>().is_err());
+        assert!("amount".parse::<MatchKey>().is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::entity::{
+    AccountId, Actor, Entry, EntryId, EntryStatus, EntryWithConditionals, LedgerFieldName, Period,
+};
+
+/// Entries a schedule can create at each occurrence.
+pub const MAX_RECURRING_ENTRIES: usize = 10;
+
+/// Occurrences a schedule posts in a run, so a schedule that missed many of them catches up
+/// over several runs.
+const MAX_OCCURRENCES_PER_RUN: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct RecurringScheduleId(String);
+
+impl RecurringScheduleId {
+    /// It is part of the `entry_id` of the entries of each occurrence, so it is kept short.
+    pub fn new(value: String) -> anyhow::Result<Self> {
+        if value.is_empty() || value.len() > 24 {
+            bail!("Schedule id must have between 1 and 24 characters");
+        }
+        if !value
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+        {
+            bail!("Schedule id can only have letters, digits, `-` and `_`");
+        }
+        Ok(Self(value))
+    }
+}
+
+impl TryFrom<String> for RecurringScheduleId {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<RecurringScheduleId> for String {
+    fn from(value: RecurringScheduleId) -> String {
+        value.0
+    }
+}
+
+impl Display for RecurringScheduleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// When a schedule occurs, in UTC.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    /// A cron expression with minutes, hours, day of month, month and day of week, like
+    /// `0 9 * * Mon-Fri`, optionally with the seconds first.
+    Cron { expression: String },
+    /// Every month on `day`, or on its last day when it has fewer days.
+    Monthly {
+        day: u32,
+        #[serde(default)]
+        time: NaiveTime,
+    },
+}
+
+impl Recurrence {
+    fn cron_schedule(expression: &str) -> anyhow::Result<cron::Schedule> {
+        let expression = if expression.split_whitespace().count() == 5 {
+            format!("0 {expression}")
+        } else {
+            expression.to_string()
+        };
+        cron::Schedule::from_str(&expression)
+            .map_err(|err| anyhow!("Invalid cron expression `{expression}`: {err}"))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Cron { expression } => {
+                Self::cron_schedule(expression)?;
+            }
+            Self::Monthly { day, .. } => {
+                if !(1..=31).contains(day) {
+                    bail!("The day of a monthly recurrence must be between 1 and 31");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The first occurrence strictly after `date_time`.
+    pub fn after(&self, date_time: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron { expression } => Self::cron_schedule(expression)
+                .ok()?
+                .after(date_time)
+                .next(),
+            Self::Monthly { day, time } => {
+                let mut period = Period::from(date_time);
+                loop {
+                    let last_day = (period.next().start() - TimeDelta::days(1)).day();
+                    let occurrence = period
+                        .start()
+                        .date_naive()
+                        .with_day((*day).min(last_day))?
+                        .and_time(*time)
+                        .and_utc();
+                    if occurrence > *date_time {
+                        return Some(occurrence);
+                    }
+                    period = period.next();
+                }
+            }
+        }
+    }
+}
+
+/// An entry created at each occurrence.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RecurringEntry {
+    pub account_id: AccountId,
+    pub ledger_fields: HashMap<LedgerFieldName, i128>,
+    #[serde(default)]
+    pub additional_fields: Value,
+}
+
+/// Creates its entries at each occurrence of the recurrence from `starts_at`, until
+/// `ends_at` or until `max_occurrences` were posted.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RecurringSchedule {
+    pub schedule_id: RecurringScheduleId,
+    pub recurrence: Recurrence,
+    pub starts_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_occurrences: Option<u32>,
+    pub entries: Vec<RecurringEntry>,
+    pub actor: Actor,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub paused: bool,
+    /// The occurrences posted so far.
+    #[serde(default)]
+    pub occurrences: u32,
+    /// The last occurrence posted, or the time it was resumed at when later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posted_until: Option<DateTime<Utc>>,
+}
+
+impl RecurringSchedule {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        schedule_id: RecurringScheduleId,
+        recurrence: Recurrence,
+        starts_at: DateTime<Utc>,
+        ends_at: Option<DateTime<Utc>>,
+        max_occurrences: Option<u32>,
+        entries: Vec<RecurringEntry>,
+        actor: Actor,
+        updated_at: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        recurrence.validate()?;
+        if entries.is_empty() || entries.len() > MAX_RECURRING_ENTRIES {
+            bail!("A schedule must create between 1 and {MAX_RECURRING_ENTRIES} entries");
+        }
+        if entries.iter().any(|entry| entry.ledger_fields.is_empty()) {
+            bail!("Every entry of a schedule needs at least one ledger field");
+        }
+        if entries
+            .iter()
+            .any(|entry| !entry.additional_fields.is_object() && !entry.additional_fields.is_null())
+        {
+            bail!("The additional fields of the entries of a schedule must be an object");
+        }
+        if ends_at.is_some_and(|ends_at| ends_at < starts_at) {
+            bail!("A schedule can't end before it starts");
+        }
+        if max_occurrences == Some(0) {
+            bail!("The max occurrences of a schedule must be positive");
+        }
+        let schedule = Self {
+            schedule_id,
+            recurrence,
+            starts_at,
+            ends_at,
+            max_occurrences,
+            entries,
+            actor,
+            updated_at,
+            paused: false,
+            occurrences: 0,
+            posted_until: None,
+        };
+        // The entry ids of the occurrences must be valid for every occurrence.
+        EntryId::new(
+            schedule
+                .entry_id(&starts_at, MAX_RECURRING_ENTRIES - 1)
+                .to_string(),
+        )?;
+        Ok(schedule)
+    }
+
+    /// The id of an entry of an occurrence. Posting it again fails because the entry already
+    /// exists, so an occurrence is never posted twice.
+    pub fn entry_id(&self, occurrence: &DateTime<Utc>, index: usize) -> EntryId {
+        EntryId::new_unchecked(format!(
+            "{}-{}-{}",
+            self.schedule_id,
+            occurrence.format("%Y%m%dT%H%M%S"),
+            index
+        ))
+    }
+
+    /// The occurrences after `posted_until`, or from `starts_at`, up to `now`, without going
+    /// over the end of the schedule.
+    pub fn occurrences_to_post(&self, now: &DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let remaining = self.max_occurrences.map_or(usize::MAX, |max_occurrences| {
+            max_occurrences.saturating_sub(self.occurrences) as usize
+        });
+        let mut after = self
+            .posted_until
+            .unwrap_or(self.starts_at - TimeDelta::nanoseconds(1));
+        let mut occurrences = Vec::new();
+        while occurrences.len() < remaining.min(MAX_OCCURRENCES_PER_RUN) {
+            let Some(occurrence) = self.recurrence.after(&after) else {
+                break;
+            };
+            if occurrence > *now || self.ends_at.is_some_and(|ends_at| occurrence > ends_at) {
+                break;
+            }
+            occurrences.push(occurrence);
+            after = occurrence;
+        }
+        occurrences
+    }
+
+    /// The entries of an occurrence, with the schedule and the occurrence in the `recurring`
+    /// key of their `additional_fields`.
+    pub fn entries_at(&self, occurrence: &DateTime<Utc>) -> Vec<EntryWithConditionals> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let mut additional_fields = match &entry.additional_fields {
+                    Value::Object(fields) => fields.clone(),
+                    _ => Default::default(),
+                };
+                additional_fields.insert(
+                    "recurring".into(),
+                    json!({
+                        "schedule_id": self.schedule_id,
+                        "occurrence": occurrence,
+                    }),
+                );
+                Entry {
+                    account_id: entry.account_id.clone(),
+                    entry_id: self.entry_id(occurrence, index),
+                    ledger_fields: entry.ledger_fields.clone(),
+                    additional_fields: additional_fields.into(),
+                    status: EntryStatus::Applied,
+                    reversal: None,
+                }
+                .into()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schedule(recurrence: Recurrence) -> RecurringSchedule {
+        RecurringSchedule::new(
+            RecurringScheduleId::new("rent".into()).expect("A valid schedule id"),
+            recurrence,
+            "2024-01-31 00:00:00 UTC".parse().expect("A valid date"),
+            Some("2024-06-30 00:00:00 UTC".parse().expect("A valid date")),
+            Some(4),
+            vec![RecurringEntry {
+                account_id: AccountId::new(Default::default()),
+                ledger_fields: [(
+                    LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+                    -1_000,
+                )]
+                .into(),
+                additional_fields: json!({"description": "Rent"}),
+            }],
+            Actor::new("test".into()).expect("A valid actor"),
+            Utc::now(),
+        )
+        .expect("A valid schedule")
+    }
+
+    fn dates(occurrences: &[DateTime<Utc>]) -> Vec<String> {
+        occurrences
+            .iter()
+            .map(|occurrence| occurrence.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn monthly_occurrences() {
+        let mut schedule = schedule(Recurrence::Monthly {
+            day: 31,
+            time: NaiveTime::from_hms_opt(9, 0, 0).expect("A valid time"),
+        });
+        let now = "2024-03-31 09:00:00 UTC".parse().expect("A valid date");
+        assert_eq!(
+            dates(&schedule.occurrences_to_post(&now)),
+            vec!["2024-01-31 09:00", "2024-02-29 09:00", "2024-03-31 09:00"]
+        );
+
+        // It continues after the last posted occurrence, up to the max occurrences.
+        schedule.posted_until = Some("2024-02-29 09:00:00 UTC".parse().expect("A valid date"));
+        schedule.occurrences = 2;
+        let now = "2024-12-01 00:00:00 UTC".parse().expect("A valid date");
+        assert_eq!(
+            dates(&schedule.occurrences_to_post(&now)),
+            vec!["2024-03-31 09:00", "2024-04-30 09:00"]
+        );
+        schedule.max_occurrences = None;
+        assert_eq!(
+            dates(&schedule.occurrences_to_post(&now)),
+            vec!["2024-03-31 09:00", "2024-04-30 09:00", "2024-05-31 09:00"]
+        );
+    }
+
+    #[test]
+    fn cron_occurrences() {
+        let schedule = schedule(Recurrence::Cron {
+            expression: "0 12 * * Fri".into(),
+        });
+        let now = "2024-02-16 12:00:00 UTC".parse().expect("A valid date");
+        assert_eq!(
+            dates(&schedule.occurrences_to_post(&now)),
+            vec!["2024-02-02 12:00", "2024-02-09 12:00", "2024-02-16 12:00"]
+        );
+    }
+
+    #[test]
+    fn occurrence_entries() {
+        let schedule = schedule(Recurrence::Monthly {
+            day: 1,
+            time: NaiveTime::default(),
+        });
+        let occurrence = "2024-02-01 00:00:00 UTC".parse().expect("A valid date");
+        let entries = schedule.entries_at(&occurrence);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].entry.entry_id,
+            EntryId::new("rent-20240201T000000-0".into()).expect("A valid entry id")
+        );
+        assert_eq!(entries[0].entry.additional_fields["description"], "Rent");
+        assert_eq!(
+            entries[0].entry.additional_fields["recurring"]["schedule_id"],
+            "rent"
+        );
+        assert_eq!(schedule.entries_at(&occurrence), entries);
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        let recurrence = Recurrence::Cron {
+            expression: "every day".into(),
+        };
+        assert!(recurrence.validate().is_err());
+        let recurrence = Recurrence::Monthly {
+            day: 32,
+            time: NaiveTime::default(),
+        };
+        assert!(recurrence.validate().is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::entity::{AccountId, EntryHash, EntryId, EntryWithBalance, LedgerBalanceName};
+
+/// The balances of an account after one of its entries, stored with the entry so the history
+/// before it doesn't have to be replayed again.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    pub account_id: AccountId,
+    pub entry_id: EntryId,
+    pub sequence: u64,
+    pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    pub pending_balances: HashMap<LedgerBalanceName, i128>,
+    pub hash: Option<EntryHash>,
+    /// When the entry was created.
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&EntryWithBalance> for Snapshot {
+    fn from(entry: &EntryWithBalance) -> Self {
+        Self {
+            account_id: entry.account_id.clone(),
+            entry_id: entry.entry_id.clone(),
+            sequence: entry.sequence,
+            ledger_balances: entry.ledger_balances.clone(),
+            pending_balances: entry.pending_balances.clone(),
+            hash: entry.hash.clone(),
+            created_at: entry.created_at,
+        }
+    }
+}
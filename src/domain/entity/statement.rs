@@ -0,0 +1,210 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::domain::entity::{
+    AccountId, EntryId, EntryStatus, EntryWithBalance, LedgerBalanceName, LedgerFieldName, Period,
+    Reversal,
+};
+
+/// Every entry of an account created in a period, between the balances it opened and closed
+/// with.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Statement {
+    pub account_id: AccountId,
+    pub period: Period,
+    pub opening_balances: HashMap<LedgerBalanceName, i128>,
+    pub lines: Vec<StatementLine>,
+    pub closing_balances: HashMap<LedgerBalanceName, i128>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct StatementLine {
+    pub entry_id: EntryId,
+    pub sequence: u64,
+    pub created_at: DateTime<Utc>,
+    pub ledger_fields: HashMap<LedgerFieldName, i128>,
+    /// The running balances after the entry, of every field known by the statement.
+    pub ledger_balances: HashMap<LedgerBalanceName, i128>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub additional_fields: Value,
+    /// Sequence of the entry reverted by this line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverses: Option<u64>,
+    /// Sequence of the line that reverts this one, when it is in the same statement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversed_by: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversal: Option<Reversal>,
+}
+
+impl Statement {
+    /// The `closing_balances` are the balances at the end of the period, and the `entries`
+    /// the ones created in it in sequence order. The opening balances are found undoing them.
+    /// Pending entries are left out, as they don't move the balances.
+    pub fn new(
+        account_id: AccountId,
+        period: Period,
+        mut closing_balances: HashMap<LedgerBalanceName, i128>,
+        entries: Vec<EntryWithBalance>,
+    ) -> Self {
+        let entries = entries
+            .into_iter()
+            .filter(|entry| !entry.status.is_pending())
+            .collect::<Vec<_>>();
+        // The last entry that moved a field has its balance at the end of the period.
+        for entry in entries.iter() {
+            for field_name in entry.ledger_fields.keys() {
+                let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                if let Some(balance) = entry.ledger_balances.get(&ledger_balance_name) {
+                    closing_balances.insert(ledger_balance_name, *balance);
+                }
+            }
+        }
+        let mut opening_balances = closing_balances.clone();
+        for entry in entries.iter().rev() {
+            for (field_name, value) in entry.ledger_fields.iter() {
+                let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                let balance = entry
+                    .ledger_balances
+                    .get(&ledger_balance_name)
+                    .unwrap_or(&0);
+                opening_balances.insert(ledger_balance_name, balance - value);
+            }
+        }
+        let sequences = entries
+            .iter()
+            .map(|entry| entry.sequence)
+            .collect::<BTreeSet<u64>>();
+        let mut ledger_balances = opening_balances.clone();
+        let lines = entries
+            .into_iter()
+            .map(|entry| {
+                for field_name in entry.ledger_fields.keys() {
+                    let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                    if let Some(balance) = entry.ledger_balances.get(&ledger_balance_name) {
+                        ledger_balances.insert(ledger_balance_name, *balance);
+                    }
+                }
+                let (reverses, reversed_by) = match entry.status {
+                    EntryStatus::Applied
+                    | EntryStatus::Pending
+                    | EntryStatus::Posted(_)
+                    | EntryStatus::Void(_)
+                    | EntryStatus::Voided(_) => (None, None),
+                    EntryStatus::Revert(sequence) => (Some(sequence), None),
+                    EntryStatus::Reverted(sequence) => {
+                        (None, sequences.contains(&sequence).then_some(sequence))
+                    }
+                };
+                StatementLine {
+                    entry_id: entry.entry_id,
+                    sequence: entry.sequence,
+                    created_at: entry.created_at,
+                    ledger_fields: entry.ledger_fields,
+                    ledger_balances: ledger_balances.clone(),
+                    additional_fields: entry.additional_fields,
+                    reverses,
+                    reversed_by,
+                    reversal: entry.reversal,
+                }
+            })
+            .collect();
+        Self {
+            account_id,
+            period,
+            opening_balances,
+            lines,
+            closing_balances,
+        }
+    }
+
+    /// The fields with a balance in the statement, sorted by name.
+    pub fn ledger_fields(&self) -> Vec<LedgerFieldName> {
+        self.opening_balances
+            .keys()
+            .chain(self.closing_balances.keys())
+            .filter_map(|ledger_balance_name| {
+                String::from(ledger_balance_name.clone())
+                    .strip_prefix("balance_")
+                    .and_then(|field_name| LedgerFieldName::new(field_name.into()).ok())
+            })
+            .collect::<BTreeSet<LedgerFieldName>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+impl StatementLine {
+    /// How the line pairs with its reversal, if it does.
+    pub fn note(&self) -> Option<String> {
+        match (self.reverses, self.reversed_by) {
+            (Some(sequence), _) => Some(format!("Reversal of #{sequence}")),
+            (None, Some(sequence)) => Some(format!("Reversed by #{sequence}")),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::entity::{EntryBuilder, EntryWithBalanceBuilder};
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    #[test]
+    fn statement_lines_and_balances() {
+        let account_id = AccountId::new(Default::default());
+        let entry = |amount: i128, balance: i128| {
+            EntryWithBalanceBuilder::from_entry(
+                EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("amount", amount)
+                    .build(),
+            )
+            .with_ledger_balance("balance_amount", balance)
+            .build()
+        };
+        let mut entries = vec![entry(100, 150), entry(-30, 120), entry(30, 150)];
+        entries[1].status = EntryStatus::Reverted(entries[2].sequence);
+        entries[2].status = EntryStatus::Revert(entries[1].sequence);
+        let balance = |value: i128| {
+            HashMap::from([(
+                LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                value,
+            )])
+        };
+        let statement = Statement::new(
+            account_id,
+            Period::from(&utc_now()),
+            balance(175),
+            entries.clone(),
+        );
+        assert_eq!(statement.opening_balances, balance(50));
+        assert_eq!(statement.closing_balances, balance(150));
+        assert_eq!(
+            statement
+                .lines
+                .iter()
+                .map(|line| line.ledger_balances.clone())
+                .collect::<Vec<_>>(),
+            vec![balance(150), balance(120), balance(150)]
+        );
+        assert_eq!(statement.lines[0].note(), None);
+        assert_eq!(
+            statement.lines[1].note(),
+            Some(format!("Reversed by #{}", entries[2].sequence))
+        );
+        assert_eq!(
+            statement.lines[2].note(),
+            Some(format!("Reversal of #{}", entries[1].sequence))
+        );
+        assert_eq!(
+            statement.ledger_fields(),
+            vec![LedgerFieldName::new("amount".into()).expect("A valid field")]
+        );
+    }
+}
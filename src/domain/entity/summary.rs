@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::domain::entity::{AccountId, EntryWithBalance, LedgerBalanceName, LedgerFieldName};
+
+/// What moved in the ledger fields of an account between two dates.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccountSummary {
+    pub account_id: AccountId,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    /// Number of entries created between the dates, reverts included.
+    pub entries: u64,
+    /// Only the fields moved by some entry between the dates.
+    pub ledger_fields: HashMap<LedgerFieldName, FieldSummary>,
+}
+
+/// The `opening_balance` plus the `credits` and `debits` is always the `closing_balance`.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct FieldSummary {
+    /// The balance before the first entry between the dates.
+    pub opening_balance: i128,
+    /// The balance after the last entry between the dates.
+    pub closing_balance: i128,
+    /// Sum of the positive amounts.
+    pub credits: i128,
+    /// Sum of the negative amounts. It is never positive.
+    pub debits: i128,
+    /// Number of entries that moved the field.
+    pub entries: u64,
+}
+
+impl AccountSummary {
+    pub fn new(account_id: AccountId, start_date: DateTime<Utc>, end_date: DateTime<Utc>) -> Self {
+        Self {
+            account_id,
+            start_date,
+            end_date,
+            entries: 0,
+            ledger_fields: HashMap::new(),
+        }
+    }
+
+    /// Adds an entry to the summary. Entries must be added in sequence order. Pending entries
+    /// are left out, as they don't move the balances.
+    pub fn add(&mut self, entry: &EntryWithBalance) {
+        if entry.status.is_pending() {
+            return;
+        }
+        self.entries += 1;
+        for (field_name, value) in entry.ledger_fields.iter() {
+            let balance = entry
+                .ledger_balances
+                .get(&LedgerBalanceName::from(field_name.clone()))
+                .copied()
+                .unwrap_or(*value);
+            let summary = self
+                .ledger_fields
+                .entry(field_name.clone())
+                .or_insert(FieldSummary {
+                    opening_balance: balance - value,
+                    closing_balance: balance,
+                    credits: 0,
+                    debits: 0,
+                    entries: 0,
+                });
+            summary.closing_balance = balance;
+            summary.entries += 1;
+            if *value >= 0 {
+                summary.credits += value;
+            } else {
+                summary.debits += value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::entity::{EntryBuilder, EntryWithBalanceBuilder};
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    #[test]
+    fn summarize_entries() {
+        let account_id = AccountId::new(Default::default());
+        let mut summary = AccountSummary::new(account_id.clone(), utc_now(), utc_now());
+        for (amount, balance) in [(100, 150), (-30, 120), (20, 140)] {
+            summary.add(
+                &EntryWithBalanceBuilder::from_entry(
+                    EntryBuilder::new()
+                        .with_account_id(account_id.clone())
+                        .with_ledger_field("amount", amount)
+                        .build(),
+                )
+                .with_ledger_balance("balance_amount", balance)
+                .build(),
+            );
+        }
+        assert_eq!(summary.entries, 3);
+        assert_eq!(
+            summary.ledger_fields,
+            HashMap::from([(
+                LedgerFieldName::new("amount".into()).expect("A valid field"),
+                FieldSummary {
+                    opening_balance: 50,
+                    closing_balance: 140,
+                    credits: 120,
+                    debits: -30,
+                    entries: 3,
+                }
+            )])
+        );
+    }
+}
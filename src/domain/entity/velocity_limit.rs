@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use anyhow::bail;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveTime, TimeDelta, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{AccountId, Actor, LedgerFieldName};
+
+/// Limits a ledger can have as defaults, and an account of its own. Each one updates a counter
+/// in the transaction of the entries, so they are kept few.
+pub const MAX_VELOCITY_LIMITS: usize = 4;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Ord, PartialOrd, Eq, Hash, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct VelocityLimitId(String);
+
+impl VelocityLimitId {
+    pub fn new(value: String) -> anyhow::Result<Self> {
+        if value.is_empty() || value.len() > 24 {
+            bail!("Limit id must have between 1 and 24 characters");
+        }
+        if !value
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+        {
+            bail!("Limit id can only have letters, digits, `-` and `_`");
+        }
+        Ok(Self(value))
+    }
+}
+
+impl TryFrom<String> for VelocityLimitId {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<VelocityLimitId> for String {
+    fn from(value: VelocityLimitId) -> String {
+        value.0
+    }
+}
+
+impl Display for VelocityLimitId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityDirection {
+    /// Counts the negative values of the ledger field.
+    Debit,
+    /// Counts the positive values of the ledger field.
+    Credit,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarUnit {
+    Day,
+    /// From Monday.
+    Week,
+    Month,
+}
+
+/// The time the amounts of a limit are counted over, in UTC.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityWindow {
+    /// The current day, week or month.
+    Calendar(CalendarUnit),
+    /// The current hour and the previous ones, up to this many hours.
+    RollingHours(u32),
+}
+
+/// At most `max_amount` of the ledger field can be counted in the direction of the limit
+/// within its window. A limit without `account_id` is a default of the ledger, and applies to
+/// every account without a limit of its own with the same id.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct VelocityLimit {
+    pub limit_id: VelocityLimitId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<AccountId>,
+    pub ledger_field: LedgerFieldName,
+    pub direction: VelocityDirection,
+    pub max_amount: i128,
+    pub window: VelocityWindow,
+    pub actor: Actor,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl VelocityLimit {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        limit_id: VelocityLimitId,
+        account_id: Option<AccountId>,
+        ledger_field: LedgerFieldName,
+        direction: VelocityDirection,
+        max_amount: i128,
+        window: VelocityWindow,
+        actor: Actor,
+        updated_at: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        if max_amount < 0 {
+            bail!("The max amount of a limit can't be negative");
+        }
+        if let VelocityWindow::RollingHours(hours) = window {
+            if !(1..=24 * 31).contains(&hours) {
+                bail!("A rolling window must have between 1 and 744 hours");
+            }
+        }
+        Ok(Self {
+            limit_id,
+            account_id,
+            ledger_field,
+            direction,
+            max_amount,
+            window,
+            actor,
+            updated_at,
+        })
+    }
+
+    /// The limits that apply to the account: its own, and the defaults it doesn't replace.
+    pub fn applicable<'a>(
+        limits: &'a [VelocityLimit],
+        account_id: &AccountId,
+    ) -> Vec<&'a VelocityLimit> {
+        let own = limits
+            .iter()
+            .filter(|limit| limit.account_id.as_ref() == Some(account_id))
+            .collect::<Vec<_>>();
+        limits
+            .iter()
+            .filter(|limit| {
+                limit.account_id.is_none() && !own.iter().any(|own| own.limit_id == limit.limit_id)
+            })
+            .chain(own.iter().copied())
+            .collect()
+    }
+
+    /// What an entry with these ledger fields counts towards the limit.
+    pub fn amount(&self, ledger_fields: &HashMap<LedgerFieldName, i128>) -> i128 {
+        let value = ledger_fields
+            .get(&self.ledger_field)
+            .copied()
+            .unwrap_or_default();
+        match self.direction {
+            VelocityDirection::Debit => (-value).max(0),
+            VelocityDirection::Credit => value.max(0),
+        }
+    }
+
+    /// The bucket the amounts counted at `date_time` are added to. The buckets of a window
+    /// sort as strings.
+    pub fn bucket(&self, date_time: &DateTime<Utc>) -> String {
+        match self.window {
+            VelocityWindow::Calendar(CalendarUnit::Day) => {
+                format!("DAY:{}", date_time.format("%Y-%m-%d"))
+            }
+            VelocityWindow::Calendar(CalendarUnit::Week) => {
+                let monday = date_time.date_naive()
+                    - TimeDelta::days(date_time.weekday().num_days_from_monday().into());
+                format!("WEEK:{}", monday.format("%Y-%m-%d"))
+            }
+            VelocityWindow::Calendar(CalendarUnit::Month) => {
+                format!("MONTH:{}", date_time.format("%Y-%m"))
+            }
+            VelocityWindow::RollingHours(_) => format!("HOUR:{}", date_time.format("%Y-%m-%dT%H")),
+        }
+    }
+
+    /// When the bucket of `date_time` stops being part of any window, so its counter can be
+    /// dropped.
+    pub fn expires_at(&self, date_time: &DateTime<Utc>) -> DateTime<Utc> {
+        let day = date_time.date_naive();
+        let end = match self.window {
+            VelocityWindow::Calendar(CalendarUnit::Day) => day.succ_opt(),
+            VelocityWindow::Calendar(CalendarUnit::Week) => day.checked_add_days(Days::new(
+                7 - u64::from(date_time.weekday().num_days_from_monday()),
+            )),
+            VelocityWindow::Calendar(CalendarUnit::Month) => day
+                .with_day(1)
+                .and_then(|first_day| first_day.checked_add_months(Months::new(1))),
+            VelocityWindow::RollingHours(hours) => {
+                return day.and_time(NaiveTime::MIN).and_utc()
+                    + TimeDelta::hours(i64::from(date_time.hour()) + i64::from(hours));
+            }
+        };
+        end.unwrap_or(NaiveDate::MAX)
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    }
+
+    /// The first bucket of the window at `date_time`. The window has every bucket from it to
+    /// the bucket of `date_time`.
+    pub fn first_bucket(&self, date_time: &DateTime<Utc>) -> String {
+        match self.window {
+            VelocityWindow::Calendar(_) => self.bucket(date_time),
+            VelocityWindow::RollingHours(hours) => {
+                self.bucket(&(*date_time - TimeDelta::hours(i64::from(hours) - 1)))
+            }
+        }
+    }
+}
+
+/// How much of a limit was counted in its current window.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct VelocityUsage {
+    #[serde(flatten)]
+    pub limit: VelocityLimit,
+    pub used_amount: i128,
+    pub remaining_amount: i128,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::entity::EntryBuilder;
+
+    use super::*;
+
+    fn limit(
+        limit_id: &str,
+        account_id: Option<AccountId>,
+        window: VelocityWindow,
+    ) -> VelocityLimit {
+        VelocityLimit::new(
+            VelocityLimitId::new(limit_id.into()).expect("A valid limit id"),
+            account_id,
+            LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+            VelocityDirection::Debit,
+            5_000,
+            window,
+            Actor::new("test".into()).expect("A valid actor"),
+            Utc::now(),
+        )
+        .expect("A valid limit")
+    }
+
+    #[test]
+    fn applicable_limits() {
+        let account_id = AccountId::new(Default::default());
+        let other_account_id = AccountId::new(uuid::Uuid::new_v4());
+        let window = VelocityWindow::Calendar(CalendarUnit::Day);
+        let limits = vec![
+            limit("daily", None, window),
+            limit("weekly", None, window),
+            limit("daily", Some(account_id.clone()), window),
+            limit("monthly", Some(other_account_id), window),
+        ];
+        let applicable = VelocityLimit::applicable(&limits, &account_id);
+        assert_eq!(applicable, vec![&limits[1], &limits[2]]);
+    }
+
+    #[test]
+    fn counted_amounts() {
+        let limit = limit("daily", None, VelocityWindow::Calendar(CalendarUnit::Day));
+        let debit = EntryBuilder::new()
+            .with_ledger_field("usd_amount", -300)
+            .build();
+        let credit = EntryBuilder::new()
+            .with_ledger_field("usd_amount", 300)
+            .build();
+        let other = EntryBuilder::new()
+            .with_ledger_field("brl_amount", -300)
+            .build();
+        assert_eq!(limit.amount(&debit.ledger_fields), 300);
+        assert_eq!(limit.amount(&credit.ledger_fields), 0);
+        assert_eq!(limit.amount(&other.ledger_fields), 0);
+    }
+
+    #[test]
+    fn window_buckets() {
+        let date_time = "2024-06-05 13:30:00 UTC".parse().expect("A valid date");
+        let cases = [
+            (CalendarUnit::Day, "DAY:2024-06-05"),
+            (CalendarUnit::Week, "WEEK:2024-06-03"),
+            (CalendarUnit::Month, "MONTH:2024-06"),
+        ];
+        for (unit, bucket) in cases {
+            let limit = limit("limit", None, VelocityWindow::Calendar(unit));
+            assert_eq!(limit.bucket(&date_time), bucket);
+            assert_eq!(limit.first_bucket(&date_time), bucket);
+        }
+        let limit = limit("limit", None, VelocityWindow::RollingHours(24));
+        assert_eq!(limit.bucket(&date_time), "HOUR:2024-06-05T13");
+        assert_eq!(limit.first_bucket(&date_time), "HOUR:2024-06-04T14");
+    }
+
+    #[test]
+    fn bucket_expiry() {
+        let date_time = "2024-06-05 13:30:00 UTC".parse().expect("A valid date");
+        let cases = [
+            (
+                VelocityWindow::Calendar(CalendarUnit::Day),
+                "2024-06-06 00:00:00 UTC",
+            ),
+            (
+                VelocityWindow::Calendar(CalendarUnit::Week),
+                "2024-06-10 00:00:00 UTC",
+            ),
+            (
+                VelocityWindow::Calendar(CalendarUnit::Month),
+                "2024-07-01 00:00:00 UTC",
+            ),
+            (VelocityWindow::RollingHours(24), "2024-06-06 13:00:00 UTC"),
+        ];
+        for (window, expires_at) in cases {
+            let limit = limit("limit", None, window);
+            assert_eq!(
+                limit.expires_at(&date_time),
+                expires_at.parse::<DateTime<Utc>>().expect("A valid date")
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::domain::entity::{
+    AccountId, EntryHash, EntryId, EntryStatus, EntryWithBalance, LedgerBalanceName,
+    LedgerFieldName, Snapshot,
+};
+
+/// An entry row as it is stored, with what tells a CurrentEntry row from a History one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StoredEntry {
+    pub entry: EntryWithBalance,
+    pub is_current: bool,
+    pub reverted_ledger_fields: HashMap<LedgerFieldName, i128>,
+}
+
+/// Every row of an account: its HEAD, if there is one, the rows of its entries ordered by
+/// sequence and its snapshots.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AccountRows {
+    pub head: Option<EntryWithBalance>,
+    /// The sequence of the first hashed entry, stored in the HEAD. Every row from it on must
+    /// have a hash.
+    pub hashed_from: Option<u64>,
+    pub entries: Vec<StoredEntry>,
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// Something in the rows of an account that doesn't match the replay of its entries.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// A stored balance is not the previous balance plus the entry, or is missing. Without a
+    /// sequence, it is a balance of the HEAD that is not the sum of every entry.
+    BalanceMismatch {
+        entry_id: EntryId,
+        sequence: Option<u64>,
+        balance: LedgerBalanceName,
+        stored: Option<i128>,
+        expected: i128,
+    },
+    /// A stored pending balance is not the previous one plus the pending entry, or minus the
+    /// pending entry posted by the row. Without a sequence, it is a pending balance of the HEAD.
+    PendingBalanceMismatch {
+        entry_id: EntryId,
+        sequence: Option<u64>,
+        balance: LedgerBalanceName,
+        stored: Option<i128>,
+        expected: i128,
+    },
+    /// The account has entries, but no HEAD.
+    MissingHead,
+    /// The HEAD doesn't point to the last entry of the account.
+    HeadMismatch {
+        entry_id: EntryId,
+        sequence: u64,
+        expected_entry_id: Option<EntryId>,
+        expected_sequence: Option<u64>,
+    },
+    /// No row has the sequences from `from` to `to`.
+    SequenceGap { from: u64, to: u64 },
+    /// More than one row has this sequence.
+    DuplicatedSequence {
+        sequence: u64,
+        entry_ids: Vec<EntryId>,
+    },
+    /// A History row without the row it reverts or that reverted it, or a posted or voided
+    /// row without the row that posted or voided it.
+    OrphanHistoryRow {
+        entry_id: EntryId,
+        sequence: u64,
+        status: EntryStatus,
+    },
+    /// The CurrentEntry row of an entry that was fully reverted, or moved to History when it
+    /// was posted or voided.
+    CurrentEntryForRevertedEntry { entry_id: EntryId, sequence: u64 },
+    /// The stored hash is not the hash of the row, or is missing after a hashed entry. Without
+    /// a sequence, it is the hash of the HEAD that is not the hash of the last entry.
+    HashMismatch {
+        entry_id: EntryId,
+        sequence: Option<u64>,
+        stored: Option<EntryHash>,
+        expected: Option<EntryHash>,
+    },
+    /// The `prev_hash` of the row is not the hash of the previous entry.
+    BrokenHashChain {
+        entry_id: EntryId,
+        sequence: u64,
+        prev_hash: Option<EntryHash>,
+        expected: Option<EntryHash>,
+    },
+    /// A snapshot whose balances or hash are not the ones of the entry at its sequence. Without
+    /// `expected_entry_id`, there is no entry at its sequence.
+    SnapshotMismatch {
+        sequence: u64,
+        entry_id: EntryId,
+        expected_entry_id: Option<EntryId>,
+    },
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct VerificationReport {
+    pub account_id: AccountId,
+    /// Number of entry rows replayed.
+    pub entries: u64,
+    /// Sequence of the snapshot the replay started from, when only the rows after it were read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_sequence: Option<u64>,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
@@ -1,31 +1,74 @@
+use std::collections::BTreeSet;
+
 use chrono::{DateTime, Utc};
 use thiserror::Error;
 
-use crate::domain::entity::Cursor;
-use crate::domain::entity::{AccountId, Conditional, EntryWithConditionals};
-use crate::domain::entity::{EntryId, EntryWithBalance};
+use crate::domain::entity::{Account, AccountCursor, AccountQuery, AccountTag, AccrualSchedule};
+use crate::domain::entity::{AccountId, AccountRows, Conditional, EntryWithConditionals};
+use crate::domain::entity::{Actor, ApiKey, AuditCursor, AuditQuery, AuditRecord};
+use crate::domain::entity::{Cursor, ScanCursor};
+use crate::domain::entity::{EntryId, EntryReversal, EntryWithBalance, LedgerId, Snapshot, Tenant};
+use crate::domain::entity::{FeeRule, FeeRuleId, LedgerFieldName, PendingResolution};
+use crate::domain::entity::{RecurringSchedule, RecurringScheduleId};
+use crate::domain::entity::{VelocityLimit, VelocityLimitId};
 
 use super::entity::EntryToContinue;
 use super::entity::Order;
+use super::entity::{Period, PeriodClosing, Reconciliation};
 
 pub trait LedgerEntryRepository {
     async fn append_entries(
         &self,
         account_id: &AccountId,
         entries: &[EntryWithConditionals],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, AppendEntriesError>;
+
+    /// Appends the entries of several accounts in one transaction, so either all of them are
+    /// applied or none is.
+    async fn append_linked_entries(
+        &self,
+        entries: &[(AccountId, Vec<EntryWithConditionals>)],
+        actor: &Actor,
     ) -> Result<Vec<EntryWithBalance>, AppendEntriesError>;
 
     async fn revert_entries(
         &self,
         account_id: &AccountId,
-        entries: &[EntryId],
+        reversals: &[EntryReversal],
+        actor: &Actor,
     ) -> Result<Vec<EntryWithBalance>, RevertEntriesError>;
 
+    async fn amend_entries(
+        &self,
+        account_id: &AccountId,
+        entries: &[EntryWithConditionals],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, AmendEntriesError>;
+
+    /// Posts or voids the pending entries of the account, returning the Applied or Void entries
+    /// appended for them. Posted entries are counted towards the velocity limits and bring the
+    /// entries the `rules` derive from them into the same transaction.
+    async fn resolve_pending_entries(
+        &self,
+        account_id: &AccountId,
+        entry_ids: &[EntryId],
+        resolution: PendingResolution,
+        rules: &[FeeRule],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, PendingEntriesError>;
+
     async fn get_balance(
         &self,
         account_id: &AccountId,
     ) -> Result<EntryWithBalance, GetBalanceError>;
 
+    /// The balances of the accounts that have one, in no particular order.
+    async fn get_balances(
+        &self,
+        account_ids: &[AccountId],
+    ) -> Result<Vec<EntryWithBalance>, GetBalanceError>;
+
     async fn get_entry(
         &self,
         account_id: &AccountId,
@@ -43,6 +86,209 @@ pub trait LedgerEntryRepository {
         order: &Order,
         sequence: Option<u64>,
     ) -> Result<(Vec<EntryWithBalance>, Option<Cursor>), GetBalanceError>;
+
+    /// One page of the entries of every account created between the dates, from one segment
+    /// of a parallel scan. The entries are not ordered, but they are the rows `get_entries`
+    /// returns for each account, one per sequence.
+    async fn scan_entries(
+        &self,
+        start_date: &DateTime<Utc>,
+        end_date: &DateTime<Utc>,
+        segment: i32,
+        total_segments: i32,
+        start_after: Option<ScanCursor>,
+    ) -> Result<(Vec<EntryWithBalance>, Option<ScanCursor>), GetBalanceError>;
+
+    /// Every row of the account, read with a parallel scan of the whole table.
+    async fn get_account_rows(
+        &self,
+        account_id: &AccountId,
+        total_segments: i32,
+    ) -> Result<AccountRows, GetBalanceError>;
+
+    /// The last snapshot of the account taken up to `as_of`.
+    async fn get_snapshot(
+        &self,
+        account_id: &AccountId,
+        as_of: &DateTime<Utc>,
+    ) -> Result<Option<Snapshot>, GetBalanceError>;
+
+    /// The HEAD of the account and the rows of the entries appended after the snapshot. The
+    /// snapshots are not read.
+    async fn get_account_rows_since(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<AccountRows, GetBalanceError>;
+
+    async fn close_period(
+        &self,
+        closing: &PeriodClosing,
+        head_sequence: u64,
+        actor: &Actor,
+    ) -> Result<(), ClosePeriodError>;
+
+    async fn get_period_closing(
+        &self,
+        account_id: &AccountId,
+        period: &Period,
+    ) -> Result<PeriodClosing, GetBalanceError>;
+
+    async fn get_audit_records(
+        &self,
+        query: &AuditQuery,
+        start_after: Option<(&AccountId, DateTime<Utc>, u64)>,
+        limit: u8,
+    ) -> Result<(Vec<AuditRecord>, Option<AuditCursor>), GetBalanceError>;
+
+    /// The parent of the account in the account hierarchy. None for a root account.
+    async fn get_parent(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<AccountId>, GetBalanceError>;
+
+    /// The direct children of the account in the account hierarchy.
+    async fn get_children(&self, account_id: &AccountId)
+        -> Result<Vec<AccountId>, GetBalanceError>;
+
+    /// Moves the account under `parent`, or makes it a root account without one. Fails when
+    /// its parent is no longer `previous_parent`.
+    async fn set_parent(
+        &self,
+        account_id: &AccountId,
+        parent: Option<&AccountId>,
+        previous_parent: Option<&AccountId>,
+        actor: &Actor,
+    ) -> Result<(), SetParentError>;
+
+    /// Accounts with entries matching the query, sorted by shard and then by id, after the
+    /// `start_after` account.
+    async fn list_accounts(
+        &self,
+        query: &AccountQuery,
+        start_after: Option<&AccountId>,
+        limit: u8,
+    ) -> Result<(Vec<Account>, Option<AccountCursor>), GetBalanceError>;
+
+    /// Replaces the tags of an account with entries. No tags removes them.
+    async fn set_account_tags(
+        &self,
+        account_id: &AccountId,
+        tags: &BTreeSet<AccountTag>,
+    ) -> Result<(), GetBalanceError>;
+
+    async fn save_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<(), ReconciliationError>;
+
+    async fn get_reconciliation(
+        &self,
+        account_id: &AccountId,
+        run_id: &str,
+    ) -> Result<Reconciliation, ReconciliationError>;
+
+    /// Creates the accrual schedule of the field, or replaces its rates. Its `posted_until`
+    /// is kept.
+    async fn save_accrual_schedule(&self, schedule: &AccrualSchedule) -> Result<(), AccrualError>;
+
+    /// The accrual schedules of the account, or of every account of the ledger.
+    async fn get_accrual_schedules(
+        &self,
+        account_id: Option<&AccountId>,
+    ) -> Result<Vec<AccrualSchedule>, AccrualError>;
+
+    async fn delete_accrual_schedule(
+        &self,
+        account_id: &AccountId,
+        ledger_field: &LedgerFieldName,
+    ) -> Result<(), AccrualError>;
+
+    /// Records that the accruals were posted until `period`. It is ignored when the schedule
+    /// was deleted or already posted a later period.
+    async fn set_accrual_posted_until(
+        &self,
+        account_id: &AccountId,
+        ledger_field: &LedgerFieldName,
+        period: &Period,
+    ) -> Result<(), AccrualError>;
+
+    /// Creates the rule, or replaces the one with the same id.
+    async fn save_fee_rule(&self, rule: &FeeRule) -> Result<(), FeeRuleError>;
+
+    /// The rules of the ledger, sorted by id.
+    async fn get_fee_rules(&self) -> Result<Vec<FeeRule>, FeeRuleError>;
+
+    async fn delete_fee_rule(&self, rule_id: &FeeRuleId) -> Result<(), FeeRuleError>;
+
+    /// Creates the recurring schedule, or replaces its definition. Its progress and whether it
+    /// is paused are kept.
+    async fn save_recurring_schedule(
+        &self,
+        schedule: &RecurringSchedule,
+    ) -> Result<(), RecurringScheduleError>;
+
+    /// The recurring schedules of the ledger, sorted by id.
+    async fn get_recurring_schedules(
+        &self,
+    ) -> Result<Vec<RecurringSchedule>, RecurringScheduleError>;
+
+    async fn pause_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+    ) -> Result<(), RecurringScheduleError>;
+
+    /// Resumes the schedule, skipping the occurrences until `resumed_at`.
+    async fn resume_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+        resumed_at: &DateTime<Utc>,
+    ) -> Result<(), RecurringScheduleError>;
+
+    async fn delete_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+    ) -> Result<(), RecurringScheduleError>;
+
+    /// Records that the schedule was posted until `occurrence`, with `occurrences` posted in
+    /// total. It is ignored when the schedule was deleted or already posted a later occurrence.
+    async fn set_recurring_posted_until(
+        &self,
+        schedule_id: &RecurringScheduleId,
+        occurrence: &DateTime<Utc>,
+        occurrences: u32,
+    ) -> Result<(), RecurringScheduleError>;
+
+    /// Creates the limit, or replaces the one with the same id and account. The amounts
+    /// already counted in its window are kept.
+    async fn save_velocity_limit(&self, limit: &VelocityLimit) -> Result<(), VelocityLimitError>;
+
+    /// The limits of the ledger, the defaults first, and then the ones of each account.
+    async fn get_velocity_limits(&self) -> Result<Vec<VelocityLimit>, VelocityLimitError>;
+
+    /// Deletes the limit of the account, or the default one without `account_id`.
+    async fn delete_velocity_limit(
+        &self,
+        account_id: Option<&AccountId>,
+        limit_id: &VelocityLimitId,
+    ) -> Result<(), VelocityLimitError>;
+
+    /// The amount counted by the account towards the limit in its window at `date_time`.
+    async fn get_velocity_used_amount(
+        &self,
+        account_id: &AccountId,
+        limit: &VelocityLimit,
+        date_time: &DateTime<Utc>,
+    ) -> Result<i128, VelocityLimitError>;
+}
+
+pub trait ApiKeyRepository {
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<(), ApiKeyError>;
+
+    async fn get_api_key(&self, key_id: &str) -> Result<ApiKey, ApiKeyError>;
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, ApiKeyError>;
+
+    async fn delete_api_key(&self, key_id: &str) -> Result<(), ApiKeyError>;
 }
 
 #[derive(Debug, Error)]
@@ -53,6 +299,8 @@ pub enum AppendEntriesError {
     EntriesAlreadyExists(AccountId, Vec<EntryId>),
     #[error("Fail processing conditions for entry `{0:?}: `{1:?}`")]
     ConditionFailed(EntryId, Conditional),
+    #[error("Entry `{0:?}` exceeds the velocity limit `{1}`")]
+    VelocityLimitExceeded(EntryId, VelocityLimitId),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -63,6 +311,12 @@ pub enum RevertEntriesError {
     OptimisticLockError(AccountId),
     #[error("Entries `{1:?}` does not exists in account `{0:?}`")]
     EntriesDoesNotExists(AccountId, Vec<EntryId>),
+    #[error("Entries `{1:?}` belong to a closed period in account `{0:?}`")]
+    PeriodClosed(AccountId, Vec<EntryId>),
+    #[error("Invalid reversal amounts for entries `{1:?}` in account `{0:?}`")]
+    InvalidReversal(AccountId, Vec<EntryId>),
+    #[error("Entries `{1:?}` are pending in account `{0:?}`")]
+    InvalidStatus(AccountId, Vec<EntryId>),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -78,6 +332,155 @@ impl From<AppendEntriesError> for RevertEntriesError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum AmendEntriesError {
+    #[error("Optimistic lock error in updating HEAD of account `{0:?}`")]
+    OptimisticLockError(AccountId),
+    #[error("Entries `{1:?}` does not exists in account `{0:?}`")]
+    EntriesDoesNotExists(AccountId, Vec<EntryId>),
+    #[error("Entries `{1:?}` belong to a closed period in account `{0:?}`")]
+    PeriodClosed(AccountId, Vec<EntryId>),
+    #[error("Fail processing conditions for entry `{0:?}: `{1:?}`")]
+    ConditionFailed(EntryId, Conditional),
+    #[error("Entries `{1:?}` are or would be pending in account `{0:?}`")]
+    InvalidStatus(AccountId, Vec<EntryId>),
+    #[error("Entry `{0:?}` exceeds the velocity limit `{1}`")]
+    VelocityLimitExceeded(EntryId, VelocityLimitId),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<AppendEntriesError> for AmendEntriesError {
+    fn from(value: AppendEntriesError) -> Self {
+        match value {
+            AppendEntriesError::OptimisticLockError(account_id) => {
+                Self::OptimisticLockError(account_id)
+            }
+            AppendEntriesError::ConditionFailed(entry_id, conditional) => {
+                Self::ConditionFailed(entry_id, conditional)
+            }
+            AppendEntriesError::VelocityLimitExceeded(entry_id, limit_id) => {
+                Self::VelocityLimitExceeded(entry_id, limit_id)
+            }
+            err => Self::Other(err.into()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PendingEntriesError {
+    #[error("Optimistic lock error in updating HEAD of account `{0:?}`")]
+    OptimisticLockError(AccountId),
+    #[error("Entries `{1:?}` does not exists in account `{0:?}`")]
+    EntriesDoesNotExists(AccountId, Vec<EntryId>),
+    #[error("Entries `{1:?}` are not pending in account `{0:?}`")]
+    InvalidStatus(AccountId, Vec<EntryId>),
+    #[error("Entry `{0:?}` exceeds the velocity limit `{1}`")]
+    VelocityLimitExceeded(EntryId, VelocityLimitId),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<AppendEntriesError> for PendingEntriesError {
+    fn from(value: AppendEntriesError) -> Self {
+        match value {
+            AppendEntriesError::OptimisticLockError(account_id) => {
+                Self::OptimisticLockError(account_id)
+            }
+            AppendEntriesError::VelocityLimitExceeded(entry_id, limit_id) => {
+                Self::VelocityLimitExceeded(entry_id, limit_id)
+            }
+            err => Self::Other(err.into()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClosePeriodError {
+    #[error("Optimistic lock error in updating HEAD of account `{0:?}`")]
+    OptimisticLockError(AccountId),
+    #[error("Period `{1}` is already closed for account `{0:?}`")]
+    PeriodAlreadyClosed(AccountId, Period),
+    #[error("Period `{0}` has not finished yet")]
+    PeriodNotFinished(Period),
+    #[error("Account not found with id `{0}`")]
+    AccountNotFound(AccountId),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum SetParentError {
+    #[error("Optimistic lock error in updating the parent of account `{0:?}`")]
+    OptimisticLockError(AccountId),
+    #[error("Account `{1:?}` is under account `{0:?}`, so it can't be its parent")]
+    Cycle(AccountId, AccountId),
+    #[error("The hierarchy of account `{0:?}` would be deeper than {1} levels")]
+    TooDeep(AccountId, usize),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RollUpError {
+    #[error("Account not found with id `{0}`")]
+    AccountNotFound(AccountId),
+    #[error("Account `{0:?}` has more than {1} accounts under it")]
+    TooManyAccounts(AccountId, usize),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ReconciliationError {
+    #[error("Reconciliation `{1}` not found for account `{0}`")]
+    NotFound(AccountId, String),
+    #[error("The statement has more than {0} entries")]
+    TooManyEntries(usize),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum AccrualError {
+    #[error("Accrual schedule of field `{1:?}` not found for account `{0}`")]
+    NotFound(AccountId, LedgerFieldName),
+    #[error("Invalid accrual schedule: {0}")]
+    InvalidSchedule(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RecurringScheduleError {
+    #[error("Recurring schedule `{0}` not found")]
+    NotFound(RecurringScheduleId),
+    #[error("Invalid recurring schedule: {0}")]
+    InvalidSchedule(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum FeeRuleError {
+    #[error("Rule `{0}` not found")]
+    NotFound(FeeRuleId),
+    #[error("Invalid rule: {0}")]
+    InvalidRule(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum VelocityLimitError {
+    #[error("Velocity limit `{0}` not found")]
+    NotFound(VelocityLimitId),
+    #[error("Invalid velocity limit: {0}")]
+    InvalidLimit(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum GetBalanceError {
     #[error("Account not found with id `{0}`")]
@@ -89,3 +492,36 @@ pub enum GetBalanceError {
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+pub trait TenantRepository {
+    async fn create_tenant(&self, tenant: &Tenant) -> Result<(), TenantError>;
+
+    async fn get_tenant(&self, ledger_id: &LedgerId) -> Result<Tenant, TenantError>;
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, TenantError>;
+
+    /// Deletes the tenant and every row of its ledger.
+    async fn delete_tenant(&self, ledger_id: &LedgerId) -> Result<(), TenantError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TenantError {
+    #[error("Ledger `{0}` already exists")]
+    AlreadyExists(LedgerId),
+    #[error("Ledger `{0}` not found")]
+    NotFound(LedgerId),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("Api key `{0}` already exists")]
+    AlreadyExists(String),
+    #[error("Api key `{0}` not found")]
+    NotFound(String),
+    #[error("Invalid credential for api key `{0}`")]
+    InvalidCredential(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeSet;
+
+use crate::domain::entity::{Account, AccountCursor, AccountId, AccountQuery, AccountTag};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+
+pub async fn list_accounts_use_case(
+    repository: &impl LedgerEntryRepository,
+    query: AccountQuery,
+    limit: u8,
+) -> Result<(Vec<Account>, Option<AccountCursor>), GetBalanceError> {
+    repository.list_accounts(&query, None, limit).await
+}
+
+pub async fn list_accounts_from_cursor_use_case(
+    repository: &impl LedgerEntryRepository,
+    cursor: AccountCursor,
+    limit: u8,
+) -> Result<(Vec<Account>, Option<AccountCursor>), GetBalanceError> {
+    repository
+        .list_accounts(&cursor.query, Some(&cursor.account_id), limit)
+        .await
+}
+
+/// Only accounts with entries can be tagged, because the tags are kept in their balance.
+pub async fn set_account_tags_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    tags: &BTreeSet<AccountTag>,
+) -> Result<(), GetBalanceError> {
+    repository.set_account_tags(account_id, tags).await
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use ulid::Ulid;
+
+    use crate::app::test::get_repository_for_ledger;
+    use crate::domain::entity::{BalanceFilter, LedgerId};
+    use crate::domain::use_case::push_entries::test::{
+        push_entry_with_date, push_multiple_entries,
+    };
+
+    use super::*;
+
+    async fn list_all(
+        repository: &impl LedgerEntryRepository,
+        query: AccountQuery,
+    ) -> Result<Vec<Account>> {
+        let (mut accounts, mut cursor) = list_accounts_use_case(repository, query, 2).await?;
+        while let Some(next) = cursor {
+            let page = list_accounts_from_cursor_use_case(repository, next, 2).await?;
+            accounts.extend(page.0);
+            cursor = page.1;
+        }
+        Ok(accounts)
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn list_accounts_with_filters() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let mut balances = Vec::new();
+        for _ in 0..5 {
+            let account_id: AccountId = Faker.fake();
+            balances.push(
+                push_multiple_entries(&repository, &account_id, 2)
+                    .await
+                    .remove(1),
+            );
+        }
+        let dormant: AccountId = Faker.fake();
+        let dormant_balance =
+            push_entry_with_date(&repository, &dormant, &"2020-01-01 00:00:00 UTC".parse()?).await;
+        balances.push(dormant_balance.clone());
+
+        let accounts = list_all(&repository, AccountQuery::default()).await?;
+        let mut account_ids = balances
+            .iter()
+            .map(|balance| balance.account_id.clone())
+            .collect::<Vec<_>>();
+        account_ids.sort();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|account| account.account_id.clone())
+                .collect::<Vec<_>>(),
+            account_ids
+        );
+
+        let overdrawn = list_all(
+            &repository,
+            AccountQuery {
+                balances: vec!["balance_amount<0".parse::<BalanceFilter>()?],
+                ..AccountQuery::default()
+            },
+        )
+        .await?;
+        let mut overdrawn_ids = balances
+            .iter()
+            .filter(|balance| balance.ledger_balances.values().all(|value| *value < 0))
+            .map(|balance| balance.account_id.clone())
+            .collect::<Vec<_>>();
+        overdrawn_ids.sort();
+        assert_eq!(
+            overdrawn
+                .iter()
+                .map(|account| account.account_id.clone())
+                .collect::<Vec<_>>(),
+            overdrawn_ids
+        );
+
+        let inactive = list_all(
+            &repository,
+            AccountQuery {
+                active_before: Some("2021-01-01 00:00:00 UTC".parse()?),
+                ..AccountQuery::default()
+            },
+        )
+        .await?;
+        assert_eq!(inactive.len(), 1);
+        assert_eq!(inactive[0].account_id, dormant);
+        assert_eq!(inactive[0].ledger_balances, dormant_balance.ledger_balances);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn list_accounts_by_tags() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let vip: AccountId = Faker.fake();
+        let other: AccountId = Faker.fake();
+        push_multiple_entries(&repository, &vip, 1).await;
+        push_multiple_entries(&repository, &other, 1).await;
+        let tags = BTreeSet::from([
+            AccountTag::new("vip".into())?,
+            AccountTag::new("eu".into())?,
+        ]);
+        set_account_tags_use_case(&repository, &vip, &tags).await?;
+        set_account_tags_use_case(
+            &repository,
+            &other,
+            &BTreeSet::from([AccountTag::new("eu".into())?]),
+        )
+        .await?;
+
+        let accounts = list_all(
+            &repository,
+            AccountQuery {
+                tags: BTreeSet::from([AccountTag::new("vip".into())?]),
+                ..AccountQuery::default()
+            },
+        )
+        .await?;
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_id, vip);
+        assert_eq!(accounts[0].tags, tags);
+
+        // Tags are kept when new entries update the balance.
+        push_multiple_entries(&repository, &vip, 1).await;
+        set_account_tags_use_case(&repository, &other, &BTreeSet::new()).await?;
+        let accounts = list_all(
+            &repository,
+            AccountQuery {
+                tags: BTreeSet::from([AccountTag::new("eu".into())?]),
+                ..AccountQuery::default()
+            },
+        )
+        .await?;
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_id, vip);
+
+        let result = set_account_tags_use_case(&repository, &Faker.fake(), &tags).await;
+        assert!(matches!(result, Err(GetBalanceError::NotFound(_))));
+        Ok(())
+    }
+}
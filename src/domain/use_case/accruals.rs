@@ -0,0 +1,248 @@
+use rand::Rng;
+
+use crate::domain::entity::{
+    AccountId, Accrual, AccrualRate, AccrualSchedule, Actor, DayCount, Entry, EntryWithBalance,
+    LedgerBalanceName, LedgerFieldName, Order, Period,
+};
+use crate::domain::gateway::{AccrualError, GetBalanceError, LedgerEntryRepository};
+use crate::domain::use_case::{
+    get_entries_from_cursor_use_case, get_entries_use_case, ledger_balances_before,
+    push_entries_use_case, NonAppliedReason,
+};
+use crate::utils::utc_now;
+
+pub async fn set_accrual_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    account_id: &AccountId,
+    ledger_field: &LedgerFieldName,
+    day_count: DayCount,
+    rates: Vec<AccrualRate>,
+) -> Result<AccrualSchedule, AccrualError> {
+    let schedule = AccrualSchedule::new(
+        account_id.clone(),
+        ledger_field.clone(),
+        day_count,
+        rates,
+        actor.clone(),
+        utc_now(),
+    )
+    .map_err(|err| AccrualError::InvalidSchedule(err.to_string()))?;
+    repository.save_accrual_schedule(&schedule).await?;
+    Ok(schedule)
+}
+
+pub async fn get_accrual_schedules_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: Option<&AccountId>,
+) -> Result<Vec<AccrualSchedule>, AccrualError> {
+    repository.get_accrual_schedules(account_id).await
+}
+
+pub async fn delete_accrual_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    ledger_field: &LedgerFieldName,
+) -> Result<(), AccrualError> {
+    repository
+        .delete_accrual_schedule(account_id, ledger_field)
+        .await
+}
+
+/// What the schedule of the field accrues in the period, without posting it. The period
+/// doesn't need to be finished, so the accrual so far can be previewed.
+pub async fn get_accrual_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    ledger_field: &LedgerFieldName,
+    period: &Period,
+) -> Result<Accrual, AccrualError> {
+    let schedule = repository
+        .get_accrual_schedules(Some(account_id))
+        .await?
+        .into_iter()
+        .find(|schedule| schedule.ledger_field == *ledger_field)
+        .ok_or(AccrualError::NotFound(
+            account_id.clone(),
+            ledger_field.clone(),
+        ))?;
+    accrue(repository, &schedule, period).await
+}
+
+/// Posts the accruals of every schedule of the ledger for the periods finished since they
+/// were last posted. Each accrual is a normal entry, created when it is posted, with the
+/// `entry_id` of its schedule and period, so a period is posted only once even when runs
+/// overlap. A schedule stops at the first period that fails to be posted, and continues from
+/// it on the next run.
+pub async fn post_accruals_use_case(
+    repository: &impl LedgerEntryRepository,
+    mut random_number_generator: impl Rng,
+    actor: &Actor,
+) -> Result<(Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>), AccrualError> {
+    let now = utc_now();
+    let mut applied_entries = Vec::new();
+    let mut non_applied_entries = Vec::new();
+    for schedule in repository.get_accrual_schedules(None).await? {
+        for period in schedule.periods_to_post(&now) {
+            let accrual = accrue(repository, &schedule, &period).await?;
+            if accrual.amount != 0 {
+                let (applied, non_applied) = push_entries_use_case(
+                    repository,
+                    &mut random_number_generator,
+                    actor,
+                    [accrual.entry().into()].into_iter(),
+                )
+                .await;
+                applied_entries.extend(applied);
+                if let Some((reason, entry)) = non_applied.into_iter().next() {
+                    if reason != NonAppliedReason::EntriesAlreadyExists {
+                        non_applied_entries.push((reason, entry));
+                        break;
+                    }
+                }
+            }
+            repository
+                .set_accrual_posted_until(&schedule.account_id, &schedule.ledger_field, &period)
+                .await?;
+        }
+    }
+    Ok((applied_entries, non_applied_entries))
+}
+
+/// The end of day balances of the period are found from the balance at its start, walking
+/// back from the HEAD, and the entries created in it.
+async fn accrue(
+    repository: &impl LedgerEntryRepository,
+    schedule: &AccrualSchedule,
+    period: &Period,
+) -> Result<Accrual, AccrualError> {
+    let head = match repository.get_balance(&schedule.account_id).await {
+        Ok(head) => head,
+        Err(GetBalanceError::NotFound(_)) => return Ok(Accrual::new(schedule, period, 0, &[])),
+        Err(err) => return Err(anyhow::Error::from(err).into()),
+    };
+    let opening_balance = ledger_balances_before(repository, &head, &period.start())
+        .await
+        .map_err(anyhow::Error::from)?
+        .get(&LedgerBalanceName::from(schedule.ledger_field.clone()))
+        .copied()
+        .unwrap_or_default();
+    let mut entries = Vec::new();
+    let mut page = get_entries_use_case(
+        repository,
+        &schedule.account_id,
+        &period.start(),
+        &period.end(),
+        100,
+        &Order::Asc,
+    )
+    .await
+    .map_err(anyhow::Error::from)?;
+    loop {
+        entries.extend(
+            page.0
+                .into_iter()
+                .filter(|entry| entry.created_at < period.end() && entry.sequence <= head.sequence),
+        );
+        let Some(cursor) = page.1 else {
+            break;
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100)
+            .await
+            .map_err(anyhow::Error::from)?;
+    }
+    Ok(Accrual::new(schedule, period, opening_balance, &entries))
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{EntryBuilder, EntryId, LedgerId};
+    use crate::utils::test::set_now;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn post_accruals_once_per_period() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let ledger_field = LedgerFieldName::new("amount".into())?;
+        for (created_at, amount) in [
+            ("2024-05-20 10:00:00 UTC", 1_000_000),
+            ("2024-06-11 10:00:00 UTC", 1_000_000),
+        ] {
+            set_now(&created_at.parse()?);
+            let (_, non_applied) = push_entries_use_case(
+                &repository,
+                get_rng().await,
+                &get_actor(),
+                [EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("amount", amount)
+                    .build()
+                    .into()]
+                .into_iter(),
+            )
+            .await;
+            assert!(non_applied.is_empty());
+        }
+        set_accrual_schedule_use_case(
+            &repository,
+            &get_actor(),
+            &account_id,
+            &ledger_field,
+            DayCount::Act360,
+            vec![AccrualRate {
+                effective_from: "2024-06-01".parse()?,
+                annual_rate_ppm: 36_000,
+            }],
+        )
+        .await?;
+
+        let june =
+            get_accrual_use_case(&repository, &account_id, &ledger_field, &"2024-06".parse()?)
+                .await?;
+        assert_eq!(june.amount, 1_000 + 4_000);
+
+        set_now(&"2024-07-15 00:00:00 UTC".parse()?);
+        let (applied, non_applied) =
+            post_accruals_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            applied[0].entry_id,
+            EntryId::new("accrual-2024-06-amount".into())?
+        );
+        assert_eq!(applied[0].ledger_fields.get(&ledger_field), Some(&5_000));
+        let schedules = get_accrual_schedules_use_case(&repository, Some(&account_id)).await?;
+        assert_eq!(schedules[0].posted_until, Some("2024-06".parse()?));
+
+        // Nothing is left to post, and posting the period again doesn't apply it twice.
+        let (applied, _) =
+            post_accruals_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert!(applied.is_empty());
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [june.entry().into()].into_iter(),
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(non_applied[0].0, NonAppliedReason::EntriesAlreadyExists);
+
+        delete_accrual_schedule_use_case(&repository, &account_id, &ledger_field).await?;
+        assert!(get_accrual_schedules_use_case(&repository, None)
+            .await?
+            .is_empty());
+        let result =
+            delete_accrual_schedule_use_case(&repository, &account_id, &ledger_field).await;
+        assert!(matches!(result, Err(AccrualError::NotFound(_, _))));
+        Ok(())
+    }
+}
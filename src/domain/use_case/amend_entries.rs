@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::domain::entity::{Actor, Entry, EntryWithBalance, EntryWithConditionals};
+use crate::domain::gateway::{AmendEntriesError, LedgerEntryRepository};
+use crate::domain::use_case;
+use crate::domain::use_case::NonAppliedReason;
+
+pub async fn amend_entries_use_case(
+    repository: &impl LedgerEntryRepository,
+    mut random_number_generator: impl Rng,
+    actor: &Actor,
+    entries: impl Iterator<Item = EntryWithConditionals> + Send + Sync,
+) -> (Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>) {
+    let entries_by_account_id = entries.into_group_map_by(|v| v.entry.account_id.clone());
+    let mut applied_entries_with_balance = Vec::new();
+    let mut non_applied_entries = Vec::new();
+
+    for (account_id, total_entries) in entries_by_account_id.into_iter() {
+        for entries in total_entries.chunks(use_case::chunk_size(&[], 3)) {
+            let mut entries = Vec::from(entries);
+            let mut tries = 0;
+            loop {
+                tries += 1;
+                if entries.is_empty() {
+                    break;
+                }
+                match repository.amend_entries(&account_id, &entries, actor).await {
+                    Ok(applied) => {
+                        applied_entries_with_balance.extend(applied);
+                        break;
+                    }
+                    Err(AmendEntriesError::OptimisticLockError(_)) if tries != 5 => {
+                        if tries == 1 {
+                            continue;
+                        }
+                        sleep(Duration::from_millis(
+                            random_number_generator.gen_range(10..100),
+                        ))
+                        .await;
+                    }
+                    Err(AmendEntriesError::EntriesDoesNotExists(_, entries_non_existent_ids)) => {
+                        let entries_not_found = use_case::extract_if(&mut entries, |entry| {
+                            entries_non_existent_ids.contains(&entry.entry.entry_id)
+                        });
+                        non_applied_entries.extend(
+                            entries_not_found
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::EntriesDoesNotExists, entry.entry)),
+                        );
+                    }
+                    Err(AmendEntriesError::PeriodClosed(_, entries_in_closed_period_ids)) => {
+                        let entries_in_closed_period =
+                            use_case::extract_if(&mut entries, |entry| {
+                                entries_in_closed_period_ids.contains(&entry.entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            entries_in_closed_period
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::PeriodClosed, entry.entry)),
+                        );
+                    }
+                    Err(AmendEntriesError::InvalidStatus(_, pending_entries_ids)) => {
+                        let pending_entries = use_case::extract_if(&mut entries, |entry| {
+                            pending_entries_ids.contains(&entry.entry.entry_id)
+                        });
+                        non_applied_entries.extend(
+                            pending_entries
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::InvalidStatus, entry.entry)),
+                        );
+                    }
+                    Err(AmendEntriesError::ConditionFailed(entry_id, _conditional)) => {
+                        let entry = use_case::extract_if(&mut entries, |entry| {
+                            entry.entry.entry_id == entry_id
+                        });
+                        non_applied_entries.extend(
+                            entry
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::ConditionFailed, entry.entry)),
+                        );
+                    }
+                    Err(AmendEntriesError::VelocityLimitExceeded(entry_id, _limit_id)) => {
+                        let entry = use_case::extract_if(&mut entries, |entry| {
+                            entry.entry.entry_id == entry_id
+                        });
+                        non_applied_entries.extend(
+                            entry.into_iter().map(|entry| {
+                                (NonAppliedReason::VelocityLimitExceeded, entry.entry)
+                            }),
+                        );
+                    }
+                    Err(err) => {
+                        non_applied_entries.extend(entries.into_iter().map(|entry| {
+                            (
+                                NonAppliedReason::from_amend_entries_error(&err),
+                                entry.entry,
+                            )
+                        }));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    (applied_entries_with_balance, non_applied_entries)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use std::collections::HashMap;
+
+    use crate::domain::entity::{
+        AccountId, DeleteEntryRequest, EntryBuilder, EntryStatus, LedgerBalanceName,
+        LedgerFieldName, Reversal,
+    };
+    use crate::domain::use_case::push_entries::test::push_multiple_entries;
+    use crate::domain::use_case::{
+        delete_entries_use_case, get_balance_use_case, get_entry_use_case, push_entries_use_case,
+    };
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn amend_entry() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries(&repository, &account_id, 2).await;
+        let mut corrected_entry: Entry = entries[0].clone().into();
+        corrected_entry
+            .ledger_fields
+            .values_mut()
+            .for_each(|value| *value = 10);
+
+        let (applied, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [corrected_entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(2, applied.len());
+        assert_eq!(EntryStatus::Revert(entries[0].sequence), applied[0].status);
+        assert_eq!(EntryStatus::Applied, applied[1].status);
+        assert_eq!(corrected_entry, applied[1].clone().into());
+        let balance_name = LedgerBalanceName::new("balance_amount".into())?;
+        assert_eq!(
+            entries[1].ledger_balances[&balance_name]
+                - entries[0].ledger_fields.values().sum::<i128>()
+                + 10,
+            get_balance_use_case(&repository, &account_id)
+                .await?
+                .ledger_balances[&balance_name]
+        );
+
+        let (entry_history, _) =
+            get_entry_use_case(&repository, &account_id, &entries[0].entry_id, 10).await?;
+        assert_eq!(
+            vec![
+                EntryStatus::Applied,
+                EntryStatus::Revert(entries[0].sequence),
+                EntryStatus::Reverted(applied[0].sequence),
+            ],
+            entry_history
+                .into_iter()
+                .map(|entry| entry.status)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn amend_entry_that_does_not_exist() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries(&repository, &account_id, 1).await;
+        let entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("amount", 10)
+            .build();
+
+        let (applied, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(
+            vec![(NonAppliedReason::EntriesDoesNotExists, entry)],
+            non_applied
+        );
+        assert_eq!(
+            entries[0],
+            get_balance_use_case(&repository, &account_id).await?
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn amend_partially_reverted_entry() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("amount", 100)
+            .build();
+        let (_, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entry.entry_id.clone(),
+                ledger_fields: Some(HashMap::from([(
+                    LedgerFieldName::new("amount".into())?,
+                    30,
+                )])),
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+
+        let corrected_entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_entry_id(entry.entry_id.clone())
+            .with_ledger_field("amount", 50)
+            .build();
+        let (applied, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [corrected_entry.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let balance_name = LedgerBalanceName::new("balance_amount".into())?;
+        assert_eq!(-70, applied[0].ledger_fields.values().sum::<i128>());
+        assert_eq!(0, applied[0].ledger_balances[&balance_name]);
+        assert_eq!(50, applied[1].ledger_balances[&balance_name]);
+        Ok(())
+    }
+}
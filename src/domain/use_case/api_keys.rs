@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use chrono::TimeDelta;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use ulid::Ulid;
+
+use crate::domain::entity::{
+    AccountScope, Actor, ApiKey, ApiKeyCredential, LedgerId, Principal, Scope,
+};
+use crate::domain::gateway::{ApiKeyError, ApiKeyRepository};
+use crate::utils::utc_now;
+
+/// How far the timestamp of a signed request can be from the server clock.
+const MAX_SIGNATURE_AGE: TimeDelta = TimeDelta::minutes(5);
+
+pub async fn create_api_key_use_case(
+    repository: &impl ApiKeyRepository,
+    actor: Actor,
+    scopes: HashSet<Scope>,
+    accounts: AccountScope,
+    ledger_id: Option<LedgerId>,
+) -> Result<ApiKey, ApiKeyError> {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let api_key = ApiKey {
+        key_id: Ulid::new().to_string(),
+        secret: hex::encode(secret),
+        actor,
+        scopes,
+        accounts,
+        ledger_id,
+        created_at: utc_now(),
+    };
+    repository.create_api_key(&api_key).await?;
+    Ok(api_key)
+}
+
+pub async fn list_api_keys_use_case(
+    repository: &impl ApiKeyRepository,
+) -> Result<Vec<ApiKey>, ApiKeyError> {
+    repository.list_api_keys().await
+}
+
+pub async fn revoke_api_key_use_case(
+    repository: &impl ApiKeyRepository,
+    key_id: &str,
+) -> Result<(), ApiKeyError> {
+    repository.delete_api_key(key_id).await
+}
+
+pub async fn authenticate_api_key_use_case(
+    repository: &impl ApiKeyRepository,
+    key_id: &str,
+    credential: ApiKeyCredential,
+) -> Result<Principal, ApiKeyError> {
+    let api_key = repository.get_api_key(key_id).await?;
+    let is_valid = match credential {
+        ApiKeyCredential::Secret(secret) => {
+            secret.as_bytes().ct_eq(api_key.secret.as_bytes()).into()
+        }
+        ApiKeyCredential::Signature {
+            signature,
+            string_to_sign,
+            signed_at,
+        } => {
+            (utc_now() - signed_at).abs() <= MAX_SIGNATURE_AGE
+                && hex::decode(signature).is_ok_and(|signature| {
+                    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(api_key.secret.as_bytes())
+                    else {
+                        return false;
+                    };
+                    mac.update(string_to_sign.as_bytes());
+                    mac.verify_slice(&signature).is_ok()
+                })
+        }
+    };
+    if !is_valid {
+        return Err(ApiKeyError::InvalidCredential(key_id.into()));
+    }
+    Ok(api_key.into())
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::get_api_key_repository;
+    use crate::domain::entity::AccountId;
+    use crate::utils::test::set_now;
+
+    use super::*;
+
+    async fn create_api_key(repository: &impl ApiKeyRepository) -> Result<ApiKey> {
+        Ok(create_api_key_use_case(
+            repository,
+            Actor::new("payments".into())?,
+            HashSet::from([Scope::Read, Scope::Push]),
+            AccountScope {
+                account_ids: HashSet::from([Faker.fake()]),
+                account_prefixes: vec![],
+            },
+            None,
+        )
+        .await?)
+    }
+
+    fn sign(secret: &str, string_to_sign: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Any key size is valid");
+        mac.update(string_to_sign.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn authenticate_with_secret() -> Result<()> {
+        let repository = get_api_key_repository().await;
+        let api_key = create_api_key(&repository).await?;
+
+        let principal = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Secret(api_key.secret.clone()),
+        )
+        .await?;
+        assert_eq!(Principal::from(api_key.clone()), principal);
+        assert!(principal.has_scope(Scope::Push));
+        assert!(!principal.has_scope(Scope::Delete));
+        assert!(!principal.accounts.allows(&Faker.fake::<AccountId>()));
+
+        let result = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Secret("wrong".into()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiKeyError::InvalidCredential(_))));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn authenticate_with_signature() -> Result<()> {
+        let repository = get_api_key_repository().await;
+        let api_key = create_api_key(&repository).await?;
+        set_now(&"2024-09-01 12:00:00 UTC".parse()?);
+        let string_to_sign = "1725192000\nPOST\n/api/v1/balance\nbody_hash";
+
+        let principal = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Signature {
+                signature: sign(&api_key.secret, string_to_sign),
+                string_to_sign: string_to_sign.into(),
+                signed_at: "2024-09-01 11:58:00 UTC".parse()?,
+            },
+        )
+        .await?;
+        assert_eq!(api_key.actor, principal.actor);
+
+        let result = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Signature {
+                signature: sign(&api_key.secret, string_to_sign),
+                string_to_sign: string_to_sign.into(),
+                signed_at: "2024-09-01 11:50:00 UTC".parse()?,
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ApiKeyError::InvalidCredential(_))));
+
+        let result = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Signature {
+                signature: sign("another secret", string_to_sign),
+                string_to_sign: string_to_sign.into(),
+                signed_at: "2024-09-01 12:00:00 UTC".parse()?,
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ApiKeyError::InvalidCredential(_))));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn revoked_api_key_cannot_authenticate() -> Result<()> {
+        let repository = get_api_key_repository().await;
+        let api_key = create_api_key(&repository).await?;
+        assert!(list_api_keys_use_case(&repository)
+            .await?
+            .contains(&api_key));
+
+        revoke_api_key_use_case(&repository, &api_key.key_id).await?;
+        let result = authenticate_api_key_use_case(
+            &repository,
+            &api_key.key_id,
+            ApiKeyCredential::Secret(api_key.secret.clone()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiKeyError::NotFound(_))));
+        assert!(matches!(
+            revoke_api_key_use_case(&repository, &api_key.key_id).await,
+            Err(ApiKeyError::NotFound(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use ed25519_dalek::SigningKey;
+
+use crate::domain::entity::{
+    AccountId, Attestation, LedgerBalanceName, LedgerId, Order, SignedAttestation,
+};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+use crate::domain::use_case::{get_entries_from_cursor_use_case, get_entries_use_case};
+use crate::utils::utc_now;
+
+/// Signs the current balance of the account, or the one it had at `as_of`.
+pub async fn attest_balance_use_case(
+    repository: &impl LedgerEntryRepository,
+    signing_key: &SigningKey,
+    account_id: &AccountId,
+    ledger_id: Option<&LedgerId>,
+    as_of: Option<DateTime<Utc>>,
+) -> Result<SignedAttestation, GetBalanceError> {
+    let attestation = balance_as_of(repository, account_id, as_of.unwrap_or_else(utc_now)).await?;
+    Ok(SignedAttestation::sign(
+        &Attestation {
+            ledger_id: ledger_id.cloned(),
+            ..attestation
+        },
+        signing_key,
+    )?)
+}
+
+async fn balance_as_of(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    as_of: DateTime<Utc>,
+) -> Result<Attestation, GetBalanceError> {
+    let head = repository.get_balance(account_id).await?;
+    let mut attestation = Attestation {
+        account_id: account_id.clone(),
+        ledger_id: None,
+        ledger_balances: head.ledger_balances.clone().into_iter().collect(),
+        sequence: head.sequence,
+        hash: head.hash.clone(),
+        as_of,
+        attested_at: utc_now(),
+    };
+    if head.created_at <= as_of {
+        return Ok(attestation);
+    }
+    if let Some(snapshot) = repository.get_snapshot(account_id, &as_of).await? {
+        // The last entry up to `as_of` is at most as old as the snapshot, so only the days
+        // since it are read.
+        let (entries, _) = get_entries_use_case(
+            repository,
+            account_id,
+            &snapshot.created_at,
+            &as_of,
+            1,
+            &Order::Desc,
+        )
+        .await?;
+        let (ledger_balances, sequence, hash) = match entries.into_iter().next() {
+            Some(entry) if entry.sequence > snapshot.sequence => {
+                (entry.ledger_balances, entry.sequence, entry.hash)
+            }
+            _ => (snapshot.ledger_balances, snapshot.sequence, snapshot.hash),
+        };
+        attestation.ledger_balances = ledger_balances.into_iter().collect();
+        attestation.sequence = sequence;
+        attestation.hash = hash;
+        return Ok(attestation);
+    }
+    // Without a snapshot, walk back from HEAD undoing every entry created after `as_of`. The
+    // first of them chains to the entry being attested.
+    let mut page = get_entries_use_case(
+        repository,
+        account_id,
+        &as_of,
+        &head.created_at,
+        100,
+        &Order::Desc,
+    )
+    .await?;
+    let mut first = None;
+    loop {
+        for entry in page
+            .0
+            .into_iter()
+            .filter(|entry| entry.sequence <= head.sequence && entry.created_at > as_of)
+        {
+            for (field_name, value) in entry.ledger_fields.iter() {
+                let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                let balance = entry
+                    .ledger_balances
+                    .get(&ledger_balance_name)
+                    .unwrap_or(&0);
+                attestation
+                    .ledger_balances
+                    .insert(ledger_balance_name, balance - value);
+            }
+            first = Some(entry);
+        }
+        let Some(cursor) = page.1 else {
+            break;
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100).await?;
+    }
+    if let Some(first) = first {
+        if first.sequence == 0 {
+            return Err(GetBalanceError::NotFound(account_id.clone()));
+        }
+        attestation.sequence = first.sequence - 1;
+        attestation.hash = first.prev_hash;
+    }
+    Ok(attestation)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use rand::rngs::OsRng;
+
+    use crate::app::test::{get_repository, get_repository_with_snapshots};
+    use crate::domain::use_case::push_entries::test::push_multiple_entries_with_date_interval;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn attest_current_and_past_balances() -> Result<()> {
+        let repository = get_repository().await;
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries_with_date_interval(&repository, &account_id, 4).await;
+
+        let current = attest_balance_use_case(&repository, &signing_key, &account_id, None, None)
+            .await?
+            .verify(&signing_key.verifying_key())?;
+        assert_eq!(current.sequence, entries[3].sequence);
+        assert_eq!(current.hash, entries[3].hash);
+        assert_eq!(
+            current.ledger_balances,
+            entries[3].ledger_balances.clone().into_iter().collect()
+        );
+
+        let as_of = entries[1].created_at + Duration::from_secs(10);
+        let past =
+            attest_balance_use_case(&repository, &signing_key, &account_id, None, Some(as_of))
+                .await?
+                .verify(&signing_key.verifying_key())?;
+        assert_eq!(past.sequence, entries[1].sequence);
+        assert_eq!(past.hash, entries[1].hash);
+        assert_eq!(
+            past.ledger_balances,
+            entries[1]
+                .ledger_balances
+                .clone()
+                .into_iter()
+                .collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(past.as_of, as_of);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn attest_past_balances_from_snapshots() -> Result<()> {
+        let repository = get_repository_with_snapshots(2).await;
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries_with_date_interval(&repository, &account_id, 4).await;
+        let snapshot = repository
+            .get_snapshot(&account_id, &entries[2].created_at)
+            .await?
+            .expect("A snapshot of the second entry");
+        assert_eq!(snapshot.sequence, entries[1].sequence);
+
+        for entry in &entries[1..3] {
+            let past = attest_balance_use_case(
+                &repository,
+                &signing_key,
+                &account_id,
+                None,
+                Some(entry.created_at + Duration::from_secs(10)),
+            )
+            .await?
+            .verify(&signing_key.verifying_key())?;
+            assert_eq!(past.sequence, entry.sequence);
+            assert_eq!(past.hash, entry.hash);
+            assert_eq!(
+                past.ledger_balances,
+                entry.ledger_balances.clone().into_iter().collect()
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn attest_before_the_first_entry() -> Result<()> {
+        let repository = get_repository().await;
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries_with_date_interval(&repository, &account_id, 2).await;
+        let result = attest_balance_use_case(
+            &repository,
+            &signing_key,
+            &account_id,
+            None,
+            Some(entries[0].created_at - Duration::from_secs(1)),
+        )
+        .await;
+        assert!(matches!(result, Err(GetBalanceError::NotFound(_))));
+        Ok(())
+    }
+}
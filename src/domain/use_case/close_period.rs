@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::domain::entity::{AccountId, Actor, Period, PeriodClosing};
+use crate::domain::gateway::{ClosePeriodError, GetBalanceError, LedgerEntryRepository};
+use crate::domain::use_case::{ledger_balances_before, NonAppliedReason};
+use crate::utils::utc_now;
+
+pub async fn close_period_use_case(
+    repository: &impl LedgerEntryRepository,
+    mut random_number_generator: impl Rng,
+    actor: &Actor,
+    period: &Period,
+    account_ids: impl Iterator<Item = AccountId> + Send + Sync,
+) -> Result<(Vec<PeriodClosing>, Vec<(NonAppliedReason, AccountId)>), ClosePeriodError> {
+    if period.end() > utc_now() {
+        return Err(ClosePeriodError::PeriodNotFinished(*period));
+    }
+    let mut closings = Vec::new();
+    let mut non_applied_accounts = Vec::new();
+
+    for account_id in account_ids.unique() {
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            match close_account_period(repository, &account_id, period, actor).await {
+                Ok(closing) => {
+                    closings.push(closing);
+                    break;
+                }
+                Err(ClosePeriodError::OptimisticLockError(_)) if tries != 5 => {
+                    if tries == 1 {
+                        continue;
+                    }
+                    sleep(Duration::from_millis(
+                        random_number_generator.gen_range(10..100),
+                    ))
+                    .await;
+                }
+                Err(err) => {
+                    non_applied_accounts.push((
+                        NonAppliedReason::from_close_period_error(&err),
+                        account_id.clone(),
+                    ));
+                    break;
+                }
+            }
+        }
+    }
+    Ok((closings, non_applied_accounts))
+}
+
+async fn close_account_period(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    period: &Period,
+    actor: &Actor,
+) -> Result<PeriodClosing, ClosePeriodError> {
+    let head = match repository.get_balance(account_id).await {
+        Ok(head) => head,
+        Err(GetBalanceError::NotFound(account_id)) => {
+            return Err(ClosePeriodError::AccountNotFound(account_id))
+        }
+        Err(err) => return Err(anyhow::Error::from(err).into()),
+    };
+    let ledger_balances = ledger_balances_before(repository, &head, &period.end())
+        .await
+        .map_err(anyhow::Error::from)?;
+    let closing = PeriodClosing {
+        account_id: account_id.clone(),
+        period: *period,
+        ledger_balances,
+        closed_at: utc_now(),
+    };
+    repository
+        .close_period(&closing, head.sequence, actor)
+        .await?;
+    Ok(closing)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use crate::domain::entity::{DeleteEntryRequest, Reversal};
+    use crate::domain::use_case::push_entries::test::push_entry_with_date;
+    use crate::domain::use_case::{delete_entries_use_case, get_period_closing_use_case};
+    use crate::utils::test::set_now;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn close_period_stores_closing_balance() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let _first = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-01-10 12:00:00 UTC".parse()?,
+        )
+        .await;
+        let last_of_january = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-01-31 23:59:59 UTC".parse()?,
+        )
+        .await;
+        let _february = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-02-01 00:00:00 UTC".parse()?,
+        )
+        .await;
+        set_now(&"2024-02-02 12:00:00 UTC".parse()?);
+
+        let period: Period = "2024-01".parse()?;
+        let (closings, non_applied) = close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &period,
+            [account_id.clone()].into_iter(),
+        )
+        .await?;
+        assert!(non_applied.is_empty());
+        assert_eq!(1, closings.len());
+        assert_eq!(last_of_january.ledger_balances, closings[0].ledger_balances);
+        assert_eq!(
+            closings[0],
+            get_period_closing_use_case(&repository, &account_id, &period).await?
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn close_period_twice_should_not_apply() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-03-10 12:00:00 UTC".parse()?,
+        )
+        .await;
+        set_now(&"2024-04-02 12:00:00 UTC".parse()?);
+
+        let period: Period = "2024-03".parse()?;
+        let (_, non_applied) = close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &period,
+            [account_id.clone()].into_iter(),
+        )
+        .await?;
+        assert!(non_applied.is_empty());
+        let (closings, non_applied) = close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &period,
+            [account_id.clone()].into_iter(),
+        )
+        .await?;
+        assert!(closings.is_empty());
+        assert_eq!(
+            vec![(NonAppliedReason::PeriodClosed, account_id)],
+            non_applied
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn close_unfinished_period_should_fail() -> Result<()> {
+        let repository = get_repository().await;
+        set_now(&"2024-05-20 12:00:00 UTC".parse()?);
+
+        let result = close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &"2024-05".parse()?,
+            [Faker.fake()].into_iter(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(ClosePeriodError::PeriodNotFinished(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn close_nonexistent_account_should_not_apply() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        set_now(&"2024-05-20 12:00:00 UTC".parse()?);
+
+        let (_, non_applied) = close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &"2024-04".parse()?,
+            [account_id.clone()].into_iter(),
+        )
+        .await?;
+        assert_eq!(
+            vec![(NonAppliedReason::AccountNotFound, account_id)],
+            non_applied
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn closed_period_rejects_revert() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entry = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-06-10 12:00:00 UTC".parse()?,
+        )
+        .await;
+        set_now(&"2024-07-01 00:00:01 UTC".parse()?);
+        close_period_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            &"2024-06".parse()?,
+            [account_id.clone()].into_iter(),
+        )
+        .await?;
+
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entry.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert_eq!(NonAppliedReason::PeriodClosed, non_applied[0].0);
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use rand::Rng;
 use tokio::time::sleep;
 
 use crate::domain::entity::DeleteEntryRequest;
-use crate::domain::entity::{EntryId, EntryWithBalance};
+use crate::domain::entity::{Actor, EntryReversal, EntryWithBalance};
 use crate::domain::gateway::{LedgerEntryRepository, RevertEntriesError};
 use crate::domain::use_case;
 use crate::domain::use_case::NonAppliedReason;
@@ -13,6 +13,7 @@ use crate::domain::use_case::NonAppliedReason;
 pub async fn delete_entries_use_case(
     repository: &impl LedgerEntryRepository,
     mut random_number_generator: impl Rng,
+    actor: &Actor,
     entries_to_delete: impl Iterator<Item = DeleteEntryRequest> + Send + Sync,
 ) -> (
     Vec<EntryWithBalance>,
@@ -23,16 +24,23 @@ pub async fn delete_entries_use_case(
     let mut non_applied_entries = Vec::new();
 
     for (account_id, total_entries) in entries_by_account_id.into_iter() {
-        for entries_to_delete in total_entries.chunks(33) {
+        for entries_to_delete in total_entries.chunks(use_case::chunk_size(&[], 3)) {
             let mut entries_to_delete = Vec::from(entries_to_delete);
-            let mut entries_ids = entries_to_delete
-                .iter()
-                .map(|entry_to_delete| entry_to_delete.entry_id.clone())
-                .collect::<Vec<EntryId>>();
             let mut tries = 0;
             loop {
                 tries += 1;
-                match repository.revert_entries(&account_id, &entries_ids).await {
+                if entries_to_delete.is_empty() {
+                    break;
+                }
+                let reversals = entries_to_delete
+                    .iter()
+                    .cloned()
+                    .map(EntryReversal::from)
+                    .collect_vec();
+                match repository
+                    .revert_entries(&account_id, &reversals, actor)
+                    .await
+                {
                     Ok(applied) => {
                         applied_entries_with_balance.extend(applied);
                         break;
@@ -51,15 +59,45 @@ pub async fn delete_entries_use_case(
                             use_case::extract_if(&mut entries_to_delete, |entry| {
                                 entries_non_existent_ids.contains(&entry.entry_id)
                             });
-                        let _ = use_case::extract_if(&mut entries_ids, |entry_id| {
-                            entries_non_existent_ids.contains(entry_id)
-                        });
                         non_applied_entries.extend(
                             entries_not_found
                                 .into_iter()
                                 .map(|entry| (NonAppliedReason::EntriesDoesNotExists, entry)),
                         );
                     }
+                    Err(RevertEntriesError::PeriodClosed(_, entries_in_closed_period_ids)) => {
+                        let entries_in_closed_period =
+                            use_case::extract_if(&mut entries_to_delete, |entry| {
+                                entries_in_closed_period_ids.contains(&entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            entries_in_closed_period
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::PeriodClosed, entry)),
+                        );
+                    }
+                    Err(RevertEntriesError::InvalidReversal(_, invalid_reversals_ids)) => {
+                        let invalid_reversals =
+                            use_case::extract_if(&mut entries_to_delete, |entry| {
+                                invalid_reversals_ids.contains(&entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            invalid_reversals
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::InvalidReversal, entry)),
+                        );
+                    }
+                    Err(RevertEntriesError::InvalidStatus(_, pending_entries_ids)) => {
+                        let pending_entries =
+                            use_case::extract_if(&mut entries_to_delete, |entry| {
+                                pending_entries_ids.contains(&entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            pending_entries
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::InvalidStatus, entry)),
+                        );
+                    }
                     Err(err) => {
                         non_applied_entries.extend(entries_to_delete.into_iter().map(|entry| {
                             (NonAppliedReason::from_revert_entries_error(&err), entry)
@@ -76,11 +114,15 @@ pub async fn delete_entries_use_case(
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use anyhow::Result;
     use fake::{Fake, Faker};
 
-    use crate::app::test::{get_repository, get_rng};
-    use crate::domain::entity::{LedgerBalanceName, Order};
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use crate::domain::entity::{
+        AccountId, EntryBuilder, EntryId, LedgerBalanceName, LedgerFieldName, Order, Reversal,
+    };
     use crate::domain::{
         entity::{DeleteEntryRequest, EntryStatus},
         use_case::{
@@ -100,14 +142,19 @@ mod test {
         let (applied, non_applied) = delete_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             vec![
                 DeleteEntryRequest {
                     account_id: account_id.clone(),
                     entry_id: EntryId::new("invalid".into())?,
+                    ledger_fields: None,
+                    reversal: Reversal::default(),
                 },
                 DeleteEntryRequest {
                     account_id: account_id.clone(),
                     entry_id: entries[0].entry_id.clone(),
+                    ledger_fields: None,
+                    reversal: Reversal::default(),
                 },
             ]
             .into_iter(),
@@ -129,6 +176,8 @@ mod test {
                 DeleteEntryRequest {
                     account_id: account_id.clone(),
                     entry_id: EntryId::new("invalid".into())?,
+                    ledger_fields: None,
+                    reversal: Reversal::default(),
                 }
             )],
             non_applied
@@ -146,14 +195,19 @@ mod test {
         let (applied, non_applied) = delete_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             vec![
                 DeleteEntryRequest {
                     account_id: account_id.clone(),
                     entry_id: entries[0].entry_id.clone(),
+                    ledger_fields: None,
+                    reversal: Reversal::default(),
                 },
                 DeleteEntryRequest {
                     account_id: account_id.clone(),
                     entry_id: entries[1].entry_id.clone(),
+                    ledger_fields: None,
+                    reversal: Reversal::default(),
                 },
             ]
             .into_iter(),
@@ -202,9 +256,12 @@ mod test {
         let (_, non_applied) = delete_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             vec![DeleteEntryRequest {
                 account_id: account_id.clone(),
                 entry_id: entries[0].entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
             }]
             .into_iter(),
         )
@@ -213,13 +270,120 @@ mod test {
         let (mut applied, non_applied) = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entries[0].clone().into()].into_iter(),
         )
         .await;
         assert!(non_applied.is_empty());
         assert_ne!(entries[0].sequence, applied[0].sequence);
+        assert_ne!(entries[0].hash, applied[0].hash);
         applied[0].sequence = entries[0].sequence;
+        applied[0].prev_hash = entries[0].prev_hash.clone();
+        applied[0].hash = entries[0].hash.clone();
         assert_eq!(entries, applied);
         Ok(())
     }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn partial_reversals() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", 100)
+            .with_ledger_field("local_amount", -500)
+            .build();
+        let (_, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let partial_reversal = |field: &str, value: i128| DeleteEntryRequest {
+            account_id: account_id.clone(),
+            entry_id: entry.entry_id.clone(),
+            ledger_fields: Some(HashMap::from([(
+                LedgerFieldName::new(field.into()).expect("A valid field name"),
+                value,
+            )])),
+            reversal: Reversal::default(),
+        };
+
+        let (applied, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [partial_reversal("usd_amount", 40)].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(
+            HashMap::from([(LedgerFieldName::new("usd_amount".into())?, -40)]),
+            applied[0].ledger_fields
+        );
+        assert_eq!(
+            HashMap::from([(LedgerBalanceName::new("balance_usd_amount".into())?, 60)]),
+            applied[0].ledger_balances
+        );
+
+        let (applied, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [
+                partial_reversal("usd_amount", 61),
+                partial_reversal("local_amount", 10),
+                partial_reversal("another_amount", 10),
+            ]
+            .into_iter(),
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(
+            vec![
+                NonAppliedReason::InvalidReversal,
+                NonAppliedReason::InvalidReversal,
+                NonAppliedReason::InvalidReversal
+            ],
+            non_applied
+                .into_iter()
+                .map(|(reason, _)| reason)
+                .collect::<Vec<_>>()
+        );
+
+        let (applied, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entry.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(
+            HashMap::from([
+                (LedgerFieldName::new("usd_amount".into())?, -60),
+                (LedgerFieldName::new("local_amount".into())?, 500)
+            ]),
+            applied[0].ledger_fields
+        );
+
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(1, applied.len());
+        Ok(())
+    }
 }
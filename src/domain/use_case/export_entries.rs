@@ -0,0 +1,212 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
+use tokio::sync::mpsc::Sender;
+
+use crate::domain::entity::{AccountId, Cursor, EntryWithBalance, Order};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+
+/// Sends every entry created between the dates, in batches, without holding them in memory.
+/// With an account they are sent in the order they were created, otherwise the entries of every
+/// account are read with a parallel scan of `segments` segments, in no particular order.
+pub async fn export_entries_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: Option<&AccountId>,
+    start_date: &DateTime<Utc>,
+    end_date: &DateTime<Utc>,
+    segments: i32,
+    sender: Sender<Vec<EntryWithBalance>>,
+) -> Result<(), GetBalanceError> {
+    match account_id {
+        Some(account_id) => {
+            let mut start_date = *start_date;
+            let mut sequence = None;
+            loop {
+                let (entries, cursor) = repository
+                    .get_entries(
+                        account_id,
+                        &start_date,
+                        end_date,
+                        100,
+                        &Order::Asc,
+                        sequence,
+                    )
+                    .await?;
+                send(&sender, entries).await?;
+                match cursor {
+                    Some(Cursor::FromEntriesQuery {
+                        start_date: next_start_date,
+                        sequence: next_sequence,
+                        ..
+                    }) => {
+                        start_date = next_start_date;
+                        sequence = Some(next_sequence);
+                    }
+                    _ => return Ok(()),
+                }
+            }
+        }
+        None => {
+            try_join_all((0..segments).map(|segment| {
+                export_segment(repository, start_date, end_date, segment, segments, &sender)
+            }))
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn export_segment(
+    repository: &impl LedgerEntryRepository,
+    start_date: &DateTime<Utc>,
+    end_date: &DateTime<Utc>,
+    segment: i32,
+    total_segments: i32,
+    sender: &Sender<Vec<EntryWithBalance>>,
+) -> Result<(), GetBalanceError> {
+    let mut cursor = None;
+    loop {
+        let (entries, next_cursor) = repository
+            .scan_entries(start_date, end_date, segment, total_segments, cursor)
+            .await?;
+        send(sender, entries).await?;
+        match next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => return Ok(()),
+        }
+    }
+}
+
+async fn send(
+    sender: &Sender<Vec<EntryWithBalance>>,
+    entries: Vec<EntryWithBalance>,
+) -> Result<(), GetBalanceError> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    sender
+        .send(entries)
+        .await
+        .map_err(|_| GetBalanceError::Other(anyhow!("The export was cancelled")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+    use chrono::Duration;
+    use fake::{Fake, Faker};
+    use tokio::sync::mpsc::channel;
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{DeleteEntryRequest, EntryId, LedgerId, Reversal};
+    use crate::domain::use_case::delete_entries_use_case;
+    use crate::domain::use_case::push_entries::test::{
+        push_multiple_entries, push_multiple_entries_with_date_interval,
+    };
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    async fn export(
+        repository: &impl LedgerEntryRepository,
+        account_id: Option<&AccountId>,
+        start_date: &DateTime<Utc>,
+        end_date: &DateTime<Utc>,
+    ) -> Result<Vec<EntryWithBalance>> {
+        let (sender, mut receiver) = channel(4);
+        let (result, entries) = tokio::join!(
+            export_entries_use_case(repository, account_id, start_date, end_date, 3, sender),
+            async move {
+                let mut entries = Vec::new();
+                while let Some(mut batch) = receiver.recv().await {
+                    entries.append(&mut batch);
+                }
+                entries
+            }
+        );
+        result?;
+        Ok(entries)
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn export_account_should_send_every_entry_in_order() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let start_date = utc_now();
+        let mut pushed = push_multiple_entries(&repository, &account_id, 150).await;
+        pushed.append(
+            &mut push_multiple_entries_with_date_interval(&repository, &account_id, 3).await,
+        );
+
+        let exported = export(
+            &repository,
+            Some(&account_id),
+            &start_date,
+            &(utc_now() + Duration::days(1)),
+        )
+        .await?;
+        assert_eq!(exported, pushed);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn export_all_accounts_should_send_every_entry_of_the_ledger() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let start_date = utc_now();
+        let mut pushed = HashSet::new();
+        for _ in 0..3 {
+            let account_id: AccountId = Faker.fake();
+            for entry in push_multiple_entries(&repository, &account_id, 20).await {
+                pushed.insert((entry.account_id, entry.entry_id));
+            }
+        }
+
+        let exported: HashSet<(AccountId, EntryId)> = export(
+            &repository,
+            None,
+            &start_date,
+            &(utc_now() + Duration::days(1)),
+        )
+        .await?
+        .into_iter()
+        .map(|entry| (entry.account_id, entry.entry_id))
+        .collect();
+        assert_eq!(exported, pushed);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn export_all_accounts_should_send_the_rows_of_the_account_export() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let start_date = utc_now();
+        let pushed = push_multiple_entries(&repository, &account_id, 3).await;
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: pushed[1].entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+
+        let end_date = utc_now() + Duration::days(1);
+        let account_export = export(&repository, Some(&account_id), &start_date, &end_date).await?;
+        let mut exported = export(&repository, None, &start_date, &end_date).await?;
+        exported.sort_by_key(|entry| entry.sequence);
+        assert_eq!(exported.len(), 4);
+        assert_eq!(exported, account_export);
+        Ok(())
+    }
+}
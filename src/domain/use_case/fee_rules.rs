@@ -0,0 +1,227 @@
+use crate::domain::entity::{
+    Actor, FeeAmount, FeeEntry, FeeRule, FeeRuleId, LedgerFieldName, RulePredicate,
+    MAX_DERIVED_ENTRIES,
+};
+use crate::domain::gateway::{FeeRuleError, LedgerEntryRepository};
+use crate::utils::utc_now;
+
+/// Creates the rule, or replaces it. It applies to the entries pushed from then on, as long as
+/// the rules of the ledger derive at most `MAX_DERIVED_ENTRIES` entries from each entry.
+pub async fn set_fee_rule_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    rule_id: &FeeRuleId,
+    when: Vec<RulePredicate>,
+    ledger_field: LedgerFieldName,
+    fee: FeeAmount,
+    entries: Vec<FeeEntry>,
+) -> Result<FeeRule, FeeRuleError> {
+    let rule = FeeRule::new(
+        rule_id.clone(),
+        when,
+        ledger_field,
+        fee,
+        entries,
+        actor.clone(),
+        utc_now(),
+    )
+    .map_err(|err| FeeRuleError::InvalidRule(err.to_string()))?;
+    let others = repository
+        .get_fee_rules()
+        .await?
+        .into_iter()
+        .filter(|other| other.rule_id != rule.rule_id)
+        .map(|other| other.entries.len())
+        .sum::<usize>();
+    if others + rule.entries.len() > MAX_DERIVED_ENTRIES {
+        return Err(FeeRuleError::InvalidRule(format!(
+            "The rules can derive at most {MAX_DERIVED_ENTRIES} entries from each entry"
+        )));
+    }
+    repository.save_fee_rule(&rule).await?;
+    Ok(rule)
+}
+
+pub async fn get_fee_rules_use_case(
+    repository: &impl LedgerEntryRepository,
+) -> Result<Vec<FeeRule>, FeeRuleError> {
+    repository.get_fee_rules().await
+}
+
+pub async fn delete_fee_rule_use_case(
+    repository: &impl LedgerEntryRepository,
+    rule_id: &FeeRuleId,
+) -> Result<(), FeeRuleError> {
+    repository.delete_fee_rule(rule_id).await
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use serde_json::json;
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{AccountId, EntryBuilder, LedgerId};
+    use crate::domain::use_case::{
+        self, get_balance_use_case, push_entries_use_case, NonAppliedReason,
+    };
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn push_entries_with_fee_rule() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let fee_account_id: AccountId = Faker.fake();
+        let ledger_field = LedgerFieldName::new("usd_amount".into())?;
+        let rule_id = FeeRuleId::new("card_fee".into())?;
+        set_fee_rule_use_case(
+            &repository,
+            &get_actor(),
+            &rule_id,
+            vec![
+                "usd_amount<0".parse()?,
+                "additional_fields.type=card_purchase".parse()?,
+            ],
+            ledger_field.clone(),
+            FeeAmount::Percentage {
+                rate_ppm: 15_000,
+                min: None,
+                max: None,
+            },
+            serde_json::from_value(json!([
+                {"ledger_field": "usd_amount", "direction": "debit"},
+                {"account_id": fee_account_id, "ledger_field": "usd_amount", "direction": "credit"},
+            ]))?,
+        )
+        .await?;
+        assert_eq!(get_fee_rules_use_case(&repository).await?.len(), 1);
+
+        let purchase = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -10_000)
+            .with_additional_fields(json!({"type": "card_purchase"}))
+            .build();
+        let deposit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", 20_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [purchase.clone().into(), deposit.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 4);
+        let balance = get_balance_use_case(&repository, &account_id).await?;
+        assert_eq!(
+            balance.ledger_balances.values().collect::<Vec<_>>(),
+            vec![&(-10_000 - 150 + 20_000)]
+        );
+        let fee_balance = get_balance_use_case(&repository, &fee_account_id).await?;
+        assert_eq!(
+            fee_balance.ledger_balances.values().collect::<Vec<_>>(),
+            vec![&150]
+        );
+
+        // Pushing the purchase again doesn't charge the fee twice.
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [purchase.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(
+            non_applied,
+            vec![(NonAppliedReason::EntriesAlreadyExists, purchase)]
+        );
+
+        delete_fee_rule_use_case(&repository, &rule_id).await?;
+        assert!(get_fee_rules_use_case(&repository).await?.is_empty());
+        let result = delete_fee_rule_use_case(&repository, &rule_id).await;
+        assert!(matches!(result, Err(FeeRuleError::NotFound(_))));
+        Ok(())
+    }
+
+    fn fee_entries(fee_account_id: &AccountId, count: usize) -> Vec<FeeEntry> {
+        (0..count)
+            .map(|_| {
+                serde_json::from_value(json!({
+                    "account_id": fee_account_id,
+                    "ledger_field": "usd_amount",
+                    "direction": "credit",
+                }))
+                .expect("A valid fee entry")
+            })
+            .collect()
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn reject_rules_deriving_too_many_entries() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let fee_account_id: AccountId = Faker.fake();
+        let set_rule = |rule_id: &'static str, count: usize| {
+            let repository = &repository;
+            let entries = fee_entries(&fee_account_id, count);
+            async move {
+                set_fee_rule_use_case(
+                    repository,
+                    &get_actor(),
+                    &FeeRuleId::new(rule_id.into())?,
+                    vec![],
+                    LedgerFieldName::new("usd_amount".into())?,
+                    FeeAmount::Fixed { amount: 10 },
+                    entries,
+                )
+                .await
+                .map_err(anyhow::Error::from)
+            }
+        };
+        set_rule("first", 4).await?;
+        let result = set_rule("second", MAX_DERIVED_ENTRIES - 3).await;
+        assert!(matches!(
+            result.map_err(|err| err.downcast::<FeeRuleError>()),
+            Err(Ok(FeeRuleError::InvalidRule(_)))
+        ));
+        set_rule("second", MAX_DERIVED_ENTRIES - 4).await?;
+        // Replacing a rule doesn't count the entries it derived before.
+        set_rule("first", 4).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn derived_entries_fit_in_a_transaction() {
+        let fee_account_id: AccountId = Faker.fake();
+        let rules = [4, MAX_DERIVED_ENTRIES - 4]
+            .into_iter()
+            .enumerate()
+            .map(|(index, count)| {
+                FeeRule::new(
+                    FeeRuleId::new(format!("rule_{index}")).expect("A valid rule id"),
+                    vec![],
+                    LedgerFieldName::new("usd_amount".into()).expect("A valid field"),
+                    FeeAmount::Fixed { amount: 10 },
+                    fee_entries(&fee_account_id, count),
+                    get_actor(),
+                    utc_now(),
+                )
+                .expect("A valid rule")
+            })
+            .collect::<Vec<_>>();
+        // Pushing takes an item for each entry, and posting two.
+        for items_per_entry in [1, 2] {
+            let entries = use_case::chunk_size(&rules, items_per_entry);
+            let transact_items = entries * (items_per_entry + MAX_DERIVED_ENTRIES)
+                + use_case::ITEMS_PER_ACCOUNT * (1 + MAX_DERIVED_ENTRIES);
+            assert!(transact_items <= use_case::MAX_TRANSACT_ITEMS);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::entity::{AccountId, Actor, AuditCursor, AuditQuery, AuditRecord};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+
+pub async fn get_audit_records_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: Option<AccountId>,
+    actor: Option<Actor>,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+    limit: u8,
+) -> Result<(Vec<AuditRecord>, Option<AuditCursor>), GetBalanceError> {
+    let query = AuditQuery {
+        account_id,
+        actor,
+        from: *from,
+        to: *to,
+    };
+    repository.get_audit_records(&query, None, limit).await
+}
+
+pub async fn get_audit_records_from_cursor_use_case(
+    repository: &impl LedgerEntryRepository,
+    cursor: AuditCursor,
+    limit: u8,
+) -> Result<(Vec<AuditRecord>, Option<AuditCursor>), GetBalanceError> {
+    repository
+        .get_audit_records(
+            &cursor.query,
+            Some((&cursor.account_id, cursor.created_at, cursor.sequence)),
+            limit,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_repository, get_rng};
+    use crate::domain::entity::{AuditAction, DeleteEntryRequest, EntryBuilder, Reversal};
+    use crate::domain::use_case::{delete_entries_use_case, push_entries_use_case};
+    use crate::utils::test::set_now;
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn every_change_is_audited() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let alice = Actor::new(format!("alice-{}", account_id))?;
+        let bob = Actor::new(format!("bob-{}", account_id))?;
+        set_now(&"2024-08-10 12:00:00 UTC".parse()?);
+        let start = utc_now();
+        let entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("amount", 10)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &alice,
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(Some(alice.clone()), applied[0].actor);
+        set_now(&(start + Duration::from_secs(60 * 60 * 24)));
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &bob,
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entry.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let end = utc_now();
+
+        let (records, cursor) = get_audit_records_use_case(
+            &repository,
+            Some(account_id.clone()),
+            None,
+            &start,
+            &end,
+            10,
+        )
+        .await?;
+        assert_eq!(None, cursor);
+        assert_eq!(
+            vec![
+                (alice.clone(), AuditAction::PushEntries, 0),
+                (bob.clone(), AuditAction::RevertEntries, 1)
+            ],
+            records
+                .iter()
+                .map(|record| (record.actor.clone(), record.action, record.sequence))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![entry.entry_id.clone()], records[1].entry_ids);
+
+        let (records, _) = get_audit_records_use_case(
+            &repository,
+            Some(account_id.clone()),
+            Some(bob.clone()),
+            &start,
+            &end,
+            10,
+        )
+        .await?;
+        assert_eq!(1, records.len());
+        assert_eq!(AuditAction::RevertEntries, records[0].action);
+
+        let (records, _) =
+            get_audit_records_use_case(&repository, None, Some(bob), &start, &end, 10).await?;
+        assert_eq!(1, records.len());
+        assert_eq!(account_id, records[0].account_id);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn get_audit_records_with_cursor() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let actor = Actor::new(format!("carol-{}", account_id))?;
+        set_now(&"2024-08-20 12:00:00 UTC".parse()?);
+        let start = utc_now();
+        for _ in 0..3 {
+            let (_, non_applied) = push_entries_use_case(
+                &repository,
+                get_rng().await,
+                &actor,
+                [EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("amount", 10)
+                    .build()
+                    .into()]
+                .into_iter(),
+            )
+            .await;
+            assert!(non_applied.is_empty());
+        }
+
+        let (records, Some(cursor)) =
+            get_audit_records_use_case(&repository, None, Some(actor), &start, &start, 2).await?
+        else {
+            panic!("Expected a cursor");
+        };
+        assert_eq!(
+            vec![0, 1],
+            records.iter().map(|r| r.sequence).collect::<Vec<_>>()
+        );
+        let (records, cursor) =
+            get_audit_records_from_cursor_use_case(&repository, cursor, 2).await?;
+        assert_eq!(
+            vec![2],
+            records.iter().map(|r| r.sequence).collect::<Vec<_>>()
+        );
+        assert_eq!(None, cursor);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn get_actor_audit_records_of_several_accounts_with_cursor() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = vec![Faker.fake(), Faker.fake()];
+        let actor = Actor::new(format!("dave-{}", account_ids[0]))?;
+        set_now(&"2024-08-25 12:00:00 UTC".parse()?);
+        let start = utc_now();
+        for account_id in &account_ids {
+            let (_, non_applied) = push_entries_use_case(
+                &repository,
+                get_rng().await,
+                &actor,
+                [EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("amount", 10)
+                    .build()
+                    .into()]
+                .into_iter(),
+            )
+            .await;
+            assert!(non_applied.is_empty());
+        }
+
+        // Both records have the same `created_at` and `sequence`.
+        let (first_page, Some(cursor)) =
+            get_audit_records_use_case(&repository, None, Some(actor), &start, &start, 1).await?
+        else {
+            panic!("Expected a cursor");
+        };
+        let (second_page, cursor) =
+            get_audit_records_from_cursor_use_case(&repository, cursor, 1).await?;
+        assert_eq!(None, cursor);
+        let mut found_account_ids = first_page
+            .iter()
+            .chain(&second_page)
+            .map(|record| record.account_id.clone())
+            .collect::<Vec<_>>();
+        found_account_ids.sort_by_key(|account_id| account_id.to_string());
+        let mut expected_account_ids = account_ids.clone();
+        expected_account_ids.sort_by_key(|account_id| account_id.to_string());
+        assert_eq!(expected_account_ids, found_account_ids);
+        Ok(())
+    }
+}
@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
 use crate::domain::entity::AccountId;
 use crate::domain::entity::EntryWithBalance;
+use crate::domain::entity::{Period, PeriodClosing};
 use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
 
 pub async fn get_balance_use_case(
@@ -9,6 +14,37 @@ pub async fn get_balance_use_case(
     repository.get_balance(account_id).await
 }
 
+/// The balances of the accounts, in the order of `account_ids`, and the accounts without
+/// one. Repeated accounts are only looked up once.
+pub async fn get_balances_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_ids: &[AccountId],
+) -> Result<(Vec<EntryWithBalance>, Vec<AccountId>), GetBalanceError> {
+    let mut balances = repository
+        .get_balances(account_ids)
+        .await?
+        .into_iter()
+        .map(|balance| (balance.account_id.clone(), balance))
+        .collect::<HashMap<AccountId, EntryWithBalance>>();
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for account_id in account_ids.iter().unique() {
+        match balances.remove(account_id) {
+            Some(balance) => found.push(balance),
+            None => missing.push(account_id.clone()),
+        }
+    }
+    Ok((found, missing))
+}
+
+pub async fn get_period_closing_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    period: &Period,
+) -> Result<PeriodClosing, GetBalanceError> {
+    repository.get_period_closing(account_id, period).await
+}
+
 #[cfg(test)]
 mod test {
     use anyhow::Result;
@@ -48,4 +84,31 @@ mod test {
         );
         Ok(())
     }
+    #[tokio_shared_rt::test(shared)]
+    async fn get_balances_of_many_accounts() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = (0..150).map(|_| Faker.fake()).collect();
+        let mut balances = Vec::new();
+        for account_id in account_ids.iter().step_by(2) {
+            balances.push(
+                push_multiple_entries(&repository, account_id, 2)
+                    .await
+                    .remove(1),
+            );
+        }
+        let mut requested = account_ids.clone();
+        requested.push(account_ids[0].clone());
+
+        let (found, missing) = get_balances_use_case(&repository, &requested).await?;
+        assert_eq!(balances, found);
+        assert_eq!(
+            account_ids
+                .into_iter()
+                .skip(1)
+                .step_by(2)
+                .collect::<Vec<_>>(),
+            missing
+        );
+        Ok(())
+    }
 }
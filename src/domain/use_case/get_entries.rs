@@ -250,9 +250,8 @@ mod test {
                     start_date: first_entries
                         .last()
                         .expect("We know the vector is not empty")
-                        .created_at
-                        .clone(),
-                    end_date: end_date,
+                        .created_at,
+                    end_date,
                     sequence: 2,
                     order: Order::Asc,
                 })
@@ -291,12 +290,11 @@ mod test {
                 first_entries.to_vec(),
                 Some(Cursor::FromEntriesQuery {
                     account_id: account_id.clone(),
-                    start_date: start_date,
+                    start_date,
                     end_date: first_entries
                         .last()
                         .expect("We know the vector is not empty")
-                        .created_at
-                        .clone(),
+                        .created_at,
                     sequence: 2,
                     order: Order::Desc,
                 })
@@ -57,9 +57,12 @@ async fn get_entry(
         account_id: account_id.clone(),
         entry_id: entry_id.clone(),
         entry_to_continue: match last.status {
-            EntryStatus::Applied => EntryToContinue::CurrentEntry,
-            EntryStatus::Reverted(_) => EntryToContinue::Sequence(last.sequence),
-            EntryStatus::Revert(_) => EntryToContinue::Sequence(last.sequence),
+            EntryStatus::Applied | EntryStatus::Pending => EntryToContinue::CurrentEntry,
+            EntryStatus::Reverted(_)
+            | EntryStatus::Revert(_)
+            | EntryStatus::Posted(_)
+            | EntryStatus::Void(_)
+            | EntryStatus::Voided(_) => EntryToContinue::Sequence(last.sequence),
         },
     });
     Ok((entries, cursor))
@@ -69,9 +72,9 @@ async fn get_entry(
 mod test {
     use super::*;
     use crate::{
-        app::test::{get_repository, get_rng},
+        app::test::{get_actor, get_repository, get_rng},
         domain::{
-            entity::DeleteEntryRequest,
+            entity::{DeleteEntryRequest, Reversal, ReversalReason},
             use_case::{
                 delete_entries_use_case, push_entries::test::push_multiple_entries,
                 push_entries_use_case,
@@ -80,6 +83,7 @@ mod test {
     };
     use anyhow::{bail, Result};
     use fake::{Fake, Faker};
+    use serde_json::json;
 
     #[tokio_shared_rt::test(shared)]
     async fn get_entry_without_any_revert() -> Result<()> {
@@ -122,19 +126,28 @@ mod test {
         let repository = get_repository().await;
         let account_id = Faker.fake();
         let mut entries = push_multiple_entries(&repository, &account_id, 1).await;
+        let reversal = Reversal {
+            reason: ReversalReason::Duplicate,
+            metadata: json!({"ticket": "OPS-1234"}),
+        };
         let (revert_entries, non_applied) = delete_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [DeleteEntryRequest {
                 account_id: entries[0].account_id.clone(),
                 entry_id: entries[0].entry_id.clone(),
+                ledger_fields: None,
+                reversal: reversal.clone(),
             }]
             .into_iter(),
         )
         .await;
         assert!(non_applied.is_empty());
+        assert_eq!(Some(reversal.clone()), revert_entries[0].reversal);
         let result = get_entry_use_case(&repository, &account_id, &entries[0].entry_id, 10).await?;
         entries[0].status = EntryStatus::Reverted(1);
+        entries[0].reversal = Some(reversal);
         assert_eq!(
             vec![revert_entries[0].clone(), entries[0].clone()],
             result.0
@@ -154,6 +167,7 @@ mod test {
         let mut entry_2 = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entry_1.clone().into()].into_iter(),
         )
         .await
@@ -163,6 +177,7 @@ mod test {
         let entry_3 = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entry_1.clone().into()].into_iter(),
         )
         .await
@@ -171,7 +186,9 @@ mod test {
 
         let result = get_entry_use_case(&repository, &account_id, &entry_1.entry_id, 10).await?;
         entry_1.status = EntryStatus::Reverted(1);
+        entry_1.reversal = Some(Reversal::default());
         entry_2.status = EntryStatus::Reverted(3);
+        entry_2.reversal = Some(Reversal::default());
         assert_eq!(
             vec![entry_3, revert_2, entry_2, revert_1, entry_1],
             result.0
@@ -192,6 +209,7 @@ mod test {
         let mut entry_2 = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entry_1.clone().into()].into_iter(),
         )
         .await
@@ -199,7 +217,9 @@ mod test {
         .remove(0);
         let revert_2 = revert_entry(&repository, &entry_1).await;
         entry_1.status = EntryStatus::Reverted(1);
+        entry_1.reversal = Some(Reversal::default());
         entry_2.status = EntryStatus::Reverted(3);
+        entry_2.reversal = Some(Reversal::default());
         let (entries, Some(cursor)) =
             get_entry_use_case(&repository, &account_id, &entry_id, 2).await?
         else {
@@ -228,9 +248,12 @@ mod test {
         let (mut revert_entries, non_applied) = delete_entries_use_case(
             repository,
             get_rng().await,
+            &get_actor(),
             [DeleteEntryRequest {
                 account_id: entry.account_id.clone(),
                 entry_id: entry.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
             }]
             .into_iter(),
         )
@@ -0,0 +1,119 @@
+use crate::domain::entity::{AccountId, Order, Period, Statement};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+use crate::domain::use_case::{
+    get_entries_from_cursor_use_case, get_entries_use_case, ledger_balances_before,
+};
+
+/// The statement of the account for the period. Its closing balances are found walking back
+/// from the HEAD, like when the period is closed, so it doesn't need to be closed first.
+pub async fn get_statement_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    period: &Period,
+) -> Result<Statement, GetBalanceError> {
+    let head = repository.get_balance(account_id).await?;
+    let closing_balances = ledger_balances_before(repository, &head, &period.end()).await?;
+    let mut entries = Vec::new();
+    let mut page = get_entries_use_case(
+        repository,
+        account_id,
+        &period.start(),
+        &period.end(),
+        100,
+        &Order::Asc,
+    )
+    .await?;
+    loop {
+        // The end of the period is the start of the next one.
+        entries.extend(
+            page.0
+                .into_iter()
+                .filter(|entry| entry.created_at < period.end() && entry.sequence <= head.sequence),
+        );
+        let Some(cursor) = page.1 else {
+            break;
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100).await?;
+    }
+    Ok(Statement::new(
+        account_id.clone(),
+        *period,
+        closing_balances,
+        entries,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use crate::domain::entity::{DeleteEntryRequest, Reversal};
+    use crate::domain::use_case::delete_entries_use_case;
+    use crate::domain::use_case::push_entries::test::push_entry_with_date;
+    use crate::utils::test::set_now;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn statement_of_a_period() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let june = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-06-30 23:59:59 UTC".parse()?,
+        )
+        .await;
+        let first = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-07-01 00:00:00 UTC".parse()?,
+        )
+        .await;
+        let second = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-07-15 12:00:00 UTC".parse()?,
+        )
+        .await;
+        set_now(&"2024-07-20 12:00:00 UTC".parse()?);
+        let (reverts, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: first.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let _august = push_entry_with_date(
+            &repository,
+            &account_id,
+            &"2024-08-01 00:00:00 UTC".parse()?,
+        )
+        .await;
+
+        let statement =
+            get_statement_use_case(&repository, &account_id, &"2024-07".parse()?).await?;
+        assert_eq!(statement.opening_balances, june.ledger_balances);
+        assert_eq!(statement.closing_balances, reverts[0].ledger_balances);
+        assert_eq!(
+            statement
+                .lines
+                .iter()
+                .map(|line| line.sequence)
+                .collect::<Vec<_>>(),
+            vec![first.sequence, second.sequence, reverts[0].sequence]
+        );
+        assert_eq!(statement.lines[0].reversed_by, Some(reverts[0].sequence));
+        assert_eq!(statement.lines[2].reverses, Some(first.sequence));
+        Ok(())
+    }
+}
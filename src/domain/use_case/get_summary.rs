@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::entity::{AccountId, AccountSummary, Order};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+use crate::domain::use_case::{get_entries_from_cursor_use_case, get_entries_use_case};
+
+/// Sums what moved in each ledger field of the account between the dates, reading every entry
+/// created between them.
+pub async fn get_summary_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    start_date: &DateTime<Utc>,
+    end_date: &DateTime<Utc>,
+) -> Result<AccountSummary, GetBalanceError> {
+    let mut summary = AccountSummary::new(account_id.clone(), *start_date, *end_date);
+    let mut page = get_entries_use_case(
+        repository,
+        account_id,
+        start_date,
+        end_date,
+        100,
+        &Order::Asc,
+    )
+    .await?;
+    loop {
+        for entry in page.0.iter() {
+            summary.add(entry);
+        }
+        let Some(cursor) = page.1 else {
+            return Ok(summary);
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100).await?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use crate::domain::entity::{
+        DeleteEntryRequest, EntryWithBalance, LedgerBalanceName, LedgerFieldName, Reversal,
+    };
+    use crate::domain::use_case::delete_entries_use_case;
+    use crate::domain::use_case::push_entries::test::push_multiple_entries_with_date_interval;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn summarize_entries_between_dates() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entries = push_multiple_entries_with_date_interval(&repository, &account_id, 4).await;
+        let (reverts, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entries[1].entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+
+        let amount = LedgerFieldName::new("amount".into())?;
+        let balance = |entry: &EntryWithBalance| {
+            entry.ledger_balances[&LedgerBalanceName::from(amount.clone())]
+        };
+        let start_date = entries[1].created_at;
+        let end_date = reverts[0].created_at + Duration::from_secs(1);
+        let summary =
+            get_summary_use_case(&repository, &account_id, &start_date, &end_date).await?;
+        let moved = [&entries[1], &entries[2], &entries[3], &reverts[0]]
+            .map(|entry| entry.ledger_fields[&amount]);
+        assert_eq!(summary.entries, 4);
+        assert_eq!(summary.ledger_fields.len(), 1);
+        let field_summary = &summary.ledger_fields[&amount];
+        assert_eq!(field_summary.opening_balance, balance(&entries[0]));
+        assert_eq!(field_summary.closing_balance, balance(&reverts[0]));
+        assert_eq!(
+            field_summary.credits,
+            moved.iter().filter(|value| **value >= 0).sum::<i128>()
+        );
+        assert_eq!(
+            field_summary.debits,
+            moved.iter().filter(|value| **value < 0).sum::<i128>()
+        );
+        assert_eq!(field_summary.entries, 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use crate::domain::entity::{AccountId, Actor, RollUp};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository, RollUpError, SetParentError};
+
+/// Accounts above an account, counting its parent.
+const MAX_DEPTH: usize = 32;
+/// Accounts read by a roll-up, counting the account itself.
+const MAX_ROLL_UP_ACCOUNTS: usize = 1_000;
+/// Accounts of a level of the subtree read at the same time.
+const ROLL_UP_FAN_OUT: usize = 16;
+
+/// Moves the account under `parent`, or makes it a root account without one. The parent
+/// can't be under the account, and the account can't end up with more than `MAX_DEPTH`
+/// accounts above it.
+pub async fn set_parent_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    account_id: &AccountId,
+    parent: Option<&AccountId>,
+) -> Result<(), SetParentError> {
+    if let Some(parent) = parent {
+        let mut ancestor = Some(parent.clone());
+        let mut depth = 0;
+        while let Some(current) = ancestor {
+            if current == *account_id {
+                return Err(SetParentError::Cycle(account_id.clone(), parent.clone()));
+            }
+            depth += 1;
+            if depth > MAX_DEPTH {
+                return Err(SetParentError::TooDeep(parent.clone(), MAX_DEPTH));
+            }
+            ancestor = repository
+                .get_parent(&current)
+                .await
+                .map_err(anyhow::Error::from)?;
+        }
+    }
+    let previous_parent = repository
+        .get_parent(account_id)
+        .await
+        .map_err(anyhow::Error::from)?;
+    repository
+        .set_parent(account_id, parent, previous_parent.as_ref(), actor)
+        .await
+}
+
+pub async fn get_children_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+) -> Result<Vec<AccountId>, GetBalanceError> {
+    repository.get_children(account_id).await
+}
+
+/// Adds the balances of the account and every account under it, reading the subtree level by
+/// level with up to `ROLL_UP_FAN_OUT` accounts at the same time. Each balance is read on its
+/// own, so entries appended during the roll-up may be left out.
+pub async fn get_roll_up_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+) -> Result<RollUp, RollUpError> {
+    let mut roll_up = RollUp::new(account_id.clone());
+    let mut visited = HashSet::from([account_id.clone()]);
+    let mut level = vec![account_id.clone()];
+    let mut balances = 0;
+    loop {
+        let heads = stream::iter(level.clone())
+            .map(|account_id| async move { repository.get_balance(&account_id).await })
+            .buffer_unordered(ROLL_UP_FAN_OUT)
+            .collect::<Vec<_>>()
+            .await;
+        for head in heads {
+            match head {
+                Ok(head) => {
+                    roll_up.add(&head);
+                    balances += 1;
+                }
+                Err(GetBalanceError::NotFound(_)) => {}
+                Err(err) => return Err(anyhow::Error::from(err).into()),
+            }
+        }
+        roll_up.accounts += level.len() as u64;
+        let children = stream::iter(level.clone())
+            .map(|account_id| async move { repository.get_children(&account_id).await })
+            .buffer_unordered(ROLL_UP_FAN_OUT)
+            .try_concat()
+            .await
+            .map_err(anyhow::Error::from)?;
+        // A concurrent move may leave an account under its own subtree for a while.
+        level = children
+            .into_iter()
+            .filter(|child| visited.insert(child.clone()))
+            .collect();
+        if level.is_empty() {
+            break;
+        }
+        if visited.len() > MAX_ROLL_UP_ACCOUNTS {
+            return Err(RollUpError::TooManyAccounts(
+                account_id.clone(),
+                MAX_ROLL_UP_ACCOUNTS,
+            ));
+        }
+        roll_up.depth += 1;
+    }
+    if balances == 0 && roll_up.accounts == 1 {
+        return Err(RollUpError::AccountNotFound(account_id.clone()));
+    }
+    Ok(roll_up)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository};
+    use crate::domain::entity::{EntryWithBalance, LedgerBalanceName};
+    use crate::domain::use_case::push_entries::test::push_multiple_entries;
+
+    use super::*;
+
+    fn added(balances: &[&EntryWithBalance]) -> HashMap<LedgerBalanceName, i128> {
+        let mut ledger_balances = HashMap::new();
+        for balance in balances {
+            for (ledger_balance_name, value) in balance.ledger_balances.iter() {
+                *ledger_balances
+                    .entry(ledger_balance_name.clone())
+                    .or_insert(0) += value;
+            }
+        }
+        ledger_balances
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn roll_up_after_reparenting() -> Result<()> {
+        let repository = get_repository().await;
+        let merchant: AccountId = Faker.fake();
+        let other_merchant: AccountId = Faker.fake();
+        let store: AccountId = Faker.fake();
+        let other_store: AccountId = Faker.fake();
+        let mut balances = Vec::new();
+        for account_id in [&merchant, &other_merchant, &store, &other_store] {
+            balances.push(
+                push_multiple_entries(&repository, account_id, 2)
+                    .await
+                    .remove(1),
+            );
+        }
+        set_parent_use_case(&repository, &get_actor(), &store, Some(&merchant)).await?;
+        set_parent_use_case(&repository, &get_actor(), &other_store, Some(&merchant)).await?;
+
+        let roll_up = get_roll_up_use_case(&repository, &merchant).await?;
+        assert_eq!(
+            roll_up.ledger_balances,
+            added(&[&balances[0], &balances[2], &balances[3]])
+        );
+        assert_eq!(roll_up.accounts, 3);
+        assert_eq!(roll_up.depth, 1);
+
+        set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &other_store,
+            Some(&other_merchant),
+        )
+        .await?;
+        assert_eq!(
+            get_roll_up_use_case(&repository, &merchant)
+                .await?
+                .ledger_balances,
+            added(&[&balances[0], &balances[2]])
+        );
+        assert_eq!(
+            get_roll_up_use_case(&repository, &other_merchant)
+                .await?
+                .ledger_balances,
+            added(&[&balances[1], &balances[3]])
+        );
+        assert_eq!(
+            get_children_use_case(&repository, &merchant).await?,
+            vec![store.clone()]
+        );
+
+        set_parent_use_case(&repository, &get_actor(), &store, None).await?;
+        assert_eq!(
+            get_roll_up_use_case(&repository, &merchant)
+                .await?
+                .ledger_balances,
+            balances[0].ledger_balances
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn roll_up_of_a_deep_tree() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = (0..10).map(|_| Faker.fake()).collect();
+        let mut balances = Vec::new();
+        for account_id in account_ids.iter() {
+            balances.push(
+                push_multiple_entries(&repository, account_id, 1)
+                    .await
+                    .remove(0),
+            );
+        }
+        for (parent, account_id) in account_ids.iter().zip(account_ids.iter().skip(1)) {
+            set_parent_use_case(&repository, &get_actor(), account_id, Some(parent)).await?;
+        }
+
+        let roll_up = get_roll_up_use_case(&repository, &account_ids[0]).await?;
+        assert_eq!(
+            roll_up.ledger_balances,
+            added(&balances.iter().collect::<Vec<_>>())
+        );
+        assert_eq!(roll_up.accounts, 10);
+        assert_eq!(roll_up.depth, 9);
+        let roll_up = get_roll_up_use_case(&repository, &account_ids[5]).await?;
+        assert_eq!(
+            roll_up.ledger_balances,
+            added(&balances[5..].iter().collect::<Vec<_>>())
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn set_parent_should_not_make_cycles() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = (0..3).map(|_| Faker.fake()).collect();
+        set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &account_ids[1],
+            Some(&account_ids[0]),
+        )
+        .await?;
+        set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &account_ids[2],
+            Some(&account_ids[1]),
+        )
+        .await?;
+
+        let result = set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &account_ids[0],
+            Some(&account_ids[2]),
+        )
+        .await;
+        assert!(matches!(result, Err(SetParentError::Cycle(_, _))));
+        let result = set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &account_ids[0],
+            Some(&account_ids[0]),
+        )
+        .await;
+        assert!(matches!(result, Err(SetParentError::Cycle(_, _))));
+        assert_eq!(repository.get_parent(&account_ids[0]).await?, None);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn set_parent_should_limit_the_depth() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = (0..MAX_DEPTH + 2).map(|_| Faker.fake()).collect();
+        for (parent, account_id) in account_ids
+            .iter()
+            .zip(account_ids.iter().skip(1))
+            .take(MAX_DEPTH)
+        {
+            set_parent_use_case(&repository, &get_actor(), account_id, Some(parent)).await?;
+        }
+
+        let result = set_parent_use_case(
+            &repository,
+            &get_actor(),
+            &account_ids[MAX_DEPTH + 1],
+            Some(&account_ids[MAX_DEPTH]),
+        )
+        .await;
+        assert!(matches!(result, Err(SetParentError::TooDeep(_, MAX_DEPTH))));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn roll_up_of_nonexistent_account() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let result = get_roll_up_use_case(&repository, &account_id).await;
+        assert!(matches!(result, Err(RollUpError::AccountNotFound(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use futures_util::{stream, StreamExt};
+use itertools::Itertools;
+use rand::Rng;
+
+use crate::domain::entity::{Actor, Entry, EntryWithBalance, EntryWithConditionals};
+use crate::domain::gateway::LedgerEntryRepository;
+use crate::domain::use_case::{push_entries_use_case, NonAppliedReason};
+
+/// Pushes a batch of imported entries through the same path as `push_entries_use_case`.
+/// The entries of an account are appended in the order they were given, while up to
+/// `concurrency` accounts are pushed at the same time.
+pub async fn import_entries_use_case(
+    repository: &impl LedgerEntryRepository,
+    random_number_generator: impl Rng + Clone,
+    actor: &Actor,
+    entries: Vec<EntryWithConditionals>,
+    concurrency: usize,
+) -> (Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>) {
+    let entries_by_account_id = entries
+        .into_iter()
+        .into_group_map_by(|entry| entry.entry.account_id.clone());
+    stream::iter(entries_by_account_id.into_values())
+        .map(|entries| {
+            push_entries_use_case(
+                repository,
+                random_number_generator.clone(),
+                actor,
+                entries.into_iter(),
+            )
+        })
+        .buffer_unordered(concurrency.max(1))
+        .fold(
+            (Vec::new(), Vec::new()),
+            |(mut applied, mut non_applied), (account_applied, account_non_applied)| async move {
+                applied.extend(account_applied);
+                non_applied.extend(account_non_applied);
+                (applied, non_applied)
+            },
+        )
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository, get_rng};
+    use crate::domain::entity::{AccountId, EntryBuilder, LedgerBalanceName};
+    use crate::domain::use_case::get_balance_use_case;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn import_should_keep_the_order_of_each_account() -> Result<()> {
+        let repository = get_repository().await;
+        let account_ids: Vec<AccountId> = (0..5).map(|_| Faker.fake()).collect();
+        let entries: Vec<Entry> = (1..=40)
+            .flat_map(|amount| {
+                account_ids.iter().map(move |account_id| {
+                    EntryBuilder::new()
+                        .with_account_id(account_id.clone())
+                        .with_ledger_field("local_amount", amount)
+                        .build()
+                })
+            })
+            .collect();
+
+        let (applied, non_applied) = import_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            entries
+                .iter()
+                .cloned()
+                .map(EntryWithConditionals::from)
+                .collect(),
+            3,
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), entries.len());
+        for account_id in account_ids.iter() {
+            let account_entries = applied
+                .iter()
+                .filter(|entry| &entry.account_id == account_id)
+                .collect::<Vec<_>>();
+            for (sequence, entry) in (0..).zip(account_entries.iter()) {
+                assert_eq!(entry.sequence, sequence);
+                assert_eq!(
+                    entry.ledger_fields.values().copied().collect::<Vec<i128>>(),
+                    vec![sequence as i128 + 1]
+                );
+            }
+            let balance = get_balance_use_case(&repository, account_id).await?;
+            assert_eq!(
+                balance.ledger_balances,
+                HashMap::from([(LedgerBalanceName::new("balance_local_amount".into())?, 820)])
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn import_twice_should_not_apply_the_entries_again() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entries: Vec<Entry> = (0..10)
+            .map(|_| {
+                EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("local_amount", 10)
+                    .build()
+            })
+            .collect();
+        import_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            entries
+                .iter()
+                .cloned()
+                .map(EntryWithConditionals::from)
+                .collect(),
+            2,
+        )
+        .await;
+
+        let (applied, non_applied) = import_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            entries
+                .iter()
+                .cloned()
+                .map(EntryWithConditionals::from)
+                .collect(),
+            2,
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(non_applied.len(), entries.len());
+        for (reason, entry) in non_applied {
+            assert_eq!(reason, NonAppliedReason::EntriesAlreadyExists);
+            assert!(entries.contains(&entry));
+        }
+        Ok(())
+    }
+}
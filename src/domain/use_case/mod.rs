@@ -1,16 +1,80 @@
+pub use accounts::{
+    list_accounts_from_cursor_use_case, list_accounts_use_case, set_account_tags_use_case,
+};
+pub use accruals::{
+    delete_accrual_schedule_use_case, get_accrual_schedules_use_case, get_accrual_use_case,
+    post_accruals_use_case, set_accrual_schedule_use_case,
+};
+pub use amend_entries::amend_entries_use_case;
+pub use api_keys::{
+    authenticate_api_key_use_case, create_api_key_use_case, list_api_keys_use_case,
+    revoke_api_key_use_case,
+};
+pub use attest_balance::attest_balance_use_case;
+pub use close_period::close_period_use_case;
 pub use delete_entries::delete_entries_use_case;
-pub use get_balance::get_balance_use_case;
+pub use export_entries::export_entries_use_case;
+pub use fee_rules::{delete_fee_rule_use_case, get_fee_rules_use_case, set_fee_rule_use_case};
+pub use get_audit_records::{get_audit_records_from_cursor_use_case, get_audit_records_use_case};
+pub use get_balance::{get_balance_use_case, get_balances_use_case, get_period_closing_use_case};
 pub use get_entries::{get_entries_from_cursor_use_case, get_entries_use_case};
 pub use get_entry::{get_entry_from_cursor_use_case, get_entry_use_case};
+pub use get_statement::get_statement_use_case;
+pub use get_summary::get_summary_use_case;
+pub use hierarchy::{get_children_use_case, get_roll_up_use_case, set_parent_use_case};
+pub use import_entries::import_entries_use_case;
+pub use pending_entries::resolve_pending_entries_use_case;
 pub use push_entries::push_entries_use_case;
+pub use reconciliation::{get_reconciliation_use_case, reconcile_use_case};
+pub use recurring::{
+    delete_recurring_schedule_use_case, get_recurring_schedule_use_case,
+    get_recurring_schedules_use_case, pause_recurring_schedule_use_case,
+    post_recurring_entries_use_case, resume_recurring_schedule_use_case,
+    set_recurring_schedule_use_case,
+};
+pub use tenants::{
+    create_tenant_use_case, delete_tenant_use_case, get_tenant_use_case, list_tenants_use_case,
+};
+pub use velocity_limits::{
+    delete_velocity_limit_use_case, get_velocity_limits_use_case, get_velocity_usage_use_case,
+    set_velocity_limit_use_case,
+};
+pub use verify_account::verify_account_use_case;
 
-use super::gateway::{AppendEntriesError, RevertEntriesError};
+use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
+
+use super::entity::{EntryWithBalance, FeeRule, LedgerBalanceName, Order, MAX_VELOCITY_LIMITS};
+use super::gateway::{
+    AmendEntriesError, AppendEntriesError, ClosePeriodError, GetBalanceError,
+    LedgerEntryRepository, PendingEntriesError, RevertEntriesError,
+};
+
+mod accounts;
+mod accruals;
+mod amend_entries;
+mod api_keys;
+mod attest_balance;
+mod close_period;
 mod delete_entries;
+mod export_entries;
+mod fee_rules;
+mod get_audit_records;
 mod get_balance;
 mod get_entries;
 mod get_entry;
+mod get_statement;
+mod get_summary;
+mod hierarchy;
+mod import_entries;
+mod pending_entries;
 mod push_entries;
+mod reconciliation;
+mod recurring;
+mod tenants;
+mod velocity_limits;
+mod verify_account;
 
 fn extract_if<T, F>(vector: &mut Vec<T>, predicate: F) -> Vec<T>
 where
@@ -28,12 +92,76 @@ where
     result
 }
 
+/// Items DynamoDB accepts in one transaction.
+const MAX_TRANSACT_ITEMS: usize = 100;
+
+/// Items of each account in a transaction besides its entries: the HEAD, the snapshot, the
+/// audit record and a counter per velocity limit, its own and the defaults.
+const ITEMS_PER_ACCOUNT: usize = 3 + 2 * MAX_VELOCITY_LIMITS;
+
+/// How many entries fit in one transaction when each of them takes `items_per_entry` items.
+/// Each entry can bring the entries derived from it, and the accounts of each of them, into the
+/// same transaction.
+fn chunk_size(rules: &[FeeRule], items_per_entry: usize) -> usize {
+    let derived_per_entry = rules.iter().map(|rule| rule.entries.len()).sum::<usize>();
+    (MAX_TRANSACT_ITEMS.saturating_sub(ITEMS_PER_ACCOUNT * (1 + derived_per_entry))
+        / (items_per_entry + derived_per_entry))
+        .max(1)
+}
+
+/// The balances of the account before the entries created from `date` on, walking back from
+/// the HEAD and undoing each of them.
+async fn ledger_balances_before(
+    repository: &impl LedgerEntryRepository,
+    head: &EntryWithBalance,
+    date: &DateTime<Utc>,
+) -> Result<HashMap<LedgerBalanceName, i128>, GetBalanceError> {
+    let mut ledger_balances = head.ledger_balances.clone();
+    if head.created_at < *date {
+        return Ok(ledger_balances);
+    }
+    let mut page = get_entries_use_case(
+        repository,
+        &head.account_id,
+        date,
+        &head.created_at,
+        100,
+        &Order::Desc,
+    )
+    .await?;
+    loop {
+        for entry in page
+            .0
+            .iter()
+            .filter(|entry| entry.sequence <= head.sequence && !entry.status.is_pending())
+        {
+            for (field_name, value) in entry.ledger_fields.iter() {
+                let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                let balance = entry
+                    .ledger_balances
+                    .get(&ledger_balance_name)
+                    .unwrap_or(&0);
+                ledger_balances.insert(ledger_balance_name, balance - value);
+            }
+        }
+        let Some(cursor) = page.1 else {
+            return Ok(ledger_balances);
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100).await?;
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NonAppliedReason {
     OptimisticLockFailed,
     EntriesAlreadyExists,
     EntriesDoesNotExists,
     ConditionFailed,
+    PeriodClosed,
+    AccountNotFound,
+    InvalidReversal,
+    VelocityLimitExceeded,
+    InvalidStatus,
     Other(String),
 }
 
@@ -44,6 +172,7 @@ impl NonAppliedReason {
             AppendEntriesError::OptimisticLockError(_) => Self::OptimisticLockFailed,
             AppendEntriesError::EntriesAlreadyExists(_, _) => Self::EntriesAlreadyExists,
             AppendEntriesError::ConditionFailed(_, _) => Self::ConditionFailed,
+            AppendEntriesError::VelocityLimitExceeded(_, _) => Self::VelocityLimitExceeded,
             AppendEntriesError::Other(err) => Self::Other(err.to_string()),
         }
     }
@@ -53,10 +182,47 @@ impl NonAppliedReason {
         match error {
             RevertEntriesError::OptimisticLockError(_) => Self::OptimisticLockFailed,
             RevertEntriesError::EntriesDoesNotExists(_, _) => Self::EntriesAlreadyExists,
+            RevertEntriesError::PeriodClosed(_, _) => Self::PeriodClosed,
+            RevertEntriesError::InvalidReversal(_, _) => Self::InvalidReversal,
+            RevertEntriesError::InvalidStatus(_, _) => Self::InvalidStatus,
             RevertEntriesError::Other(err) => Self::Other(err.to_string()),
         }
     }
 
+    pub fn from_amend_entries_error(error: &AmendEntriesError) -> Self {
+        tracing::warn!("Error amending entries: {error}");
+        match error {
+            AmendEntriesError::OptimisticLockError(_) => Self::OptimisticLockFailed,
+            AmendEntriesError::EntriesDoesNotExists(_, _) => Self::EntriesDoesNotExists,
+            AmendEntriesError::PeriodClosed(_, _) => Self::PeriodClosed,
+            AmendEntriesError::ConditionFailed(_, _) => Self::ConditionFailed,
+            AmendEntriesError::InvalidStatus(_, _) => Self::InvalidStatus,
+            AmendEntriesError::VelocityLimitExceeded(_, _) => Self::VelocityLimitExceeded,
+            AmendEntriesError::Other(err) => Self::Other(err.to_string()),
+        }
+    }
+
+    pub fn from_pending_entries_error(error: &PendingEntriesError) -> Self {
+        tracing::warn!("Error posting or voiding entries: {error}");
+        match error {
+            PendingEntriesError::OptimisticLockError(_) => Self::OptimisticLockFailed,
+            PendingEntriesError::EntriesDoesNotExists(_, _) => Self::EntriesDoesNotExists,
+            PendingEntriesError::InvalidStatus(_, _) => Self::InvalidStatus,
+            PendingEntriesError::VelocityLimitExceeded(_, _) => Self::VelocityLimitExceeded,
+            PendingEntriesError::Other(err) => Self::Other(err.to_string()),
+        }
+    }
+
+    pub fn from_close_period_error(error: &ClosePeriodError) -> Self {
+        tracing::warn!("Error closing period: {error}");
+        match error {
+            ClosePeriodError::OptimisticLockError(_) => Self::OptimisticLockFailed,
+            ClosePeriodError::PeriodAlreadyClosed(_, _) => Self::PeriodClosed,
+            ClosePeriodError::AccountNotFound(_) => Self::AccountNotFound,
+            err => Self::Other(err.to_string()),
+        }
+    }
+
     pub fn message(&self) -> String {
         match self {
             Self::OptimisticLockFailed => "Optimistic lock failed. Try again later".into(),
@@ -65,6 +231,13 @@ impl NonAppliedReason {
                 "Entry does not exist or reverted for this account".into()
             }
             Self::ConditionFailed => "Condition failed for this entry".into(),
+            Self::PeriodClosed => "The accounting period is closed for this account".into(),
+            Self::AccountNotFound => "Account not found".into(),
+            Self::InvalidReversal => {
+                "Reversal amounts are not part of what remains of this entry".into()
+            }
+            Self::VelocityLimitExceeded => "Velocity limit exceeded for this entry".into(),
+            Self::InvalidStatus => "The entry status doesn't allow this change".into(),
             Self::Other(err) => format!("Other unexpected error: {err}"),
         }
     }
@@ -75,7 +248,12 @@ impl NonAppliedReason {
             Self::EntriesAlreadyExists => 200,
             Self::EntriesDoesNotExists => 300,
             Self::ConditionFailed => 400,
+            Self::PeriodClosed => 500,
+            Self::AccountNotFound => 600,
+            Self::InvalidReversal => 700,
+            Self::VelocityLimitExceeded => 800,
             Self::Other(_) => 900,
+            Self::InvalidStatus => 1000,
         }
     }
 }
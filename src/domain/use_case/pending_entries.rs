@@ -0,0 +1,343 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::domain::entity::{Actor, EntryWithBalance, PendingEntryRequest, PendingResolution};
+use crate::domain::gateway::{LedgerEntryRepository, PendingEntriesError};
+use crate::domain::use_case;
+use crate::domain::use_case::NonAppliedReason;
+
+/// Posts the pending entries to the ledger balances, or voids them, returning the Applied or
+/// Void entries appended for them.
+pub async fn resolve_pending_entries_use_case(
+    repository: &impl LedgerEntryRepository,
+    mut random_number_generator: impl Rng,
+    actor: &Actor,
+    resolution: PendingResolution,
+    pending_entries: impl Iterator<Item = PendingEntryRequest> + Send + Sync,
+) -> (
+    Vec<EntryWithBalance>,
+    Vec<(NonAppliedReason, PendingEntryRequest)>,
+) {
+    let entries_by_account_id = pending_entries.into_group_map_by(|v| v.account_id.clone());
+    let mut applied_entries_with_balance = Vec::new();
+    let mut non_applied_entries = Vec::new();
+    // Voided entries don't match the fee rules.
+    let rules = match resolution {
+        PendingResolution::Post => match repository.get_fee_rules().await {
+            Ok(rules) => rules,
+            Err(err) => {
+                tracing::warn!("Error reading fee rules: {err}");
+                non_applied_entries.extend(
+                    entries_by_account_id
+                        .into_values()
+                        .flatten()
+                        .map(|entry| (NonAppliedReason::Other(err.to_string()), entry)),
+                );
+                return (applied_entries_with_balance, non_applied_entries);
+            }
+        },
+        PendingResolution::Void => Vec::new(),
+    };
+    // Each entry replaces its pending row and moves it to the history, and a voided one also
+    // deletes its CurrentEntry row.
+    let chunk_size = match resolution {
+        PendingResolution::Post => use_case::chunk_size(&rules, 2),
+        PendingResolution::Void => 32,
+    };
+
+    for (account_id, total_entries) in entries_by_account_id.into_iter() {
+        for pending_entries in total_entries.chunks(chunk_size) {
+            let mut pending_entries = Vec::from(pending_entries);
+            let mut tries = 0;
+            loop {
+                tries += 1;
+                if pending_entries.is_empty() {
+                    break;
+                }
+                let entry_ids = pending_entries
+                    .iter()
+                    .map(|entry| entry.entry_id.clone())
+                    .collect_vec();
+                match repository
+                    .resolve_pending_entries(&account_id, &entry_ids, resolution, &rules, actor)
+                    .await
+                {
+                    Ok(applied) => {
+                        applied_entries_with_balance.extend(applied);
+                        break;
+                    }
+                    Err(PendingEntriesError::OptimisticLockError(_)) if tries != 5 => {
+                        if tries == 1 {
+                            continue;
+                        }
+                        sleep(Duration::from_millis(
+                            random_number_generator.gen_range(10..100),
+                        ))
+                        .await;
+                    }
+                    Err(PendingEntriesError::EntriesDoesNotExists(_, entries_non_existent_ids)) => {
+                        let entries_not_found =
+                            use_case::extract_if(&mut pending_entries, |entry| {
+                                entries_non_existent_ids.contains(&entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            entries_not_found
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::EntriesDoesNotExists, entry)),
+                        );
+                    }
+                    Err(PendingEntriesError::InvalidStatus(_, not_pending_entries_ids)) => {
+                        let not_pending_entries =
+                            use_case::extract_if(&mut pending_entries, |entry| {
+                                not_pending_entries_ids.contains(&entry.entry_id)
+                            });
+                        non_applied_entries.extend(
+                            not_pending_entries
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::InvalidStatus, entry)),
+                        );
+                    }
+                    Err(PendingEntriesError::VelocityLimitExceeded(entry_id, _limit_id)) => {
+                        let exceeding_entries =
+                            use_case::extract_if(&mut pending_entries, |entry| {
+                                entry.entry_id == entry_id
+                            });
+                        if exceeding_entries.is_empty() {
+                            non_applied_entries.extend(
+                                pending_entries
+                                    .into_iter()
+                                    .map(|entry| (NonAppliedReason::VelocityLimitExceeded, entry)),
+                            );
+                            break;
+                        }
+                        non_applied_entries.extend(
+                            exceeding_entries
+                                .into_iter()
+                                .map(|entry| (NonAppliedReason::VelocityLimitExceeded, entry)),
+                        );
+                    }
+                    Err(err) => {
+                        non_applied_entries.extend(pending_entries.into_iter().map(|entry| {
+                            (NonAppliedReason::from_pending_entries_error(&err), entry)
+                        }));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    (applied_entries_with_balance, non_applied_entries)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use serde_json::json;
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{
+        AccountId, DeleteEntryRequest, Entry, EntryBuilder, EntryStatus, FeeAmount, FeeRuleId,
+        LedgerBalanceName, LedgerFieldName, LedgerId, Reversal,
+    };
+    use crate::domain::use_case::{
+        delete_entries_use_case, get_balance_use_case, get_entry_use_case, push_entries_use_case,
+        set_fee_rule_use_case,
+    };
+
+    use super::*;
+
+    fn pending_entry(account_id: &AccountId, amount: i128) -> Entry {
+        let mut entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("amount", amount)
+            .build();
+        entry.status = EntryStatus::Pending;
+        entry
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn post_and_void_pending_entries() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let balance = LedgerBalanceName::new("balance_amount".into())?;
+        let deposit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("amount", 100)
+            .build();
+        let to_post = pending_entry(&account_id, -30);
+        let to_void = pending_entry(&account_id, -20);
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [
+                deposit.into(),
+                to_post.clone().into(),
+                to_void.clone().into(),
+            ]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(
+            applied[2].ledger_balances,
+            HashMap::from([(balance.clone(), 100)])
+        );
+        assert_eq!(
+            applied[2].pending_balances,
+            HashMap::from([(balance.clone(), -50)])
+        );
+
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: to_post.entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert_eq!(non_applied[0].0, NonAppliedReason::InvalidStatus);
+
+        let request = |entry: &Entry| PendingEntryRequest {
+            account_id: account_id.clone(),
+            entry_id: entry.entry_id.clone(),
+        };
+        let (posted, non_applied) = resolve_pending_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            PendingResolution::Post,
+            [request(&to_post)].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(posted[0].status, EntryStatus::Applied);
+        assert_eq!(
+            posted[0].ledger_balances,
+            HashMap::from([(balance.clone(), 70)])
+        );
+        assert_eq!(
+            posted[0].pending_balances,
+            HashMap::from([(balance.clone(), -20)])
+        );
+
+        let (voided, non_applied) = resolve_pending_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            PendingResolution::Void,
+            [request(&to_void), request(&to_post)].into_iter(),
+        )
+        .await;
+        assert_eq!(
+            non_applied,
+            vec![(NonAppliedReason::InvalidStatus, request(&to_post))]
+        );
+        assert_eq!(voided[0].status, EntryStatus::Void(2));
+        assert!(voided[0].pending_balances.is_empty());
+
+        let balance_entry = get_balance_use_case(&repository, &account_id).await?;
+        assert_eq!(
+            balance_entry.ledger_balances,
+            HashMap::from([(balance, 70)])
+        );
+        assert!(balance_entry.pending_balances.is_empty());
+        let history = get_entry_use_case(&repository, &account_id, &to_post.entry_id, 10)
+            .await?
+            .0;
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| &entry.status)
+                .collect::<Vec<_>>(),
+            vec![&EntryStatus::Applied, &EntryStatus::Posted(3)]
+        );
+        let history = get_entry_use_case(&repository, &account_id, &to_void.entry_id, 10)
+            .await?
+            .0;
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| &entry.status)
+                .collect::<Vec<_>>(),
+            vec![&EntryStatus::Void(2), &EntryStatus::Voided(4)]
+        );
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn post_pending_entry_with_fee_rule() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let fee_account_id: AccountId = Faker.fake();
+        let balance = LedgerBalanceName::new("balance_amount".into())?;
+        set_fee_rule_use_case(
+            &repository,
+            &get_actor(),
+            &FeeRuleId::new("hold_fee".into())?,
+            vec!["amount<0".parse()?],
+            LedgerFieldName::new("amount".into())?,
+            FeeAmount::Percentage {
+                rate_ppm: 100_000,
+                min: None,
+                max: None,
+            },
+            serde_json::from_value(json!([
+                {"ledger_field": "amount", "direction": "debit"},
+                {"account_id": fee_account_id, "ledger_field": "amount", "direction": "credit"},
+            ]))?,
+        )
+        .await?;
+        let pending = pending_entry(&account_id, -50);
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [pending.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+
+        let (posted, non_applied) = resolve_pending_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            PendingResolution::Post,
+            [PendingEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: pending.entry_id.clone(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(posted.len(), 3);
+        assert_eq!(
+            posted[1].additional_fields["derived_from"]["entry_id"],
+            json!(pending.entry_id)
+        );
+        let balance_entry = get_balance_use_case(&repository, &account_id).await?;
+        assert_eq!(
+            balance_entry.ledger_balances,
+            HashMap::from([(balance.clone(), -55)])
+        );
+        let fee_balance = get_balance_use_case(&repository, &fee_account_id).await?;
+        assert_eq!(fee_balance.ledger_balances, HashMap::from([(balance, 5)]));
+        Ok(())
+    }
+}
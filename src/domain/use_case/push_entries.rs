@@ -4,7 +4,9 @@ use itertools::Itertools;
 use rand::Rng;
 use tokio::time::sleep;
 
-use crate::domain::entity::{Entry, EntryWithBalance, EntryWithConditionals};
+use crate::domain::entity::{
+    Actor, Entry, EntryId, EntryWithBalance, EntryWithConditionals, FeeRule,
+};
 use crate::domain::gateway::{AppendEntriesError, LedgerEntryRepository};
 use crate::domain::use_case;
 use crate::domain::use_case::NonAppliedReason;
@@ -12,19 +14,48 @@ use crate::domain::use_case::NonAppliedReason;
 pub async fn push_entries_use_case(
     repository: &impl LedgerEntryRepository,
     mut random_number_generator: impl Rng,
+    actor: &Actor,
     entries: impl Iterator<Item = EntryWithConditionals> + Send + Sync,
 ) -> (Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>) {
     let entries_by_account_id = entries.into_group_map_by(|v| v.entry.account_id.clone());
     let mut applied_entries_with_balance = Vec::new();
     let mut non_applied_entries = Vec::new();
+    let rules = match repository.get_fee_rules().await {
+        Ok(rules) => rules,
+        Err(err) => {
+            tracing::warn!("Error reading fee rules: {err}");
+            non_applied_entries.extend(
+                entries_by_account_id
+                    .into_values()
+                    .flatten()
+                    .map(|entry| (NonAppliedReason::Other(err.to_string()), entry.entry)),
+            );
+            return (applied_entries_with_balance, non_applied_entries);
+        }
+    };
+    let chunk_size = use_case::chunk_size(&rules, 1);
 
     for (account_id, total_entries) in entries_by_account_id.into_iter() {
-        for entries in total_entries.chunks(99) {
+        for entries in total_entries.chunks(chunk_size) {
             let mut entries = Vec::from(entries);
             let mut tries = 0;
             loop {
                 tries += 1;
-                match repository.append_entries(&account_id, &entries).await {
+                let (account_entries, linked_entries) =
+                    FeeRule::with_derived_entries(&account_id, &entries, &rules);
+                let result = if linked_entries.is_empty() {
+                    repository
+                        .append_entries(&account_id, &account_entries, actor)
+                        .await
+                } else {
+                    let linked_entries = std::iter::once((account_id.clone(), account_entries))
+                        .chain(linked_entries)
+                        .collect_vec();
+                    repository
+                        .append_linked_entries(&linked_entries, actor)
+                        .await
+                };
+                match result {
                     Ok(applied) => {
                         applied_entries_with_balance.extend(applied);
                         break;
@@ -40,7 +71,7 @@ pub async fn push_entries_use_case(
                     }
                     Err(AppendEntriesError::EntriesAlreadyExists(_, duplicated_entries_ids)) => {
                         let duplicated_entries = use_case::extract_if(&mut entries, |entry| {
-                            duplicated_entries_ids.contains(&entry.entry.entry_id)
+                            is_or_derives(entry, &rules, &duplicated_entries_ids)
                         });
                         non_applied_entries.extend(
                             duplicated_entries
@@ -58,6 +89,22 @@ pub async fn push_entries_use_case(
                                 .map(|entry| (NonAppliedReason::ConditionFailed, entry.entry)),
                         );
                     }
+                    Err(AppendEntriesError::VelocityLimitExceeded(entry_id, _limit_id)) => {
+                        let exceeding_entries = use_case::extract_if(&mut entries, |entry| {
+                            is_or_derives(entry, &rules, std::slice::from_ref(&entry_id))
+                        });
+                        if exceeding_entries.is_empty() {
+                            non_applied_entries.extend(entries.into_iter().map(|entry| {
+                                (NonAppliedReason::VelocityLimitExceeded, entry.entry)
+                            }));
+                            break;
+                        }
+                        non_applied_entries.extend(
+                            exceeding_entries.into_iter().map(|entry| {
+                                (NonAppliedReason::VelocityLimitExceeded, entry.entry)
+                            }),
+                        );
+                    }
                     Err(err) => {
                         non_applied_entries.extend(entries.into_iter().map(|entry| {
                             (
@@ -71,10 +118,20 @@ pub async fn push_entries_use_case(
             }
         }
     }
+    // An entry sent twice in the same request is reported once.
     non_applied_entries.dedup();
     (applied_entries_with_balance, non_applied_entries)
 }
 
+/// Whether the entry, or one of the entries the fee rules derive from it, has one of the ids.
+fn is_or_derives(entry: &EntryWithConditionals, rules: &[FeeRule], entry_ids: &[EntryId]) -> bool {
+    entry_ids.contains(&entry.entry.entry_id)
+        || rules
+            .iter()
+            .flat_map(|rule| rule.derive(&entry.entry))
+            .any(|derived| entry_ids.contains(&derived.entry.entry_id))
+}
+
 #[cfg(test)]
 pub mod test {
     use anyhow::Result;
@@ -88,7 +145,7 @@ pub mod test {
     use crate::utils::test::set_now;
     use crate::utils::utc_now;
     use crate::{
-        app::test::{get_repository, get_rng},
+        app::test::{get_actor, get_repository, get_rng},
         domain::entity::{
             AccountId, {Conditional, EntryBuilder, EntryWithBalanceBuilder},
         },
@@ -106,8 +163,13 @@ pub mod test {
             .with_ledger_field("usd_amount", 301)
             .build();
 
-        let (applied, non_applied) =
-            push_entries_use_case(&repository, rng, [entry.clone().into()].into_iter()).await;
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            rng,
+            &get_actor(),
+            [entry.clone().into()].into_iter(),
+        )
+        .await;
         assert!(non_applied.is_empty());
         assert_eq!(
             Vec::from([EntryWithBalanceBuilder::from_entry(entry)
@@ -137,6 +199,7 @@ pub mod test {
         let (applied, non_applied) = push_entries_use_case(
             &repository,
             rng,
+            &get_actor(),
             [entry_1.clone().into(), entry_2.clone().into()].into_iter(),
         )
         .await;
@@ -188,6 +251,7 @@ pub mod test {
         let (applied, non_applied) = push_entries_use_case(
             &repository,
             rng,
+            &get_actor(),
             [
                 entry_1.clone().into(),
                 entry_2.clone().into(),
@@ -251,6 +315,7 @@ pub mod test {
         let (applied, non_applied) = push_entries_use_case(
             &repository,
             rng,
+            &get_actor(),
             [
                 entry_2.clone().into(),
                 entry_1.clone().into(),
@@ -295,12 +360,14 @@ pub mod test {
         let (applied_1, non_applied_1) = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entry_1.clone().into(), entry_2.clone().into()].into_iter(),
         )
         .await;
         let (applied_2, non_applied_2) = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [
                 entry_1.clone().into(),
                 entry_2.clone().into(),
@@ -378,6 +445,7 @@ pub mod test {
         let (applied, non_applied) = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [entry_1.clone().into()].into_iter(),
         )
         .await;
@@ -413,6 +481,7 @@ pub mod test {
         let (applied, non_applied) = push_entries_use_case(
             &repository,
             get_rng().await,
+            &get_actor(),
             [
                 EntryWithConditionals {
                     entry: entry_1.clone(),
@@ -474,8 +543,13 @@ pub mod test {
                 .build()
                 .into()
         });
-        let (applied, non_applied) =
-            push_entries_use_case(repository, get_rng().await, entries.into_iter()).await;
+        let (applied, non_applied) = push_entries_use_case(
+            repository,
+            get_rng().await,
+            &get_actor(),
+            entries.into_iter(),
+        )
+        .await;
         assert!(non_applied.is_empty());
         applied
     }
@@ -488,7 +562,7 @@ pub mod test {
         let mut date = utc_now();
         let mut result = Vec::new();
         for _ in 0..n_entries {
-            result.push(push_entry_with_date(repository, &account_id, &date).await);
+            result.push(push_entry_with_date(repository, account_id, &date).await);
             date += Duration::from_secs(35);
         }
         result
@@ -0,0 +1,163 @@
+use chrono::{DateTime, Days, Utc};
+use ulid::Ulid;
+
+use crate::domain::entity::{
+    reconcile, AccountId, Actor, ExpectedEntry, Order, Reconciliation, ReconciliationOptions,
+};
+use crate::domain::gateway::{LedgerEntryRepository, ReconciliationError};
+use crate::domain::use_case::{get_entries_from_cursor_use_case, get_entries_use_case};
+use crate::utils::utc_now;
+
+/// Lines of a statement reconciled in a run.
+pub const MAX_EXPECTED_ENTRIES: usize = 10_000;
+
+/// Reconciles the entries of the account between `start_date` and `end_date` with the lines
+/// of an external statement, and saves the result as a new run. The entries are read one day
+/// past the date tolerance on each side, so lines near the edges can still be matched.
+pub async fn reconcile_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    account_id: &AccountId,
+    start_date: &DateTime<Utc>,
+    end_date: &DateTime<Utc>,
+    options: ReconciliationOptions,
+    expected_entries: Vec<ExpectedEntry>,
+) -> Result<Reconciliation, ReconciliationError> {
+    if expected_entries.len() > MAX_EXPECTED_ENTRIES {
+        return Err(ReconciliationError::TooManyEntries(MAX_EXPECTED_ENTRIES));
+    }
+    let margin = Days::new(options.date_tolerance as u64 + 1);
+    let mut entries = Vec::new();
+    let mut page = get_entries_use_case(
+        repository,
+        account_id,
+        &(*start_date - margin),
+        &(*end_date + margin),
+        100,
+        &Order::Asc,
+    )
+    .await
+    .map_err(anyhow::Error::from)?;
+    loop {
+        entries.extend(page.0);
+        let Some(cursor) = page.1 else {
+            break;
+        };
+        page = get_entries_from_cursor_use_case(repository, cursor, 100)
+            .await
+            .map_err(anyhow::Error::from)?;
+    }
+
+    let sets = reconcile(&options, expected_entries, &entries, start_date, end_date);
+    let reconciliation = Reconciliation {
+        run_id: Ulid::new().to_string(),
+        account_id: account_id.clone(),
+        start_date: *start_date,
+        end_date: *end_date,
+        options,
+        actor: actor.clone(),
+        created_at: utc_now(),
+        matched: sets.matched,
+        amount_mismatches: sets.amount_mismatches,
+        missing_in_ledger: sets.missing_in_ledger,
+        missing_in_statement: sets.missing_in_statement,
+    };
+    repository.save_reconciliation(&reconciliation).await?;
+    Ok(reconciliation)
+}
+
+pub async fn get_reconciliation_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    run_id: &str,
+) -> Result<Reconciliation, ReconciliationError> {
+    repository.get_reconciliation(account_id, run_id).await
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+
+    use crate::app::test::{get_actor, get_repository};
+    use crate::domain::entity::LedgerFieldName;
+    use crate::domain::use_case::push_entries::test::push_entry_with_date;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn reconcile_and_get_the_run() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let mut entries = Vec::new();
+        for day in 1..=3 {
+            entries.push(
+                push_entry_with_date(
+                    &repository,
+                    &account_id,
+                    &format!("2024-07-0{day} 12:00:00 UTC").parse()?,
+                )
+                .await,
+            );
+        }
+        let expected = |index: usize, amount: i128| ExpectedEntry {
+            reference: entries[index].entry_id.to_string(),
+            amount,
+            date: entries[index].created_at.date_naive(),
+        };
+        let amount = |index: usize| {
+            entries[index]
+                .ledger_fields
+                .values()
+                .next()
+                .copied()
+                .unwrap_or_default()
+        };
+        let missing = ExpectedEntry {
+            reference: "not-in-the-ledger".into(),
+            amount: 10,
+            date: "2024-07-02".parse()?,
+        };
+
+        let reconciliation = reconcile_use_case(
+            &repository,
+            &get_actor(),
+            &account_id,
+            &"2024-07-01 00:00:00 UTC".parse()?,
+            &"2024-08-01 00:00:00 UTC".parse()?,
+            ReconciliationOptions {
+                ledger_field: LedgerFieldName::new("amount".into())?,
+                match_by: "entry_id".parse()?,
+                amount_tolerance: 0,
+                date_tolerance: 0,
+            },
+            vec![
+                expected(0, amount(0)),
+                expected(1, amount(1).wrapping_add(1)),
+                missing.clone(),
+            ],
+        )
+        .await?;
+        assert_eq!(reconciliation.matched.len(), 1);
+        assert_eq!(reconciliation.matched[0].entry_id, entries[0].entry_id);
+        assert_eq!(reconciliation.amount_mismatches.len(), 1);
+        assert_eq!(
+            reconciliation.amount_mismatches[0].entry_id,
+            entries[1].entry_id
+        );
+        assert_eq!(reconciliation.missing_in_ledger, vec![missing]);
+        assert_eq!(reconciliation.missing_in_statement.len(), 1);
+        assert_eq!(
+            reconciliation.missing_in_statement[0].entry_id,
+            entries[2].entry_id
+        );
+
+        assert_eq!(
+            get_reconciliation_use_case(&repository, &account_id, &reconciliation.run_id).await?,
+            reconciliation
+        );
+        let result = get_reconciliation_use_case(&repository, &account_id, "unknown").await;
+        assert!(matches!(result, Err(ReconciliationError::NotFound(_, _))));
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+
+use crate::domain::entity::{
+    Actor, Entry, EntryWithBalance, Recurrence, RecurringEntry, RecurringSchedule,
+    RecurringScheduleId,
+};
+use crate::domain::gateway::{LedgerEntryRepository, RecurringScheduleError};
+use crate::domain::use_case::{push_entries_use_case, NonAppliedReason};
+use crate::utils::utc_now;
+
+/// Creates the schedule, or replaces its definition. A replaced schedule keeps the
+/// occurrences it posted, and continues after the last one.
+#[allow(clippy::too_many_arguments)]
+pub async fn set_recurring_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    schedule_id: &RecurringScheduleId,
+    recurrence: Recurrence,
+    starts_at: DateTime<Utc>,
+    ends_at: Option<DateTime<Utc>>,
+    max_occurrences: Option<u32>,
+    entries: Vec<RecurringEntry>,
+) -> Result<RecurringSchedule, RecurringScheduleError> {
+    let schedule = RecurringSchedule::new(
+        schedule_id.clone(),
+        recurrence,
+        starts_at,
+        ends_at,
+        max_occurrences,
+        entries,
+        actor.clone(),
+        utc_now(),
+    )
+    .map_err(|err| RecurringScheduleError::InvalidSchedule(err.to_string()))?;
+    repository.save_recurring_schedule(&schedule).await?;
+    get_recurring_schedule_use_case(repository, schedule_id).await
+}
+
+pub async fn get_recurring_schedules_use_case(
+    repository: &impl LedgerEntryRepository,
+) -> Result<Vec<RecurringSchedule>, RecurringScheduleError> {
+    repository.get_recurring_schedules().await
+}
+
+pub async fn get_recurring_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    schedule_id: &RecurringScheduleId,
+) -> Result<RecurringSchedule, RecurringScheduleError> {
+    repository
+        .get_recurring_schedules()
+        .await?
+        .into_iter()
+        .find(|schedule| schedule.schedule_id == *schedule_id)
+        .ok_or(RecurringScheduleError::NotFound(schedule_id.clone()))
+}
+
+pub async fn pause_recurring_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    schedule_id: &RecurringScheduleId,
+) -> Result<RecurringSchedule, RecurringScheduleError> {
+    repository.pause_recurring_schedule(schedule_id).await?;
+    get_recurring_schedule_use_case(repository, schedule_id).await
+}
+
+/// Resumes a paused schedule. The occurrences it missed while paused are skipped.
+pub async fn resume_recurring_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    schedule_id: &RecurringScheduleId,
+) -> Result<RecurringSchedule, RecurringScheduleError> {
+    repository
+        .resume_recurring_schedule(schedule_id, &utc_now())
+        .await?;
+    get_recurring_schedule_use_case(repository, schedule_id).await
+}
+
+pub async fn delete_recurring_schedule_use_case(
+    repository: &impl LedgerEntryRepository,
+    schedule_id: &RecurringScheduleId,
+) -> Result<(), RecurringScheduleError> {
+    repository.delete_recurring_schedule(schedule_id).await
+}
+
+/// Posts the occurrences of every schedule of the ledger that are due, including the ones
+/// missed while no run happened. The entries of an occurrence have the `entry_id` of their
+/// schedule and occurrence, so an occurrence is posted only once even when runs overlap. A
+/// schedule stops at the first occurrence that fails to be posted, and continues from it on
+/// the next run.
+pub async fn post_recurring_entries_use_case(
+    repository: &impl LedgerEntryRepository,
+    mut random_number_generator: impl Rng,
+    actor: &Actor,
+) -> Result<(Vec<EntryWithBalance>, Vec<(NonAppliedReason, Entry)>), RecurringScheduleError> {
+    let now = utc_now();
+    let mut applied_entries = Vec::new();
+    let mut non_applied_entries = Vec::new();
+    for schedule in repository.get_recurring_schedules().await? {
+        if schedule.paused {
+            continue;
+        }
+        let mut occurrences = schedule.occurrences;
+        for occurrence in schedule.occurrences_to_post(&now) {
+            let (applied, non_applied) = push_entries_use_case(
+                repository,
+                &mut random_number_generator,
+                actor,
+                schedule.entries_at(&occurrence).into_iter(),
+            )
+            .await;
+            applied_entries.extend(applied);
+            let failed = non_applied
+                .into_iter()
+                .filter(|(reason, _)| *reason != NonAppliedReason::EntriesAlreadyExists)
+                .collect::<Vec<_>>();
+            if !failed.is_empty() {
+                non_applied_entries.extend(failed);
+                break;
+            }
+            occurrences += 1;
+            repository
+                .set_recurring_posted_until(&schedule.schedule_id, &occurrence, occurrences)
+                .await?;
+        }
+    }
+    Ok((applied_entries, non_applied_entries))
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use chrono::NaiveTime;
+    use fake::{Fake, Faker};
+    use serde_json::json;
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{AccountId, EntryId, LedgerFieldName, LedgerId};
+    use crate::domain::use_case::get_balance_use_case;
+    use crate::utils::test::set_now;
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn post_recurring_entries_once_per_occurrence() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let payer: AccountId = Faker.fake();
+        let payee: AccountId = Faker.fake();
+        let ledger_field = LedgerFieldName::new("usd_amount".into())?;
+        let schedule_id = RecurringScheduleId::new("rent".into())?;
+        set_now(&"2024-01-15 00:00:00 UTC".parse()?);
+        set_recurring_schedule_use_case(
+            &repository,
+            &get_actor(),
+            &schedule_id,
+            Recurrence::Monthly {
+                day: 31,
+                time: NaiveTime::default(),
+            },
+            "2024-01-01 00:00:00 UTC".parse()?,
+            None,
+            Some(3),
+            vec![
+                RecurringEntry {
+                    account_id: payer.clone(),
+                    ledger_fields: [(ledger_field.clone(), -1_000)].into(),
+                    additional_fields: json!({"description": "Rent"}),
+                },
+                RecurringEntry {
+                    account_id: payee.clone(),
+                    ledger_fields: [(ledger_field.clone(), 1_000)].into(),
+                    additional_fields: json!({"description": "Rent"}),
+                },
+            ],
+        )
+        .await?;
+
+        // The occurrences missed until now are caught up.
+        set_now(&"2024-03-10 00:00:00 UTC".parse()?);
+        let (applied, non_applied) =
+            post_recurring_entries_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 4);
+        let entry_id = EntryId::new("rent-20240131T000000-0".into())?;
+        assert!(applied.iter().any(|entry| entry.entry_id == entry_id));
+        let schedule = get_recurring_schedule_use_case(&repository, &schedule_id).await?;
+        assert_eq!(schedule.occurrences, 2);
+        assert_eq!(
+            schedule.posted_until,
+            Some("2024-02-29 00:00:00 UTC".parse()?)
+        );
+        let (applied, _) =
+            post_recurring_entries_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert!(applied.is_empty());
+
+        // The occurrences while paused are skipped.
+        pause_recurring_schedule_use_case(&repository, &schedule_id).await?;
+        set_now(&"2024-04-05 00:00:00 UTC".parse()?);
+        let (applied, _) =
+            post_recurring_entries_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert!(applied.is_empty());
+        let schedule = resume_recurring_schedule_use_case(&repository, &schedule_id).await?;
+        assert!(!schedule.paused);
+
+        // It ends after the max occurrences.
+        set_now(&"2024-12-31 00:00:00 UTC".parse()?);
+        let (applied, _) =
+            post_recurring_entries_use_case(&repository, get_rng().await, &get_actor()).await?;
+        assert_eq!(applied.len(), 2);
+        let entry_id = EntryId::new("rent-20240430T000000-0".into())?;
+        assert!(applied.iter().any(|entry| entry.entry_id == entry_id));
+        let balance = get_balance_use_case(&repository, &payee).await?;
+        assert_eq!(
+            balance.ledger_balances.values().collect::<Vec<_>>(),
+            vec![&3_000]
+        );
+
+        delete_recurring_schedule_use_case(&repository, &schedule_id).await?;
+        assert!(get_recurring_schedules_use_case(&repository)
+            .await?
+            .is_empty());
+        let result = delete_recurring_schedule_use_case(&repository, &schedule_id).await;
+        assert!(matches!(result, Err(RecurringScheduleError::NotFound(_))));
+        Ok(())
+    }
+}
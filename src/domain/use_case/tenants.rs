@@ -0,0 +1,146 @@
+use serde_json::Value;
+
+use crate::domain::entity::{Actor, LedgerId, Tenant};
+use crate::domain::gateway::{TenantError, TenantRepository};
+use crate::utils::utc_now;
+
+pub async fn create_tenant_use_case(
+    repository: &impl TenantRepository,
+    ledger_id: LedgerId,
+    description: Option<String>,
+    metadata: Value,
+    actor: &Actor,
+) -> Result<Tenant, TenantError> {
+    let tenant = Tenant {
+        ledger_id,
+        description,
+        metadata,
+        actor: actor.clone(),
+        created_at: utc_now(),
+    };
+    repository.create_tenant(&tenant).await?;
+    Ok(tenant)
+}
+
+pub async fn get_tenant_use_case(
+    repository: &impl TenantRepository,
+    ledger_id: &LedgerId,
+) -> Result<Tenant, TenantError> {
+    repository.get_tenant(ledger_id).await
+}
+
+pub async fn list_tenants_use_case(
+    repository: &impl TenantRepository,
+) -> Result<Vec<Tenant>, TenantError> {
+    repository.list_tenants().await
+}
+
+pub async fn delete_tenant_use_case(
+    repository: &impl TenantRepository,
+    ledger_id: &LedgerId,
+) -> Result<(), TenantError> {
+    repository.delete_tenant(ledger_id).await
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use ulid::Ulid;
+
+    use crate::app::test::{
+        get_actor, get_repository, get_repository_for_ledger, get_rng, get_tenant_repository,
+    };
+    use crate::domain::entity::{AccountId, EntryBuilder};
+    use crate::domain::gateway::GetBalanceError;
+    use crate::domain::use_case::{get_balance_use_case, push_entries_use_case};
+
+    use super::*;
+
+    fn fake_ledger_id() -> LedgerId {
+        LedgerId::new(format!("test-{}", Ulid::new())).expect("A valid ledger id")
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn create_tenant_twice_should_fail() -> Result<()> {
+        let repository = get_tenant_repository().await;
+        let ledger_id = fake_ledger_id();
+        let tenant = create_tenant_use_case(
+            &repository,
+            ledger_id.clone(),
+            None,
+            Value::Null,
+            &get_actor(),
+        )
+        .await?;
+        assert_eq!(tenant, get_tenant_use_case(&repository, &ledger_id).await?);
+        assert!(list_tenants_use_case(&repository).await?.contains(&tenant));
+
+        let result = create_tenant_use_case(
+            &repository,
+            ledger_id.clone(),
+            None,
+            Value::Null,
+            &get_actor(),
+        )
+        .await;
+        assert!(matches!(result, Err(TenantError::AlreadyExists(_))));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn ledgers_are_isolated() -> Result<()> {
+        let tenant_repository = get_tenant_repository().await;
+        let mut rng = get_rng().await;
+        let ledger_a = fake_ledger_id();
+        let ledger_b = fake_ledger_id();
+        for ledger_id in [&ledger_a, &ledger_b] {
+            create_tenant_use_case(
+                &tenant_repository,
+                ledger_id.clone(),
+                None,
+                Value::Null,
+                &get_actor(),
+            )
+            .await?;
+        }
+        let repository_a = get_repository_for_ledger(&ledger_a).await;
+        let repository_b = get_repository_for_ledger(&ledger_b).await;
+        let account_id: AccountId = Faker.fake();
+        let entry = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("local_amount", 100)
+            .build();
+
+        let (applied, _) = push_entries_use_case(
+            &repository_a,
+            &mut rng,
+            &get_actor(),
+            vec![entry.into()].into_iter(),
+        )
+        .await;
+        assert_eq!(applied.len(), 1);
+        assert!(get_balance_use_case(&repository_a, &account_id)
+            .await
+            .is_ok());
+        assert!(matches!(
+            get_balance_use_case(&repository_b, &account_id).await,
+            Err(GetBalanceError::NotFound(_))
+        ));
+        assert!(matches!(
+            get_balance_use_case(&get_repository().await, &account_id).await,
+            Err(GetBalanceError::NotFound(_))
+        ));
+
+        delete_tenant_use_case(&tenant_repository, &ledger_a).await?;
+        assert!(matches!(
+            get_balance_use_case(&repository_a, &account_id).await,
+            Err(GetBalanceError::NotFound(_))
+        ));
+        assert!(matches!(
+            delete_tenant_use_case(&tenant_repository, &ledger_a).await,
+            Err(TenantError::NotFound(_))
+        ));
+        Ok(())
+    }
+}
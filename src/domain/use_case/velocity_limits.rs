@@ -0,0 +1,357 @@
+use crate::domain::entity::{
+    AccountId, Actor, LedgerFieldName, VelocityDirection, VelocityLimit, VelocityLimitId,
+    VelocityUsage, VelocityWindow, MAX_VELOCITY_LIMITS,
+};
+use crate::domain::gateway::{LedgerEntryRepository, VelocityLimitError};
+use crate::utils::utc_now;
+
+/// Creates the limit of the account, or the default one without `account_id`, or replaces
+/// it. It applies to the entries pushed from then on, counting the amounts already counted in
+/// its window.
+#[allow(clippy::too_many_arguments)]
+pub async fn set_velocity_limit_use_case(
+    repository: &impl LedgerEntryRepository,
+    actor: &Actor,
+    account_id: Option<&AccountId>,
+    limit_id: &VelocityLimitId,
+    ledger_field: LedgerFieldName,
+    direction: VelocityDirection,
+    max_amount: i128,
+    window: VelocityWindow,
+) -> Result<VelocityLimit, VelocityLimitError> {
+    let limit = VelocityLimit::new(
+        limit_id.clone(),
+        account_id.cloned(),
+        ledger_field,
+        direction,
+        max_amount,
+        window,
+        actor.clone(),
+        utc_now(),
+    )
+    .map_err(|err| VelocityLimitError::InvalidLimit(err.to_string()))?;
+    let others = repository
+        .get_velocity_limits()
+        .await?
+        .into_iter()
+        .filter(|other| other.account_id == limit.account_id && other.limit_id != limit.limit_id)
+        .count();
+    if others >= MAX_VELOCITY_LIMITS {
+        return Err(VelocityLimitError::InvalidLimit(format!(
+            "At most {MAX_VELOCITY_LIMITS} limits can be set as defaults, and for each account"
+        )));
+    }
+    repository.save_velocity_limit(&limit).await?;
+    Ok(limit)
+}
+
+/// The limits of the ledger, the defaults first.
+pub async fn get_velocity_limits_use_case(
+    repository: &impl LedgerEntryRepository,
+) -> Result<Vec<VelocityLimit>, VelocityLimitError> {
+    repository.get_velocity_limits().await
+}
+
+/// The limits that apply to the account, with the amounts it counted in their current windows.
+pub async fn get_velocity_usage_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+) -> Result<Vec<VelocityUsage>, VelocityLimitError> {
+    let now = utc_now();
+    let limits = repository.get_velocity_limits().await?;
+    let mut usages = Vec::new();
+    for limit in VelocityLimit::applicable(&limits, account_id) {
+        let used_amount = repository
+            .get_velocity_used_amount(account_id, limit, &now)
+            .await?;
+        usages.push(VelocityUsage {
+            limit: limit.clone(),
+            used_amount,
+            remaining_amount: (limit.max_amount - used_amount).max(0),
+        });
+    }
+    Ok(usages)
+}
+
+pub async fn delete_velocity_limit_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: Option<&AccountId>,
+    limit_id: &VelocityLimitId,
+) -> Result<(), VelocityLimitError> {
+    repository.delete_velocity_limit(account_id, limit_id).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use serde_json::json;
+    use ulid::Ulid;
+
+    use crate::app::test::{get_actor, get_repository_for_ledger, get_rng};
+    use crate::domain::entity::{DeleteEntryRequest, EntryBuilder, LedgerId, Reversal};
+    use crate::domain::use_case::{
+        amend_entries_use_case, delete_entries_use_case, push_entries_use_case, NonAppliedReason,
+    };
+
+    use super::*;
+
+    #[tokio_shared_rt::test(shared)]
+    async fn push_entries_within_velocity_limits() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let other_account_id: AccountId = Faker.fake();
+        let limit_id = VelocityLimitId::new("daily_debits".into())?;
+        let window: VelocityWindow = serde_json::from_value(json!({"calendar": "day"}))?;
+        set_velocity_limit_use_case(
+            &repository,
+            &get_actor(),
+            None,
+            &limit_id,
+            LedgerFieldName::new("usd_amount".into())?,
+            VelocityDirection::Debit,
+            5_000,
+            window,
+        )
+        .await?;
+        set_velocity_limit_use_case(
+            &repository,
+            &get_actor(),
+            Some(&other_account_id),
+            &limit_id,
+            LedgerFieldName::new("usd_amount".into())?,
+            VelocityDirection::Debit,
+            10_000,
+            window,
+        )
+        .await?;
+        assert_eq!(get_velocity_limits_use_case(&repository).await?.len(), 2);
+
+        let deposit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", 20_000)
+            .build();
+        let first = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -3_000)
+            .build();
+        let second = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -3_000)
+            .build();
+        let third = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -2_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [
+                deposit.into(),
+                first.into(),
+                second.clone().into(),
+                third.into(),
+            ]
+            .into_iter(),
+        )
+        .await;
+        assert_eq!(applied.len(), 3);
+        assert_eq!(
+            non_applied,
+            vec![(NonAppliedReason::VelocityLimitExceeded, second)]
+        );
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].used_amount, 5_000);
+        assert_eq!(usages[0].remaining_amount, 0);
+
+        // The limit of its own replaces the default.
+        let debit = EntryBuilder::new()
+            .with_account_id(other_account_id.clone())
+            .with_ledger_field("usd_amount", -8_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [debit.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+
+        delete_velocity_limit_use_case(&repository, None, &limit_id).await?;
+        let result = delete_velocity_limit_use_case(&repository, None, &limit_id).await;
+        assert!(matches!(result, Err(VelocityLimitError::NotFound(_))));
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert!(usages.is_empty());
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn revert_entries_to_release_velocity() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let window: VelocityWindow = serde_json::from_value(json!({"rolling_hours": 24}))?;
+        set_velocity_limit_use_case(
+            &repository,
+            &get_actor(),
+            None,
+            &VelocityLimitId::new("daily_debits".into())?,
+            LedgerFieldName::new("usd_amount".into())?,
+            VelocityDirection::Debit,
+            5_000,
+            window,
+        )
+        .await?;
+
+        let debit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -4_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [debit.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+
+        let (reverts, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: debit.entry_id.clone(),
+                ledger_fields: Some(HashMap::from([(
+                    LedgerFieldName::new("usd_amount".into())?,
+                    -1_000,
+                )])),
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(reverts.len(), 1);
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages[0].used_amount, 3_000);
+
+        let debit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -2_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [debit.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages[0].used_amount, 5_000);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn amend_entries_within_velocity_limits() -> Result<()> {
+        let ledger_id = LedgerId::new(format!("test-{}", Ulid::new()))?;
+        let repository = get_repository_for_ledger(&ledger_id).await;
+        let account_id: AccountId = Faker.fake();
+        let window: VelocityWindow = serde_json::from_value(json!({"calendar": "day"}))?;
+        set_velocity_limit_use_case(
+            &repository,
+            &get_actor(),
+            None,
+            &VelocityLimitId::new("daily_debits".into())?,
+            LedgerFieldName::new("usd_amount".into())?,
+            VelocityDirection::Debit,
+            5_000,
+            window,
+        )
+        .await?;
+        let usd_amount = |amount: i128| -> Result<HashMap<LedgerFieldName, i128>> {
+            Ok(HashMap::from([(
+                LedgerFieldName::new("usd_amount".into())?,
+                amount,
+            )]))
+        };
+
+        let small_debit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -100)
+            .build();
+        let large_debit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -4_000)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [small_debit.clone().into(), large_debit.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 2);
+
+        // Amending an entry counts its new amount instead of the old one.
+        let mut over_the_limit = small_debit;
+        over_the_limit.ledger_fields = usd_amount(-100_000)?;
+        let (applied, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [over_the_limit.clone().into()].into_iter(),
+        )
+        .await;
+        assert!(applied.is_empty());
+        assert_eq!(
+            non_applied,
+            vec![(NonAppliedReason::VelocityLimitExceeded, over_the_limit)]
+        );
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages[0].used_amount, 4_100);
+
+        let mut lowered = large_debit;
+        lowered.ledger_fields = usd_amount(-1_000)?;
+        let (applied, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [lowered.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 2);
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages[0].used_amount, 1_100);
+
+        let debit = EntryBuilder::new()
+            .with_account_id(account_id.clone())
+            .with_ledger_field("usd_amount", -3_900)
+            .build();
+        let (applied, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [debit.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        assert_eq!(applied.len(), 1);
+        let usages = get_velocity_usage_use_case(&repository, &account_id).await?;
+        assert_eq!(usages[0].used_amount, 5_000);
+        Ok(())
+    }
+}
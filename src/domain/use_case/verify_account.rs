@@ -0,0 +1,852 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::domain::entity::{
+    AccountId, AccountRows, Discrepancy, EntryId, EntryStatus, EntryWithBalance, LedgerBalanceName,
+    Snapshot, StoredEntry, VerificationReport,
+};
+use crate::domain::gateway::{GetBalanceError, LedgerEntryRepository};
+use crate::utils::utc_now;
+
+/// Reads every row of the account and replays its entries, reporting the rows that don't
+/// match the replay. With `since_snapshot`, only the rows after the last snapshot are read
+/// and replayed from it, if the account has one.
+pub async fn verify_account_use_case(
+    repository: &impl LedgerEntryRepository,
+    account_id: &AccountId,
+    total_segments: i32,
+    since_snapshot: bool,
+) -> Result<VerificationReport, GetBalanceError> {
+    let snapshot = if since_snapshot {
+        repository.get_snapshot(account_id, &utc_now()).await?
+    } else {
+        None
+    };
+    let rows = match &snapshot {
+        Some(snapshot) => repository.get_account_rows_since(snapshot).await?,
+        None => {
+            repository
+                .get_account_rows(account_id, total_segments)
+                .await?
+        }
+    };
+    if rows.head.is_none() && rows.entries.is_empty() && snapshot.is_none() {
+        return Err(GetBalanceError::NotFound(account_id.clone()));
+    }
+    Ok(verify_account_rows(account_id, &rows, snapshot.as_ref()))
+}
+
+fn verify_account_rows(
+    account_id: &AccountId,
+    rows: &AccountRows,
+    since: Option<&Snapshot>,
+) -> VerificationReport {
+    let mut discrepancies = Vec::new();
+    verify_sequences(&rows.entries, since, &mut discrepancies);
+    verify_balances(rows, since, &mut discrepancies);
+    verify_reversals(&rows.entries, since, &mut discrepancies);
+    verify_hashes(rows, since, &mut discrepancies);
+    verify_snapshots(rows, &mut discrepancies);
+    VerificationReport {
+        account_id: account_id.clone(),
+        entries: rows.entries.len() as u64,
+        snapshot_sequence: since.map(|snapshot| snapshot.sequence),
+        discrepancies,
+    }
+}
+
+/// The sequence of the first row that is replayed.
+fn first_sequence(since: Option<&Snapshot>) -> u64 {
+    since.map(|snapshot| snapshot.sequence + 1).unwrap_or(0)
+}
+
+/// Every sequence from the first one to the last one must be used by exactly one row.
+fn verify_sequences(
+    entries: &[StoredEntry],
+    since: Option<&Snapshot>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let mut next_sequence = first_sequence(since);
+    for (sequence, rows) in &entries.iter().group_by(|row| row.entry.sequence) {
+        if sequence > next_sequence {
+            discrepancies.push(Discrepancy::SequenceGap {
+                from: next_sequence,
+                to: sequence - 1,
+            });
+        }
+        let entry_ids = rows.map(|row| row.entry.entry_id.clone()).collect_vec();
+        if entry_ids.len() > 1 {
+            discrepancies.push(Discrepancy::DuplicatedSequence {
+                sequence,
+                entry_ids,
+            });
+        }
+        next_sequence = sequence + 1;
+    }
+}
+
+/// Each stored balance must be the previous balance plus the entry, and the balances of the
+/// HEAD must be the sum of every entry. Pending entries are summed in the pending balances
+/// instead, until the Applied row that posts them. A snapshot stands for the sum of the entries
+/// before it.
+fn verify_balances(
+    rows: &AccountRows,
+    since: Option<&Snapshot>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let mut sums: HashMap<LedgerBalanceName, i128> = since
+        .map(|snapshot| snapshot.ledger_balances.clone())
+        .unwrap_or_default();
+    let mut previous_balances = sums.clone();
+    let mut pending_sums: HashMap<LedgerBalanceName, i128> = since
+        .map(|snapshot| snapshot.pending_balances.clone())
+        .unwrap_or_default();
+    let posted_rows: HashMap<(&EntryId, u64), &StoredEntry> = rows
+        .entries
+        .iter()
+        .filter_map(|row| match row.entry.status {
+            EntryStatus::Posted(posting_sequence) => {
+                Some(((&row.entry.entry_id, posting_sequence), row))
+            }
+            _ => None,
+        })
+        .collect();
+    for row in rows.entries.iter() {
+        let entry = &row.entry;
+        let is_pending = entry.status.is_pending();
+        for (field_name, value) in entry.ledger_fields.iter() {
+            let balance = LedgerBalanceName::from(field_name.clone());
+            if is_pending {
+                *pending_sums.entry(balance).or_insert(0) += value;
+            } else {
+                *sums.entry(balance).or_insert(0) += value;
+            }
+        }
+        if let Some(posted) = posted_rows.get(&(&entry.entry_id, entry.sequence)) {
+            for (field_name, value) in posted.entry.ledger_fields.iter() {
+                *pending_sums
+                    .entry(LedgerBalanceName::from(field_name.clone()))
+                    .or_insert(0) -= value;
+            }
+        }
+        pending_sums.retain(|_, balance| *balance != 0);
+        verify_pending_balances(
+            entry,
+            Some(entry.sequence),
+            &mut pending_sums,
+            discrepancies,
+        );
+        for (field_name, value) in entry.ledger_fields.iter().sorted() {
+            let balance = LedgerBalanceName::from(field_name.clone());
+            let value = if is_pending { 0 } else { *value };
+            let expected = previous_balances.get(&balance).unwrap_or(&0) + value;
+            let stored = entry.ledger_balances.get(&balance).copied();
+            if stored != Some(expected) {
+                discrepancies.push(Discrepancy::BalanceMismatch {
+                    entry_id: entry.entry_id.clone(),
+                    sequence: Some(entry.sequence),
+                    balance: balance.clone(),
+                    stored,
+                    expected,
+                });
+            }
+            // Continue from what is stored, so only the row where a balance breaks is reported.
+            previous_balances.insert(balance, stored.unwrap_or(expected));
+        }
+    }
+
+    let Some(head) = &rows.head else {
+        discrepancies.push(Discrepancy::MissingHead);
+        return;
+    };
+    let last = rows
+        .entries
+        .last()
+        .map(|row| (&row.entry.entry_id, row.entry.sequence))
+        .or(since.map(|snapshot| (&snapshot.entry_id, snapshot.sequence)));
+    if last != Some((&head.entry_id, head.sequence)) {
+        discrepancies.push(Discrepancy::HeadMismatch {
+            entry_id: head.entry_id.clone(),
+            sequence: head.sequence,
+            expected_entry_id: last.map(|(entry_id, _)| entry_id.clone()),
+            expected_sequence: last.map(|(_, sequence)| sequence),
+        });
+    }
+    for (balance, stored) in head.ledger_balances.iter().sorted() {
+        let expected = sums.get(balance).copied().unwrap_or(0);
+        if *stored != expected {
+            discrepancies.push(Discrepancy::BalanceMismatch {
+                entry_id: head.entry_id.clone(),
+                sequence: None,
+                balance: balance.clone(),
+                stored: Some(*stored),
+                expected,
+            });
+        }
+    }
+    verify_pending_balances(head, None, &mut pending_sums, discrepancies);
+}
+
+/// The stored pending balances must be the replayed ones, that are then continued from what is
+/// stored, so only the row where a pending balance breaks is reported.
+fn verify_pending_balances(
+    entry: &EntryWithBalance,
+    sequence: Option<u64>,
+    pending_sums: &mut HashMap<LedgerBalanceName, i128>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    for balance in pending_sums
+        .keys()
+        .chain(entry.pending_balances.keys())
+        .unique()
+        .sorted()
+    {
+        let stored = entry.pending_balances.get(balance).copied();
+        let expected = pending_sums.get(balance).copied().unwrap_or(0);
+        if stored.unwrap_or(0) != expected {
+            discrepancies.push(Discrepancy::PendingBalanceMismatch {
+                entry_id: entry.entry_id.clone(),
+                sequence,
+                balance: balance.clone(),
+                stored,
+                expected,
+            });
+        }
+    }
+    pending_sums.clone_from(&entry.pending_balances);
+}
+
+/// A Revert row must point to the entry it reverted, a Reverted row to the Revert row that
+/// reverted it, and only entries that were not fully reverted can have a CurrentEntry row.
+/// Likewise, a Posted row must point to the Applied row that posted it, and a Void row and
+/// the Voided row it voided to each other. Reverted and voided entries from before the
+/// snapshot are not read, so they are not checked.
+fn verify_reversals(
+    entries: &[StoredEntry],
+    since: Option<&Snapshot>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let first_sequence = first_sequence(since);
+    let rows_by_entry: HashMap<(&EntryId, u64), Vec<&StoredEntry>> = entries
+        .iter()
+        .into_group_map_by(|row| (&row.entry.entry_id, row.entry.sequence));
+    let has_row = |entry_id: &EntryId, sequence: u64, predicate: &dyn Fn(&StoredEntry) -> bool| {
+        rows_by_entry
+            .get(&(entry_id, sequence))
+            .map(|rows| rows.iter().any(|row| predicate(row)))
+            .unwrap_or(false)
+    };
+    for row in entries.iter() {
+        let entry = &row.entry;
+        let is_orphan = match &entry.status {
+            EntryStatus::Applied | EntryStatus::Pending => !row.is_current,
+            EntryStatus::Revert(reverted_sequence) if *reverted_sequence < first_sequence => false,
+            EntryStatus::Revert(reverted_sequence) => !has_row(
+                &entry.entry_id,
+                *reverted_sequence,
+                &|reverted| match reverted.entry.status {
+                    EntryStatus::Applied => reverted.is_current,
+                    EntryStatus::Reverted(_) => true,
+                    EntryStatus::Revert(_)
+                    | EntryStatus::Pending
+                    | EntryStatus::Posted(_)
+                    | EntryStatus::Void(_)
+                    | EntryStatus::Voided(_) => false,
+                },
+            ),
+            EntryStatus::Reverted(revert_sequence) => {
+                !has_row(&entry.entry_id, *revert_sequence, &|revert| {
+                    revert.entry.status == EntryStatus::Revert(entry.sequence)
+                })
+            }
+            EntryStatus::Posted(posting_sequence) => {
+                !has_row(&entry.entry_id, *posting_sequence, &|posting| {
+                    matches!(
+                        posting.entry.status,
+                        EntryStatus::Applied | EntryStatus::Reverted(_)
+                    )
+                })
+            }
+            EntryStatus::Void(voided_sequence) if *voided_sequence < first_sequence => false,
+            EntryStatus::Void(voided_sequence) => {
+                !has_row(&entry.entry_id, *voided_sequence, &|voided| {
+                    voided.entry.status == EntryStatus::Voided(entry.sequence)
+                })
+            }
+            EntryStatus::Voided(void_sequence) => {
+                !has_row(&entry.entry_id, *void_sequence, &|void| {
+                    void.entry.status == EntryStatus::Void(entry.sequence)
+                })
+            }
+        };
+        if is_orphan {
+            discrepancies.push(Discrepancy::OrphanHistoryRow {
+                entry_id: entry.entry_id.clone(),
+                sequence: entry.sequence,
+                status: entry.status.clone(),
+            });
+            continue;
+        }
+        if !row.is_current {
+            continue;
+        }
+        let is_fully_reverted = !row.reverted_ledger_fields.is_empty()
+            && entry.ledger_fields.iter().all(|(field_name, value)| {
+                row.reverted_ledger_fields.get(field_name).unwrap_or(&0) == value
+            });
+        let was_moved_to_history = has_row(&entry.entry_id, entry.sequence, &|history| {
+            matches!(
+                history.entry.status,
+                EntryStatus::Reverted(_) | EntryStatus::Posted(_) | EntryStatus::Voided(_)
+            )
+        });
+        if is_fully_reverted || was_moved_to_history {
+            discrepancies.push(Discrepancy::CurrentEntryForRevertedEntry {
+                entry_id: entry.entry_id.clone(),
+                sequence: entry.sequence,
+            });
+        }
+    }
+}
+
+/// Each hashed row must match its hash and chain to the previous row, and the HEAD must have
+/// the hash of the last row. Rows appended before entries were hashed are skipped, but a row
+/// without hash from the sequence the HEAD started hashing at was stripped of it.
+fn verify_hashes(
+    rows: &AccountRows,
+    since: Option<&Snapshot>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let mut previous_hash = since.and_then(|snapshot| snapshot.hash.clone());
+    for row in rows.entries.iter() {
+        let entry = &row.entry;
+        let must_be_hashed = rows
+            .hashed_from
+            .is_some_and(|hashed_from| entry.sequence >= hashed_from);
+        if entry.hash.is_none() && previous_hash.is_none() && !must_be_hashed {
+            continue;
+        }
+        let expected = entry.compute_hash();
+        if entry.hash.as_ref() != Some(&expected) {
+            discrepancies.push(Discrepancy::HashMismatch {
+                entry_id: entry.entry_id.clone(),
+                sequence: Some(entry.sequence),
+                stored: entry.hash.clone(),
+                expected: Some(expected),
+            });
+        }
+        if entry.prev_hash != previous_hash {
+            discrepancies.push(Discrepancy::BrokenHashChain {
+                entry_id: entry.entry_id.clone(),
+                sequence: entry.sequence,
+                prev_hash: entry.prev_hash.clone(),
+                expected: previous_hash,
+            });
+        }
+        previous_hash = entry.hash.clone();
+    }
+    if let Some(head) = &rows.head {
+        if head.hash != previous_hash {
+            discrepancies.push(Discrepancy::HashMismatch {
+                entry_id: head.entry_id.clone(),
+                sequence: None,
+                stored: head.hash.clone(),
+                expected: previous_hash,
+            });
+        }
+    }
+}
+
+/// Each snapshot must have the balances and hash of the entry at its sequence, the same the
+/// HEAD had after it.
+fn verify_snapshots(rows: &AccountRows, discrepancies: &mut Vec<Discrepancy>) {
+    for snapshot in rows.snapshots.iter() {
+        let entry = rows
+            .entries
+            .iter()
+            .map(|row| &row.entry)
+            .find(|entry| entry.sequence == snapshot.sequence);
+        let matches = entry.is_some_and(|entry| {
+            entry.entry_id == snapshot.entry_id
+                && entry.ledger_balances == snapshot.ledger_balances
+                && entry.pending_balances == snapshot.pending_balances
+                && entry.hash == snapshot.hash
+        });
+        if !matches {
+            discrepancies.push(Discrepancy::SnapshotMismatch {
+                sequence: snapshot.sequence,
+                entry_id: snapshot.entry_id.clone(),
+                expected_entry_id: entry.map(|entry| entry.entry_id.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use serde_json::Value;
+
+    use crate::app::test::{get_actor, get_repository, get_repository_with_snapshots, get_rng};
+    use crate::domain::entity::{
+        DeleteEntryRequest, EntryBuilder, EntryHash, EntryWithBalance, LedgerFieldName, Reversal,
+    };
+    use crate::domain::use_case::push_entries::test::push_multiple_entries;
+    use crate::domain::use_case::{
+        amend_entries_use_case, delete_entries_use_case, push_entries_use_case,
+    };
+    use crate::utils::utc_now;
+
+    use super::*;
+
+    fn row(
+        entry_id: &str,
+        sequence: u64,
+        status: EntryStatus,
+        amount: i128,
+        balance: i128,
+    ) -> StoredEntry {
+        StoredEntry {
+            entry: EntryWithBalance {
+                account_id: AccountId::new(Default::default()),
+                entry_id: EntryId::new_unchecked(entry_id.into()),
+                ledger_balances: HashMap::from([(
+                    LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                    balance,
+                )]),
+                pending_balances: HashMap::new(),
+                ledger_fields: HashMap::from([(
+                    LedgerFieldName::new("amount".into()).expect("A valid field"),
+                    amount,
+                )]),
+                additional_fields: Value::Null,
+                status: status.clone(),
+                reversal: None,
+                actor: Some(get_actor()),
+                sequence,
+                created_at: utc_now(),
+                prev_hash: None,
+                hash: None,
+            },
+            is_current: status == EntryStatus::Applied,
+            reverted_ledger_fields: HashMap::new(),
+        }
+    }
+
+    fn chained(mut entries: Vec<StoredEntry>) -> Vec<StoredEntry> {
+        let mut previous_hash = None;
+        for row in entries.iter_mut() {
+            row.entry.prev_hash = previous_hash;
+            row.entry.hash = Some(row.entry.compute_hash());
+            previous_hash = row.entry.hash.clone();
+        }
+        entries
+    }
+
+    fn rows(entries: Vec<StoredEntry>) -> AccountRows {
+        AccountRows {
+            head: entries.last().map(|row| row.entry.clone()),
+            hashed_from: entries
+                .iter()
+                .find(|row| row.entry.hash.is_some())
+                .map(|row| row.entry.sequence),
+            entries,
+            snapshots: Vec::new(),
+        }
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn verify_account_after_pushes_reversals_and_amendments() -> Result<()> {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let entries = (0..4)
+            .map(|amount| {
+                EntryBuilder::new()
+                    .with_account_id(account_id.clone())
+                    .with_ledger_field("amount", 100 + amount)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let (_, non_applied) = push_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            entries.iter().cloned().map(Into::into),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let reversal = |entry_id: &EntryId, amount: Option<i128>| DeleteEntryRequest {
+            account_id: account_id.clone(),
+            entry_id: entry_id.clone(),
+            ledger_fields: amount.map(|amount| {
+                HashMap::from([(
+                    LedgerFieldName::new("amount".into()).expect("A valid field"),
+                    amount,
+                )])
+            }),
+            reversal: Reversal::default(),
+        };
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [
+                reversal(&entries[0].entry_id, Some(30)),
+                reversal(&entries[1].entry_id, None),
+                reversal(&entries[2].entry_id, Some(50)),
+                reversal(&entries[2].entry_id, None),
+            ]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+        let mut amended = entries[3].clone();
+        amended.ledger_fields = HashMap::from([(LedgerFieldName::new("amount".into())?, 7)]);
+        let (_, non_applied) = amend_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [amended.into()].into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+
+        let report = verify_account_use_case(&repository, &account_id, 3, false).await?;
+        assert_eq!(report.discrepancies, vec![]);
+        assert_eq!(report.entries, 10);
+        let rows = repository.get_account_rows(&account_id, 1).await?;
+        assert!(rows.entries.iter().all(|row| row.entry.hash.is_some()));
+        assert_eq!(rows.hashed_from, Some(0));
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn verify_account_since_snapshot() -> Result<()> {
+        let repository = get_repository_with_snapshots(4).await;
+        let account_id: AccountId = Faker.fake();
+        let mut entries = push_multiple_entries(&repository, &account_id, 3).await;
+        entries.extend(push_multiple_entries(&repository, &account_id, 3).await);
+        let (_, non_applied) = delete_entries_use_case(
+            &repository,
+            get_rng().await,
+            &get_actor(),
+            [DeleteEntryRequest {
+                account_id: account_id.clone(),
+                entry_id: entries[0].entry_id.clone(),
+                ledger_fields: None,
+                reversal: Reversal::default(),
+            }]
+            .into_iter(),
+        )
+        .await;
+        assert!(non_applied.is_empty());
+
+        let report = verify_account_use_case(&repository, &account_id, 2, true).await?;
+        assert_eq!(report.discrepancies, vec![]);
+        assert_eq!(report.snapshot_sequence, Some(entries[5].sequence));
+        assert_eq!(report.entries, 1);
+        let report = verify_account_use_case(&repository, &account_id, 2, false).await?;
+        assert_eq!(report.discrepancies, vec![]);
+        assert_eq!(report.snapshot_sequence, None);
+        assert_eq!(report.entries, 7);
+        Ok(())
+    }
+
+    #[tokio_shared_rt::test(shared)]
+    async fn verify_nonexistent_account() {
+        let repository = get_repository().await;
+        let account_id: AccountId = Faker.fake();
+        let result = verify_account_use_case(&repository, &account_id, 2, true).await;
+        assert!(matches!(result, Err(GetBalanceError::NotFound(_))));
+    }
+
+    #[test]
+    fn verify_balance_mismatches() {
+        let mut rows = rows(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 1, EntryStatus::Applied, 5, 16),
+            row("c", 2, EntryStatus::Applied, 1, 17),
+        ]);
+        rows.head.as_mut().expect("A head").ledger_balances = HashMap::from([(
+            LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+            17,
+        )]);
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::BalanceMismatch {
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    sequence: Some(1),
+                    balance: LedgerBalanceName::new("balance_amount".into())
+                        .expect("A valid balance"),
+                    stored: Some(16),
+                    expected: 15,
+                },
+                Discrepancy::BalanceMismatch {
+                    entry_id: EntryId::new_unchecked("c".into()),
+                    sequence: None,
+                    balance: LedgerBalanceName::new("balance_amount".into())
+                        .expect("A valid balance"),
+                    stored: Some(17),
+                    expected: 16,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_sequence_gaps_and_head() {
+        let mut rows = rows(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 3, EntryStatus::Applied, 5, 15),
+            row("c", 3, EntryStatus::Applied, 0, 15),
+        ]);
+        rows.head = None;
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::SequenceGap { from: 1, to: 2 },
+                Discrepancy::DuplicatedSequence {
+                    sequence: 3,
+                    entry_ids: vec![
+                        EntryId::new_unchecked("b".into()),
+                        EntryId::new_unchecked("c".into())
+                    ],
+                },
+                Discrepancy::MissingHead,
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_reversal_rows() {
+        let mut partially_reverted = row("b", 1, EntryStatus::Applied, 5, 15);
+        partially_reverted.reverted_ledger_fields = HashMap::from([(
+            LedgerFieldName::new("amount".into()).expect("A valid field"),
+            5,
+        )]);
+        let rows = rows(vec![
+            row("a", 0, EntryStatus::Reverted(2), 10, 10),
+            partially_reverted,
+            row("a", 2, EntryStatus::Revert(0), -10, 5),
+            row("c", 3, EntryStatus::Revert(9), -5, 0),
+            row("b", 4, EntryStatus::Revert(1), -5, -5),
+            row("d", 5, EntryStatus::Reverted(8), 5, 0),
+        ]);
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::CurrentEntryForRevertedEntry {
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    sequence: 1,
+                },
+                Discrepancy::OrphanHistoryRow {
+                    entry_id: EntryId::new_unchecked("c".into()),
+                    sequence: 3,
+                    status: EntryStatus::Revert(9),
+                },
+                Discrepancy::OrphanHistoryRow {
+                    entry_id: EntryId::new_unchecked("d".into()),
+                    sequence: 5,
+                    status: EntryStatus::Reverted(8),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_pending_rows() {
+        let pending = |mut row: StoredEntry, balance: i128| {
+            row.is_current = row.entry.status == EntryStatus::Pending;
+            row.entry.pending_balances = HashMap::from([(
+                LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                balance,
+            )]);
+            row
+        };
+        let rows = rows(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            pending(row("b", 1, EntryStatus::Posted(2), 5, 10), 5),
+            row("b", 2, EntryStatus::Applied, 5, 15),
+            pending(row("c", 3, EntryStatus::Voided(4), 3, 15), 3),
+            row("c", 4, EntryStatus::Void(3), -3, 15),
+            pending(row("d", 5, EntryStatus::Pending, 2, 15), 4),
+            pending(row("e", 6, EntryStatus::Posted(9), 1, 15), 5),
+        ]);
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::PendingBalanceMismatch {
+                    entry_id: EntryId::new_unchecked("d".into()),
+                    sequence: Some(5),
+                    balance: LedgerBalanceName::new("balance_amount".into())
+                        .expect("A valid balance"),
+                    stored: Some(4),
+                    expected: 2,
+                },
+                Discrepancy::OrphanHistoryRow {
+                    entry_id: EntryId::new_unchecked("e".into()),
+                    sequence: 6,
+                    status: EntryStatus::Posted(9),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_hash_chain() {
+        let mut rows = rows(chained(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 1, EntryStatus::Applied, 5, 15),
+            row("c", 2, EntryStatus::Applied, 1, 16),
+        ]));
+        let tampered_hash = rows.entries[1].entry.hash.clone();
+        rows.entries[1].entry.created_at = utc_now() + chrono::Duration::days(1);
+        let forged_prev_hash = Some(EntryHash::new_unchecked("forged".into()));
+        rows.entries[2].entry.prev_hash = forged_prev_hash.clone();
+        rows.entries[2].entry.hash = Some(rows.entries[2].entry.compute_hash());
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::HashMismatch {
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    sequence: Some(1),
+                    stored: tampered_hash.clone(),
+                    expected: Some(rows.entries[1].entry.compute_hash()),
+                },
+                Discrepancy::BrokenHashChain {
+                    entry_id: EntryId::new_unchecked("c".into()),
+                    sequence: 2,
+                    prev_hash: forged_prev_hash,
+                    expected: tampered_hash,
+                },
+                Discrepancy::HashMismatch {
+                    entry_id: EntryId::new_unchecked("c".into()),
+                    sequence: None,
+                    stored: rows.head.as_ref().and_then(|head| head.hash.clone()),
+                    expected: rows.entries[2].entry.hash.clone(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_stripped_hashes() {
+        let mut rows = rows(chained(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 1, EntryStatus::Applied, 5, 15),
+        ]));
+        for row in rows.entries.iter_mut() {
+            row.entry.prev_hash = None;
+            row.entry.hash = None;
+        }
+        rows.head = rows.entries.last().map(|row| row.entry.clone());
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::HashMismatch {
+                    entry_id: EntryId::new_unchecked("a".into()),
+                    sequence: Some(0),
+                    stored: None,
+                    expected: Some(rows.entries[0].entry.compute_hash()),
+                },
+                Discrepancy::HashMismatch {
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    sequence: Some(1),
+                    stored: None,
+                    expected: Some(rows.entries[1].entry.compute_hash()),
+                },
+            ]
+        );
+
+        rows.hashed_from = None;
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(report.discrepancies, vec![]);
+    }
+
+    #[test]
+    fn verify_rows_since_snapshot() {
+        let entries = chained(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 1, EntryStatus::Applied, 5, 15),
+            row("c", 2, EntryStatus::Applied, 1, 16),
+            row("a", 3, EntryStatus::Revert(0), -10, 6),
+        ]);
+        let snapshot = Snapshot::from(&entries[1].entry);
+        let rows = rows(entries[2..].to_vec());
+        let report =
+            verify_account_rows(&AccountId::new(Default::default()), &rows, Some(&snapshot));
+        assert_eq!(report.discrepancies, vec![]);
+        assert_eq!(report.snapshot_sequence, Some(1));
+        assert_eq!(report.entries, 2);
+
+        let forged = Snapshot {
+            ledger_balances: HashMap::from([(
+                LedgerBalanceName::new("balance_amount".into()).expect("A valid balance"),
+                20,
+            )]),
+            ..snapshot
+        };
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, Some(&forged));
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::BalanceMismatch {
+                    entry_id: EntryId::new_unchecked("c".into()),
+                    sequence: Some(2),
+                    balance: LedgerBalanceName::new("balance_amount".into())
+                        .expect("A valid balance"),
+                    stored: Some(16),
+                    expected: 21,
+                },
+                Discrepancy::BalanceMismatch {
+                    entry_id: EntryId::new_unchecked("a".into()),
+                    sequence: None,
+                    balance: LedgerBalanceName::new("balance_amount".into())
+                        .expect("A valid balance"),
+                    stored: Some(6),
+                    expected: 11,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_snapshot_mismatches() {
+        let mut rows = rows(chained(vec![
+            row("a", 0, EntryStatus::Applied, 10, 10),
+            row("b", 1, EntryStatus::Applied, 5, 15),
+        ]));
+        let mut forged = Snapshot::from(&rows.entries[1].entry);
+        forged.hash = Some(EntryHash::new_unchecked("forged".into()));
+        let mut missing = Snapshot::from(&rows.entries[1].entry);
+        missing.sequence = 2;
+        rows.snapshots = vec![Snapshot::from(&rows.entries[0].entry), forged, missing];
+        let report = verify_account_rows(&AccountId::new(Default::default()), &rows, None);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::SnapshotMismatch {
+                    sequence: 1,
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    expected_entry_id: Some(EntryId::new_unchecked("b".into())),
+                },
+                Discrepancy::SnapshotMismatch {
+                    sequence: 2,
+                    entry_id: EntryId::new_unchecked("b".into()),
+                    expected_entry_id: None,
+                },
+            ]
+        );
+    }
+}
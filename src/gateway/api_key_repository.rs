@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::{operation::put_item::PutItemError, types::AttributeValue, Client};
+use chrono::DateTime;
+use uuid::Uuid;
+
+use crate::domain::entity::{AccountId, AccountScope, Actor, ApiKey, LedgerId, Scope};
+use crate::domain::gateway::{ApiKeyError, ApiKeyRepository};
+use crate::gateway::Table;
+
+pub struct DynamoDbApiKeyRepository {
+    client: Client,
+    table_name: String,
+}
+
+impl From<Table> for DynamoDbApiKeyRepository {
+    fn from(table: Table) -> Self {
+        Self {
+            client: table.client,
+            table_name: table.name,
+        }
+    }
+}
+
+impl ApiKeyRepository for DynamoDbApiKeyRepository {
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<(), ApiKeyError> {
+        let mut put_item = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("pk", api_key_pk(&api_key.key_id))
+            .item("sk", AttributeValue::S("|~".into()))
+            .item("key_id", AttributeValue::S(api_key.key_id.clone()))
+            .item("secret", AttributeValue::S(api_key.secret.clone()))
+            .item("actor", AttributeValue::S(api_key.actor.to_string()))
+            .item(
+                "scopes",
+                string_list(api_key.scopes.iter().map(|scope| scope.to_string())),
+            )
+            .item(
+                "account_ids",
+                string_list(
+                    api_key
+                        .accounts
+                        .account_ids
+                        .iter()
+                        .map(|account_id| account_id.to_string()),
+                ),
+            )
+            .item(
+                "account_prefixes",
+                string_list(api_key.accounts.account_prefixes.iter().cloned()),
+            )
+            .item(
+                "created_at",
+                AttributeValue::S(api_key.created_at.to_string()),
+            )
+            .condition_expression("attribute_not_exists(pk)");
+        if let Some(ledger_id) = &api_key.ledger_id {
+            put_item = put_item.item("ledger_id", AttributeValue::S(ledger_id.to_string()));
+        }
+        match put_item.send().await {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if let Some(PutItemError::ConditionalCheckFailedException(_)) =
+                    error.as_service_error()
+                {
+                    return Err(ApiKeyError::AlreadyExists(api_key.key_id.clone()));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn get_api_key(&self, key_id: &str) -> Result<ApiKey, ApiKeyError> {
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", api_key_pk(key_id))
+            .key("sk", AttributeValue::S("|~".into()))
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        match item.item() {
+            Some(item) => Ok(api_key_from_item(item)?),
+            None => Err(ApiKeyError::NotFound(key_id.into())),
+        }
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let mut api_keys = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("begins_with(pk, :prefix)")
+                .expression_attribute_values(":prefix", AttributeValue::S("API_KEY:".into()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(anyhow::Error::from)?;
+            for item in output.items() {
+                api_keys.push(api_key_from_item(item)?);
+            }
+            exclusive_start_key = output.last_evaluated_key().cloned();
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(api_keys)
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> Result<(), ApiKeyError> {
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", api_key_pk(key_id))
+            .key("sk", AttributeValue::S("|~".into()))
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        if output.attributes().is_none() {
+            return Err(ApiKeyError::NotFound(key_id.into()));
+        }
+        Ok(())
+    }
+}
+
+fn api_key_pk(key_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("API_KEY:{}", key_id))
+}
+
+fn string_list(values: impl Iterator<Item = String>) -> AttributeValue {
+    AttributeValue::L(values.map(AttributeValue::S).collect())
+}
+
+fn api_key_from_item(item: &HashMap<String, AttributeValue>) -> anyhow::Result<ApiKey> {
+    let string_field = |field: &str| -> anyhow::Result<&String> {
+        item.get(field)
+            .ok_or(anyhow!("Missing field {field} in api key"))?
+            .as_s()
+            .map_err(|_| anyhow!("Error reading field {field} in api key"))
+    };
+    let string_list_field = |field: &str| -> anyhow::Result<Vec<&String>> {
+        item.get(field)
+            .ok_or(anyhow!("Missing field {field} in api key"))?
+            .as_l()
+            .map_err(|_| anyhow!("Error reading field {field} in api key"))?
+            .iter()
+            .map(|value| {
+                value
+                    .as_s()
+                    .map_err(|_| anyhow!("Error reading field {field} in api key"))
+            })
+            .collect()
+    };
+    Ok(ApiKey {
+        key_id: string_field("key_id")?.clone(),
+        secret: string_field("secret")?.clone(),
+        actor: Actor::new(string_field("actor")?.clone())?,
+        scopes: string_list_field("scopes")?
+            .into_iter()
+            .map(|scope| Scope::from_str(scope))
+            .collect::<anyhow::Result<_>>()?,
+        accounts: AccountScope {
+            account_ids: string_list_field("account_ids")?
+                .into_iter()
+                .map(|account_id| Ok(AccountId::new(Uuid::from_str(account_id)?)))
+                .collect::<anyhow::Result<_>>()?,
+            account_prefixes: string_list_field("account_prefixes")?
+                .into_iter()
+                .cloned()
+                .collect(),
+        },
+        ledger_id: item
+            .get("ledger_id")
+            .map(|_| LedgerId::new(string_field("ledger_id")?.clone()))
+            .transpose()?,
+        created_at: DateTime::from_str(string_field("created_at")?)?,
+    })
+}
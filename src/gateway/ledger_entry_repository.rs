@@ -1,56 +1,351 @@
-use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
-};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fmt::Display, str::FromStr};
 
 use anyhow::{anyhow, bail, Result};
 use aws_sdk_dynamodb::types::ComparisonOperator;
 use aws_sdk_dynamodb::{
+    operation::query::builders::QueryFluentBuilder,
     operation::transact_write_items::{
         builders::TransactWriteItemsFluentBuilder, TransactWriteItemsError,
     },
+    types::builders::PutBuilder,
     types::{
-        AttributeValue, Condition, Delete, KeysAndAttributes, Put,
-        ReturnValuesOnConditionCheckFailure, TransactWriteItem, Update,
+        AttributeValue, Condition, Delete, KeysAndAttributes, Put, PutRequest,
+        ReturnValuesOnConditionCheckFailure, TransactWriteItem, Update, WriteRequest,
     },
     Client,
 };
-use chrono::{DateTime, Days, Utc};
+use chrono::{DateTime, Days, NaiveDate, SecondsFormat, Utc};
+use futures_util::future::try_join_all;
 use itertools::Itertools;
+use tokio::time::sleep;
 use uuid::Uuid;
 
-use crate::domain::entity::{Conditional, EntryWithConditionals};
+use crate::domain::entity::{
+    Account, AccountCursor, AccountQuery, AccountRows, AccountTag, AccrualSchedule, Actor,
+    AuditAction, AuditCursor, AuditQuery, AuditRecord, Conditional, EntryReversal,
+    EntryWithConditionals, FeeRule, FeeRuleId, LedgerId, PendingResolution, Period, PeriodClosing,
+    Reconciliation, ReconciliationOptions, RecurringSchedule, RecurringScheduleId, Reversal,
+    ReversalReason, ScanCursor, Snapshot, StoredEntry, VelocityLimit, VelocityLimitId,
+    MAX_VELOCITY_LIMITS,
+};
 use crate::domain::{
     entity::{
-        AccountId, Entry, EntryId, EntryStatus, EntryToContinue, EntryWithBalance,
+        AccountId, Entry, EntryHash, EntryId, EntryStatus, EntryToContinue, EntryWithBalance,
         LedgerBalanceName, LedgerFieldName, Order,
     },
-    gateway::{AppendEntriesError, GetBalanceError, LedgerEntryRepository, RevertEntriesError},
+    gateway::{
+        AccrualError, AmendEntriesError, AppendEntriesError, ClosePeriodError, FeeRuleError,
+        GetBalanceError, LedgerEntryRepository, PendingEntriesError, ReconciliationError,
+        RecurringScheduleError, RevertEntriesError, SetParentError, VelocityLimitError,
+    },
 };
+use crate::gateway::table_config::SnapshotPolicy;
+use crate::gateway::{Table, TTL_ATTRIBUTE};
 use crate::{domain::entity::Cursor, utils::utc_now};
 
+/// BatchGetItem accepts at most 100 keys.
+const MAX_BATCH_GET_ITEMS: usize = 100;
+/// Requests made to read a batch of balances, while some keys are left unprocessed.
+const MAX_BATCH_GET_TRIES: u32 = 5;
+/// BatchWriteItem accepts at most 25 items.
+const MAX_BATCH_WRITE_ITEMS: usize = 25;
+/// Requests made to write a batch of items, while some are left unprocessed.
+const MAX_BATCH_WRITE_TRIES: u32 = 5;
+/// How long the fee rules read for a push are reused by the next ones. A rule set or deleted
+/// in another server is applied after it.
+const FEE_RULES_TTL: Duration = Duration::from_secs(10);
+/// Partitions of the accounts GSI, one per first hex digit of the account id.
+const ACCOUNTS_SHARDS: u32 = 16;
+/// Velocity buckets updated with the entries of an account, one per limit it can have.
+const MAX_VELOCITY_UPDATES: usize = 2 * MAX_VELOCITY_LIMITS;
+
 pub struct DynamoDbLedgerEntryRepository {
     client: Client,
+    table_name: String,
+    index_name: String,
+    accounts_index_name: String,
+    /// Prepended to every PK and GSI key, so each ledger only sees its own rows.
+    key_prefix: String,
+    snapshot_policy: SnapshotPolicy,
+    fee_rules: FeeRulesCache,
+}
+
+/// The fee rules last read from each ledger, shared by the repositories of a table.
+#[derive(Clone, Debug, Default)]
+pub struct FeeRulesCache(Arc<Mutex<HashMap<String, CachedFeeRules>>>);
+
+/// The rules and when they were read.
+type CachedFeeRules = (Instant, Vec<FeeRule>);
+
+impl FeeRulesCache {
+    fn get(&self, key_prefix: &str) -> Option<CachedFeeRules> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(key_prefix).cloned())
+    }
+
+    fn set(&self, key_prefix: &str, rules: Option<&[FeeRule]>) {
+        if let Ok(mut cache) = self.0.lock() {
+            match rules {
+                Some(rules) => cache.insert(key_prefix.into(), (Instant::now(), rules.to_vec())),
+                None => cache.remove(key_prefix),
+            };
+        }
+    }
+}
+
+impl From<Table> for DynamoDbLedgerEntryRepository {
+    fn from(table: Table) -> Self {
+        Self::new(table, None)
+    }
 }
 
-impl From<Client> for DynamoDbLedgerEntryRepository {
-    fn from(client: Client) -> Self {
-        Self { client }
+impl DynamoDbLedgerEntryRepository {
+    /// Repository of a tenant ledger, or of the default ledger without a `ledger_id`.
+    pub fn new(table: Table, ledger_id: Option<&LedgerId>) -> Self {
+        Self {
+            client: table.client,
+            table_name: table.name,
+            index_name: table.index_name,
+            accounts_index_name: table.accounts_index_name,
+            key_prefix: ledger_id.map(key_prefix).unwrap_or_default(),
+            snapshot_policy: table.snapshot_policy,
+            fee_rules: table.fee_rules,
+        }
     }
 }
 
+/// The prefix of every PK and GSI key of a tenant ledger.
+pub fn key_prefix(ledger_id: &LedgerId) -> String {
+    format!("LEDGER:{}|", ledger_id)
+}
+
 impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
     async fn append_entries(
         &self,
         account_id: &AccountId,
         entries: &[EntryWithConditionals],
+        actor: &Actor,
     ) -> Result<Vec<EntryWithBalance>, AppendEntriesError> {
+        let head = self.get_head(account_id).await?;
         let (transact, entries_with_balance) = self
-            .internal_append_entries(account_id, entries, self.client.transact_write_items())
+            .internal_append_entries(
+                account_id,
+                entries,
+                head,
+                &HashMap::new(),
+                (actor, AuditAction::PushEntries),
+                self.client.transact_write_items(),
+            )
             .await?;
 
         match transact.send().await {
             Ok(_) => Ok(entries_with_balance),
+            Err(error) => Err(self.append_entries_error(error, account_id)),
+        }
+    }
+
+    async fn append_linked_entries(
+        &self,
+        entries: &[(AccountId, Vec<EntryWithConditionals>)],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, AppendEntriesError> {
+        let heads = try_join_all(
+            entries
+                .iter()
+                .map(|(account_id, _)| self.get_head(account_id)),
+        )
+        .await?;
+        let mut transact = self.client.transact_write_items();
+        let mut entries_with_balance = Vec::new();
+        for ((account_id, account_entries), head) in entries.iter().zip(heads) {
+            let appended;
+            (transact, appended) = self
+                .internal_append_entries(
+                    account_id,
+                    account_entries,
+                    head,
+                    &HashMap::new(),
+                    (actor, AuditAction::PushEntries),
+                    transact,
+                )
+                .await?;
+            entries_with_balance.extend(appended);
+        }
+        let account_id = &entries
+            .first()
+            .ok_or(anyhow!("Missing entries to append"))?
+            .0;
+        match transact.send().await {
+            Ok(_) => Ok(entries_with_balance),
+            Err(error) => Err(self.append_entries_error(error, account_id)),
+        }
+    }
+
+    async fn revert_entries(
+        &self,
+        account_id: &AccountId,
+        reversals: &[EntryReversal],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, RevertEntriesError> {
+        let entries_ids = reversals
+            .iter()
+            .map(|reversal| reversal.entry_id.clone())
+            .collect_vec();
+        let mut current_entries = self.get_current_entries(account_id, &entries_ids).await?;
+        let missing_entries = missing_entries(&entries_ids, &current_entries);
+        if !missing_entries.is_empty() {
+            return Err(RevertEntriesError::EntriesDoesNotExists(
+                account_id.clone(),
+                missing_entries,
+            ));
+        }
+        let pending_entries = pending_entries(&entries_ids, &current_entries);
+        if !pending_entries.is_empty() {
+            return Err(RevertEntriesError::InvalidStatus(
+                account_id.clone(),
+                pending_entries,
+            ));
+        }
+        let head = self.get_head(account_id).await?;
+        let entries_in_closed_period =
+            entries_in_closed_period(head.as_ref(), &entries_ids, &current_entries);
+        if !entries_in_closed_period.is_empty() {
+            return Err(RevertEntriesError::PeriodClosed(
+                account_id.clone(),
+                entries_in_closed_period,
+            ));
+        }
+        let mut already_reverted = Vec::new();
+        let mut invalid_reversals = Vec::new();
+        let mut revert_entries = Vec::new();
+        let mut last_reversals = HashMap::new();
+        for reversal in reversals {
+            let current_entry = current_entries
+                .get_mut(&reversal.entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?;
+            if current_entry.is_fully_reverted() {
+                already_reverted.push(reversal.entry_id.clone());
+                continue;
+            }
+            match current_entry.ledger_fields_to_revert(reversal.ledger_fields.as_ref()) {
+                Some(ledger_fields) => {
+                    for (field_name, value) in ledger_fields.iter() {
+                        *current_entry
+                            .reverted_ledger_fields
+                            .entry(field_name.clone())
+                            .or_insert(0) += value;
+                    }
+                    revert_entries
+                        .push(current_entry.revert_entry(ledger_fields, &reversal.reversal));
+                    last_reversals.insert(reversal.entry_id.clone(), reversal.reversal.clone());
+                }
+                None => invalid_reversals.push(reversal.entry_id.clone()),
+            }
+        }
+        if !already_reverted.is_empty() {
+            return Err(RevertEntriesError::EntriesDoesNotExists(
+                account_id.clone(),
+                already_reverted,
+            ));
+        }
+        if !invalid_reversals.is_empty() {
+            return Err(RevertEntriesError::InvalidReversal(
+                account_id.clone(),
+                invalid_reversals,
+            ));
+        }
+        let (mut transact, new_entries_with_balance) = self
+            .internal_append_entries(
+                account_id,
+                &revert_entries,
+                head,
+                &current_entries,
+                (actor, AuditAction::RevertEntries),
+                self.client.transact_write_items(),
+            )
+            .await?;
+        let last_revert_sequences: HashMap<EntryId, u64> = new_entries_with_balance
+            .iter()
+            .map(|entry| (entry.entry_id.clone(), entry.sequence))
+            .collect();
+        for (entry_id, revert_sequence) in last_revert_sequences {
+            let current_entry = current_entries
+                .remove(&entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?;
+            if current_entry.is_fully_reverted() {
+                let mut old_entry = current_entry.entry;
+                old_entry.status = EntryStatus::Reverted(revert_sequence);
+                old_entry.reversal = last_reversals.remove(&entry_id);
+                transact = transact.transact_items(create_transact_item_for_entry(
+                    &old_entry,
+                    false,
+                    &self.table_name,
+                    &self.key_prefix,
+                )?);
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .delete(
+                            Delete::builder()
+                                .table_name(&self.table_name)
+                                .key(
+                                    "pk",
+                                    Pk::Entry(account_id.clone(), old_entry.entry_id.clone())
+                                        .key(&self.key_prefix),
+                                )
+                                .key("sk", Sk::CurrentEntry.into())
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            } else {
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .update(
+                            Update::builder()
+                                .table_name(&self.table_name)
+                                .key(
+                                    "pk",
+                                    Pk::Entry(account_id.clone(), entry_id.clone())
+                                        .key(&self.key_prefix),
+                                )
+                                .key("sk", Sk::CurrentEntry.into())
+                                .expression_attribute_values(
+                                    ":reverted_ledger_fields",
+                                    AttributeValue::M(
+                                        current_entry
+                                            .reverted_ledger_fields
+                                            .into_iter()
+                                            .map(|(k, v)| {
+                                                (k.into(), AttributeValue::N(v.to_string()))
+                                            })
+                                            .collect(),
+                                    ),
+                                )
+                                .expression_attribute_values(
+                                    ":sequence",
+                                    AttributeValue::N(current_entry.entry.sequence.to_string()),
+                                )
+                                .expression_attribute_names("#sequence_field", "sequence")
+                                .update_expression(
+                                    "SET reverted_ledger_fields = :reverted_ledger_fields",
+                                )
+                                .condition_expression("#sequence_field = :sequence")
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            }
+        }
+
+        match transact.send().await {
+            Ok(_) => Ok(new_entries_with_balance),
             Err(error) => {
                 if let Some(TransactWriteItemsError::TransactionCanceledException(err)) =
                     error.as_service_error()
@@ -61,26 +356,19 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
                         .map(|msg| msg.contains("ConditionalCheckFailed"))
                         .unwrap_or(false)
                     {
-                        let mut entries = Vec::new();
                         for cancellation_reason in err.cancellation_reasons() {
                             if let Some(pk) =
                                 cancellation_reason.item().and_then(|item| item.get("pk"))
                             {
-                                let pk = Pk::try_from(pk.clone())?;
-                                match pk {
-                                    Pk::Balance(account_id) => {
-                                        return Err(AppendEntriesError::OptimisticLockError(
-                                            account_id,
-                                        ))
-                                    }
-                                    Pk::Entry(_, entry_id) => entries.push(entry_id),
+                                let pk = Pk::from_key(pk, &self.key_prefix)?;
+                                if let Pk::Balance(account_id) = pk {
+                                    return Err(RevertEntriesError::OptimisticLockError(
+                                        account_id,
+                                    ));
                                 }
                             }
                         }
-                        return Err(AppendEntriesError::EntriesAlreadyExists(
-                            account_id.clone(),
-                            entries,
-                        ));
+                        return Err(anyhow::Error::from(error).into());
                     }
                 }
                 Err(anyhow::Error::from(error).into())
@@ -88,119 +376,90 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
         }
     }
 
-    async fn revert_entries(
+    async fn amend_entries(
         &self,
         account_id: &AccountId,
-        entries_ids: &[EntryId],
-    ) -> Result<Vec<EntryWithBalance>, RevertEntriesError> {
-        let mut keys_and_attributes_builder = KeysAndAttributes::builder();
-
-        for entry_id in entries_ids {
-            keys_and_attributes_builder = keys_and_attributes_builder.keys(HashMap::from([
-                (
-                    "pk".into(),
-                    Pk::Entry(account_id.clone(), entry_id.clone()).into(),
-                ),
-                ("sk".into(), Sk::CurrentEntry.into()),
-            ]));
-        }
-        let items = self
-            .client
-            .batch_get_item()
-            .request_items(
-                "a_ledger",
-                keys_and_attributes_builder
-                    .build()
-                    .map_err(anyhow::Error::from)?,
-            )
-            .send()
-            .await
-            .map_err(anyhow::Error::from)?;
-        let mut entry_with_balances = items
-            .responses()
-            .and_then(|responses| responses.get("a_ledger"))
-            .map(
-                |responses| -> Result<HashMap<EntryId, EntryWithBalance>, GetBalanceError> {
-                    Ok(responses
-                        .iter()
-                        .map(|item| {
-                            let entry = entry_with_balance_from_item(item)?;
-                            Ok((entry.entry_id.clone(), entry))
-                        })
-                        .collect::<Result<HashMap<EntryId, EntryWithBalance>, GetBalanceError>>()
-                        .map_err(anyhow::Error::from)?)
-                },
-            )
-            .transpose()
-            .map_err(anyhow::Error::from)?
-            .unwrap_or_default();
-
-        let found_entries_ids: HashSet<EntryId> = entry_with_balances.keys().cloned().collect();
-        let missing_entries = entries_ids
+        entries: &[EntryWithConditionals],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, AmendEntriesError> {
+        let entries_ids = entries
             .iter()
-            .cloned()
-            .collect::<HashSet<EntryId>>()
-            .difference(&found_entries_ids)
-            .cloned()
+            .map(|entry| entry.entry.entry_id.clone())
             .collect_vec();
+        let mut current_entries = self.get_current_entries(account_id, &entries_ids).await?;
+        let missing_entries = missing_entries(&entries_ids, &current_entries);
         if !missing_entries.is_empty() {
-            return Err(RevertEntriesError::EntriesDoesNotExists(
+            return Err(AmendEntriesError::EntriesDoesNotExists(
                 account_id.clone(),
                 missing_entries,
             ));
         }
+        // Neither pending entries can be amended, nor can they be amended into pending ones.
+        let invalid_status_entries = pending_entries(&entries_ids, &current_entries)
+            .into_iter()
+            .chain(
+                entries
+                    .iter()
+                    .filter(|entry| entry.entry.status != EntryStatus::Applied)
+                    .map(|entry| entry.entry.entry_id.clone()),
+            )
+            .unique()
+            .collect_vec();
+        if !invalid_status_entries.is_empty() {
+            return Err(AmendEntriesError::InvalidStatus(
+                account_id.clone(),
+                invalid_status_entries,
+            ));
+        }
+        let head = self.get_head(account_id).await?;
+        let entries_in_closed_period =
+            entries_in_closed_period(head.as_ref(), &entries_ids, &current_entries);
+        if !entries_in_closed_period.is_empty() {
+            return Err(AmendEntriesError::PeriodClosed(
+                account_id.clone(),
+                entries_in_closed_period,
+            ));
+        }
+        let reversal = Reversal {
+            reason: ReversalReason::Amendment,
+            ..Default::default()
+        };
+        let mut new_entries = Vec::new();
+        for entry in entries {
+            let current_entry = current_entries
+                .get(&entry.entry.entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?;
+            new_entries.push(
+                current_entry.revert_entry(current_entry.remaining_ledger_fields(), &reversal),
+            );
+            new_entries.push(entry.clone());
+        }
         let (mut transact, new_entries_with_balance) = self
             .internal_append_entries(
                 account_id,
-                &entries_ids
-                    .iter()
-                    .filter_map(|entry_id| entry_with_balances.get(entry_id).cloned())
-                    .map(|entry: EntryWithBalance| {
-                        let sequence = entry.sequence;
-                        let mut entry: Entry = entry.into();
-                        entry.status = EntryStatus::Revert(sequence);
-                        entry.ledger_fields = entry
-                            .ledger_fields
-                            .into_iter()
-                            .map(|(key, value)| (key, -value))
-                            .collect();
-                        entry.into()
-                    })
-                    .collect_vec(),
+                &new_entries,
+                head,
+                &current_entries,
+                (actor, AuditAction::AmendEntries),
                 self.client.transact_write_items(),
             )
             .await?;
         for entry in new_entries_with_balance.iter() {
-            let EntryStatus::Revert(sequence) = &entry.status else {
-                return Err(anyhow!("Expects status to be revert").into());
+            let EntryStatus::Revert(_) = &entry.status else {
+                continue;
             };
-            let mut old_entry = entry_with_balances
-                .remove(
-                    &entry_with_balances
-                        .iter()
-                        .find(|(_, entry_with_balance)| entry_with_balance.sequence == *sequence)
-                        .ok_or(anyhow!("We should alway be able to get the old entry here"))?
-                        .0
-                        .clone(),
-                )
-                .ok_or(anyhow!("We should alway be able to get the old entry here"))?;
+            let mut old_entry = current_entries
+                .remove(&entry.entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?
+                .entry;
             old_entry.status = EntryStatus::Reverted(entry.sequence);
-            transact = transact.transact_items(create_transact_item_for_entry(&old_entry, false)?);
-            transact = transact.transact_items(
-                TransactWriteItem::builder()
-                    .delete(
-                        Delete::builder()
-                            .table_name("a_ledger")
-                            .key(
-                                "pk",
-                                Pk::Entry(account_id.clone(), old_entry.entry_id.clone()).into(),
-                            )
-                            .key("sk", Sk::CurrentEntry.into())
-                            .build()
-                            .map_err(anyhow::Error::from)?,
-                    )
-                    .build(),
-            );
+            old_entry.reversal = entry.reversal.clone();
+            transact = transact.transact_items(create_transact_item_for_entry(
+                &old_entry,
+                false,
+                &self.table_name,
+                &self.key_prefix,
+            )?);
         }
 
         match transact.send().await {
@@ -215,19 +474,8 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
                         .map(|msg| msg.contains("ConditionalCheckFailed"))
                         .unwrap_or(false)
                     {
-                        for cancellation_reason in err.cancellation_reasons() {
-                            if let Some(pk) =
-                                cancellation_reason.item().and_then(|item| item.get("pk"))
-                            {
-                                let pk = Pk::try_from(pk.clone())?;
-                                if let Pk::Balance(account_id) = pk {
-                                    return Err(RevertEntriesError::OptimisticLockError(
-                                        account_id,
-                                    ));
-                                }
-                            }
-                        }
-                        return Err(anyhow::Error::from(error).into());
+                        // Either the HEAD or one of the amended entries changed since we read them.
+                        return Err(AmendEntriesError::OptimisticLockError(account_id.clone()));
                     }
                 }
                 Err(anyhow::Error::from(error).into())
@@ -235,27 +483,208 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
         }
     }
 
-    async fn get_balance(
+    async fn resolve_pending_entries(
         &self,
         account_id: &AccountId,
-    ) -> Result<EntryWithBalance, GetBalanceError> {
-        let item = self
-            .client
-            .get_item()
-            .table_name("a_ledger")
-            .key("pk", Pk::Balance(account_id.clone()).into())
-            .key("sk", Sk::CurrentEntry.into())
-            .send()
+        entry_ids: &[EntryId],
+        resolution: PendingResolution,
+        rules: &[FeeRule],
+        actor: &Actor,
+    ) -> Result<Vec<EntryWithBalance>, PendingEntriesError> {
+        let entry_ids = entry_ids.iter().unique().cloned().collect_vec();
+        let mut current_entries = self.get_current_entries(account_id, &entry_ids).await?;
+        let missing_entries = missing_entries(&entry_ids, &current_entries);
+        if !missing_entries.is_empty() {
+            return Err(PendingEntriesError::EntriesDoesNotExists(
+                account_id.clone(),
+                missing_entries,
+            ));
+        }
+        let not_pending_entries = entry_ids
+            .iter()
+            .filter(|entry_id| {
+                current_entries
+                    .get(entry_id)
+                    .is_some_and(|current_entry| current_entry.entry.status != EntryStatus::Pending)
+            })
+            .cloned()
+            .collect_vec();
+        if !not_pending_entries.is_empty() {
+            return Err(PendingEntriesError::InvalidStatus(
+                account_id.clone(),
+                not_pending_entries,
+            ));
+        }
+        let head = self.get_head(account_id).await?;
+        let mut resolved_entries = Vec::new();
+        for entry_id in entry_ids.iter() {
+            let current_entry = current_entries
+                .get(entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?;
+            resolved_entries.push(current_entry.resolve_entry(resolution));
+        }
+        // Posting replaces the CurrentEntry row of the pending entry with the Applied one, which
+        // the fee rules match like a pushed entry.
+        let no_replaced_entries = HashMap::new();
+        let (action, replaced_entries, (new_entries, linked_entries)) = match resolution {
+            PendingResolution::Post => (
+                AuditAction::PostEntries,
+                &current_entries,
+                FeeRule::with_derived_entries(account_id, &resolved_entries, rules),
+            ),
+            PendingResolution::Void => (
+                AuditAction::VoidEntries,
+                &no_replaced_entries,
+                (resolved_entries.clone(), Vec::new()),
+            ),
+        };
+        let linked_heads = try_join_all(
+            linked_entries
+                .iter()
+                .map(|(linked_account_id, _)| self.get_head(linked_account_id)),
+        )
+        .await?;
+        // A derived entry that exceeds a velocity limit is reported as the entry it derives from.
+        let velocity_error = |error: AppendEntriesError| match error {
+            AppendEntriesError::VelocityLimitExceeded(entry_id, limit_id) => {
+                let entry_id = resolved_entries
+                    .iter()
+                    .find(|resolved| {
+                        resolved.entry.entry_id == entry_id
+                            || rules
+                                .iter()
+                                .flat_map(|rule| rule.derive(&resolved.entry))
+                                .any(|derived| derived.entry.entry_id == entry_id)
+                    })
+                    .map(|resolved| resolved.entry.entry_id.clone())
+                    .unwrap_or(entry_id);
+                PendingEntriesError::VelocityLimitExceeded(entry_id, limit_id)
+            }
+            error => error.into(),
+        };
+        let (mut transact, mut new_entries_with_balance) = self
+            .internal_append_entries(
+                account_id,
+                &new_entries,
+                head,
+                replaced_entries,
+                (actor, action),
+                self.client.transact_write_items(),
+            )
             .await
-            .map_err(anyhow::Error::from)?;
-        match item.item {
-            None => Err(GetBalanceError::NotFound(account_id.clone())),
-            Some(item) => entry_with_balance_from_item(&item),
+            .map_err(velocity_error)?;
+        for entry in new_entries_with_balance
+            .iter()
+            .filter(|entry| entry_ids.contains(&entry.entry_id))
+        {
+            let mut old_entry = current_entries
+                .remove(&entry.entry_id)
+                .ok_or(anyhow!("We should alway be able to get the old entry here"))?
+                .entry;
+            old_entry.status = match resolution {
+                PendingResolution::Post => EntryStatus::Posted(entry.sequence),
+                PendingResolution::Void => EntryStatus::Voided(entry.sequence),
+            };
+            transact = transact.transact_items(create_transact_item_for_entry(
+                &old_entry,
+                false,
+                &self.table_name,
+                &self.key_prefix,
+            )?);
+            if resolution == PendingResolution::Void {
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .delete(
+                            Delete::builder()
+                                .table_name(&self.table_name)
+                                .key(
+                                    "pk",
+                                    Pk::Entry(account_id.clone(), old_entry.entry_id.clone())
+                                        .key(&self.key_prefix),
+                                )
+                                .key("sk", Sk::CurrentEntry.into())
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            }
+        }
+        for ((linked_account_id, linked_entries), linked_head) in
+            linked_entries.iter().zip(linked_heads)
+        {
+            let appended;
+            (transact, appended) = self
+                .internal_append_entries(
+                    linked_account_id,
+                    linked_entries,
+                    linked_head,
+                    &HashMap::new(),
+                    (actor, action),
+                    transact,
+                )
+                .await
+                .map_err(velocity_error)?;
+            new_entries_with_balance.extend(appended);
         }
-    }
 
-    async fn get_entry(
-        &self,
+        match transact.send().await {
+            Ok(_) => Ok(new_entries_with_balance),
+            Err(error) => {
+                if let Some(TransactWriteItemsError::TransactionCanceledException(err)) =
+                    error.as_service_error()
+                {
+                    if err
+                        .message
+                        .as_ref()
+                        .map(|msg| msg.contains("ConditionalCheckFailed"))
+                        .unwrap_or(false)
+                    {
+                        // Either a HEAD, a velocity counter or one of the pending entries changed
+                        // since we read them.
+                        return Err(PendingEntriesError::OptimisticLockError(account_id.clone()));
+                    }
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn get_balance(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<EntryWithBalance, GetBalanceError> {
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+            .key("sk", Sk::CurrentEntry.into())
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        match item.item {
+            None => Err(GetBalanceError::NotFound(account_id.clone())),
+            Some(item) => entry_with_balance_from_item(&item, &self.key_prefix),
+        }
+    }
+
+    async fn get_balances(
+        &self,
+        account_ids: &[AccountId],
+    ) -> Result<Vec<EntryWithBalance>, GetBalanceError> {
+        let account_ids = account_ids.iter().unique().collect::<Vec<&AccountId>>();
+        let chunks = try_join_all(
+            account_ids
+                .chunks(MAX_BATCH_GET_ITEMS)
+                .map(|account_ids| self.batch_get_balances(account_ids)),
+        )
+        .await?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    async fn get_entry(
+        &self,
         account_id: &AccountId,
         entry_id: &EntryId,
         entry_to_continue: EntryToContinue,
@@ -282,12 +711,14 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
             .client
             .query()
             .limit(limit as i32)
-            .table_name("a_ledger")
+            .table_name(&self.table_name)
             .key_conditions(
                 "pk",
                 Condition::builder()
                     .comparison_operator(ComparisonOperator::Eq)
-                    .attribute_value_list(Pk::Entry(account_id.clone(), entry_id.clone()).into())
+                    .attribute_value_list(
+                        Pk::Entry(account_id.clone(), entry_id.clone()).key(&self.key_prefix),
+                    )
                     .build()
                     .map_err(anyhow::Error::from)?,
             )
@@ -299,7 +730,7 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
         let entry_with_balances = items
             .items()
             .iter()
-            .map(entry_with_balance_from_item)
+            .map(|item| entry_with_balance_from_item(item, &self.key_prefix))
             .collect::<Result<Vec<EntryWithBalance>, GetBalanceError>>()?;
         if entry_with_balances.is_empty() {
             if let EntryToContinue::Start = entry_to_continue {
@@ -330,15 +761,15 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
                 .client
                 .query()
                 .limit((limit as usize - result.len()) as i32 + 1)
-                .table_name("a_ledger")
-                .index_name("a_ledger_created_at_idx")
+                .table_name(&self.table_name)
+                .index_name(&self.index_name)
                 .key_conditions(
                     "account_id_and_date",
                     Condition::builder()
                         .comparison_operator(ComparisonOperator::Eq)
                         .attribute_value_list(AttributeValue::S(format!(
-                            "{}|{}",
-                            account_id, current_date
+                            "{}{}|{}",
+                            self.key_prefix, account_id, current_date
                         )))
                         .build()
                         .map_err(anyhow::Error::from)?,
@@ -383,9 +814,8 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
             let mut entry_with_balances = items
                 .items()
                 .iter()
-                .map(entry_with_balance_from_item)
-                .collect::<Result<Vec<EntryWithBalance>, GetBalanceError>>(
-            )?;
+                .map(|item| entry_with_balance_from_item(item, &self.key_prefix))
+                .collect::<Result<Vec<EntryWithBalance>, GetBalanceError>>()?;
             result.append(&mut entry_with_balances);
 
             if result.len() > limit as usize {
@@ -441,271 +871,3404 @@ impl LedgerEntryRepository for DynamoDbLedgerEntryRepository {
 
         Ok((result, cursor))
     }
-}
 
-impl DynamoDbLedgerEntryRepository {
-    async fn internal_append_entries(
+    async fn scan_entries(
+        &self,
+        start_date: &DateTime<Utc>,
+        end_date: &DateTime<Utc>,
+        segment: i32,
+        total_segments: i32,
+        start_after: Option<ScanCursor>,
+    ) -> Result<(Vec<EntryWithBalance>, Option<ScanCursor>), GetBalanceError> {
+        let items = self
+            .client
+            .scan()
+            .table_name(&self.table_name)
+            .segment(segment)
+            .total_segments(total_segments)
+            // The CurrentEntry and History rows of the entries, like the date GSI has them.
+            .filter_expression(
+                "begins_with(pk, :account) AND contains(pk, :entry) AND (sk = :current OR begins_with(sk, :history)) AND created_at BETWEEN :start_date AND :end_date",
+            )
+            .expression_attribute_values(
+                ":account",
+                AttributeValue::S(format!("{}ACCOUNT_ID:", self.key_prefix)),
+            )
+            .expression_attribute_values(":entry", AttributeValue::S("|ENTRY_ID:".into()))
+            .expression_attribute_values(":current", Sk::CurrentEntry.into())
+            .expression_attribute_values(":history", AttributeValue::S("|HISTORY:".into()))
+            .expression_attribute_values(":start_date", AttributeValue::S(start_date.to_string()))
+            .expression_attribute_values(
+                ":end_date",
+                AttributeValue::S(format_created_at_and_sequence(end_date, u64::MAX)),
+            )
+            .set_exclusive_start_key(start_after.map(|cursor| {
+                HashMap::from([
+                    ("pk".into(), AttributeValue::S(cursor.pk)),
+                    ("sk".into(), AttributeValue::S(cursor.sk)),
+                ])
+            }))
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let entries = items
+            .items()
+            .iter()
+            .map(|item| entry_with_balance_from_item(item, &self.key_prefix))
+            .collect::<Result<Vec<EntryWithBalance>, GetBalanceError>>()?;
+        let cursor = items
+            .last_evaluated_key()
+            .map(|key| -> Result<ScanCursor> {
+                let string_key = |name: &str| -> Result<String> {
+                    Ok(key
+                        .get(name)
+                        .ok_or(anyhow!("Missing {name} in last evaluated key"))?
+                        .as_s()
+                        .map_err(|_| anyhow!("Error reading {name} in last evaluated key"))?
+                        .clone())
+                };
+                Ok(ScanCursor {
+                    pk: string_key("pk")?,
+                    sk: string_key("sk")?,
+                })
+            })
+            .transpose()?;
+        Ok((entries, cursor))
+    }
+
+    async fn get_account_rows(
         &self,
         account_id: &AccountId,
-        entries: &[EntryWithConditionals],
-        mut transact: TransactWriteItemsFluentBuilder,
-    ) -> Result<(TransactWriteItemsFluentBuilder, Vec<EntryWithBalance>), AppendEntriesError> {
-        let head_balances = self
+        total_segments: i32,
+    ) -> Result<AccountRows, GetBalanceError> {
+        let item = self
             .client
             .get_item()
-            .table_name("a_ledger")
-            .key("pk", Pk::Balance(account_id.clone()).into())
+            .table_name(&self.table_name)
+            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
             .key("sk", Sk::CurrentEntry.into())
             .send()
             .await
             .map_err(anyhow::Error::from)?
-            .item()
-            .map(|item| -> Result<(HashMap<LedgerBalanceName, i128>, u64)> {
-                Ok((
-                    item.get("ledger_balances")
-                        .ok_or(anyhow!(
-                            "Missing ledger_balances for HEAD of account_id {}",
-                            account_id.to_string()
-                        ))?
-                        .as_m()
-                        .map_err(|_| anyhow!("Not a map"))?
-                        .iter()
-                        .map(|(k, v)| -> Result<(LedgerBalanceName, i128)> {
-                            Ok((
-                                LedgerBalanceName::new(k.clone())?,
-                                v.as_n()
-                                    .map_err(|_| anyhow!("Not a number"))?
-                                    .parse::<i128>()?,
-                            ))
-                        })
-                        .collect::<Result<HashMap<LedgerBalanceName, i128>>>()?,
-                    item.get("sequence")
-                        .ok_or(anyhow!(
-                            "Missing sequence for HEAD of account_id {}",
-                            account_id.to_string()
-                        ))?
-                        .as_n()
-                        .map_err(|_| anyhow!("Not a number"))?
-                        .parse()
-                        .map_err(|err| anyhow!("Error parsing sequence number: {err}"))?,
-                ))
-            })
+            .item;
+        // Without the HEAD, or the day it was opened, the rows can only be found by a scan.
+        let Some((item, opened_on)) = item.and_then(|item| {
+            let opened_on = item.get("opened_on")?.as_s().ok()?.parse().ok()?;
+            Some((item, opened_on))
+        }) else {
+            return self.scan_account_rows(account_id, total_segments).await;
+        };
+        let head = entry_with_balance_from_item(&item, &self.key_prefix)?;
+        let hashed_from = hashed_from_item(&item)?;
+        let entries = self
+            .query_entry_rows(account_id, opened_on, head.created_at.date_naive(), None)
+            .await?;
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(
+                ":pk",
+                Pk::Snapshot(account_id.clone()).key(&self.key_prefix),
+            );
+        let mut snapshots = self
+            .query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| snapshot_from_item(account_id.clone(), item))
+            .collect::<Result<Vec<Snapshot>, GetBalanceError>>()?;
+        snapshots.sort_by_key(|snapshot| snapshot.sequence);
+        Ok(AccountRows {
+            head: Some(head),
+            hashed_from,
+            entries,
+            snapshots,
+        })
+    }
+
+    async fn get_snapshot(
+        &self,
+        account_id: &AccountId,
+        as_of: &DateTime<Utc>,
+    ) -> Result<Option<Snapshot>, GetBalanceError> {
+        let items = self
+            .client
+            .query()
+            .limit(1)
+            .table_name(&self.table_name)
+            .key_conditions(
+                "pk",
+                Condition::builder()
+                    .comparison_operator(ComparisonOperator::Eq)
+                    .attribute_value_list(Pk::Snapshot(account_id.clone()).key(&self.key_prefix))
+                    .build()
+                    .map_err(anyhow::Error::from)?,
+            )
+            .key_conditions(
+                "sk",
+                Condition::builder()
+                    .comparison_operator(ComparisonOperator::Le)
+                    .attribute_value_list(
+                        Sk::Snapshot(format_created_at_and_sequence(as_of, u64::MAX)).into(),
+                    )
+                    .build()
+                    .map_err(anyhow::Error::from)?,
+            )
+            .scan_index_forward(false)
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        items
+            .items()
+            .first()
+            .map(|item| snapshot_from_item(account_id.clone(), item))
+            .transpose()
+    }
+
+    async fn get_account_rows_since(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<AccountRows, GetBalanceError> {
+        let account_id = &snapshot.account_id;
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+            .key("sk", Sk::CurrentEntry.into())
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?
+            .item;
+        let head = item
+            .as_ref()
+            .map(|item| entry_with_balance_from_item(item, &self.key_prefix))
             .transpose()?;
-        let mut entries_with_balance: Vec<EntryWithBalance> = Vec::new();
-        for entry_with_conditional in entries {
-            let entry = &entry_with_conditional.entry;
-            let conditionals = &entry_with_conditional.conditionals;
-            let new_entry = match entries_with_balance.last() {
-                Some(entry_with_balance) => EntryWithBalance {
-                    account_id: entry.account_id.clone(),
-                    entry_id: entry.entry_id.clone(),
-                    ledger_balances: entry
-                        .ledger_fields
-                        .iter()
-                        .map(|(field_name, value)| {
-                            let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
-                            let balance = entry_with_balance
-                                .ledger_balances
-                                .get(&ledger_balance_name)
-                                .unwrap_or(&0);
-                            let new_balance = balance + value;
+        let hashed_from = item.as_ref().map(hashed_from_item).transpose()?.flatten();
+        let end_date = head
+            .as_ref()
+            .map(|head| head.created_at)
+            .unwrap_or_else(utc_now)
+            .date_naive();
+        let entries = self
+            .query_entry_rows(
+                account_id,
+                snapshot.created_at.date_naive(),
+                end_date,
+                Some((&snapshot.created_at, snapshot.sequence)),
+            )
+            .await?;
+        Ok(AccountRows {
+            head,
+            hashed_from,
+            entries,
+            snapshots: Vec::new(),
+        })
+    }
 
-                            (ledger_balance_name, new_balance)
-                        })
-                        .collect(),
-                    status: entry.status.clone(),
-                    ledger_fields: entry.ledger_fields.clone(),
-                    additional_fields: entry.additional_fields.clone(),
-                    sequence: entry_with_balance.sequence + 1,
-                    created_at: utc_now(),
+    async fn close_period(
+        &self,
+        closing: &PeriodClosing,
+        head_sequence: u64,
+        actor: &Actor,
+    ) -> Result<(), ClosePeriodError> {
+        let account_id = &closing.account_id;
+        let transact = self
+            .client
+            .transact_write_items()
+            .transact_items(
+                TransactWriteItem::builder()
+                    .update(
+                        Update::builder()
+                            .table_name(&self.table_name)
+                            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+                            .key("sk", Sk::CurrentEntry.into())
+                            .expression_attribute_values(
+                                ":period",
+                                AttributeValue::S(closing.period.to_string()),
+                            )
+                            .expression_attribute_values(
+                                ":head_sequence",
+                                AttributeValue::N(head_sequence.to_string()),
+                            )
+                            .expression_attribute_names("#sequence_field", "sequence")
+                            .update_expression("SET closed_until = :period")
+                            .condition_expression("#sequence_field = :head_sequence AND (attribute_not_exists(closed_until) OR closed_until < :period)")
+                            .return_values_on_condition_check_failure(
+                                ReturnValuesOnConditionCheckFailure::AllOld,
+                            )
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .build(),
+            )
+            .transact_items(
+                TransactWriteItem::builder()
+                    .put(
+                        Put::builder()
+                            .table_name(&self.table_name)
+                            .item(
+                                "pk",
+                                Pk::Period(account_id.clone(), closing.period).key(&self.key_prefix),
+                            )
+                            .item("sk", Sk::CurrentEntry.into())
+                            .item(
+                                "ledger_balances",
+                                AttributeValue::M(
+                                    closing
+                                        .ledger_balances
+                                        .clone()
+                                        .into_iter()
+                                        .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                                        .collect(),
+                                ),
+                            )
+                            .item(
+                                "closed_at",
+                                AttributeValue::S(closing.closed_at.to_string()),
+                            )
+                            .item("actor", AttributeValue::S(actor.to_string()))
+                            .condition_expression("attribute_not_exists(pk)")
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .build(),
+            )
+            .transact_items(create_transact_item_for_audit(&AuditRecord {
+                account_id: account_id.clone(),
+                actor: actor.clone(),
+                action: AuditAction::ClosePeriod,
+                entry_ids: vec![],
+                sequence: head_sequence,
+                created_at: closing.closed_at,
                 },
-                None => EntryWithBalance {
-                    account_id: entry.account_id.clone(),
-                    entry_id: entry.entry_id.clone(),
-                    ledger_balances: entry
-                        .ledger_fields
-                        .iter()
-                        .map(|(field_name, value)| {
-                            let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
-                            let balance = head_balances
-                                .as_ref()
-                                .and_then(|(balances, _)| {
-                                    balances.get(&ledger_balance_name).cloned()
-                                })
-                                .unwrap_or(0);
-                            let new_balance = balance + value;
-                            (ledger_balance_name, new_balance)
-                        })
-                        .collect(),
-                    status: entry.status.clone(),
-                    ledger_fields: entry.ledger_fields.clone(),
-                    additional_fields: entry.additional_fields.clone(),
-                    sequence: head_balances
+                &self.table_name, &self.key_prefix,
+            )?);
+
+        match transact.send().await {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if let Some(TransactWriteItemsError::TransactionCanceledException(err)) =
+                    error.as_service_error()
+                {
+                    if err
+                        .message
                         .as_ref()
-                        .map(|(_, sequence)| sequence + 1)
-                        .unwrap_or(0),
-                    created_at: utc_now(),
-                },
-            };
-            Self::validate_conditionals(conditionals, &new_entry)?;
-            entries_with_balance.push(new_entry);
-        }
-        for entry in entries_with_balance.iter() {
-            transact = transact.transact_items(create_transact_item_for_entry(entry, false)?);
+                        .map(|msg| msg.contains("ConditionalCheckFailed"))
+                        .unwrap_or(false)
+                    {
+                        for cancellation_reason in err.cancellation_reasons() {
+                            if let Some(item) = cancellation_reason.item() {
+                                let closed_until = closed_until_from_item(item)?;
+                                if closed_until
+                                    .is_some_and(|closed_until| closed_until >= closing.period)
+                                {
+                                    return Err(ClosePeriodError::PeriodAlreadyClosed(
+                                        account_id.clone(),
+                                        closing.period,
+                                    ));
+                                }
+                                return Err(ClosePeriodError::OptimisticLockError(
+                                    account_id.clone(),
+                                ));
+                            }
+                        }
+                        return Err(ClosePeriodError::PeriodAlreadyClosed(
+                            account_id.clone(),
+                            closing.period,
+                        ));
+                    }
+                }
+                Err(anyhow::Error::from(error).into())
+            }
         }
-        match head_balances {
-            Some((balance, last_sequence)) => {
-                let entry = entries_with_balance.last().ok_or(anyhow!(
-                    "Missing last entry for account_id {}",
-                    account_id.to_string()
-                ))?;
-                transact = transact.transact_items(
-                    TransactWriteItem::builder()
-                        .update(
-                            Update::builder()
-                                .table_name("a_ledger")
-                                .key("pk", Pk::Balance(account_id.clone()).into())
-                                .key("sk", Sk::CurrentEntry.into())
-                                .expression_attribute_values(
-                                    ":ledger_balances",
-                                    AttributeValue::M(
-                                        entry
-                                            .ledger_balances
-                                            .clone()
-                                            .into_iter()
-                                            .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
-                                            .collect(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":ledger_fields",
-                                    AttributeValue::M(
-                                        entry
-                                            .ledger_fields
-                                            .clone()
-                                            .into_iter()
-                                            .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
-                                            .collect(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":additional_fields",
-                                    AttributeValue::S(
-                                        serde_json::to_string(&entry.additional_fields).map_err(anyhow::Error::from)?,
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":status",
-                                    AttributeValue::S(
-                                        serde_json::to_string(&entry.status).map_err(anyhow::Error::from)?,
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":entry_id",
-                                    AttributeValue::S(
-                                        entry.entry_id.to_string(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":sequence",
-                                    AttributeValue::N(
-                                        entry.sequence.to_string(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":created_at",
-                                    AttributeValue::S(
-                                        entry.created_at.to_string(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":old_ledger_balances",
-                                    AttributeValue::M(
-                                        balance
-                                            .into_iter()
-                                            .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
-                                            .collect(),
-                                    ),
-                                )
-                                .expression_attribute_values(
-                                    ":old_sequence",
-                                    AttributeValue::N(
-                                        last_sequence.to_string(),
-                                    ),
-                                )
-                                .expression_attribute_names("#sequence_field", "sequence")
-                                .update_expression("SET ledger_balances = :ledger_balances, ledger_fields = :ledger_fields, additional_fields = :additional_fields, entry_id = :entry_id, created_at = :created_at, entry_status = :status, #sequence_field = :sequence")
-                                .condition_expression("ledger_balances = :old_ledger_balances AND #sequence_field = :old_sequence")
-                                .return_values_on_condition_check_failure(
-                                    ReturnValuesOnConditionCheckFailure::AllOld,
-                                )
+    }
+
+    async fn get_period_closing(
+        &self,
+        account_id: &AccountId,
+        period: &Period,
+    ) -> Result<PeriodClosing, GetBalanceError> {
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "pk",
+                Pk::Period(account_id.clone(), *period).key(&self.key_prefix),
+            )
+            .key("sk", Sk::CurrentEntry.into())
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let Some(item) = item.item else {
+            return Err(GetBalanceError::NotFound(account_id.clone()));
+        };
+        Ok(PeriodClosing {
+            account_id: account_id.clone(),
+            period: *period,
+            ledger_balances: ledger_balances_from_item(&item)?,
+            closed_at: DateTime::from_str(
+                item.get("closed_at")
+                    .ok_or(GetBalanceError::MissingField("closed_at".into()))?
+                    .as_s()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("closed_at".into()))?,
+            )
+            .map_err(|_| GetBalanceError::ErrorReadingField("closed_at".into()))?,
+        })
+    }
+
+    async fn get_audit_records(
+        &self,
+        query: &AuditQuery,
+        start_after: Option<(&AccountId, DateTime<Utc>, u64)>,
+        limit: u8,
+    ) -> Result<(Vec<AuditRecord>, Option<AuditCursor>), GetBalanceError> {
+        // The next page starts after the full key of the last record, since records of several
+        // accounts can share a `created_at` and `sequence` in the actor GSI.
+        let start_after = start_after.map(|(account_id, created_at, sequence)| {
+            let created_at_and_sequence = format_created_at_and_sequence(&created_at, sequence);
+            (
+                created_at,
+                created_at_and_sequence.clone(),
+                HashMap::from([
+                    (
+                        "pk".into(),
+                        Pk::Audit(account_id.clone()).key(&self.key_prefix),
+                    ),
+                    ("sk".into(), Sk::Audit(created_at_and_sequence).into()),
+                ]),
+            )
+        });
+        let lower_bound = match &start_after {
+            Some((_, created_at_and_sequence, _)) => created_at_and_sequence.clone(),
+            None => query.from.to_string(),
+        };
+        let upper_bound = format_created_at_and_sequence(&query.to, u64::MAX);
+        let max_items = limit as usize + 1;
+        let mut items = Vec::new();
+        match (&query.account_id, &query.actor) {
+            (Some(account_id), actor) => {
+                let mut query_builder = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .key_conditions(
+                        "pk",
+                        Condition::builder()
+                            .comparison_operator(ComparisonOperator::Eq)
+                            .attribute_value_list(
+                                Pk::Audit(account_id.clone()).key(&self.key_prefix),
+                            )
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .key_conditions(
+                        "sk",
+                        Condition::builder()
+                            .comparison_operator(ComparisonOperator::Between)
+                            .attribute_value_list(Sk::Audit(lower_bound).into())
+                            .attribute_value_list(Sk::Audit(upper_bound).into())
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .set_exclusive_start_key(start_after.map(|(_, _, key)| key));
+                if let Some(actor) = actor {
+                    query_builder = query_builder
+                        .filter_expression("actor = :actor")
+                        .expression_attribute_values(
+                            ":actor",
+                            AttributeValue::S(actor.to_string()),
+                        );
+                }
+                items = self.query_at_most(query_builder, max_items).await?;
+            }
+            (None, Some(actor)) => {
+                // Audit records of an actor are spread in one GSI partition per day.
+                let end_date = query.to.date_naive();
+                let mut current_date = start_after
+                    .as_ref()
+                    .map(|(created_at, _, _)| *created_at)
+                    .unwrap_or(query.from)
+                    .date_naive();
+                let mut start_key = start_after.map(|(_, created_at_and_sequence, mut key)| {
+                    key.insert(
+                        "account_id_and_date".into(),
+                        AttributeValue::S(format!(
+                            "{}ACTOR:{}|{}",
+                            self.key_prefix, actor, current_date
+                        )),
+                    );
+                    key.insert(
+                        "created_at".into(),
+                        AttributeValue::S(created_at_and_sequence),
+                    );
+                    key
+                });
+                loop {
+                    let query_builder = self
+                        .client
+                        .query()
+                        .table_name(&self.table_name)
+                        .index_name(&self.index_name)
+                        .key_conditions(
+                            "account_id_and_date",
+                            Condition::builder()
+                                .comparison_operator(ComparisonOperator::Eq)
+                                .attribute_value_list(AttributeValue::S(format!(
+                                    "{}ACTOR:{}|{}",
+                                    self.key_prefix, actor, current_date
+                                )))
                                 .build()
                                 .map_err(anyhow::Error::from)?,
                         )
+                        .key_conditions(
+                            "created_at",
+                            Condition::builder()
+                                .comparison_operator(ComparisonOperator::Between)
+                                .attribute_value_list(AttributeValue::S(lower_bound.clone()))
+                                .attribute_value_list(AttributeValue::S(upper_bound.clone()))
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        // Only the day of the last record resumes after it.
+                        .set_exclusive_start_key(start_key.take());
+                    items.extend(
+                        self.query_at_most(query_builder, max_items - items.len())
+                            .await?,
+                    );
+                    if items.len() >= max_items || current_date >= end_date {
+                        break;
+                    }
+                    current_date = current_date
+                        .checked_add_days(Days::new(1))
+                        .ok_or(anyhow!("Failed to increment current_date"))?;
+                }
+            }
+            (None, None) => {
+                return Err(anyhow!("An audit query needs an account_id or an actor").into())
+            }
+        }
+        let mut records = items
+            .iter()
+            .map(audit_record_from_item)
+            .collect::<Result<Vec<AuditRecord>, GetBalanceError>>()?;
+        let cursor = if records.len() > limit as usize {
+            records.truncate(limit as usize);
+            let last = records
+                .last()
+                .ok_or(anyhow!("Expects at least one record in the vector"))?;
+            Some(AuditCursor {
+                query: query.clone(),
+                account_id: last.account_id.clone(),
+                created_at: last.created_at,
+                sequence: last.sequence,
+            })
+        } else {
+            None
+        };
+        Ok((records, cursor))
+    }
+
+    async fn get_parent(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<AccountId>, GetBalanceError> {
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "pk",
+                Pk::Hierarchy(account_id.clone()).key(&self.key_prefix),
+            )
+            .key("sk", Sk::CurrentEntry.into())
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        item.item()
+            .and_then(|item| item.get("parent_id"))
+            .map(|parent_id| account_id_from_attribute(parent_id, "parent_id"))
+            .transpose()
+    }
+
+    async fn get_children(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<AccountId>, GetBalanceError> {
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_conditions(
+                "pk",
+                Condition::builder()
+                    .comparison_operator(ComparisonOperator::Eq)
+                    .attribute_value_list(Pk::Hierarchy(account_id.clone()).key(&self.key_prefix))
+                    .build()
+                    .map_err(anyhow::Error::from)?,
+            )
+            .key_conditions(
+                "sk",
+                Condition::builder()
+                    .comparison_operator(ComparisonOperator::BeginsWith)
+                    .attribute_value_list(AttributeValue::S("|CHILD:".into()))
+                    .build()
+                    .map_err(anyhow::Error::from)?,
+            );
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| {
+                account_id_from_attribute(
+                    item.get("account_id")
+                        .ok_or(GetBalanceError::MissingField("account_id".into()))?,
+                    "account_id",
+                )
+            })
+            .collect()
+    }
+
+    async fn set_parent(
+        &self,
+        account_id: &AccountId,
+        parent: Option<&AccountId>,
+        previous_parent: Option<&AccountId>,
+        actor: &Actor,
+    ) -> Result<(), SetParentError> {
+        if parent == previous_parent {
+            return Ok(());
+        }
+        let now = utc_now();
+        let parent_key = Pk::Hierarchy(account_id.clone()).key(&self.key_prefix);
+        let mut transact = self.client.transact_write_items();
+        transact = match parent {
+            Some(parent) => {
+                let put = Put::builder()
+                    .table_name(&self.table_name)
+                    .item("pk", parent_key)
+                    .item("sk", Sk::CurrentEntry.into())
+                    .item("parent_id", AttributeValue::S(parent.to_string()))
+                    .item("updated_at", AttributeValue::S(now.to_string()))
+                    .item("actor", AttributeValue::S(actor.to_string()));
+                let put = match previous_parent {
+                    Some(previous_parent) => put
+                        .condition_expression("parent_id = :previous_parent")
+                        .expression_attribute_values(
+                            ":previous_parent",
+                            AttributeValue::S(previous_parent.to_string()),
+                        ),
+                    None => put.condition_expression("attribute_not_exists(parent_id)"),
+                };
+                transact.transact_items(
+                    TransactWriteItem::builder()
+                        .put(put.build().map_err(anyhow::Error::from)?)
                         .build(),
+                )
+            }
+            None => transact.transact_items(
+                TransactWriteItem::builder()
+                    .delete(
+                        Delete::builder()
+                            .table_name(&self.table_name)
+                            .key("pk", parent_key)
+                            .key("sk", Sk::CurrentEntry.into())
+                            .condition_expression("parent_id = :previous_parent")
+                            .expression_attribute_values(
+                                ":previous_parent",
+                                AttributeValue::S(
+                                    previous_parent
+                                        .map(AccountId::to_string)
+                                        .unwrap_or_default(),
+                                ),
+                            )
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .build(),
+            ),
+        };
+        if let Some(previous_parent) = previous_parent {
+            transact = transact.transact_items(
+                TransactWriteItem::builder()
+                    .delete(
+                        Delete::builder()
+                            .table_name(&self.table_name)
+                            .key(
+                                "pk",
+                                Pk::Hierarchy(previous_parent.clone()).key(&self.key_prefix),
+                            )
+                            .key("sk", Sk::Child(account_id.clone()).into())
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .build(),
+            );
+        }
+        if let Some(parent) = parent {
+            transact = transact.transact_items(
+                TransactWriteItem::builder()
+                    .put(
+                        Put::builder()
+                            .table_name(&self.table_name)
+                            .item("pk", Pk::Hierarchy(parent.clone()).key(&self.key_prefix))
+                            .item("sk", Sk::Child(account_id.clone()).into())
+                            .item("account_id", AttributeValue::S(account_id.to_string()))
+                            .item("updated_at", AttributeValue::S(now.to_string()))
+                            .item("actor", AttributeValue::S(actor.to_string()))
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                    )
+                    .build(),
+            );
+        }
+        match transact.send().await {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if let Some(TransactWriteItemsError::TransactionCanceledException(err)) =
+                    error.as_service_error()
+                {
+                    if err
+                        .message
+                        .as_ref()
+                        .map(|msg| msg.contains("ConditionalCheckFailed"))
+                        .unwrap_or(false)
+                    {
+                        return Err(SetParentError::OptimisticLockError(account_id.clone()));
+                    }
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn list_accounts(
+        &self,
+        query: &AccountQuery,
+        start_after: Option<&AccountId>,
+        limit: u8,
+    ) -> Result<(Vec<Account>, Option<AccountCursor>), GetBalanceError> {
+        let max_items = limit as usize + 1;
+        let mut filters = Vec::new();
+        let mut query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(&self.accounts_index_name);
+        for (index, filter) in query.balances.iter().enumerate() {
+            filters.push(format!(
+                "ledger_balances.#balance_{index} {} :balance_{index}",
+                filter.operator.as_str()
+            ));
+            query_builder = query_builder
+                .expression_attribute_names(
+                    format!("#balance_{index}"),
+                    String::from(filter.ledger_balance_name.clone()),
+                )
+                .expression_attribute_values(
+                    format!(":balance_{index}"),
+                    AttributeValue::N(filter.value.to_string()),
                 );
+        }
+        // The HEAD `created_at` may be followed by `|{sequence}`, which sorts after the date alone.
+        if let Some(active_after) = query.active_after {
+            filters.push("created_at >= :active_after".into());
+            query_builder = query_builder.expression_attribute_values(
+                ":active_after",
+                AttributeValue::S(active_after.to_string()),
+            );
+        }
+        if let Some(active_before) = query.active_before {
+            filters.push("created_at < :active_before".into());
+            query_builder = query_builder.expression_attribute_values(
+                ":active_before",
+                AttributeValue::S(active_before.to_string()),
+            );
+        }
+        for (index, tag) in query.tags.iter().enumerate() {
+            filters.push(format!("contains(tags, :tag_{index})"));
+            query_builder = query_builder.expression_attribute_values(
+                format!(":tag_{index}"),
+                AttributeValue::S(tag.to_string()),
+            );
+        }
+        if !filters.is_empty() {
+            query_builder = query_builder.filter_expression(filters.join(" AND "));
+        }
+
+        let first_shard = start_after.map(accounts_shard_index).unwrap_or_default();
+        let mut items = Vec::new();
+        for shard in first_shard..ACCOUNTS_SHARDS {
+            let mut shard_query = query_builder
+                .clone()
+                .expression_attribute_values(":shard", accounts_shard(&self.key_prefix, shard));
+            shard_query = match start_after {
+                Some(start_after) if shard == first_shard => shard_query
+                    .key_condition_expression(
+                        "accounts_shard = :shard AND account_id > :start_after",
+                    )
+                    .expression_attribute_values(
+                        ":start_after",
+                        AttributeValue::S(start_after.to_string()),
+                    ),
+                _ => shard_query.key_condition_expression("accounts_shard = :shard"),
+            };
+            items.extend(
+                self.query_at_most(shard_query, max_items - items.len())
+                    .await?,
+            );
+            if items.len() >= max_items {
+                break;
+            }
+        }
+        let mut accounts = items
+            .iter()
+            .map(account_from_item)
+            .collect::<Result<Vec<Account>, GetBalanceError>>()?;
+        let cursor = if accounts.len() > limit as usize {
+            accounts.truncate(limit as usize);
+            let last = accounts
+                .last()
+                .ok_or(anyhow!("Expects at least one account in the vector"))?;
+            Some(AccountCursor {
+                query: query.clone(),
+                account_id: last.account_id.clone(),
+            })
+        } else {
+            None
+        };
+        Ok((accounts, cursor))
+    }
+
+    async fn set_account_tags(
+        &self,
+        account_id: &AccountId,
+        tags: &BTreeSet<AccountTag>,
+    ) -> Result<(), GetBalanceError> {
+        let update = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+            .key("sk", Sk::CurrentEntry.into())
+            .condition_expression("attribute_exists(pk)");
+        // DynamoDB has no empty sets, so the attribute is removed instead.
+        let update = if tags.is_empty() {
+            update.update_expression("REMOVE tags")
+        } else {
+            update
+                .update_expression("SET tags = :tags")
+                .expression_attribute_values(
+                    ":tags",
+                    AttributeValue::Ss(tags.iter().map(AccountTag::to_string).collect()),
+                )
+        };
+        match update.send().await {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception())
+                {
+                    return Err(GetBalanceError::NotFound(account_id.clone()));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    /// A run is stored in the RECONCILIATION PK of the account, with a row for each line of
+    /// its result under the SK of the run. The run row is written last, so a run that
+    /// failed to be saved is never found.
+    async fn save_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<(), ReconciliationError> {
+        let pk = Pk::Reconciliation(reconciliation.account_id.clone()).key(&self.key_prefix);
+        let lines = reconciliation
+            .matched
+            .iter()
+            .map(|line| ("matched", serde_json::to_string(line)))
+            .chain(
+                reconciliation
+                    .amount_mismatches
+                    .iter()
+                    .map(|line| ("amount_mismatch", serde_json::to_string(line))),
+            )
+            .chain(
+                reconciliation
+                    .missing_in_ledger
+                    .iter()
+                    .map(|line| ("missing_in_ledger", serde_json::to_string(line))),
+            )
+            .chain(
+                reconciliation
+                    .missing_in_statement
+                    .iter()
+                    .map(|line| ("missing_in_statement", serde_json::to_string(line))),
+            )
+            .enumerate()
+            .map(|(index, (kind, line))| {
+                Ok(WriteRequest::builder()
+                    .put_request(
+                        PutRequest::builder()
+                            .item("pk", pk.clone())
+                            .item(
+                                "sk",
+                                Sk::RunItem(reconciliation.run_id.clone(), index).into(),
+                            )
+                            .item("kind", AttributeValue::S(kind.into()))
+                            .item("line", AttributeValue::S(line?))
+                            .build()?,
+                    )
+                    .build())
+            })
+            .collect::<Result<Vec<WriteRequest>>>()?;
+        let lines_count = lines.len();
+        for chunk in lines.chunks(MAX_BATCH_WRITE_ITEMS) {
+            self.batch_write(chunk.to_vec()).await?;
+        }
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("pk", pk)
+            .item("sk", Sk::Run(reconciliation.run_id.clone()).into())
+            .item(
+                "account_id",
+                AttributeValue::S(reconciliation.account_id.to_string()),
+            )
+            .item(
+                "start_date",
+                AttributeValue::S(reconciliation.start_date.to_string()),
+            )
+            .item(
+                "end_date",
+                AttributeValue::S(reconciliation.end_date.to_string()),
+            )
+            .item(
+                "options",
+                AttributeValue::S(
+                    serde_json::to_string(&reconciliation.options).map_err(anyhow::Error::from)?,
+                ),
+            )
+            .item("actor", AttributeValue::S(reconciliation.actor.to_string()))
+            .item(
+                "created_at",
+                AttributeValue::S(reconciliation.created_at.to_string()),
+            )
+            .item("lines", AttributeValue::N(lines_count.to_string()))
+            .condition_expression("attribute_not_exists(pk)")
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn get_reconciliation(
+        &self,
+        account_id: &AccountId,
+        run_id: &str,
+    ) -> Result<Reconciliation, ReconciliationError> {
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk AND begins_with(sk, :run)")
+            .expression_attribute_values(
+                ":pk",
+                Pk::Reconciliation(account_id.clone()).key(&self.key_prefix),
+            )
+            .expression_attribute_values(":run", Sk::Run(run_id.into()).into());
+        let items = self.query_at_most(query_builder, usize::MAX).await?;
+        let mut items = items.iter();
+        // The run row sorts before its lines, which have a longer SK.
+        let run = items
+            .next()
+            .filter(|item| item.get("sk") == Some(&Sk::Run(run_id.into()).into()))
+            .ok_or(ReconciliationError::NotFound(
+                account_id.clone(),
+                run_id.into(),
+            ))?;
+        let string_field = |field: &str| -> Result<&String> {
+            run.get(field)
+                .ok_or(anyhow!("Missing field {field} in reconciliation"))?
+                .as_s()
+                .map_err(|_| anyhow!("Error reading field {field} in reconciliation"))
+        };
+        let mut reconciliation = Reconciliation {
+            run_id: run_id.into(),
+            account_id: account_id.clone(),
+            start_date: DateTime::from_str(string_field("start_date")?)
+                .map_err(anyhow::Error::from)?,
+            end_date: DateTime::from_str(string_field("end_date")?).map_err(anyhow::Error::from)?,
+            options: serde_json::from_str::<ReconciliationOptions>(string_field("options")?)
+                .map_err(anyhow::Error::from)?,
+            actor: Actor::new(string_field("actor")?.clone())?,
+            created_at: DateTime::from_str(string_field("created_at")?)
+                .map_err(anyhow::Error::from)?,
+            matched: Vec::new(),
+            amount_mismatches: Vec::new(),
+            missing_in_ledger: Vec::new(),
+            missing_in_statement: Vec::new(),
+        };
+        for item in items {
+            let line = item
+                .get("line")
+                .ok_or(anyhow!("Missing field line in reconciliation"))?
+                .as_s()
+                .map_err(|_| anyhow!("Error reading field line in reconciliation"))?;
+            let kind = item
+                .get("kind")
+                .ok_or(anyhow!("Missing field kind in reconciliation"))?
+                .as_s()
+                .map_err(|_| anyhow!("Error reading field kind in reconciliation"))?;
+            match kind.as_str() {
+                "matched" => reconciliation
+                    .matched
+                    .push(serde_json::from_str(line).map_err(anyhow::Error::from)?),
+                "amount_mismatch" => reconciliation
+                    .amount_mismatches
+                    .push(serde_json::from_str(line).map_err(anyhow::Error::from)?),
+                "missing_in_ledger" => reconciliation
+                    .missing_in_ledger
+                    .push(serde_json::from_str(line).map_err(anyhow::Error::from)?),
+                "missing_in_statement" => reconciliation
+                    .missing_in_statement
+                    .push(serde_json::from_str(line).map_err(anyhow::Error::from)?),
+                kind => return Err(anyhow!("Unexpected reconciliation line {kind}").into()),
+            }
+        }
+        Ok(reconciliation)
+    }
+
+    /// The schedules are stored in the ACCRUALS PK of the ledger, with the schedule as JSON
+    /// and its `posted_until` in its own attribute, so saving the rates doesn't move it.
+    async fn save_accrual_schedule(&self, schedule: &AccrualSchedule) -> Result<(), AccrualError> {
+        let rates = AccrualSchedule {
+            posted_until: None,
+            ..schedule.clone()
+        };
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Accruals.key(&self.key_prefix))
+            .key(
+                "sk",
+                Sk::AccrualSchedule(schedule.account_id.clone(), schedule.ledger_field.clone())
+                    .into(),
+            )
+            .update_expression("SET account_id = :account_id, schedule = :schedule")
+            .expression_attribute_values(
+                ":account_id",
+                AttributeValue::S(schedule.account_id.to_string()),
+            )
+            .expression_attribute_values(
+                ":schedule",
+                AttributeValue::S(serde_json::to_string(&rates).map_err(anyhow::Error::from)?),
+            )
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn get_accrual_schedules(
+        &self,
+        account_id: Option<&AccountId>,
+    ) -> Result<Vec<AccrualSchedule>, AccrualError> {
+        let sk_prefix = match account_id {
+            Some(account_id) => format!("|SCHEDULE:{}|", account_id),
+            None => "|SCHEDULE:".into(),
+        };
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk AND begins_with(sk, :schedule)")
+            .expression_attribute_values(":pk", Pk::Accruals.key(&self.key_prefix))
+            .expression_attribute_values(":schedule", AttributeValue::S(sk_prefix));
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| {
+                let schedule = item
+                    .get("schedule")
+                    .ok_or(anyhow!("Missing field schedule in accrual schedule"))?
+                    .as_s()
+                    .map_err(|_| anyhow!("Error reading field schedule in accrual schedule"))?;
+                let mut schedule = serde_json::from_str::<AccrualSchedule>(schedule)
+                    .map_err(anyhow::Error::from)?;
+                schedule.posted_until = item
+                    .get("posted_until")
+                    .map(|posted_until| {
+                        posted_until
+                            .as_s()
+                            .map_err(|_| anyhow!("Error reading field posted_until"))?
+                            .parse()
+                    })
+                    .transpose()?;
+                Ok(schedule)
+            })
+            .collect()
+    }
+
+    async fn delete_accrual_schedule(
+        &self,
+        account_id: &AccountId,
+        ledger_field: &LedgerFieldName,
+    ) -> Result<(), AccrualError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Accruals.key(&self.key_prefix))
+            .key(
+                "sk",
+                Sk::AccrualSchedule(account_id.clone(), ledger_field.clone()).into(),
+            )
+            .condition_expression("attribute_exists(pk)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception())
+                {
+                    return Err(AccrualError::NotFound(
+                        account_id.clone(),
+                        ledger_field.clone(),
+                    ));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn set_accrual_posted_until(
+        &self,
+        account_id: &AccountId,
+        ledger_field: &LedgerFieldName,
+        period: &Period,
+    ) -> Result<(), AccrualError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Accruals.key(&self.key_prefix))
+            .key(
+                "sk",
+                Sk::AccrualSchedule(account_id.clone(), ledger_field.clone()).into(),
+            )
+            .update_expression("SET posted_until = :period")
+            // The periods are YYYY-MM, so they sort as strings.
+            .condition_expression(
+                "attribute_exists(pk) AND \
+                (attribute_not_exists(posted_until) OR posted_until < :period)",
+            )
+            .expression_attribute_values(":period", AttributeValue::S(period.to_string()))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception()) =>
+            {
+                Ok(())
+            }
+            Err(error) => Err(anyhow::Error::from(error).into()),
+        }
+    }
+
+    async fn save_fee_rule(&self, rule: &FeeRule) -> Result<(), FeeRuleError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("pk", Pk::Rules.key(&self.key_prefix))
+            .item("sk", Sk::Rule(rule.rule_id.clone()).into())
+            .item(
+                "rule",
+                AttributeValue::S(serde_json::to_string(rule).map_err(anyhow::Error::from)?),
+            )
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        self.fee_rules.set(&self.key_prefix, None);
+        Ok(())
+    }
+
+    /// The rules are read again once they are older than `FEE_RULES_TTL`, and while that read
+    /// fails the ones read before are kept, so pushes don't depend on it.
+    async fn get_fee_rules(&self) -> Result<Vec<FeeRule>, FeeRuleError> {
+        let cached = self.fee_rules.get(&self.key_prefix);
+        if let Some((read_at, rules)) = &cached {
+            if read_at.elapsed() < FEE_RULES_TTL {
+                return Ok(rules.clone());
+            }
+        }
+        match self.read_fee_rules().await {
+            Ok(rules) => {
+                self.fee_rules.set(&self.key_prefix, Some(&rules));
+                Ok(rules)
+            }
+            Err(err) => match cached {
+                Some((_, rules)) => {
+                    tracing::warn!("Error reading fee rules, keeping the ones read before: {err}");
+                    Ok(rules)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    async fn delete_fee_rule(&self, rule_id: &FeeRuleId) -> Result<(), FeeRuleError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Rules.key(&self.key_prefix))
+            .key("sk", Sk::Rule(rule_id.clone()).into())
+            .condition_expression("attribute_exists(pk)")
+            .send()
+            .await;
+        self.fee_rules.set(&self.key_prefix, None);
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception())
+                {
+                    return Err(FeeRuleError::NotFound(rule_id.clone()));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn save_recurring_schedule(
+        &self,
+        schedule: &RecurringSchedule,
+    ) -> Result<(), RecurringScheduleError> {
+        let definition = RecurringSchedule {
+            paused: false,
+            occurrences: 0,
+            posted_until: None,
+            ..schedule.clone()
+        };
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Recurring.key(&self.key_prefix))
+            .key(
+                "sk",
+                Sk::RecurringSchedule(schedule.schedule_id.clone()).into(),
+            )
+            .update_expression("SET schedule = :schedule")
+            .expression_attribute_values(
+                ":schedule",
+                AttributeValue::S(serde_json::to_string(&definition).map_err(anyhow::Error::from)?),
+            )
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn get_recurring_schedules(
+        &self,
+    ) -> Result<Vec<RecurringSchedule>, RecurringScheduleError> {
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", Pk::Recurring.key(&self.key_prefix));
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| {
+                let schedule = item
+                    .get("schedule")
+                    .ok_or(anyhow!("Missing field schedule in recurring schedule"))?
+                    .as_s()
+                    .map_err(|_| anyhow!("Error reading field schedule in recurring schedule"))?;
+                let mut schedule = serde_json::from_str::<RecurringSchedule>(schedule)
+                    .map_err(anyhow::Error::from)?;
+                if let Some(paused) = item.get("paused") {
+                    schedule.paused = *paused
+                        .as_bool()
+                        .map_err(|_| anyhow!("Error reading field paused"))?;
+                }
+                if let Some(occurrences) = item.get("occurrences") {
+                    schedule.occurrences = occurrences
+                        .as_n()
+                        .map_err(|_| anyhow!("Error reading field occurrences"))?
+                        .parse()
+                        .map_err(anyhow::Error::from)?;
+                }
+                if let Some(posted_until) = item.get("posted_until") {
+                    schedule.posted_until = Some(
+                        posted_until
+                            .as_s()
+                            .map_err(|_| anyhow!("Error reading field posted_until"))?
+                            .parse()
+                            .map_err(anyhow::Error::from)?,
+                    );
+                }
+                Ok(schedule)
+            })
+            .collect()
+    }
+
+    async fn pause_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+    ) -> Result<(), RecurringScheduleError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Recurring.key(&self.key_prefix))
+            .key("sk", Sk::RecurringSchedule(schedule_id.clone()).into())
+            .update_expression("SET paused = :paused")
+            .condition_expression("attribute_exists(pk)")
+            .expression_attribute_values(":paused", AttributeValue::Bool(true))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception()) =>
+            {
+                Err(RecurringScheduleError::NotFound(schedule_id.clone()))
+            }
+            Err(error) => Err(anyhow::Error::from(error).into()),
+        }
+    }
+
+    async fn resume_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+        resumed_at: &DateTime<Utc>,
+    ) -> Result<(), RecurringScheduleError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Recurring.key(&self.key_prefix))
+            .key("sk", Sk::RecurringSchedule(schedule_id.clone()).into())
+            .update_expression("SET paused = :resumed, posted_until = :resumed_at")
+            // Only a paused schedule skips the occurrences until it is resumed.
+            .condition_expression("attribute_exists(pk) AND paused = :paused")
+            .expression_attribute_values(":resumed", AttributeValue::Bool(false))
+            .expression_attribute_values(":paused", AttributeValue::Bool(true))
+            .expression_attribute_values(
+                ":resumed_at",
+                AttributeValue::S(format_occurrence(resumed_at)),
+            )
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception()) =>
+            {
+                if self
+                    .get_recurring_schedules()
+                    .await?
+                    .iter()
+                    .any(|schedule| schedule.schedule_id == *schedule_id)
+                {
+                    Ok(())
+                } else {
+                    Err(RecurringScheduleError::NotFound(schedule_id.clone()))
+                }
+            }
+            Err(error) => Err(anyhow::Error::from(error).into()),
+        }
+    }
+
+    async fn delete_recurring_schedule(
+        &self,
+        schedule_id: &RecurringScheduleId,
+    ) -> Result<(), RecurringScheduleError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Recurring.key(&self.key_prefix))
+            .key("sk", Sk::RecurringSchedule(schedule_id.clone()).into())
+            .condition_expression("attribute_exists(pk)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception()) =>
+            {
+                Err(RecurringScheduleError::NotFound(schedule_id.clone()))
+            }
+            Err(error) => Err(anyhow::Error::from(error).into()),
+        }
+    }
+
+    async fn set_recurring_posted_until(
+        &self,
+        schedule_id: &RecurringScheduleId,
+        occurrence: &DateTime<Utc>,
+        occurrences: u32,
+    ) -> Result<(), RecurringScheduleError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Recurring.key(&self.key_prefix))
+            .key("sk", Sk::RecurringSchedule(schedule_id.clone()).into())
+            .update_expression("SET posted_until = :occurrence, occurrences = :occurrences")
+            // The occurrences have a fixed width, so they sort as strings.
+            .condition_expression(
+                "attribute_exists(pk) AND \
+                (attribute_not_exists(posted_until) OR posted_until < :occurrence)",
+            )
+            .expression_attribute_values(
+                ":occurrence",
+                AttributeValue::S(format_occurrence(occurrence)),
+            )
+            .expression_attribute_values(":occurrences", AttributeValue::N(occurrences.to_string()))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception()) =>
+            {
+                Ok(())
+            }
+            Err(error) => Err(anyhow::Error::from(error).into()),
+        }
+    }
+
+    async fn save_velocity_limit(&self, limit: &VelocityLimit) -> Result<(), VelocityLimitError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("pk", Pk::VelocityLimits.key(&self.key_prefix))
+            .item(
+                "sk",
+                Sk::VelocityLimit(limit.account_id.clone(), limit.limit_id.clone()).into(),
+            )
+            .item(
+                "limit",
+                AttributeValue::S(serde_json::to_string(limit).map_err(anyhow::Error::from)?),
+            )
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn get_velocity_limits(&self) -> Result<Vec<VelocityLimit>, VelocityLimitError> {
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", Pk::VelocityLimits.key(&self.key_prefix));
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| {
+                let limit = item
+                    .get("limit")
+                    .ok_or(anyhow!("Missing field limit in velocity limit"))?
+                    .as_s()
+                    .map_err(|_| anyhow!("Error reading field limit in velocity limit"))?;
+                Ok(serde_json::from_str::<VelocityLimit>(limit).map_err(anyhow::Error::from)?)
+            })
+            .collect()
+    }
+
+    async fn delete_velocity_limit(
+        &self,
+        account_id: Option<&AccountId>,
+        limit_id: &VelocityLimitId,
+    ) -> Result<(), VelocityLimitError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::VelocityLimits.key(&self.key_prefix))
+            .key(
+                "sk",
+                Sk::VelocityLimit(account_id.cloned(), limit_id.clone()).into(),
+            )
+            .condition_expression("attribute_exists(pk)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_conditional_check_failed_exception())
+                {
+                    return Err(VelocityLimitError::NotFound(limit_id.clone()));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn get_velocity_used_amount(
+        &self,
+        account_id: &AccountId,
+        limit: &VelocityLimit,
+        date_time: &DateTime<Utc>,
+    ) -> Result<i128, VelocityLimitError> {
+        Ok(self
+            .get_velocity_buckets(account_id, limit, date_time)
+            .await?
+            .values()
+            .sum())
+    }
+}
+
+impl DynamoDbLedgerEntryRepository {
+    /// The error of a transaction of appended entries: the account whose HEAD or velocity
+    /// counters changed, or the entries that already exist. Without a HEAD that changed, they are reported in
+    /// `account_id`.
+    fn append_entries_error<R: std::fmt::Debug + Send + Sync + 'static>(
+        &self,
+        error: aws_sdk_dynamodb::error::SdkError<TransactWriteItemsError, R>,
+        account_id: &AccountId,
+    ) -> AppendEntriesError {
+        if let Some(TransactWriteItemsError::TransactionCanceledException(err)) =
+            error.as_service_error()
+        {
+            if err
+                .message
+                .as_ref()
+                .map(|msg| msg.contains("ConditionalCheckFailed"))
+                .unwrap_or(false)
+            {
+                let mut entries = Vec::new();
+                for cancellation_reason in err.cancellation_reasons() {
+                    if let Some(pk) = cancellation_reason.item().and_then(|item| item.get("pk")) {
+                        let pk = match Pk::from_key(pk, &self.key_prefix) {
+                            Ok(pk) => pk,
+                            Err(err) => return err.into(),
+                        };
+                        match pk {
+                            Pk::Balance(account_id) | Pk::Velocity(account_id) => {
+                                return AppendEntriesError::OptimisticLockError(account_id)
+                            }
+                            Pk::Entry(_, entry_id) => entries.push(entry_id),
+                            Pk::Period(_, _)
+                            | Pk::Audit(_)
+                            | Pk::Snapshot(_)
+                            | Pk::Hierarchy(_)
+                            | Pk::Reconciliation(_)
+                            | Pk::Accruals
+                            | Pk::Rules
+                            | Pk::Recurring
+                            | Pk::VelocityLimits => {}
+                        }
+                    }
+                }
+                return AppendEntriesError::EntriesAlreadyExists(account_id.clone(), entries);
+            }
+        }
+        anyhow::Error::from(error).into()
+    }
+
+    async fn read_fee_rules(&self) -> Result<Vec<FeeRule>, FeeRuleError> {
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", Pk::Rules.key(&self.key_prefix));
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .iter()
+            .map(|item| {
+                let rule = item
+                    .get("rule")
+                    .ok_or(anyhow!("Missing field rule in fee rule"))?
+                    .as_s()
+                    .map_err(|_| anyhow!("Error reading field rule in fee rule"))?;
+                Ok(serde_json::from_str::<FeeRule>(rule).map_err(anyhow::Error::from)?)
+            })
+            .collect()
+    }
+
+    /// The amounts counted by the account towards the limit in each bucket of its window at
+    /// `date_time`.
+    async fn get_velocity_buckets(
+        &self,
+        account_id: &AccountId,
+        limit: &VelocityLimit,
+        date_time: &DateTime<Utc>,
+    ) -> Result<HashMap<String, i128>> {
+        let sk = |bucket| -> AttributeValue {
+            Sk::VelocityBucket(limit.limit_id.clone(), bucket).into()
+        };
+        let query_builder = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :first AND :last")
+            .expression_attribute_values(
+                ":pk",
+                Pk::Velocity(account_id.clone()).key(&self.key_prefix),
+            )
+            .expression_attribute_values(":first", sk(limit.first_bucket(date_time)))
+            .expression_attribute_values(":last", sk(limit.bucket(date_time)));
+        self.query_at_most(query_builder, usize::MAX)
+            .await?
+            .into_iter()
+            .map(|item| {
+                let Sk::VelocityBucket(_, bucket) = Sk::try_from(
+                    item.get("sk")
+                        .ok_or(anyhow!("Missing field sk in velocity bucket"))?
+                        .clone(),
+                )?
+                else {
+                    bail!("Expected a velocity bucket SK");
+                };
+                let total = item
+                    .get("total")
+                    .ok_or(anyhow!("Missing field total in velocity bucket"))?
+                    .as_n()
+                    .map_err(|_| anyhow!("Error reading field total in velocity bucket"))?
+                    .parse()?;
+                Ok((bucket, total))
+            })
+            .collect()
+    }
+
+    /// Counts the Applied entries appended to the account towards its velocity limits, and
+    /// gives back what the `released` ledger fields counted in the bucket of their creation
+    /// time, failing with the first entry that exceeds a limit. Pending entries are counted
+    /// when posted, so voiding one has nothing to give back. A bucket never goes below zero, as
+    /// entries appended before a limit existed were not counted, and at most
+    /// `MAX_VELOCITY_UPDATES` buckets are updated: the current one of each limit that counts an
+    /// entry, then the largest releases. What doesn't fit stays counted until its bucket leaves
+    /// the window. Each counter is only updated while it still has the total read, so a
+    /// concurrent append to the account fails with an optimistic lock error instead of going
+    /// over a limit.
+    async fn update_velocity(
+        &self,
+        account_id: &AccountId,
+        now: &DateTime<Utc>,
+        entries: &[EntryWithBalance],
+        released: &[(DateTime<Utc>, HashMap<LedgerFieldName, i128>)],
+        mut transact: TransactWriteItemsFluentBuilder,
+    ) -> Result<TransactWriteItemsFluentBuilder, AppendEntriesError> {
+        let entries = entries
+            .iter()
+            .filter(|entry| entry.status == EntryStatus::Applied)
+            .collect_vec();
+        if entries.is_empty() && released.is_empty() {
+            return Ok(transact);
+        }
+        let limits = self
+            .get_velocity_limits()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let mut counters = Vec::new();
+        for limit in VelocityLimit::applicable(&limits, account_id) {
+            let counted = entries
+                .iter()
+                .map(|entry| (*entry, limit.amount(&entry.ledger_fields)))
+                .filter(|(_, amount)| *amount != 0)
+                .collect_vec();
+            let first_bucket = limit.first_bucket(now);
+            let mut released_amounts = HashMap::<String, i128>::new();
+            for (created_at, ledger_fields) in released {
+                let bucket = limit.bucket(created_at);
+                let amount = limit.amount(ledger_fields);
+                if amount != 0 && bucket >= first_bucket {
+                    *released_amounts.entry(bucket).or_default() += amount;
+                }
+            }
+            if counted.is_empty() && released_amounts.is_empty() {
+                continue;
+            }
+            let buckets = self.get_velocity_buckets(account_id, limit, now).await?;
+            let released_amounts = released_amounts
+                .into_iter()
+                .filter_map(|(bucket, amount)| {
+                    let total = buckets.get(&bucket).copied().filter(|total| *total > 0)?;
+                    Some((bucket, amount.min(total)))
+                })
+                .collect::<HashMap<_, _>>();
+            counters.push((limit, buckets, counted, released_amounts));
+        }
+
+        // The current bucket of a limit that counts entries is always updated, so a release
+        // from it comes for free. The other releases share what is left.
+        let current_buckets = counters
+            .iter()
+            .filter(|(_, _, counted, _)| !counted.is_empty())
+            .count();
+        let mut other_releases = counters
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (limit, _, counted, released_amounts))| {
+                let current_bucket = (!counted.is_empty()).then(|| limit.bucket(now));
+                released_amounts
+                    .iter()
+                    .filter(move |(bucket, _)| Some(*bucket) != current_bucket.as_ref())
+                    .map(move |(bucket, amount)| (index, bucket.clone(), *amount))
+            })
+            .collect_vec();
+        other_releases.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+        other_releases.truncate(MAX_VELOCITY_UPDATES.saturating_sub(current_buckets));
+
+        for (index, (limit, buckets, counted, released_amounts)) in counters.iter().enumerate() {
+            let current_bucket = limit.bucket(now);
+            let mut deltas = HashMap::<String, i128>::new();
+            if !counted.is_empty() {
+                if let Some(amount) = released_amounts.get(&current_bucket) {
+                    deltas.insert(current_bucket.clone(), -amount);
+                }
+            }
+            for (_, bucket, amount) in other_releases.iter().filter(|(i, _, _)| *i == index) {
+                deltas.insert(bucket.clone(), -amount);
+            }
+            let mut used_amount = buckets.values().sum::<i128>() + deltas.values().sum::<i128>();
+            for (entry, amount) in counted.iter() {
+                used_amount += amount;
+                *deltas.entry(current_bucket.clone()).or_default() += amount;
+                if used_amount > limit.max_amount {
+                    return Err(AppendEntriesError::VelocityLimitExceeded(
+                        entry.entry_id.clone(),
+                        limit.limit_id.clone(),
+                    ));
+                }
+            }
+            for (bucket, delta) in deltas {
+                let previous_total = buckets.get(&bucket).copied();
+                let update = Update::builder()
+                    .table_name(&self.table_name)
+                    .key("pk", Pk::Velocity(account_id.clone()).key(&self.key_prefix))
+                    .key(
+                        "sk",
+                        Sk::VelocityBucket(limit.limit_id.clone(), bucket.clone()).into(),
+                    )
+                    .expression_attribute_names("#total", "total")
+                    .expression_attribute_values(":delta", AttributeValue::N(delta.to_string()));
+                // Only the current bucket can be new, the released ones already expire.
+                let update = if bucket == current_bucket {
+                    update
+                        .update_expression("ADD #total :delta SET #expires_at = :expires_at")
+                        .expression_attribute_names("#expires_at", TTL_ATTRIBUTE)
+                        .expression_attribute_values(
+                            ":expires_at",
+                            AttributeValue::N(limit.expires_at(now).timestamp().to_string()),
+                        )
+                } else {
+                    update.update_expression("ADD #total :delta")
+                };
+                let update = match previous_total {
+                    Some(previous_total) => update
+                        .condition_expression("#total = :previous_total")
+                        .expression_attribute_values(
+                            ":previous_total",
+                            AttributeValue::N(previous_total.to_string()),
+                        ),
+                    None => update.condition_expression("attribute_not_exists(pk)"),
+                };
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .update(
+                            update
+                                .return_values_on_condition_check_failure(
+                                    ReturnValuesOnConditionCheckFailure::AllOld,
+                                )
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            }
+        }
+        Ok(transact)
+    }
+
+    /// Follows the query pages, from the exclusive start key of the builder if it has one, until
+    /// `max_items` items are found or there is nothing left.
+    async fn query_at_most(
+        &self,
+        query_builder: QueryFluentBuilder,
+        max_items: usize,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>> {
+        let mut items = Vec::new();
+        let mut exclusive_start_key = query_builder.get_exclusive_start_key().clone();
+        loop {
+            let output = query_builder
+                .clone()
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            items.extend(output.items().iter().cloned());
+            exclusive_start_key = output.last_evaluated_key().cloned();
+            if items.len() >= max_items || exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        items.truncate(max_items);
+        Ok(items)
+    }
+
+    /// Writes up to `MAX_BATCH_WRITE_ITEMS` items, retrying the ones left unprocessed by
+    /// DynamoDB with an exponential backoff.
+    async fn batch_write(&self, mut write_requests: Vec<WriteRequest>) -> Result<()> {
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            let output = self
+                .client
+                .batch_write_item()
+                .request_items(&self.table_name, write_requests)
+                .send()
+                .await?;
+            let Some(unprocessed_items) = output
+                .unprocessed_items
+                .and_then(|mut unprocessed_items| unprocessed_items.remove(&self.table_name))
+                .filter(|unprocessed_items| !unprocessed_items.is_empty())
+            else {
+                return Ok(());
+            };
+            if tries == MAX_BATCH_WRITE_TRIES {
+                bail!(
+                    "{} items were left unprocessed after {} tries",
+                    unprocessed_items.len(),
+                    tries
+                );
+            }
+            sleep(Duration::from_millis(25 << tries)).await;
+            write_requests = unprocessed_items;
+        }
+    }
+
+    /// The HEADs of up to `MAX_BATCH_GET_ITEMS` accounts, retrying the keys left unprocessed
+    /// by DynamoDB with an exponential backoff.
+    async fn batch_get_balances(
+        &self,
+        account_ids: &[&AccountId],
+    ) -> Result<Vec<EntryWithBalance>, GetBalanceError> {
+        let mut keys_and_attributes_builder = KeysAndAttributes::builder();
+        for account_id in account_ids {
+            keys_and_attributes_builder = keys_and_attributes_builder.keys(HashMap::from([
+                (
+                    "pk".into(),
+                    Pk::Balance((*account_id).clone()).key(&self.key_prefix),
+                ),
+                ("sk".into(), Sk::CurrentEntry.into()),
+            ]));
+        }
+        let mut keys_and_attributes = keys_and_attributes_builder
+            .build()
+            .map_err(anyhow::Error::from)?;
+        let mut balances = Vec::new();
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            let mut output = self
+                .client
+                .batch_get_item()
+                .request_items(&self.table_name, keys_and_attributes)
+                .send()
+                .await
+                .map_err(anyhow::Error::from)?;
+            for item in output
+                .responses
+                .as_mut()
+                .and_then(|responses| responses.remove(&self.table_name))
+                .unwrap_or_default()
+            {
+                balances.push(entry_with_balance_from_item(&item, &self.key_prefix)?);
+            }
+            let Some(unprocessed_keys) = output
+                .unprocessed_keys
+                .and_then(|mut unprocessed_keys| unprocessed_keys.remove(&self.table_name))
+                .filter(|unprocessed_keys| !unprocessed_keys.keys().is_empty())
+            else {
+                return Ok(balances);
+            };
+            if tries == MAX_BATCH_GET_TRIES {
+                return Err(anyhow!(
+                    "{} balances were left unprocessed after {} tries",
+                    unprocessed_keys.keys().len(),
+                    tries
+                )
+                .into());
+            }
+            sleep(Duration::from_millis(25 << tries)).await;
+            keys_and_attributes = unprocessed_keys;
+        }
+    }
+
+    /// Every row of the account found by a parallel scan of the whole table, for the accounts
+    /// whose HEAD doesn't tell where their rows start.
+    async fn scan_account_rows(
+        &self,
+        account_id: &AccountId,
+        total_segments: i32,
+    ) -> Result<AccountRows, GetBalanceError> {
+        let pk_prefix = Pk::Balance(account_id.clone()).key(&self.key_prefix);
+        let segments = try_join_all(
+            (0..total_segments)
+                .map(|segment| self.scan_pk_prefix(&pk_prefix, segment, total_segments)),
+        )
+        .await?;
+        let mut head = None;
+        let mut hashed_from = None;
+        let mut entries = Vec::new();
+        let mut snapshots = Vec::new();
+        for item in segments.into_iter().flatten() {
+            let pk = Pk::from_key(
+                item.get("pk")
+                    .ok_or(GetBalanceError::MissingField("pk".into()))?,
+                &self.key_prefix,
+            )?;
+            match pk {
+                Pk::Balance(_) => {
+                    head = Some(entry_with_balance_from_item(&item, &self.key_prefix)?);
+                    hashed_from = hashed_from_item(&item)?;
+                }
+                Pk::Entry(_, _) => entries.push(stored_entry_from_item(&item, &self.key_prefix)?),
+                Pk::Snapshot(account_id) => snapshots.push(snapshot_from_item(account_id, &item)?),
+                Pk::Period(_, _)
+                | Pk::Audit(_)
+                | Pk::Hierarchy(_)
+                | Pk::Reconciliation(_)
+                | Pk::Accruals
+                | Pk::Rules
+                | Pk::Recurring
+                | Pk::VelocityLimits
+                | Pk::Velocity(_) => {}
+            }
+        }
+        entries.sort_by_key(|entry| entry.entry.sequence);
+        snapshots.sort_by_key(|snapshot| snapshot.sequence);
+        Ok(AccountRows {
+            head,
+            hashed_from,
+            entries,
+            snapshots,
+        })
+    }
+
+    /// The entry rows of the account created between the dates, and after `after` when given,
+    /// sorted by sequence. They are spread in one GSI partition per day, like in `get_entries`.
+    async fn query_entry_rows(
+        &self,
+        account_id: &AccountId,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        after: Option<(&DateTime<Utc>, u64)>,
+    ) -> Result<Vec<StoredEntry>, GetBalanceError> {
+        let mut current_date = start_date;
+        let mut items = Vec::new();
+        loop {
+            let mut query_builder = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(&self.index_name)
+                .key_conditions(
+                    "account_id_and_date",
+                    Condition::builder()
+                        .comparison_operator(ComparisonOperator::Eq)
+                        .attribute_value_list(AttributeValue::S(format!(
+                            "{}{}|{}",
+                            self.key_prefix, account_id, current_date
+                        )))
+                        .build()
+                        .map_err(anyhow::Error::from)?,
+                );
+            if let Some((created_at, sequence)) = after {
+                query_builder = query_builder.key_conditions(
+                    "created_at",
+                    Condition::builder()
+                        .comparison_operator(ComparisonOperator::Gt)
+                        .attribute_value_list(AttributeValue::S(format_created_at_and_sequence(
+                            created_at, sequence,
+                        )))
+                        .build()
+                        .map_err(anyhow::Error::from)?,
+                );
+            }
+            items.extend(self.query_at_most(query_builder, usize::MAX).await?);
+            if current_date >= end_date {
+                break;
+            }
+            current_date = current_date
+                .checked_add_days(Days::new(1))
+                .ok_or(anyhow!("Failed to increment current_date"))?;
+        }
+        let mut entries = items
+            .iter()
+            .map(|item| stored_entry_from_item(item, &self.key_prefix))
+            .collect::<Result<Vec<StoredEntry>, GetBalanceError>>()?;
+        entries.sort_by_key(|entry| entry.entry.sequence);
+        Ok(entries)
+    }
+
+    /// Every item of one segment of a parallel scan whose PK starts with the prefix.
+    async fn scan_pk_prefix(
+        &self,
+        pk_prefix: &AttributeValue,
+        segment: i32,
+        total_segments: i32,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>> {
+        let mut items = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .segment(segment)
+                .total_segments(total_segments)
+                .filter_expression("begins_with(pk, :pk_prefix)")
+                .expression_attribute_values(":pk_prefix", pk_prefix.clone())
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            items.extend(output.items().iter().cloned());
+            exclusive_start_key = output.last_evaluated_key().cloned();
+            if exclusive_start_key.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    async fn get_head(&self, account_id: &AccountId) -> Result<Option<Head>> {
+        self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+            .key("sk", Sk::CurrentEntry.into())
+            .send()
+            .await?
+            .item()
+            .map(|item| -> Result<Head> {
+                Ok(Head {
+                    ledger_balances: item
+                        .get("ledger_balances")
+                        .ok_or(anyhow!(
+                            "Missing ledger_balances for HEAD of account_id {}",
+                            account_id.to_string()
+                        ))?
+                        .as_m()
+                        .map_err(|_| anyhow!("Not a map"))?
+                        .iter()
+                        .map(|(k, v)| -> Result<(LedgerBalanceName, i128)> {
+                            Ok((
+                                LedgerBalanceName::new(k.clone())?,
+                                v.as_n()
+                                    .map_err(|_| anyhow!("Not a number"))?
+                                    .parse::<i128>()?,
+                            ))
+                        })
+                        .collect::<Result<HashMap<LedgerBalanceName, i128>>>()?,
+                    pending_balances: pending_balances_from_item(item)?,
+                    sequence: item
+                        .get("sequence")
+                        .ok_or(anyhow!(
+                            "Missing sequence for HEAD of account_id {}",
+                            account_id.to_string()
+                        ))?
+                        .as_n()
+                        .map_err(|_| anyhow!("Not a number"))?
+                        .parse()
+                        .map_err(|err| anyhow!("Error parsing sequence number: {err}"))?,
+                    created_at: created_at_from_item(item)?,
+                    closed_until: closed_until_from_item(item)?,
+                    hash: hash_from_item(item, "hash")?,
+                })
+            })
+            .transpose()
+    }
+
+    async fn get_current_entries(
+        &self,
+        account_id: &AccountId,
+        entries_ids: &[EntryId],
+    ) -> Result<HashMap<EntryId, CurrentEntry>> {
+        let mut keys_and_attributes_builder = KeysAndAttributes::builder();
+        for entry_id in entries_ids.iter().unique() {
+            keys_and_attributes_builder = keys_and_attributes_builder.keys(HashMap::from([
+                (
+                    "pk".into(),
+                    Pk::Entry(account_id.clone(), entry_id.clone()).key(&self.key_prefix),
+                ),
+                ("sk".into(), Sk::CurrentEntry.into()),
+            ]));
+        }
+        let items = self
+            .client
+            .batch_get_item()
+            .request_items(&self.table_name, keys_and_attributes_builder.build()?)
+            .send()
+            .await?;
+        items
+            .responses()
+            .and_then(|responses| responses.get(&self.table_name))
+            .map(|responses| -> Result<HashMap<EntryId, CurrentEntry>> {
+                responses
+                    .iter()
+                    .map(|item| {
+                        let entry = entry_with_balance_from_item(item, &self.key_prefix)?;
+                        let reverted_ledger_fields = item
+                            .get("reverted_ledger_fields")
+                            .map(|reverted_ledger_fields| {
+                                ledger_fields_from_attribute(reverted_ledger_fields)
+                            })
+                            .transpose()?
+                            .unwrap_or_default();
+                        Ok((
+                            entry.entry_id.clone(),
+                            CurrentEntry {
+                                entry,
+                                reverted_ledger_fields,
+                            },
+                        ))
+                    })
+                    .collect()
+            })
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    async fn internal_append_entries(
+        &self,
+        account_id: &AccountId,
+        entries: &[EntryWithConditionals],
+        head: Option<Head>,
+        replaced_entries: &HashMap<EntryId, CurrentEntry>,
+        (actor, action): (&Actor, AuditAction),
+        mut transact: TransactWriteItemsFluentBuilder,
+    ) -> Result<(TransactWriteItemsFluentBuilder, Vec<EntryWithBalance>), AppendEntriesError> {
+        let previous = head.as_ref().map(|head| (head.sequence, head.created_at));
+        let mut pending_balances = head
+            .as_ref()
+            .map(|head| head.pending_balances.clone())
+            .unwrap_or_default();
+        let mut entries_with_balance: Vec<EntryWithBalance> = Vec::new();
+        // The entries appended together share their creation time, so they land in the same
+        // velocity bucket.
+        let created_at = utc_now();
+        for entry_with_conditional in entries {
+            let entry = &entry_with_conditional.entry;
+            let conditionals = &entry_with_conditional.conditionals;
+            // Pending entries only move the pending balances, until the Applied entry that
+            // posts them replaces them and takes them out.
+            let is_pending = entry.status.is_pending();
+            if is_pending {
+                for (field_name, value) in entry.ledger_fields.iter() {
+                    *pending_balances
+                        .entry(LedgerBalanceName::from(field_name.clone()))
+                        .or_insert(0) += value;
+                }
+            }
+            if let (EntryStatus::Applied, Some(replaced)) =
+                (&entry.status, replaced_entries.get(&entry.entry_id))
+            {
+                if replaced.entry.status == EntryStatus::Pending {
+                    for (field_name, value) in replaced.entry.ledger_fields.iter() {
+                        *pending_balances
+                            .entry(LedgerBalanceName::from(field_name.clone()))
+                            .or_insert(0) -= value;
+                    }
+                }
+            }
+            pending_balances.retain(|_, balance| *balance != 0);
+            let new_entry = match entries_with_balance.last() {
+                Some(entry_with_balance) => EntryWithBalance {
+                    account_id: entry.account_id.clone(),
+                    entry_id: entry.entry_id.clone(),
+                    ledger_balances: entry
+                        .ledger_fields
+                        .iter()
+                        .map(|(field_name, value)| {
+                            let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                            let balance = entry_with_balance
+                                .ledger_balances
+                                .get(&ledger_balance_name)
+                                .unwrap_or(&0);
+                            let new_balance = if is_pending {
+                                *balance
+                            } else {
+                                balance + value
+                            };
+
+                            (ledger_balance_name, new_balance)
+                        })
+                        .collect(),
+                    pending_balances: pending_balances.clone(),
+                    status: entry.status.clone(),
+                    reversal: entry.reversal.clone(),
+                    actor: Some(actor.clone()),
+                    ledger_fields: entry.ledger_fields.clone(),
+                    additional_fields: entry.additional_fields.clone(),
+                    sequence: entry_with_balance.sequence + 1,
+                    created_at,
+                    prev_hash: entry_with_balance.hash.clone(),
+                    hash: None,
+                },
+                None => EntryWithBalance {
+                    account_id: entry.account_id.clone(),
+                    entry_id: entry.entry_id.clone(),
+                    ledger_balances: entry
+                        .ledger_fields
+                        .iter()
+                        .map(|(field_name, value)| {
+                            let ledger_balance_name = LedgerBalanceName::from(field_name.clone());
+                            let balance = head
+                                .as_ref()
+                                .and_then(|head| {
+                                    head.ledger_balances.get(&ledger_balance_name).cloned()
+                                })
+                                .unwrap_or(0);
+                            let new_balance = if is_pending { balance } else { balance + value };
+                            (ledger_balance_name, new_balance)
+                        })
+                        .collect(),
+                    pending_balances: pending_balances.clone(),
+                    status: entry.status.clone(),
+                    reversal: entry.reversal.clone(),
+                    actor: Some(actor.clone()),
+                    ledger_fields: entry.ledger_fields.clone(),
+                    additional_fields: entry.additional_fields.clone(),
+                    sequence: head.as_ref().map(|head| head.sequence + 1).unwrap_or(0),
+                    created_at,
+                    prev_hash: head.as_ref().and_then(|head| head.hash.clone()),
+                    hash: None,
+                },
+            };
+            let new_entry = EntryWithBalance {
+                hash: Some(new_entry.compute_hash()),
+                ..new_entry
+            };
+            Self::validate_conditionals(conditionals, &new_entry)?;
+            entries_with_balance.push(new_entry);
+        }
+        // Reverts give back what the entries they revert counted, so an amendment only counts
+        // what it adds.
+        if matches!(
+            action,
+            AuditAction::PushEntries
+                | AuditAction::PostEntries
+                | AuditAction::AmendEntries
+                | AuditAction::RevertEntries
+        ) {
+            let released = entries_with_balance
+                .iter()
+                .filter(|entry| matches!(entry.status, EntryStatus::Revert(_)))
+                .filter_map(|entry| {
+                    let reverted = replaced_entries.get(&entry.entry_id)?;
+                    let ledger_fields = entry
+                        .ledger_fields
+                        .iter()
+                        .map(|(field_name, value)| (field_name.clone(), -value))
+                        .collect();
+                    Some((reverted.entry.created_at, ledger_fields))
+                })
+                .collect_vec();
+            transact = self
+                .update_velocity(
+                    account_id,
+                    &created_at,
+                    &entries_with_balance,
+                    &released,
+                    transact,
+                )
+                .await?;
+        }
+        for entry in entries_with_balance.iter() {
+            transact = transact.transact_items(
+                match (&entry.status, replaced_entries.get(&entry.entry_id)) {
+                    (EntryStatus::Applied, Some(replaced)) => {
+                        create_transact_item_for_replaced_entry(
+                            entry,
+                            replaced.entry.sequence,
+                            &self.table_name,
+                            &self.key_prefix,
+                        )?
+                    }
+                    _ => create_transact_item_for_entry(
+                        entry,
+                        false,
+                        &self.table_name,
+                        &self.key_prefix,
+                    )?,
+                },
+            );
+        }
+        match head {
+            Some(head) => {
+                let entry = entries_with_balance.last().ok_or(anyhow!(
+                    "Missing last entry for account_id {}",
+                    account_id.to_string()
+                ))?;
+                // Kept from the first append, or set by the first one since entries are hashed.
+                let hashed_from = head.sequence + 1;
+                let update = Update::builder()
+                    .table_name(&self.table_name)
+                    .key("pk", Pk::Balance(account_id.clone()).key(&self.key_prefix))
+                    .key("sk", Sk::CurrentEntry.into())
+                    .expression_attribute_values(
+                        ":ledger_balances",
+                        AttributeValue::M(
+                            entry
+                                .ledger_balances
+                                .clone()
+                                .into_iter()
+                                .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                                .collect(),
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":ledger_fields",
+                        AttributeValue::M(
+                            entry
+                                .ledger_fields
+                                .clone()
+                                .into_iter()
+                                .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                                .collect(),
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":additional_fields",
+                        AttributeValue::S(
+                            serde_json::to_string(&entry.additional_fields)
+                                .map_err(anyhow::Error::from)?,
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":status",
+                        AttributeValue::S(
+                            serde_json::to_string(&entry.status).map_err(anyhow::Error::from)?,
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":entry_id",
+                        AttributeValue::S(entry.entry_id.to_string()),
+                    )
+                    .expression_attribute_values(
+                        ":sequence",
+                        AttributeValue::N(entry.sequence.to_string()),
+                    )
+                    .expression_attribute_values(
+                        ":created_at",
+                        AttributeValue::S(entry.created_at.to_string()),
+                    )
+                    .expression_attribute_values(":actor", AttributeValue::S(actor.to_string()))
+                    .expression_attribute_values(
+                        ":old_ledger_balances",
+                        AttributeValue::M(
+                            head.ledger_balances
+                                .into_iter()
+                                .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                                .collect(),
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":old_sequence",
+                        AttributeValue::N(head.sequence.to_string()),
+                    )
+                    .expression_attribute_values(
+                        ":hash",
+                        AttributeValue::S(
+                            entry
+                                .hash
+                                .as_ref()
+                                .ok_or(anyhow!("Missing hash of the last entry"))?
+                                .to_string(),
+                        ),
+                    )
+                    .expression_attribute_values(
+                        ":hashed_from",
+                        AttributeValue::N(hashed_from.to_string()),
+                    )
+                    .expression_attribute_names("#sequence_field", "sequence")
+                    .expression_attribute_names("#hash_field", "hash");
+                let mut update_expression = String::from("SET ledger_balances = :ledger_balances, ledger_fields = :ledger_fields, additional_fields = :additional_fields, entry_id = :entry_id, created_at = :created_at, entry_status = :status, #sequence_field = :sequence, actor = :actor, #hash_field = :hash, hashed_from = if_not_exists(hashed_from, :hashed_from)");
+                // Only the first entry hashed after a HEAD without hash has no prev_hash.
+                let update = match &entry.prev_hash {
+                    Some(prev_hash) => {
+                        update_expression.push_str(", prev_hash = :prev_hash");
+                        update.expression_attribute_values(
+                            ":prev_hash",
+                            AttributeValue::S(prev_hash.to_string()),
+                        )
+                    }
+                    None => update,
+                };
+                let update = if entry.pending_balances.is_empty() {
+                    update_expression.push_str(" REMOVE pending_balances");
+                    update
+                } else {
+                    update_expression.push_str(", pending_balances = :pending_balances");
+                    update.expression_attribute_values(
+                        ":pending_balances",
+                        AttributeValue::M(
+                            entry
+                                .pending_balances
+                                .clone()
+                                .into_iter()
+                                .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                                .collect(),
+                        ),
+                    )
+                };
+                let update = update.update_expression(update_expression);
+                let update = match head.closed_until {
+                    Some(closed_until) => update
+                        .expression_attribute_values(
+                            ":old_closed_until",
+                            AttributeValue::S(closed_until.to_string()),
+                        )
+                        .condition_expression("ledger_balances = :old_ledger_balances AND #sequence_field = :old_sequence AND closed_until = :old_closed_until"),
+                    None => update
+                        .condition_expression("ledger_balances = :old_ledger_balances AND #sequence_field = :old_sequence AND attribute_not_exists(closed_until)"),
+                };
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .update(
+                            update
+                                .return_values_on_condition_check_failure(
+                                    ReturnValuesOnConditionCheckFailure::AllOld,
+                                )
+                                .build()
+                                .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            }
+            None => {
+                let first_entry = entries_with_balance.first().ok_or(anyhow!(
+                    "Missing first entry for account_id {}",
+                    account_id.to_string()
+                ))?;
+                let last_entry = entries_with_balance.last().ok_or(anyhow!(
+                    "Missing last entry for account_id {}",
+                    account_id.to_string()
+                ))?;
+                transact = transact.transact_items(
+                    TransactWriteItem::builder()
+                        .put(
+                            put_builder_for_entry(
+                                last_entry,
+                                true,
+                                &self.table_name,
+                                &self.key_prefix,
+                            )?
+                            // The first day of the account in the date GSI.
+                            .item(
+                                "opened_on",
+                                AttributeValue::S(first_entry.created_at.date_naive().to_string()),
+                            )
+                            .item(
+                                "hashed_from",
+                                AttributeValue::N(first_entry.sequence.to_string()),
+                            )
+                            .condition_expression("attribute_not_exists(pk)")
+                            .return_values_on_condition_check_failure(
+                                ReturnValuesOnConditionCheckFailure::AllOld,
+                            )
+                            .build()
+                            .map_err(anyhow::Error::from)?,
+                        )
+                        .build(),
+                );
+            }
+        }
+        let last_entry = entries_with_balance.last().ok_or(anyhow!(
+            "Missing last entry for account_id {}",
+            account_id.to_string()
+        ))?;
+        if self
+            .snapshot_policy
+            .is_due(previous, last_entry.sequence, &last_entry.created_at)
+        {
+            transact = transact.transact_items(create_transact_item_for_snapshot(
+                &Snapshot::from(last_entry),
+                &self.table_name,
+                &self.key_prefix,
+            )?);
+        }
+        transact = transact.transact_items(create_transact_item_for_audit(
+            &AuditRecord {
+                account_id: account_id.clone(),
+                actor: actor.clone(),
+                action,
+                entry_ids: entries_with_balance
+                    .iter()
+                    .map(|entry| entry.entry_id.clone())
+                    .unique()
+                    .collect(),
+                sequence: last_entry.sequence,
+                created_at: last_entry.created_at,
+            },
+            &self.table_name,
+            &self.key_prefix,
+        )?);
+        Ok((transact, entries_with_balance))
+    }
+
+    fn validate_conditionals(
+        conditionals: &Vec<Conditional>,
+        new_entry: &EntryWithBalance,
+    ) -> Result<(), AppendEntriesError> {
+        for conditional in conditionals {
+            match conditional {
+                Conditional::GreaterThanOrEqualTo { balance, value } => {
+                    let balance = new_entry.ledger_balances.get(balance).unwrap_or(&0);
+                    if balance < value {
+                        return Err(AppendEntriesError::ConditionFailed(
+                            new_entry.entry_id.clone(),
+                            conditional.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_transact_item_for_entry(
+    entry: &EntryWithBalance,
+    is_head: bool,
+    table_name: &str,
+    key_prefix: &str,
+) -> Result<TransactWriteItem> {
+    Ok(TransactWriteItem::builder()
+        .put(
+            put_builder_for_entry(entry, is_head, table_name, key_prefix)?
+                .condition_expression("attribute_not_exists(pk)")
+                .return_values_on_condition_check_failure(
+                    ReturnValuesOnConditionCheckFailure::AllOld,
+                )
+                .build()?,
+        )
+        .build())
+}
+
+fn put_builder_for_entry(
+    entry: &EntryWithBalance,
+    is_head: bool,
+    table_name: &str,
+    key_prefix: &str,
+) -> Result<PutBuilder> {
+    let (pk, sk) = match (is_head, &entry.status) {
+        (true, _) => (Pk::Balance(entry.account_id.clone()), Sk::CurrentEntry),
+        (
+            false,
+            EntryStatus::Reverted(_)
+            | EntryStatus::Revert(_)
+            | EntryStatus::Posted(_)
+            | EntryStatus::Void(_)
+            | EntryStatus::Voided(_),
+        ) => (
+            Pk::Entry(entry.account_id.clone(), entry.entry_id.clone()),
+            Sk::History(entry.sequence),
+        ),
+        (false, EntryStatus::Applied | EntryStatus::Pending) => (
+            Pk::Entry(entry.account_id.clone(), entry.entry_id.clone()),
+            Sk::CurrentEntry,
+        ),
+    };
+    let mut put_builder = Put::builder()
+        .table_name(table_name)
+        .item("pk", pk.key(key_prefix))
+        .item("sk", sk.into())
+        .item(
+            "ledger_balances",
+            AttributeValue::M(
+                entry
+                    .ledger_balances
+                    .clone()
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                    .collect(),
+            ),
+        )
+        .item(
+            "ledger_fields",
+            AttributeValue::M(
+                entry
+                    .ledger_fields
+                    .clone()
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                    .collect(),
+            ),
+        )
+        .item(
+            "additional_fields",
+            AttributeValue::S(serde_json::to_string(&entry.additional_fields)?),
+        )
+        .item(
+            "account_id_and_date",
+            if is_head {
+                AttributeValue::S("head".into())
+            } else {
+                AttributeValue::S(format!(
+                    "{}{}|{}",
+                    key_prefix,
+                    entry.account_id,
+                    entry.created_at.date_naive()
+                ))
+            },
+        )
+        .item(
+            "entry_status",
+            AttributeValue::S(serde_json::to_string(&entry.status)?),
+        )
+        .item("sequence", AttributeValue::N(entry.sequence.to_string()))
+        .item(
+            "created_at",
+            AttributeValue::S(format_created_at_and_sequence(
+                &entry.created_at,
+                entry.sequence,
+            )),
+        );
+    if is_head {
+        put_builder = put_builder
+            .item("entry_id", AttributeValue::S(entry.entry_id.to_string()))
+            .item(
+                "account_id",
+                AttributeValue::S(entry.account_id.to_string()),
+            )
+            .item(
+                "accounts_shard",
+                accounts_shard(key_prefix, accounts_shard_index(&entry.account_id)),
+            );
+    } else if let Some(reversal) = &entry.reversal {
+        put_builder = put_builder.item(
+            "reversal",
+            AttributeValue::S(serde_json::to_string(reversal)?),
+        );
+    }
+    if !entry.pending_balances.is_empty() {
+        put_builder = put_builder.item(
+            "pending_balances",
+            AttributeValue::M(
+                entry
+                    .pending_balances
+                    .clone()
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
+                    .collect(),
+            ),
+        );
+    }
+    if let Some(actor) = &entry.actor {
+        put_builder = put_builder.item("actor", AttributeValue::S(actor.to_string()));
+    }
+    if let Some(prev_hash) = &entry.prev_hash {
+        put_builder = put_builder.item("prev_hash", AttributeValue::S(prev_hash.to_string()));
+    }
+    if let Some(hash) = &entry.hash {
+        put_builder = put_builder.item("hash", AttributeValue::S(hash.to_string()));
+    }
+    Ok(put_builder)
+}
+
+fn create_transact_item_for_replaced_entry(
+    entry: &EntryWithBalance,
+    replaced_sequence: u64,
+    table_name: &str,
+    key_prefix: &str,
+) -> Result<TransactWriteItem> {
+    Ok(TransactWriteItem::builder()
+        .put(
+            put_builder_for_entry(entry, false, table_name, key_prefix)?
+                .expression_attribute_names("#sequence_field", "sequence")
+                .expression_attribute_values(
+                    ":replaced_sequence",
+                    AttributeValue::N(replaced_sequence.to_string()),
+                )
+                .condition_expression("#sequence_field = :replaced_sequence")
+                .return_values_on_condition_check_failure(
+                    ReturnValuesOnConditionCheckFailure::AllOld,
+                )
+                .build()?,
+        )
+        .build())
+}
+
+fn entry_with_balance_from_item(
+    item: &HashMap<String, AttributeValue>,
+    key_prefix: &str,
+) -> Result<EntryWithBalance, GetBalanceError> {
+    let pk = Pk::from_key(
+        item.get("pk")
+            .ok_or(GetBalanceError::MissingField("pk".into()))?,
+        key_prefix,
+    )?;
+    let (account_id, entry_id) = match pk {
+        Pk::Entry(account_id, entry_id) => (account_id, entry_id),
+        Pk::Period(_, _)
+        | Pk::Audit(_)
+        | Pk::Snapshot(_)
+        | Pk::Hierarchy(_)
+        | Pk::Reconciliation(_)
+        | Pk::Accruals
+        | Pk::Rules
+        | Pk::Recurring
+        | Pk::VelocityLimits
+        | Pk::Velocity(_) => return Err(anyhow!("Expected an entry or balance PK").into()),
+        Pk::Balance(account_id) => (
+            account_id,
+            EntryId::new_unchecked(
+                item.get("entry_id")
+                    .ok_or(GetBalanceError::MissingField("entry_id".into()))?
+                    .as_s()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("entry_id".into()))?
+                    .clone(),
+            ),
+        ),
+    };
+
+    Ok(EntryWithBalance {
+        account_id,
+        entry_id,
+        ledger_balances: ledger_balances_from_item(item)?,
+        pending_balances: pending_balances_from_item(item)?,
+        ledger_fields: item
+            .get("ledger_fields")
+            .ok_or(GetBalanceError::MissingField("ledger_fields".into()))?
+            .as_m()
+            .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?
+            .iter()
+            .map(|(k, v)| {
+                Ok((
+                    LedgerFieldName::new(k.clone())
+                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?,
+                    v.as_n()
+                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?
+                        .parse::<i128>()
+                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?,
+                ))
+            })
+            .collect::<Result<HashMap<LedgerFieldName, i128>>>()?,
+        additional_fields: serde_json::from_str(
+            item.get("additional_fields")
+                .ok_or(GetBalanceError::MissingField("additional_fields".into()))?
+                .as_s()
+                .map_err(|_| GetBalanceError::ErrorReadingField("additional_fields".into()))?,
+        )
+        .map_err(|_| GetBalanceError::ErrorReadingField("additional_fields".into()))?,
+        status: serde_json::from_str(
+            item.get("entry_status")
+                .ok_or(GetBalanceError::MissingField("entry_status".into()))?
+                .as_s()
+                .map_err(|_| GetBalanceError::ErrorReadingField("entry_status".into()))?,
+        )
+        .map_err(|_| GetBalanceError::ErrorReadingField("entry_status".into()))?,
+        reversal: item
+            .get("reversal")
+            .map(|reversal| {
+                serde_json::from_str(
+                    reversal
+                        .as_s()
+                        .map_err(|_| GetBalanceError::ErrorReadingField("reversal".into()))?,
+                )
+                .map_err(|_| GetBalanceError::ErrorReadingField("reversal".into()))
+            })
+            .transpose()?,
+        actor: item
+            .get("actor")
+            .map(|actor| {
+                Actor::new(
+                    actor
+                        .as_s()
+                        .map_err(|_| GetBalanceError::ErrorReadingField("actor".into()))?
+                        .clone(),
+                )
+                .map_err(|_| GetBalanceError::ErrorReadingField("actor".into()))
+            })
+            .transpose()?,
+        sequence: item
+            .get("sequence")
+            .ok_or(GetBalanceError::MissingField("sequence".into()))?
+            .as_n()
+            .map_err(|_| GetBalanceError::ErrorReadingField("sequence".into()))?
+            .parse::<u64>()
+            .map_err(|_| GetBalanceError::ErrorReadingField("sequence".into()))?,
+        created_at: created_at_from_item(item)?,
+        prev_hash: hash_from_item(item, "prev_hash")?,
+        hash: hash_from_item(item, "hash")?,
+    })
+}
+
+/// The `created_at` of a row, without the sequence appended to it in the entry rows.
+fn created_at_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<DateTime<Utc>, GetBalanceError> {
+    let mut created_at = item
+        .get("created_at")
+        .ok_or(GetBalanceError::MissingField("created_at".into()))?
+        .as_s()
+        .map_err(|_| GetBalanceError::ErrorReadingField("created_at".into()))?
+        .as_str();
+    if let Some((separated_created_at, _sequence)) = created_at.split_once('|') {
+        created_at = separated_created_at;
+    }
+    DateTime::from_str(created_at)
+        .map_err(|_| GetBalanceError::ErrorReadingField("created_at".into()))
+}
+
+fn stored_entry_from_item(
+    item: &HashMap<String, AttributeValue>,
+    key_prefix: &str,
+) -> Result<StoredEntry, GetBalanceError> {
+    Ok(StoredEntry {
+        entry: entry_with_balance_from_item(item, key_prefix)?,
+        is_current: matches!(
+            Sk::try_from(
+                item.get("sk")
+                    .ok_or(GetBalanceError::MissingField("sk".into()))?
+                    .clone()
+            )?,
+            Sk::CurrentEntry
+        ),
+        reverted_ledger_fields: item
+            .get("reverted_ledger_fields")
+            .map(ledger_fields_from_attribute)
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+/// The sequence the HEAD started hashing at, missing in accounts not appended to since entries
+/// are hashed.
+fn hashed_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<Option<u64>, GetBalanceError> {
+    item.get("hashed_from")
+        .map(|hashed_from| {
+            hashed_from
+                .as_n()
+                .ok()
+                .and_then(|hashed_from| hashed_from.parse().ok())
+                .ok_or(GetBalanceError::ErrorReadingField("hashed_from".into()))
+        })
+        .transpose()
+}
+
+fn snapshot_from_item(
+    account_id: AccountId,
+    item: &HashMap<String, AttributeValue>,
+) -> Result<Snapshot, GetBalanceError> {
+    Ok(Snapshot {
+        account_id,
+        entry_id: EntryId::new_unchecked(
+            item.get("entry_id")
+                .ok_or(GetBalanceError::MissingField("entry_id".into()))?
+                .as_s()
+                .map_err(|_| GetBalanceError::ErrorReadingField("entry_id".into()))?
+                .clone(),
+        ),
+        sequence: item
+            .get("sequence")
+            .ok_or(GetBalanceError::MissingField("sequence".into()))?
+            .as_n()
+            .map_err(|_| GetBalanceError::ErrorReadingField("sequence".into()))?
+            .parse::<u64>()
+            .map_err(|_| GetBalanceError::ErrorReadingField("sequence".into()))?,
+        ledger_balances: ledger_balances_from_item(item)?,
+        pending_balances: pending_balances_from_item(item)?,
+        hash: hash_from_item(item, "hash")?,
+        created_at: created_at_from_item(item)?,
+    })
+}
+
+fn hash_from_item(
+    item: &HashMap<String, AttributeValue>,
+    field: &str,
+) -> Result<Option<EntryHash>, GetBalanceError> {
+    item.get(field)
+        .map(|hash| {
+            Ok(EntryHash::new_unchecked(
+                hash.as_s()
+                    .map_err(|_| GetBalanceError::ErrorReadingField(field.into()))?
+                    .clone(),
+            ))
+        })
+        .transpose()
+}
+
+fn account_id_from_attribute(
+    value: &AttributeValue,
+    field: &str,
+) -> Result<AccountId, GetBalanceError> {
+    Ok(AccountId::new(
+        value
+            .as_s()
+            .ok()
+            .and_then(|value| Uuid::from_str(value).ok())
+            .ok_or(GetBalanceError::ErrorReadingField(field.into()))?,
+    ))
+}
+
+fn ledger_balances_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<HashMap<LedgerBalanceName, i128>, GetBalanceError> {
+    Ok(item
+        .get("ledger_balances")
+        .ok_or(GetBalanceError::MissingField("ledger_balances".into()))?
+        .as_m()
+        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?
+        .iter()
+        .map(|(k, v)| {
+            Ok((
+                LedgerBalanceName::new(k.clone())
+                    .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?,
+                v.as_n()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?
+                    .parse::<i128>()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?,
+            ))
+        })
+        .collect::<Result<HashMap<LedgerBalanceName, i128>>>()?)
+}
+
+/// The pending balances of a row, that only has them while some are not zero.
+fn pending_balances_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<HashMap<LedgerBalanceName, i128>, GetBalanceError> {
+    let Some(pending_balances) = item.get("pending_balances") else {
+        return Ok(HashMap::new());
+    };
+    pending_balances
+        .as_m()
+        .map_err(|_| GetBalanceError::ErrorReadingField("pending_balances".into()))?
+        .iter()
+        .map(|(k, v)| {
+            Ok((
+                LedgerBalanceName::new(k.clone())
+                    .map_err(|_| GetBalanceError::ErrorReadingField("pending_balances".into()))?,
+                v.as_n()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("pending_balances".into()))?
+                    .parse::<i128>()
+                    .map_err(|_| GetBalanceError::ErrorReadingField("pending_balances".into()))?,
+            ))
+        })
+        .collect()
+}
+
+fn closed_until_from_item(item: &HashMap<String, AttributeValue>) -> Result<Option<Period>> {
+    item.get("closed_until")
+        .map(|closed_until| -> Result<Period> {
+            closed_until
+                .as_s()
+                .map_err(|_| anyhow!("Expect closed_until to be a string"))?
+                .parse()
+        })
+        .transpose()
+}
+
+fn ledger_fields_from_attribute(
+    attribute: &AttributeValue,
+) -> Result<HashMap<LedgerFieldName, i128>> {
+    attribute
+        .as_m()
+        .map_err(|_| anyhow!("Not a map"))?
+        .iter()
+        .map(|(k, v)| -> Result<(LedgerFieldName, i128)> {
+            Ok((
+                LedgerFieldName::new(k.clone())?,
+                v.as_n()
+                    .map_err(|_| anyhow!("Not a number"))?
+                    .parse::<i128>()?,
+            ))
+        })
+        .collect()
+}
+
+fn missing_entries(
+    entries_ids: &[EntryId],
+    current_entries: &HashMap<EntryId, CurrentEntry>,
+) -> Vec<EntryId> {
+    entries_ids
+        .iter()
+        .filter(|entry_id| !current_entries.contains_key(entry_id))
+        .cloned()
+        .unique()
+        .collect_vec()
+}
+
+/// The entries that are still pending, and so can only be posted or voided.
+fn pending_entries(
+    entries_ids: &[EntryId],
+    current_entries: &HashMap<EntryId, CurrentEntry>,
+) -> Vec<EntryId> {
+    entries_ids
+        .iter()
+        .filter(|entry_id| {
+            current_entries
+                .get(entry_id)
+                .is_some_and(|current_entry| current_entry.entry.status == EntryStatus::Pending)
+        })
+        .cloned()
+        .unique()
+        .collect_vec()
+}
+
+fn entries_in_closed_period(
+    head: Option<&Head>,
+    entries_ids: &[EntryId],
+    current_entries: &HashMap<EntryId, CurrentEntry>,
+) -> Vec<EntryId> {
+    let Some(closed_until) = head.and_then(|head| head.closed_until) else {
+        return Vec::new();
+    };
+    entries_ids
+        .iter()
+        .filter(|entry_id| {
+            current_entries
+                .get(entry_id)
+                .map(|current_entry| Period::from(&current_entry.entry.created_at) <= closed_until)
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect_vec()
+}
+
+/// The CurrentEntry row of an entry, with what was already partially reverted from it.
+struct CurrentEntry {
+    entry: EntryWithBalance,
+    reverted_ledger_fields: HashMap<LedgerFieldName, i128>,
+}
+
+impl CurrentEntry {
+    fn remaining_ledger_fields(&self) -> HashMap<LedgerFieldName, i128> {
+        self.entry
+            .ledger_fields
+            .iter()
+            .map(|(field_name, value)| {
+                (
+                    field_name.clone(),
+                    value - self.reverted_ledger_fields.get(field_name).unwrap_or(&0),
+                )
+            })
+            .collect()
+    }
+
+    fn is_fully_reverted(&self) -> bool {
+        self.remaining_ledger_fields()
+            .values()
+            .all(|remaining| *remaining == 0)
+    }
+
+    /// Returns `None` if any requested amount is not part of what remains to be reverted.
+    fn ledger_fields_to_revert(
+        &self,
+        requested: Option<&HashMap<LedgerFieldName, i128>>,
+    ) -> Option<HashMap<LedgerFieldName, i128>> {
+        let remaining = self.remaining_ledger_fields();
+        let Some(requested) = requested else {
+            return Some(remaining);
+        };
+        for (field_name, value) in requested {
+            let remaining = remaining.get(field_name)?;
+            let is_within_remaining = if *remaining >= 0 {
+                (0..=*remaining).contains(value)
+            } else {
+                (*remaining..=0).contains(value)
+            };
+            if !is_within_remaining {
+                return None;
             }
-            None => {
-                transact = transact.transact_items(create_transact_item_for_entry(
-                    entries_with_balance.last().ok_or(anyhow!(
-                        "Missing last entry for account_id {}",
-                        account_id.to_string()
-                    ))?,
-                    true,
-                )?);
+        }
+        Some(requested.clone())
+    }
+
+    /// The entry that posts this pending one, or the one that voids it.
+    fn resolve_entry(&self, resolution: PendingResolution) -> EntryWithConditionals {
+        let mut entry: Entry = self.entry.clone().into();
+        match resolution {
+            PendingResolution::Post => entry.status = EntryStatus::Applied,
+            PendingResolution::Void => {
+                entry.status = EntryStatus::Void(self.entry.sequence);
+                entry.ledger_fields = entry
+                    .ledger_fields
+                    .into_iter()
+                    .map(|(key, value)| (key, -value))
+                    .collect();
             }
         }
-        Ok((transact, entries_with_balance))
+        entry.into()
     }
 
-    fn validate_conditionals(
-        conditionals: &Vec<Conditional>,
-        new_entry: &EntryWithBalance,
-    ) -> Result<(), AppendEntriesError> {
-        for conditional in conditionals {
-            match conditional {
-                Conditional::GreaterThanOrEqualTo { balance, value } => {
-                    let balance = new_entry.ledger_balances.get(&balance).unwrap_or(&0);
-                    if !(balance >= value) {
-                        return Err(AppendEntriesError::ConditionFailed(
-                            new_entry.entry_id.clone(),
-                            conditional.clone(),
-                        ));
-                    }
-                }
+    fn revert_entry(
+        &self,
+        ledger_fields: HashMap<LedgerFieldName, i128>,
+        reversal: &Reversal,
+    ) -> EntryWithConditionals {
+        let mut entry: Entry = self.entry.clone().into();
+        entry.status = EntryStatus::Revert(self.entry.sequence);
+        entry.reversal = Some(reversal.clone());
+        entry.ledger_fields = ledger_fields
+            .into_iter()
+            .map(|(key, value)| (key, -value))
+            .collect();
+        entry.into()
+    }
+}
+
+struct Head {
+    ledger_balances: HashMap<LedgerBalanceName, i128>,
+    pending_balances: HashMap<LedgerBalanceName, i128>,
+    sequence: u64,
+    created_at: DateTime<Utc>,
+    closed_until: Option<Period>,
+    hash: Option<EntryHash>,
+}
+
+enum Pk {
+    Entry(AccountId, EntryId),
+    Balance(AccountId),
+    Period(AccountId, Period),
+    Audit(AccountId),
+    Snapshot(AccountId),
+    /// The parent of the account, and a row for each of its children.
+    Hierarchy(AccountId),
+    /// The reconciliation runs of the account, as described in `save_reconciliation`.
+    Reconciliation(AccountId),
+    /// The accrual schedules of every account of the ledger, so they can be listed together.
+    Accruals,
+    /// The fee rules of the ledger, read on every push.
+    Rules,
+    /// The recurring schedules of the ledger, read on every run of the worker.
+    Recurring,
+    /// The velocity limits of the ledger, read on every push.
+    VelocityLimits,
+    /// The amounts counted by the account towards its velocity limits, a row per window bucket.
+    Velocity(AccountId),
+}
+
+impl Display for Pk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pk::Entry(account_id, entry_id) => {
+                write!(f, "ACCOUNT_ID:{}|ENTRY_ID:{}", account_id, entry_id)
+            }
+            Pk::Balance(account_id) => write!(f, "ACCOUNT_ID:{}", account_id),
+            Pk::Period(account_id, period) => {
+                write!(f, "ACCOUNT_ID:{}|PERIOD:{}", account_id, period)
+            }
+            Pk::Audit(account_id) => write!(f, "ACCOUNT_ID:{}|AUDIT", account_id),
+            Pk::Snapshot(account_id) => write!(f, "ACCOUNT_ID:{}|SNAPSHOT", account_id),
+            Pk::Hierarchy(account_id) => write!(f, "ACCOUNT_ID:{}|HIERARCHY", account_id),
+            Pk::Reconciliation(account_id) => {
+                write!(f, "ACCOUNT_ID:{}|RECONCILIATION", account_id)
             }
+            Pk::Accruals => write!(f, "ACCRUALS"),
+            Pk::Rules => write!(f, "RULES"),
+            Pk::Recurring => write!(f, "RECURRING"),
+            Pk::VelocityLimits => write!(f, "VELOCITY_LIMITS"),
+            Pk::Velocity(account_id) => write!(f, "ACCOUNT_ID:{}|VELOCITY", account_id),
         }
-        Ok(())
     }
 }
 
-fn create_transact_item_for_entry(
-    entry: &EntryWithBalance,
-    is_head: bool,
+impl Pk {
+    fn key(self, key_prefix: &str) -> AttributeValue {
+        AttributeValue::S(format!("{}{}", key_prefix, self))
+    }
+
+    fn from_key(value: &AttributeValue, key_prefix: &str) -> Result<Self> {
+        let value = value
+            .as_s()
+            .map_err(|_| anyhow!("Expect PK to be a string"))?;
+        let Some(value) = value.strip_prefix(key_prefix) else {
+            bail!("Expected {} prefix", key_prefix)
+        };
+        if value == "ACCRUALS" {
+            return Ok(Pk::Accruals);
+        }
+        if value == "RULES" {
+            return Ok(Pk::Rules);
+        }
+        if value == "RECURRING" {
+            return Ok(Pk::Recurring);
+        }
+        if value == "VELOCITY_LIMITS" {
+            return Ok(Pk::VelocityLimits);
+        }
+        if let Some((account, entry)) = value.split_once('|') {
+            let Some(account_id) = account.strip_prefix("ACCOUNT_ID:") else {
+                bail!("Expected ACCOUNT_ID: prefix")
+            };
+            let account_id = AccountId::new(Uuid::from_str(account_id)?);
+            if let Some(period) = entry.strip_prefix("PERIOD:") {
+                return Ok(Pk::Period(account_id, period.parse()?));
+            }
+            if entry == "AUDIT" {
+                return Ok(Pk::Audit(account_id));
+            }
+            if entry == "SNAPSHOT" {
+                return Ok(Pk::Snapshot(account_id));
+            }
+            if entry == "HIERARCHY" {
+                return Ok(Pk::Hierarchy(account_id));
+            }
+            if entry == "RECONCILIATION" {
+                return Ok(Pk::Reconciliation(account_id));
+            }
+            if entry == "VELOCITY" {
+                return Ok(Pk::Velocity(account_id));
+            }
+            let Some(entry_id) = entry.strip_prefix("ENTRY_ID:") else {
+                bail!("Expected ENTRY_ID: prefix")
+            };
+            return Ok(Pk::Entry(
+                account_id,
+                EntryId::new_unchecked(entry_id.into()),
+            ));
+        }
+        let Some(account_id) = value.strip_prefix("ACCOUNT_ID:") else {
+            bail!("Expected ACCOUNT_ID: prefix")
+        };
+        Ok(Pk::Balance(AccountId::new(Uuid::from_str(account_id)?)))
+    }
+}
+
+enum Sk {
+    CurrentEntry,
+    History(u64),
+    /// Audit records sorted by `created_at` and `sequence`.
+    Audit(String),
+    /// Snapshots sorted by the `created_at` and `sequence` of their entry.
+    Snapshot(String),
+    /// A child of the account in the hierarchy.
+    Child(AccountId),
+    /// A reconciliation run.
+    Run(String),
+    /// A line of the result of a reconciliation run, after the run.
+    RunItem(String, usize),
+    /// The accrual schedule of a field of an account.
+    AccrualSchedule(AccountId, LedgerFieldName),
+    /// A fee rule.
+    Rule(FeeRuleId),
+    /// A recurring schedule.
+    RecurringSchedule(RecurringScheduleId),
+    /// A velocity limit of an account, or a default one without account. The defaults sort
+    /// first.
+    VelocityLimit(Option<AccountId>, VelocityLimitId),
+    /// The amount counted towards a velocity limit in a bucket of its window.
+    VelocityBucket(VelocityLimitId, String),
+}
+
+impl From<Sk> for AttributeValue {
+    fn from(value: Sk) -> Self {
+        match value {
+            Sk::CurrentEntry => AttributeValue::S("|~".into()),
+            Sk::History(sequence) => AttributeValue::S(format!("|HISTORY:{}", sequence)),
+            Sk::Audit(created_at_and_sequence) => {
+                AttributeValue::S(format!("|AUDIT:{}", created_at_and_sequence))
+            }
+            Sk::Snapshot(created_at_and_sequence) => {
+                AttributeValue::S(format!("|SNAPSHOT:{}", created_at_and_sequence))
+            }
+            Sk::Child(account_id) => AttributeValue::S(format!("|CHILD:{}", account_id)),
+            Sk::Run(run_id) => AttributeValue::S(format!("|RUN:{}", run_id)),
+            Sk::RunItem(run_id, index) => {
+                AttributeValue::S(format!("|RUN:{}|ITEM:{:010}", run_id, index))
+            }
+            Sk::AccrualSchedule(account_id, ledger_field) => AttributeValue::S(format!(
+                "|SCHEDULE:{}|{}",
+                account_id,
+                String::from(ledger_field)
+            )),
+            Sk::Rule(rule_id) => AttributeValue::S(format!("|RULE:{}", rule_id)),
+            Sk::RecurringSchedule(schedule_id) => {
+                AttributeValue::S(format!("|RECURRING:{}", schedule_id))
+            }
+            Sk::VelocityLimit(None, limit_id) => {
+                AttributeValue::S(format!("|DEFAULT_LIMIT:{}", limit_id))
+            }
+            Sk::VelocityLimit(Some(account_id), limit_id) => {
+                AttributeValue::S(format!("|LIMIT:{}|{}", account_id, limit_id))
+            }
+            Sk::VelocityBucket(limit_id, bucket) => {
+                AttributeValue::S(format!("|WINDOW:{}|{}", limit_id, bucket))
+            }
+        }
+    }
+}
+
+impl TryFrom<AttributeValue> for Sk {
+    type Error = anyhow::Error;
+
+    fn try_from(value: AttributeValue) -> Result<Self, Self::Error> {
+        let value = value
+            .as_s()
+            .map_err(|_| anyhow!("Expect PK to be a string"))?;
+        if value == "|~" {
+            return Ok(Sk::CurrentEntry);
+        }
+        if let Some(sequence) = value.strip_prefix("|HISTORY:") {
+            return Ok(Sk::History(sequence.parse()?));
+        }
+        if let Some(created_at_and_sequence) = value.strip_prefix("|AUDIT:") {
+            return Ok(Sk::Audit(created_at_and_sequence.into()));
+        }
+        if let Some(created_at_and_sequence) = value.strip_prefix("|SNAPSHOT:") {
+            return Ok(Sk::Snapshot(created_at_and_sequence.into()));
+        }
+        if let Some(account_id) = value.strip_prefix("|CHILD:") {
+            return Ok(Sk::Child(AccountId::new(Uuid::from_str(account_id)?)));
+        }
+        if let Some(run) = value.strip_prefix("|RUN:") {
+            return Ok(match run.split_once("|ITEM:") {
+                Some((run_id, index)) => Sk::RunItem(run_id.into(), index.parse()?),
+                None => Sk::Run(run.into()),
+            });
+        }
+        if let Some(schedule) = value.strip_prefix("|SCHEDULE:") {
+            let (account_id, ledger_field) = schedule
+                .split_once('|')
+                .ok_or(anyhow!("Expected a field in the accrual schedule SK"))?;
+            return Ok(Sk::AccrualSchedule(
+                AccountId::new(Uuid::from_str(account_id)?),
+                LedgerFieldName::new(ledger_field.into())?,
+            ));
+        }
+        if let Some(rule_id) = value.strip_prefix("|RULE:") {
+            return Ok(Sk::Rule(FeeRuleId::new(rule_id.into())?));
+        }
+        if let Some(schedule_id) = value.strip_prefix("|RECURRING:") {
+            return Ok(Sk::RecurringSchedule(RecurringScheduleId::new(
+                schedule_id.into(),
+            )?));
+        }
+        if let Some(limit_id) = value.strip_prefix("|DEFAULT_LIMIT:") {
+            return Ok(Sk::VelocityLimit(
+                None,
+                VelocityLimitId::new(limit_id.into())?,
+            ));
+        }
+        if let Some(limit) = value.strip_prefix("|LIMIT:") {
+            let (account_id, limit_id) = limit
+                .split_once('|')
+                .ok_or(anyhow!("Expected a limit id in the velocity limit SK"))?;
+            return Ok(Sk::VelocityLimit(
+                Some(AccountId::new(Uuid::from_str(account_id)?)),
+                VelocityLimitId::new(limit_id.into())?,
+            ));
+        }
+        if let Some(window) = value.strip_prefix("|WINDOW:") {
+            let (limit_id, bucket) = window
+                .split_once('|')
+                .ok_or(anyhow!("Expected a bucket in the velocity window SK"))?;
+            return Ok(Sk::VelocityBucket(
+                VelocityLimitId::new(limit_id.into())?,
+                bucket.into(),
+            ));
+        }
+        bail!("Unexpectes SK");
+    }
+}
+
+fn format_occurrence(occurrence: &DateTime<Utc>) -> String {
+    occurrence.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn create_transact_item_for_audit(
+    record: &AuditRecord,
+    table_name: &str,
+    key_prefix: &str,
 ) -> Result<TransactWriteItem> {
-    let (pk, sk) = match (is_head, &entry.status) {
-        (true, _) => (Pk::Balance(entry.account_id.clone()), Sk::CurrentEntry),
-        (false, EntryStatus::Reverted(_)) => (
-            Pk::Entry(entry.account_id.clone(), entry.entry_id.clone()),
-            Sk::History(entry.sequence),
-        ),
-        (false, EntryStatus::Revert(_)) => (
-            Pk::Entry(entry.account_id.clone(), entry.entry_id.clone()),
-            Sk::History(entry.sequence),
-        ),
-        (false, EntryStatus::Applied) => (
-            Pk::Entry(entry.account_id.clone(), entry.entry_id.clone()),
-            Sk::CurrentEntry,
-        ),
-    };
+    let created_at_and_sequence =
+        format_created_at_and_sequence(&record.created_at, record.sequence);
+    Ok(TransactWriteItem::builder()
+        .put(
+            Put::builder()
+                .table_name(table_name)
+                .item("pk", Pk::Audit(record.account_id.clone()).key(key_prefix))
+                .item("sk", Sk::Audit(created_at_and_sequence.clone()).into())
+                .item(
+                    "account_id",
+                    AttributeValue::S(record.account_id.to_string()),
+                )
+                .item("actor", AttributeValue::S(record.actor.to_string()))
+                .item(
+                    "audit_action",
+                    AttributeValue::S(serde_json::to_string(&record.action)?),
+                )
+                .item(
+                    "entry_ids",
+                    AttributeValue::L(
+                        record
+                            .entry_ids
+                            .iter()
+                            .map(|entry_id| AttributeValue::S(entry_id.to_string()))
+                            .collect(),
+                    ),
+                )
+                .item("sequence", AttributeValue::N(record.sequence.to_string()))
+                .item(
+                    "account_id_and_date",
+                    AttributeValue::S(format!(
+                        "{}ACTOR:{}|{}",
+                        key_prefix,
+                        record.actor,
+                        record.created_at.date_naive()
+                    )),
+                )
+                .item("created_at", AttributeValue::S(created_at_and_sequence))
+                .condition_expression("attribute_not_exists(pk)")
+                .build()?,
+        )
+        .build())
+}
+
+/// Snapshot rows have no `account_id_and_date`, so they are left out of the GSI.
+fn create_transact_item_for_snapshot(
+    snapshot: &Snapshot,
+    table_name: &str,
+    key_prefix: &str,
+) -> Result<TransactWriteItem> {
+    let created_at_and_sequence =
+        format_created_at_and_sequence(&snapshot.created_at, snapshot.sequence);
     let mut put_builder = Put::builder()
-        .table_name("a_ledger")
-        .item("pk", pk.into())
-        .item("sk", sk.into())
+        .table_name(table_name)
+        .item(
+            "pk",
+            Pk::Snapshot(snapshot.account_id.clone()).key(key_prefix),
+        )
+        .item("sk", Sk::Snapshot(created_at_and_sequence.clone()).into())
+        .item("entry_id", AttributeValue::S(snapshot.entry_id.to_string()))
+        .item("sequence", AttributeValue::N(snapshot.sequence.to_string()))
         .item(
             "ledger_balances",
             AttributeValue::M(
-                entry
+                snapshot
                     .ledger_balances
                     .clone()
                     .into_iter()
@@ -713,140 +4276,69 @@ fn create_transact_item_for_entry(
                     .collect(),
             ),
         )
-        .item(
-            "ledger_fields",
+        .item("created_at", AttributeValue::S(created_at_and_sequence));
+    if !snapshot.pending_balances.is_empty() {
+        put_builder = put_builder.item(
+            "pending_balances",
             AttributeValue::M(
-                entry
-                    .ledger_fields
+                snapshot
+                    .pending_balances
                     .clone()
                     .into_iter()
                     .map(|(k, v)| (k.into(), AttributeValue::N(v.to_string())))
                     .collect(),
             ),
-        )
-        .item(
-            "additional_fields",
-            AttributeValue::S(serde_json::to_string(&entry.additional_fields)?),
-        )
-        .item(
-            "account_id_and_date",
-            if is_head {
-                AttributeValue::S("head".into())
-            } else {
-                AttributeValue::S(format!(
-                    "{}|{}",
-                    entry.account_id,
-                    entry.created_at.date_naive()
-                ))
-            },
-        )
-        .item(
-            "entry_status",
-            AttributeValue::S(serde_json::to_string(&entry.status)?),
-        )
-        .item("sequence", AttributeValue::N(entry.sequence.to_string()))
-        .item(
-            "created_at",
-            AttributeValue::S(format_created_at_and_sequence(
-                &entry.created_at,
-                entry.sequence,
-            )),
-        )
-        .condition_expression("attribute_not_exists(pk)")
-        .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld);
-    if is_head {
-        put_builder = put_builder.item("entry_id", AttributeValue::S(entry.entry_id.to_string()));
+        );
+    }
+    if let Some(hash) = &snapshot.hash {
+        put_builder = put_builder.item("hash", AttributeValue::S(hash.to_string()));
     }
     Ok(TransactWriteItem::builder()
-        .put(put_builder.build()?)
+        .put(
+            put_builder
+                .condition_expression("attribute_not_exists(pk)")
+                .build()?,
+        )
         .build())
-}
-
-fn entry_with_balance_from_item(
-    item: &HashMap<String, AttributeValue>,
-) -> Result<EntryWithBalance, GetBalanceError> {
-    let pk = Pk::try_from(
-        item.get("pk")
-            .ok_or(GetBalanceError::MissingField("pk".into()))?
-            .clone(),
-    )?;
-    let (account_id, entry_id) = match pk {
-        Pk::Entry(account_id, entry_id) => (account_id, entry_id),
-        Pk::Balance(account_id) => (
-            account_id,
-            EntryId::new_unchecked(
-                item.get("entry_id")
-                    .ok_or(GetBalanceError::MissingField("entry_id".into()))?
-                    .as_s()
-                    .map_err(|_| GetBalanceError::ErrorReadingField("entry_id".into()))?
-                    .clone(),
-            ),
-        ),
-    };
-
-    let mut created_at = item
-        .get("created_at")
-        .ok_or(GetBalanceError::MissingField("created_at".into()))?
-        .as_s()
-        .map_err(|_| GetBalanceError::ErrorReadingField("created_at".into()))?
-        .as_str();
-    if let Some((separated_created_at, _sequence)) = created_at.split_once('|') {
-        created_at = separated_created_at;
-    }
-    Ok(EntryWithBalance {
-        account_id,
-        entry_id,
-        ledger_balances: item
-            .get("ledger_balances")
-            .ok_or(GetBalanceError::MissingField("ledger_balances".into()))?
-            .as_m()
-            .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?
-            .iter()
-            .map(|(k, v)| {
-                Ok((
-                    LedgerBalanceName::new(k.clone()).map_err(|_| {
-                        GetBalanceError::ErrorReadingField("ledger_balances".into())
-                    })?,
-                    v.as_n()
-                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_balances".into()))?
-                        .parse::<i128>()
-                        .map_err(|_| {
-                            GetBalanceError::ErrorReadingField("ledger_balances".into())
-                        })?,
-                ))
-            })
-            .collect::<Result<HashMap<LedgerBalanceName, i128>>>()?,
-        ledger_fields: item
-            .get("ledger_fields")
-            .ok_or(GetBalanceError::MissingField("ledger_fields".into()))?
-            .as_m()
-            .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?
+}
+
+fn audit_record_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<AuditRecord, GetBalanceError> {
+    let string_field = |field: &str| -> Result<&String, GetBalanceError> {
+        item.get(field)
+            .ok_or(GetBalanceError::MissingField(field.into()))?
+            .as_s()
+            .map_err(|_| GetBalanceError::ErrorReadingField(field.into()))
+    };
+    let created_at = string_field("created_at")?
+        .split_once('|')
+        .map(|(created_at, _sequence)| created_at)
+        .ok_or(GetBalanceError::ErrorReadingField("created_at".into()))?;
+    Ok(AuditRecord {
+        account_id: AccountId::new(
+            Uuid::from_str(string_field("account_id")?)
+                .map_err(|_| GetBalanceError::ErrorReadingField("account_id".into()))?,
+        ),
+        actor: Actor::new(string_field("actor")?.clone())
+            .map_err(|_| GetBalanceError::ErrorReadingField("actor".into()))?,
+        action: serde_json::from_str(string_field("audit_action")?)
+            .map_err(|_| GetBalanceError::ErrorReadingField("audit_action".into()))?,
+        entry_ids: item
+            .get("entry_ids")
+            .ok_or(GetBalanceError::MissingField("entry_ids".into()))?
+            .as_l()
+            .map_err(|_| GetBalanceError::ErrorReadingField("entry_ids".into()))?
             .iter()
-            .map(|(k, v)| {
-                Ok((
-                    LedgerFieldName::new(k.clone())
-                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?,
-                    v.as_n()
-                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?
-                        .parse::<i128>()
-                        .map_err(|_| GetBalanceError::ErrorReadingField("ledger_fields".into()))?,
+            .map(|entry_id| {
+                Ok(EntryId::new_unchecked(
+                    entry_id
+                        .as_s()
+                        .map_err(|_| GetBalanceError::ErrorReadingField("entry_ids".into()))?
+                        .clone(),
                 ))
             })
-            .collect::<Result<HashMap<LedgerFieldName, i128>>>()?,
-        additional_fields: serde_json::from_str(
-            item.get("additional_fields")
-                .ok_or(GetBalanceError::MissingField("additional_fields".into()))?
-                .as_s()
-                .map_err(|_| GetBalanceError::ErrorReadingField("additional_fields".into()))?,
-        )
-        .map_err(|_| GetBalanceError::ErrorReadingField("additional_fields".into()))?,
-        status: serde_json::from_str(
-            item.get("entry_status")
-                .ok_or(GetBalanceError::MissingField("entry_status".into()))?
-                .as_s()
-                .map_err(|_| GetBalanceError::ErrorReadingField("entry_status".into()))?,
-        )
-        .map_err(|_| GetBalanceError::ErrorReadingField("entry_status".into()))?,
+            .collect::<Result<Vec<EntryId>, GetBalanceError>>()?,
         sequence: item
             .get("sequence")
             .ok_or(GetBalanceError::MissingField("sequence".into()))?
@@ -859,77 +4351,75 @@ fn entry_with_balance_from_item(
     })
 }
 
-enum Pk {
-    Entry(AccountId, EntryId),
-    Balance(AccountId),
-}
-
-impl From<Pk> for AttributeValue {
-    fn from(value: Pk) -> Self {
-        match value {
-            Pk::Entry(account_id, entry_id) => {
-                AttributeValue::S(format!("ACCOUNT_ID:{}|ENTRY_ID:{}", account_id, entry_id))
-            }
-            Pk::Balance(account_id) => AttributeValue::S(format!("ACCOUNT_ID:{}", account_id)),
-        }
-    }
+fn format_created_at_and_sequence(created_at: &DateTime<Utc>, sequence: u64) -> String {
+    format!("{}|{:0>20}", created_at, sequence)
 }
 
-impl TryFrom<AttributeValue> for Pk {
-    type Error = anyhow::Error;
-
-    fn try_from(value: AttributeValue) -> Result<Self, Self::Error> {
-        let value = value
-            .as_s()
-            .map_err(|_| anyhow!("Expect PK to be a string"))?;
-        if let Some((account, entry)) = value.split_once('|') {
-            let Some(account_id) = account.strip_prefix("ACCOUNT_ID:") else {
-                bail!("Expected ACCOUNT_ID: prefix")
-            };
-            let Some(entry_id) = entry.strip_prefix("ENTRY_ID:") else {
-                bail!("Expected ENTRY_ID: prefix")
-            };
-            return Ok(Pk::Entry(
-                AccountId::new(Uuid::from_str(account_id)?),
-                EntryId::new_unchecked(entry_id.into()),
-            ));
-        }
-        let Some(account_id) = value.strip_prefix("ACCOUNT_ID:") else {
-            bail!("Expected ACCOUNT_ID: prefix")
-        };
-        Ok(Pk::Balance(AccountId::new(Uuid::from_str(account_id)?)))
-    }
+/// The shard of the account in the accounts GSI, from the first hex digit of its id.
+fn accounts_shard_index(account_id: &AccountId) -> u32 {
+    account_id
+        .to_string()
+        .chars()
+        .next()
+        .and_then(|digit| digit.to_digit(16))
+        .unwrap_or_default()
 }
 
-enum Sk {
-    CurrentEntry,
-    History(u64),
+fn accounts_shard(key_prefix: &str, shard: u32) -> AttributeValue {
+    AttributeValue::S(format!("{}ACCOUNTS|{:x}", key_prefix, shard))
 }
 
-impl From<Sk> for AttributeValue {
-    fn from(value: Sk) -> Self {
-        match value {
-            Sk::CurrentEntry => AttributeValue::S("|~".into()),
-            Sk::History(sequence) => AttributeValue::S(format!("|HISTORY:{}", sequence)),
-        }
+/// Adds the HEADs written before the accounts GSI to it. Only the first entry of an account
+/// puts its HEAD, so the later ones are indexed when written.
+pub(crate) fn backfill_accounts_index(
+    item: &HashMap<String, AttributeValue>,
+) -> Option<HashMap<String, AttributeValue>> {
+    if item.get("sk")?.as_s().ok()? != "|~" {
+        return None;
     }
+    let pk = item.get("pk")?.as_s().ok()?;
+    let (key_prefix, pk) = match pk.strip_prefix("LEDGER:") {
+        Some(ledger_pk) => {
+            let (ledger_id, pk) = ledger_pk.split_once('|')?;
+            (format!("LEDGER:{}|", ledger_id), pk)
+        }
+        None => (String::new(), pk.as_str()),
+    };
+    let account_id = AccountId::new(Uuid::from_str(pk.strip_prefix("ACCOUNT_ID:")?).ok()?);
+    Some(HashMap::from([
+        (
+            "account_id".into(),
+            AttributeValue::S(account_id.to_string()),
+        ),
+        (
+            "accounts_shard".into(),
+            accounts_shard(&key_prefix, accounts_shard_index(&account_id)),
+        ),
+    ]))
 }
 
-impl TryFrom<AttributeValue> for Sk {
-    type Error = anyhow::Error;
-
-    fn try_from(value: AttributeValue) -> Result<Self, Self::Error> {
-        let value = value
-            .as_s()
-            .map_err(|_| anyhow!("Expect PK to be a string"))?;
-        if value == "|~" {
-            return Ok(Sk::CurrentEntry);
-        }
-        if let Some(sequence) = value.strip_prefix("|HISTORY:") {
-            return Ok(Sk::History(sequence.parse()?));
-        }
-        bail!("Unexpectes SK");
-    }
+fn account_from_item(item: &HashMap<String, AttributeValue>) -> Result<Account, GetBalanceError> {
+    Ok(Account {
+        account_id: account_id_from_attribute(
+            item.get("account_id")
+                .ok_or(GetBalanceError::MissingField("account_id".into()))?,
+            "account_id",
+        )?,
+        ledger_balances: ledger_balances_from_item(item)?,
+        last_activity_at: created_at_from_item(item)?,
+        tags: match item.get("tags") {
+            Some(tags) => tags
+                .as_ss()
+                .map_err(|_| GetBalanceError::ErrorReadingField("tags".into()))?
+                .iter()
+                .map(|tag| {
+                    AccountTag::new(tag.clone())
+                        .map_err(|_| GetBalanceError::ErrorReadingField("tags".into()))
+                })
+                .collect::<Result<BTreeSet<AccountTag>, GetBalanceError>>()?,
+            None => BTreeSet::new(),
+        },
+    })
 }
 
 #[cfg(test)]
@@ -975,20 +4465,50 @@ pub mod test {
             &self,
             _account_id: &AccountId,
             _entries: &[EntryWithConditionals],
+            _actor: &Actor,
         ) -> Result<Vec<EntryWithBalance>, AppendEntriesError> {
             let mut internal_state = self.internal_state.lock().await;
             internal_state.append_entries_call_count += 1;
             internal_state.append_entries_response.remove(0)
         }
 
+        async fn append_linked_entries(
+            &self,
+            _entries: &[(AccountId, Vec<EntryWithConditionals>)],
+            _actor: &Actor,
+        ) -> Result<Vec<EntryWithBalance>, AppendEntriesError> {
+            todo!()
+        }
+
         async fn revert_entries(
             &self,
             _account_id: &AccountId,
-            _entries: &[EntryId],
+            _reversals: &[EntryReversal],
+            _actor: &Actor,
         ) -> Result<Vec<EntryWithBalance>, RevertEntriesError> {
             todo!()
         }
 
+        async fn amend_entries(
+            &self,
+            _account_id: &AccountId,
+            _entries: &[EntryWithConditionals],
+            _actor: &Actor,
+        ) -> Result<Vec<EntryWithBalance>, AmendEntriesError> {
+            todo!()
+        }
+
+        async fn resolve_pending_entries(
+            &self,
+            _account_id: &AccountId,
+            _entry_ids: &[EntryId],
+            _resolution: PendingResolution,
+            _rules: &[FeeRule],
+            _actor: &Actor,
+        ) -> Result<Vec<EntryWithBalance>, PendingEntriesError> {
+            todo!()
+        }
+
         async fn get_balance(
             &self,
             _account_id: &AccountId,
@@ -996,6 +4516,13 @@ pub mod test {
             todo!()
         }
 
+        async fn get_balances(
+            &self,
+            _account_ids: &[AccountId],
+        ) -> Result<Vec<EntryWithBalance>, GetBalanceError> {
+            todo!()
+        }
+
         async fn get_entry(
             &self,
             _account_id: &AccountId,
@@ -1017,9 +4544,237 @@ pub mod test {
         ) -> Result<(Vec<EntryWithBalance>, Option<Cursor>), GetBalanceError> {
             todo!()
         }
-    }
-}
 
-fn format_created_at_and_sequence(created_at: &DateTime<Utc>, sequence: u64) -> String {
-    format!("{}|{:0>20}", created_at, sequence)
+        async fn scan_entries(
+            &self,
+            _start_date: &DateTime<Utc>,
+            _end_date: &DateTime<Utc>,
+            _segment: i32,
+            _total_segments: i32,
+            _start_after: Option<ScanCursor>,
+        ) -> Result<(Vec<EntryWithBalance>, Option<ScanCursor>), GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_account_rows(
+            &self,
+            _account_id: &AccountId,
+            _total_segments: i32,
+        ) -> Result<AccountRows, GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_snapshot(
+            &self,
+            _account_id: &AccountId,
+            _as_of: &DateTime<Utc>,
+        ) -> Result<Option<Snapshot>, GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_account_rows_since(
+            &self,
+            _snapshot: &Snapshot,
+        ) -> Result<AccountRows, GetBalanceError> {
+            todo!()
+        }
+
+        async fn close_period(
+            &self,
+            _closing: &PeriodClosing,
+            _head_sequence: u64,
+            _actor: &Actor,
+        ) -> Result<(), ClosePeriodError> {
+            todo!()
+        }
+
+        async fn get_period_closing(
+            &self,
+            _account_id: &AccountId,
+            _period: &Period,
+        ) -> Result<PeriodClosing, GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_audit_records(
+            &self,
+            _query: &AuditQuery,
+            _start_after: Option<(&AccountId, DateTime<Utc>, u64)>,
+            _limit: u8,
+        ) -> Result<(Vec<AuditRecord>, Option<AuditCursor>), GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_parent(
+            &self,
+            _account_id: &AccountId,
+        ) -> Result<Option<AccountId>, GetBalanceError> {
+            todo!()
+        }
+
+        async fn get_children(
+            &self,
+            _account_id: &AccountId,
+        ) -> Result<Vec<AccountId>, GetBalanceError> {
+            todo!()
+        }
+
+        async fn set_parent(
+            &self,
+            _account_id: &AccountId,
+            _parent: Option<&AccountId>,
+            _previous_parent: Option<&AccountId>,
+            _actor: &Actor,
+        ) -> Result<(), SetParentError> {
+            todo!()
+        }
+
+        async fn list_accounts(
+            &self,
+            _query: &AccountQuery,
+            _start_after: Option<&AccountId>,
+            _limit: u8,
+        ) -> Result<(Vec<Account>, Option<AccountCursor>), GetBalanceError> {
+            todo!()
+        }
+
+        async fn set_account_tags(
+            &self,
+            _account_id: &AccountId,
+            _tags: &BTreeSet<AccountTag>,
+        ) -> Result<(), GetBalanceError> {
+            todo!()
+        }
+
+        async fn save_reconciliation(
+            &self,
+            _reconciliation: &Reconciliation,
+        ) -> Result<(), ReconciliationError> {
+            todo!()
+        }
+
+        async fn get_reconciliation(
+            &self,
+            _account_id: &AccountId,
+            _run_id: &str,
+        ) -> Result<Reconciliation, ReconciliationError> {
+            todo!()
+        }
+
+        async fn save_accrual_schedule(
+            &self,
+            _schedule: &AccrualSchedule,
+        ) -> Result<(), AccrualError> {
+            todo!()
+        }
+
+        async fn get_accrual_schedules(
+            &self,
+            _account_id: Option<&AccountId>,
+        ) -> Result<Vec<AccrualSchedule>, AccrualError> {
+            todo!()
+        }
+
+        async fn delete_accrual_schedule(
+            &self,
+            _account_id: &AccountId,
+            _ledger_field: &LedgerFieldName,
+        ) -> Result<(), AccrualError> {
+            todo!()
+        }
+
+        async fn set_accrual_posted_until(
+            &self,
+            _account_id: &AccountId,
+            _ledger_field: &LedgerFieldName,
+            _period: &Period,
+        ) -> Result<(), AccrualError> {
+            todo!()
+        }
+
+        async fn save_fee_rule(&self, _rule: &FeeRule) -> Result<(), FeeRuleError> {
+            todo!()
+        }
+
+        /// No rules, so the pushed entries are appended as they are.
+        async fn get_fee_rules(&self) -> Result<Vec<FeeRule>, FeeRuleError> {
+            Ok(Vec::new())
+        }
+
+        async fn delete_fee_rule(&self, _rule_id: &FeeRuleId) -> Result<(), FeeRuleError> {
+            todo!()
+        }
+
+        async fn save_recurring_schedule(
+            &self,
+            _schedule: &RecurringSchedule,
+        ) -> Result<(), RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn get_recurring_schedules(
+            &self,
+        ) -> Result<Vec<RecurringSchedule>, RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn pause_recurring_schedule(
+            &self,
+            _schedule_id: &RecurringScheduleId,
+        ) -> Result<(), RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn resume_recurring_schedule(
+            &self,
+            _schedule_id: &RecurringScheduleId,
+            _resumed_at: &DateTime<Utc>,
+        ) -> Result<(), RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn delete_recurring_schedule(
+            &self,
+            _schedule_id: &RecurringScheduleId,
+        ) -> Result<(), RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn set_recurring_posted_until(
+            &self,
+            _schedule_id: &RecurringScheduleId,
+            _occurrence: &DateTime<Utc>,
+            _occurrences: u32,
+        ) -> Result<(), RecurringScheduleError> {
+            todo!()
+        }
+
+        async fn save_velocity_limit(
+            &self,
+            _limit: &VelocityLimit,
+        ) -> Result<(), VelocityLimitError> {
+            todo!()
+        }
+
+        async fn get_velocity_limits(&self) -> Result<Vec<VelocityLimit>, VelocityLimitError> {
+            todo!()
+        }
+
+        async fn delete_velocity_limit(
+            &self,
+            _account_id: Option<&AccountId>,
+            _limit_id: &VelocityLimitId,
+        ) -> Result<(), VelocityLimitError> {
+            todo!()
+        }
+
+        async fn get_velocity_used_amount(
+            &self,
+            _account_id: &AccountId,
+            _limit: &VelocityLimit,
+            _date_time: &DateTime<Utc>,
+        ) -> Result<i128, VelocityLimitError> {
+            todo!()
+        }
+    }
 }
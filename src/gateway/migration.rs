@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use aws_sdk_dynamodb::{
+    operation::transact_write_items::TransactWriteItemsError,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, CreateGlobalSecondaryIndexAction,
+        GlobalSecondaryIndexUpdate, KeySchemaElement, KeyType, Projection, ProjectionType,
+        ProvisionedThroughput, Put, ScalarAttributeType, TimeToLiveSpecification, TimeToLiveStatus,
+        TransactWriteItem,
+    },
+    Client,
+};
+use chrono::{DateTime, Utc};
+use tokio::task::JoinSet;
+
+use crate::gateway::ledger_entry_repository::backfill_accounts_index;
+use crate::gateway::table_config::TableConfig;
+use crate::gateway::{wait_table_active, TTL_ATTRIBUTE};
+use crate::utils::utc_now;
+
+/// Every migration of the table, in order. `db-create` records all of them as applied, so a
+/// migration must bring an existing table to the same schema that `create_database` creates.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "created_at_index",
+        step: MigrationStep::AddIndex {
+            name_suffix: "created_at_idx",
+            hash_key: "account_id_and_date",
+            range_key: Some("created_at"),
+        },
+    },
+    Migration {
+        version: 2,
+        name: "accounts_index",
+        step: MigrationStep::AddIndex {
+            name_suffix: "accounts_idx",
+            hash_key: "accounts_shard",
+            range_key: Some("account_id"),
+        },
+    },
+    Migration {
+        version: 3,
+        name: "backfill_accounts_index",
+        step: MigrationStep::Backfill(backfill_accounts_index),
+    },
+    Migration {
+        version: 4,
+        name: "velocity_ttl",
+        step: MigrationStep::EnableTtl {
+            attribute_name: TTL_ATTRIBUTE,
+        },
+    },
+];
+
+/// Items of a backfill that get updated, and the attributes that are set on them.
+pub type BackfillFn =
+    fn(&HashMap<String, AttributeValue>) -> Option<HashMap<String, AttributeValue>>;
+
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub version: u64,
+    pub name: &'static str,
+    pub step: MigrationStep,
+}
+
+#[derive(Clone, Copy)]
+pub enum MigrationStep {
+    /// Adds a GSI named `{table_name}_{name_suffix}` and waits until it is active. Skipped if
+    /// the index already exists.
+    AddIndex {
+        name_suffix: &'static str,
+        hash_key: &'static str,
+        range_key: Option<&'static str>,
+    },
+    /// Scans the whole table in parallel segments, setting the attributes returned for each item.
+    Backfill(BackfillFn),
+    /// Makes DynamoDB delete the items once the epoch seconds in the attribute are past. Skipped
+    /// if the TTL is already enabled on the attribute.
+    EnableTtl { attribute_name: &'static str },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MigrationStatus {
+    /// Version of the last migration applied, 0 if none was.
+    pub version: u64,
+    pub applied: Vec<AppliedMigration>,
+    /// Versions and names of the migrations not applied yet.
+    pub pending: Vec<(u64, &'static str)>,
+}
+
+/// The migrations are recorded in the table, under the `META:MIGRATIONS` PK. The current
+/// version is in the `|~` SK and each applied migration in a `|VERSION:{version}` SK.
+fn meta_pk() -> AttributeValue {
+    AttributeValue::S("META:MIGRATIONS".into())
+}
+
+fn version_sk(version: u64) -> AttributeValue {
+    AttributeValue::S(format!("|VERSION:{:020}", version))
+}
+
+pub async fn migration_status(
+    client: &Client,
+    config: &TableConfig,
+    migrations: &'static [Migration],
+) -> Result<MigrationStatus> {
+    let mut applied = Vec::new();
+    let mut exclusive_start_key = None;
+    loop {
+        let output = client
+            .query()
+            .table_name(&config.name)
+            .key_condition_expression("pk = :pk AND begins_with(sk, :version)")
+            .expression_attribute_values(":pk", meta_pk())
+            .expression_attribute_values(":version", AttributeValue::S("|VERSION:".into()))
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
+        for item in output.items() {
+            applied.push(applied_migration_from_item(item)?);
+        }
+        exclusive_start_key = output.last_evaluated_key().cloned();
+        if exclusive_start_key.is_none() {
+            break;
+        }
+    }
+    let version = applied
+        .last()
+        .map(|migration| migration.version)
+        .unwrap_or(0);
+    Ok(MigrationStatus {
+        version,
+        applied,
+        pending: migrations
+            .iter()
+            .filter(|migration| migration.version > version)
+            .map(|migration| (migration.version, migration.name))
+            .collect(),
+    })
+}
+
+/// Applies the pending migrations in order, recording each one as soon as it is done. It
+/// returns the versions applied.
+pub async fn migrate(
+    client: &Client,
+    config: &TableConfig,
+    migrations: &'static [Migration],
+    segments: i32,
+) -> Result<Vec<u64>> {
+    let status = migration_status(client, config, migrations).await?;
+    let mut version = status.version;
+    let mut applied = Vec::new();
+    for migration in migrations
+        .iter()
+        .filter(|migration| migration.version > status.version)
+    {
+        tracing::info!(
+            "Applying migration {} {}",
+            migration.version,
+            migration.name
+        );
+        match &migration.step {
+            MigrationStep::AddIndex {
+                name_suffix,
+                hash_key,
+                range_key,
+            } => add_index(client, config, name_suffix, hash_key, *range_key).await?,
+            MigrationStep::Backfill(backfill) => {
+                backfill_items(client, config, *backfill, segments).await?
+            }
+            MigrationStep::EnableTtl { attribute_name } => {
+                enable_ttl(client, config, attribute_name).await?
+            }
+        }
+        record_migration(client, config, version, migration).await?;
+        version = migration.version;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Records every migration as applied, used when the table is created with the latest schema.
+pub async fn record_all_migrations(
+    client: &Client,
+    config: &TableConfig,
+    migrations: &'static [Migration],
+) -> Result<()> {
+    let mut version = 0;
+    for migration in migrations {
+        record_migration(client, config, version, migration).await?;
+        version = migration.version;
+    }
+    Ok(())
+}
+
+/// Moves the current version from `previous_version` to the migration version. It fails if
+/// another process recorded a migration in the meantime.
+async fn record_migration(
+    client: &Client,
+    config: &TableConfig,
+    previous_version: u64,
+    migration: &Migration,
+) -> Result<()> {
+    let applied_at = AttributeValue::S(utc_now().to_string());
+    let result = client
+        .transact_write_items()
+        .transact_items(
+            TransactWriteItem::builder()
+                .put(
+                    Put::builder()
+                        .table_name(&config.name)
+                        .item("pk", meta_pk())
+                        .item("sk", AttributeValue::S("|~".into()))
+                        .item("version", AttributeValue::N(migration.version.to_string()))
+                        .item("applied_at", applied_at.clone())
+                        .condition_expression("attribute_not_exists(pk) OR #version = :previous")
+                        .expression_attribute_names("#version", "version")
+                        .expression_attribute_values(
+                            ":previous",
+                            AttributeValue::N(previous_version.to_string()),
+                        )
+                        .build()?,
+                )
+                .build(),
+        )
+        .transact_items(
+            TransactWriteItem::builder()
+                .put(
+                    Put::builder()
+                        .table_name(&config.name)
+                        .item("pk", meta_pk())
+                        .item("sk", version_sk(migration.version))
+                        .item("version", AttributeValue::N(migration.version.to_string()))
+                        .item("name", AttributeValue::S(migration.name.into()))
+                        .item("applied_at", applied_at)
+                        .build()?,
+                )
+                .build(),
+        )
+        .send()
+        .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            if let Some(TransactWriteItemsError::TransactionCanceledException(_)) =
+                error.as_service_error()
+            {
+                bail!(
+                    "Migration {} was not recorded, the table is not at version {}",
+                    migration.version,
+                    previous_version
+                );
+            }
+            Err(error.into())
+        }
+    }
+}
+
+async fn add_index(
+    client: &Client,
+    config: &TableConfig,
+    name_suffix: &str,
+    hash_key: &str,
+    range_key: Option<&str>,
+) -> Result<()> {
+    let index_name = format!("{}_{}", config.name, name_suffix);
+    let table = client
+        .describe_table()
+        .table_name(&config.name)
+        .send()
+        .await?
+        .table
+        .ok_or(anyhow!("Table {} not found", config.name))?;
+    if table
+        .global_secondary_indexes()
+        .iter()
+        .any(|index| index.index_name() == Some(index_name.as_str()))
+    {
+        tracing::info!("Index {} already exists", index_name);
+        return Ok(());
+    }
+
+    let mut key_schema = vec![KeySchemaElement::builder()
+        .key_type(KeyType::Hash)
+        .attribute_name(hash_key)
+        .build()?];
+    if let Some(range_key) = range_key {
+        key_schema.push(
+            KeySchemaElement::builder()
+                .key_type(KeyType::Range)
+                .attribute_name(range_key)
+                .build()?,
+        );
+    }
+    let attribute_definitions = key_schema
+        .iter()
+        .map(|key| {
+            AttributeDefinition::builder()
+                .attribute_name(key.attribute_name())
+                .attribute_type(ScalarAttributeType::S)
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // The billing mode of the table decides if the index needs a throughput.
+    let is_provisioned = table
+        .billing_mode_summary()
+        .and_then(|summary| summary.billing_mode())
+        .is_none_or(|billing_mode| billing_mode == &BillingMode::Provisioned);
+    let throughput = is_provisioned
+        .then(|| {
+            ProvisionedThroughput::builder()
+                .read_capacity_units(config.index_read_capacity)
+                .write_capacity_units(config.index_write_capacity)
+                .build()
+        })
+        .transpose()?;
+
+    client
+        .update_table()
+        .table_name(&config.name)
+        .set_attribute_definitions(Some(attribute_definitions))
+        .global_secondary_index_updates(
+            GlobalSecondaryIndexUpdate::builder()
+                .create(
+                    CreateGlobalSecondaryIndexAction::builder()
+                        .index_name(&index_name)
+                        .set_key_schema(Some(key_schema))
+                        .projection(
+                            Projection::builder()
+                                .projection_type(ProjectionType::All)
+                                .build(),
+                        )
+                        .set_provisioned_throughput(throughput)
+                        .build()?,
+                )
+                .build(),
+        )
+        .send()
+        .await?;
+    wait_table_active(client, &config.name).await?;
+    tracing::info!("Index {} created", index_name);
+    Ok(())
+}
+
+pub(crate) async fn enable_ttl(
+    client: &Client,
+    config: &TableConfig,
+    attribute_name: &str,
+) -> Result<()> {
+    let ttl = client
+        .describe_time_to_live()
+        .table_name(&config.name)
+        .send()
+        .await?
+        .time_to_live_description;
+    if let Some(ttl) = ttl {
+        let is_enabled = matches!(
+            ttl.time_to_live_status(),
+            Some(TimeToLiveStatus::Enabled | TimeToLiveStatus::Enabling)
+        );
+        if is_enabled && ttl.attribute_name() == Some(attribute_name) {
+            tracing::info!("TTL on {} already enabled", attribute_name);
+            return Ok(());
+        }
+    }
+
+    client
+        .update_time_to_live()
+        .table_name(&config.name)
+        .time_to_live_specification(
+            TimeToLiveSpecification::builder()
+                .enabled(true)
+                .attribute_name(attribute_name)
+                .build()?,
+        )
+        .send()
+        .await?;
+    tracing::info!("TTL on {} enabled", attribute_name);
+    Ok(())
+}
+
+async fn backfill_items(
+    client: &Client,
+    config: &TableConfig,
+    backfill: BackfillFn,
+    segments: i32,
+) -> Result<()> {
+    let mut tasks = JoinSet::new();
+    for segment in 0..segments {
+        let client = client.clone();
+        let table_name = config.name.clone();
+        tasks.spawn(async move {
+            backfill_segment(&client, &table_name, backfill, segment, segments).await
+        });
+    }
+    let mut updated = 0;
+    while let Some(result) = tasks.join_next().await {
+        updated += result??;
+    }
+    tracing::info!("{} items backfilled", updated);
+    Ok(())
+}
+
+async fn backfill_segment(
+    client: &Client,
+    table_name: &str,
+    backfill: BackfillFn,
+    segment: i32,
+    total_segments: i32,
+) -> Result<usize> {
+    let mut updated = 0;
+    let mut exclusive_start_key = None;
+    loop {
+        let output = client
+            .scan()
+            .table_name(table_name)
+            .segment(segment)
+            .total_segments(total_segments)
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
+        for item in output.items() {
+            let Some(attributes) = backfill(item).filter(|attributes| !attributes.is_empty())
+            else {
+                continue;
+            };
+            let mut update_item = client
+                .update_item()
+                .table_name(table_name)
+                .set_key(Some(
+                    item.iter()
+                        .filter(|(name, _)| *name == "pk" || *name == "sk")
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .collect(),
+                ))
+                // The item may have been deleted since it was scanned.
+                .condition_expression("attribute_exists(pk)");
+            let mut assignments = Vec::new();
+            for (index, (name, value)) in attributes.into_iter().enumerate() {
+                assignments.push(format!("#attribute_{index} = :value_{index}"));
+                update_item = update_item
+                    .expression_attribute_names(format!("#attribute_{index}"), name)
+                    .expression_attribute_values(format!(":value_{index}"), value);
+            }
+            let result = update_item
+                .update_expression(format!("SET {}", assignments.join(", ")))
+                .send()
+                .await;
+            match result {
+                Ok(_) => updated += 1,
+                Err(error)
+                    if error
+                        .as_service_error()
+                        .is_some_and(|error| error.is_conditional_check_failed_exception()) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        exclusive_start_key = output.last_evaluated_key().cloned();
+        if exclusive_start_key.is_none() {
+            break;
+        }
+    }
+    Ok(updated)
+}
+
+fn applied_migration_from_item(item: &HashMap<String, AttributeValue>) -> Result<AppliedMigration> {
+    let string_field = |field: &str| -> Result<&String> {
+        item.get(field)
+            .ok_or(anyhow!("Missing field {field} in migration"))?
+            .as_s()
+            .map_err(|_| anyhow!("Error reading field {field} in migration"))
+    };
+    Ok(AppliedMigration {
+        version: item
+            .get("version")
+            .ok_or(anyhow!("Missing field version in migration"))?
+            .as_n()
+            .map_err(|_| anyhow!("Error reading field version in migration"))?
+            .parse()?,
+        name: string_field("name")?.clone(),
+        applied_at: string_field("applied_at")?.parse()?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use ulid::Ulid;
+
+    use crate::app::test::set_up_dynamo_db_for_test;
+    use crate::gateway::table_config::BillingMode as ConfigBillingMode;
+    use crate::gateway::{create_database, delete_database};
+
+    use super::*;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        MIGRATIONS[0],
+        MIGRATIONS[1],
+        MIGRATIONS[2],
+        MIGRATIONS[3],
+        Migration {
+            version: 5,
+            name: "test_index",
+            step: MigrationStep::AddIndex {
+                name_suffix: "test_idx",
+                hash_key: "test_key",
+                range_key: None,
+            },
+        },
+        Migration {
+            version: 6,
+            name: "backfill_test_key",
+            step: MigrationStep::Backfill(|item| {
+                let pk = item.get("pk")?.as_s().ok()?;
+                pk.starts_with("ITEM:").then(|| {
+                    HashMap::from([("test_key".into(), AttributeValue::S("backfilled".into()))])
+                })
+            }),
+        },
+    ];
+
+    #[tokio_shared_rt::test(shared)]
+    async fn migrate_should_apply_pending_migrations_once() -> Result<()> {
+        let client = set_up_dynamo_db_for_test().await.client;
+        let config = TableConfig {
+            name: format!("migration_test_{}", Ulid::new()),
+            billing_mode: ConfigBillingMode::OnDemand,
+            ..TableConfig::default()
+        };
+        create_database(&client, &config).await?;
+
+        let status = migration_status(&client, &config, TEST_MIGRATIONS).await?;
+        assert_eq!(status.version, 4);
+        assert_eq!(
+            status.pending,
+            vec![(5, "test_index"), (6, "backfill_test_key")]
+        );
+
+        for index in 0..10 {
+            client
+                .put_item()
+                .table_name(&config.name)
+                .item("pk", AttributeValue::S(format!("ITEM:{index}")))
+                .item("sk", AttributeValue::S("|~".into()))
+                .send()
+                .await?;
+        }
+
+        assert_eq!(
+            migrate(&client, &config, TEST_MIGRATIONS, 3).await?,
+            vec![5, 6]
+        );
+        let status = migration_status(&client, &config, TEST_MIGRATIONS).await?;
+        assert_eq!(status.version, 6);
+        assert_eq!(status.applied.len(), 6);
+        assert!(status.pending.is_empty());
+
+        let backfilled = client
+            .query()
+            .table_name(&config.name)
+            .index_name(format!("{}_test_idx", config.name))
+            .key_condition_expression("test_key = :test_key")
+            .expression_attribute_values(":test_key", AttributeValue::S("backfilled".into()))
+            .send()
+            .await?;
+        assert_eq!(backfilled.items().len(), 10);
+
+        assert!(migrate(&client, &config, TEST_MIGRATIONS, 3)
+            .await?
+            .is_empty());
+
+        delete_database(&client, &config).await?;
+        Ok(())
+    }
+}
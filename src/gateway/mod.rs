@@ -1,25 +1,101 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use aws_sdk_dynamodb::{
     types::{
-        AttributeDefinition, GlobalSecondaryIndex, KeySchemaElement, KeyType, Projection,
-        ProjectionType, ProvisionedThroughput, ScalarAttributeType,
+        AttributeDefinition, BillingMode as DynamoDbBillingMode, GlobalSecondaryIndex, IndexStatus,
+        KeySchemaElement, KeyType, PointInTimeRecoverySpecification, Projection, ProjectionType,
+        ProvisionedThroughput, ScalarAttributeType, StreamSpecification, TableStatus, Tag,
     },
     Client,
 };
 
+use crate::gateway::ledger_entry_repository::FeeRulesCache;
+use crate::gateway::migration::{enable_ttl, record_all_migrations, MIGRATIONS};
+use crate::gateway::table_config::{BillingMode, SnapshotPolicy, TableConfig};
+
+pub mod api_key_repository;
 pub mod ledger_entry_repository;
+pub mod migration;
+pub mod table_config;
+pub mod tenant_repository;
+
+/// Attribute with the epoch seconds after which DynamoDB deletes an item.
+pub(crate) const TTL_ATTRIBUTE: &str = "expires_at";
+
+/// The client and the names of the table and GSIs used by the repositories, when the
+/// balances are snapshotted, and the fee rules they read.
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub client: Client,
+    pub name: String,
+    pub index_name: String,
+    pub accounts_index_name: String,
+    pub snapshot_policy: SnapshotPolicy,
+    pub fee_rules: FeeRulesCache,
+}
 
-pub async fn delete_database(client: &Client) -> Result<()> {
-    let _ = client.delete_table().table_name("a_ledger").send().await;
-    tracing::info!("a_ledger table dropped!");
+impl Table {
+    pub fn new(client: Client, config: &TableConfig) -> Self {
+        Self {
+            client,
+            name: config.name.clone(),
+            index_name: config.index_name(),
+            accounts_index_name: config.accounts_index_name(),
+            snapshot_policy: config.snapshot_policy(),
+            fee_rules: FeeRulesCache::default(),
+        }
+    }
+}
+
+pub async fn delete_database(client: &Client, config: &TableConfig) -> Result<()> {
+    let _ = client.delete_table().table_name(&config.name).send().await;
+    tracing::info!("{} table dropped!", config.name);
 
     Ok(())
 }
 
-pub async fn create_database(client: &Client) -> Result<()> {
+pub async fn create_database(client: &Client, config: &TableConfig) -> Result<()> {
+    let (billing_mode, throughput, index_throughput) = match config.billing_mode {
+        BillingMode::OnDemand => (DynamoDbBillingMode::PayPerRequest, None, None),
+        BillingMode::Provisioned => (
+            DynamoDbBillingMode::Provisioned,
+            Some(
+                ProvisionedThroughput::builder()
+                    .read_capacity_units(config.read_capacity)
+                    .write_capacity_units(config.write_capacity)
+                    .build()?,
+            ),
+            Some(
+                ProvisionedThroughput::builder()
+                    .read_capacity_units(config.index_read_capacity)
+                    .write_capacity_units(config.index_write_capacity)
+                    .build()?,
+            ),
+        ),
+    };
+    let stream_specification = config
+        .stream_view_type
+        .map(|stream_view_type| {
+            StreamSpecification::builder()
+                .stream_enabled(true)
+                .stream_view_type(stream_view_type.into())
+                .build()
+        })
+        .transpose()?;
+    let tags = config
+        .tags
+        .iter()
+        .map(|(key, value)| Tag::builder().key(key).value(value).build())
+        .collect::<Result<Vec<Tag>, _>>()?;
+
     client
         .create_table()
-        .table_name("a_ledger")
+        .table_name(&config.name)
+        .billing_mode(billing_mode)
+        .set_provisioned_throughput(throughput)
+        .set_stream_specification(stream_specification)
+        .set_tags((!tags.is_empty()).then_some(tags))
         .attribute_definitions(
             AttributeDefinition::builder()
                 .attribute_name("pk")
@@ -44,6 +120,18 @@ pub async fn create_database(client: &Client) -> Result<()> {
                 .attribute_type(ScalarAttributeType::S)
                 .build()?,
         )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name("accounts_shard")
+                .attribute_type(ScalarAttributeType::S)
+                .build()?,
+        )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name("account_id")
+                .attribute_type(ScalarAttributeType::S)
+                .build()?,
+        )
         .key_schema(
             KeySchemaElement::builder()
                 .key_type(KeyType::Hash)
@@ -58,7 +146,7 @@ pub async fn create_database(client: &Client) -> Result<()> {
         )
         .global_secondary_indexes(
             GlobalSecondaryIndex::builder()
-                .index_name("a_ledger_created_at_idx")
+                .index_name(config.index_name())
                 .key_schema(
                     KeySchemaElement::builder()
                         .key_type(KeyType::Hash)
@@ -76,22 +164,74 @@ pub async fn create_database(client: &Client) -> Result<()> {
                         .projection_type(ProjectionType::All)
                         .build(),
                 )
-                .provisioned_throughput(
-                    ProvisionedThroughput::builder()
-                        .read_capacity_units(1)
-                        .write_capacity_units(1)
-                        .build()?,
-                )
+                .set_provisioned_throughput(index_throughput.clone())
                 .build()?,
         )
-        .provisioned_throughput(
-            ProvisionedThroughput::builder()
-                .read_capacity_units(1)
-                .write_capacity_units(1)
+        .global_secondary_indexes(
+            GlobalSecondaryIndex::builder()
+                .index_name(config.accounts_index_name())
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .key_type(KeyType::Hash)
+                        .attribute_name("accounts_shard")
+                        .build()?,
+                )
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .key_type(KeyType::Range)
+                        .attribute_name("account_id")
+                        .build()?,
+                )
+                .projection(
+                    Projection::builder()
+                        .projection_type(ProjectionType::All)
+                        .build(),
+                )
+                .set_provisioned_throughput(index_throughput)
                 .build()?,
         )
         .send()
         .await?;
-    tracing::info!("a_ledger table created!");
+    tracing::info!("{} table created!", config.name);
+
+    wait_table_active(client, &config.name).await?;
+    enable_ttl(client, config, TTL_ATTRIBUTE).await?;
+    // The table is created with the latest schema, so there is nothing to migrate.
+    record_all_migrations(client, config, MIGRATIONS).await?;
+    if config.point_in_time_recovery {
+        client
+            .update_continuous_backups()
+            .table_name(&config.name)
+            .point_in_time_recovery_specification(
+                PointInTimeRecoverySpecification::builder()
+                    .point_in_time_recovery_enabled(true)
+                    .build()?,
+            )
+            .send()
+            .await?;
+        tracing::info!("Point in time recovery enabled on {}!", config.name);
+    }
     Ok(())
 }
+
+/// Waits until the table and its indexes are created or updated.
+pub(crate) async fn wait_table_active(client: &Client, table_name: &str) -> Result<()> {
+    loop {
+        let output = client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await?;
+        let is_active = output.table().is_some_and(|table| {
+            table.table_status() == Some(&TableStatus::Active)
+                && table
+                    .global_secondary_indexes()
+                    .iter()
+                    .all(|index| index.index_status() == Some(&IndexStatus::Active))
+        });
+        if is_active {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
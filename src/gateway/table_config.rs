@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use dotenv::var;
+use serde::Deserialize;
+
+/// Read when the `CONFIG_FILE` env is not set. It is optional.
+const DEFAULT_CONFIG_FILE: &str = "aledger.toml";
+
+/// The DynamoDB table used by every repository and how `db-create` provisions it.
+///
+/// It is loaded from the `[table]` section of the config file, then overridden by the
+/// `TABLE_*` envs. `db-create` and `db-reset` can still override it with their arguments.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableConfig {
+    pub name: String,
+    pub billing_mode: BillingMode,
+    /// Read and write capacity units of the table, when the billing mode is provisioned.
+    pub read_capacity: i64,
+    pub write_capacity: i64,
+    /// Read and write capacity units of the GSI, when the billing mode is provisioned.
+    pub index_read_capacity: i64,
+    pub index_write_capacity: i64,
+    pub point_in_time_recovery: bool,
+    /// Enables DynamoDB Streams with this view type.
+    pub stream_view_type: Option<StreamViewType>,
+    pub tags: HashMap<String, String>,
+    /// Writes a snapshot of the balances every this many entries of an account. 0 disables it.
+    pub snapshot_every_entries: u64,
+    /// Writes a snapshot of the balances with the first append of each day to an account.
+    pub snapshot_daily: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BillingMode {
+    Provisioned,
+    OnDemand,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamViewType {
+    KeysOnly,
+    NewImage,
+    OldImage,
+    NewAndOldImages,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    table: Option<TableConfig>,
+}
+
+impl Default for TableConfig {
+    fn default() -> Self {
+        Self {
+            name: "a_ledger".into(),
+            billing_mode: BillingMode::Provisioned,
+            read_capacity: 1,
+            write_capacity: 1,
+            index_read_capacity: 1,
+            index_write_capacity: 1,
+            point_in_time_recovery: false,
+            stream_view_type: None,
+            tags: HashMap::new(),
+            snapshot_every_entries: 1000,
+            snapshot_daily: false,
+        }
+    }
+}
+
+impl TableConfig {
+    /// Loads the config file set in the `CONFIG_FILE` env, or `aledger.toml` if it exists,
+    /// and applies the `TABLE_*` envs over it.
+    pub fn load() -> Result<Self> {
+        let mut config = match var("CONFIG_FILE") {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            Err(_) => Self::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading config file {}", path.display()))?;
+        let config_file: ConfigFile = toml::from_str(&content)
+            .with_context(|| format!("Error parsing config file {}", path.display()))?;
+        Ok(config_file.table.unwrap_or_default())
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        if let Ok(name) = var("TABLE_NAME") {
+            self.name = name;
+        }
+        if let Ok(billing_mode) = var("TABLE_BILLING_MODE") {
+            self.billing_mode = billing_mode.parse()?;
+        }
+        for (env, capacity) in [
+            ("TABLE_READ_CAPACITY", &mut self.read_capacity),
+            ("TABLE_WRITE_CAPACITY", &mut self.write_capacity),
+            ("TABLE_INDEX_READ_CAPACITY", &mut self.index_read_capacity),
+            ("TABLE_INDEX_WRITE_CAPACITY", &mut self.index_write_capacity),
+        ] {
+            if let Ok(value) = var(env) {
+                *capacity = value.parse().with_context(|| format!("Invalid {env}"))?;
+            }
+        }
+        if let Ok(point_in_time_recovery) = var("TABLE_POINT_IN_TIME_RECOVERY") {
+            self.point_in_time_recovery = point_in_time_recovery
+                .parse()
+                .context("Invalid TABLE_POINT_IN_TIME_RECOVERY")?;
+        }
+        if let Ok(stream_view_type) = var("TABLE_STREAM_VIEW_TYPE") {
+            self.stream_view_type = match stream_view_type.as_str() {
+                "" | "none" => None,
+                stream_view_type => Some(stream_view_type.parse()?),
+            };
+        }
+        if let Ok(tags) = var("TABLE_TAGS") {
+            for tag in tags.split(',').filter(|tag| !tag.is_empty()) {
+                let (key, value) = parse_tag(tag)?;
+                self.tags.insert(key, value);
+            }
+        }
+        if let Ok(snapshot_every_entries) = var("TABLE_SNAPSHOT_EVERY_ENTRIES") {
+            self.snapshot_every_entries = snapshot_every_entries
+                .parse()
+                .context("Invalid TABLE_SNAPSHOT_EVERY_ENTRIES")?;
+        }
+        if let Ok(snapshot_daily) = var("TABLE_SNAPSHOT_DAILY") {
+            self.snapshot_daily = snapshot_daily
+                .parse()
+                .context("Invalid TABLE_SNAPSHOT_DAILY")?;
+        }
+        Ok(())
+    }
+
+    /// The GSI used to query the entries and audit records by date.
+    pub fn index_name(&self) -> String {
+        format!("{}_created_at_idx", self.name)
+    }
+
+    /// The sparse GSI of the balance HEADs, used to list the accounts.
+    pub fn accounts_index_name(&self) -> String {
+        format!("{}_accounts_idx", self.name)
+    }
+
+    pub fn snapshot_policy(&self) -> SnapshotPolicy {
+        SnapshotPolicy {
+            every_entries: self.snapshot_every_entries,
+            daily: self.snapshot_daily,
+        }
+    }
+}
+
+/// When an append writes a snapshot of the balances after its last entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    pub every_entries: u64,
+    pub daily: bool,
+}
+
+impl SnapshotPolicy {
+    /// Whether appending up to the entry at `last_sequence` is due a snapshot, given the
+    /// sequence and creation of the entry the HEAD pointed to before.
+    pub fn is_due(
+        &self,
+        previous: Option<(u64, DateTime<Utc>)>,
+        last_sequence: u64,
+        last_created_at: &DateTime<Utc>,
+    ) -> bool {
+        let entries_before = previous.map(|(sequence, _)| sequence + 1).unwrap_or(0);
+        let crossed_every_entries = self.every_entries > 0
+            && (last_sequence + 1) / self.every_entries > entries_before / self.every_entries;
+        let is_new_day = self.daily
+            && previous.is_some_and(|(_, created_at)| {
+                created_at.date_naive() < last_created_at.date_naive()
+            });
+        crossed_every_entries || is_new_day
+    }
+}
+
+/// Parses a `key=value` tag.
+pub fn parse_tag(tag: &str) -> Result<(String, String)> {
+    let (key, value) = tag
+        .split_once('=')
+        .ok_or(anyhow!("Tag {tag} must be in the format key=value"))?;
+    Ok((key.into(), value.into()))
+}
+
+impl FromStr for BillingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "provisioned" => Ok(Self::Provisioned),
+            "on_demand" => Ok(Self::OnDemand),
+            _ => bail!("Billing mode must be provisioned or on_demand"),
+        }
+    }
+}
+
+impl FromStr for StreamViewType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "keys_only" => Ok(Self::KeysOnly),
+            "new_image" => Ok(Self::NewImage),
+            "old_image" => Ok(Self::OldImage),
+            "new_and_old_images" => Ok(Self::NewAndOldImages),
+            _ => bail!(
+                "Stream view type must be one of keys_only, new_image, old_image or new_and_old_images"
+            ),
+        }
+    }
+}
+
+impl From<StreamViewType> for aws_sdk_dynamodb::types::StreamViewType {
+    fn from(value: StreamViewType) -> Self {
+        match value {
+            StreamViewType::KeysOnly => Self::KeysOnly,
+            StreamViewType::NewImage => Self::NewImage,
+            StreamViewType::OldImage => Self::OldImage,
+            StreamViewType::NewAndOldImages => Self::NewAndOldImages,
+        }
+    }
+}
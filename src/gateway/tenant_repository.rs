@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::{
+    operation::put_item::PutItemError,
+    types::{AttributeValue, DeleteRequest, ReturnValue, WriteRequest},
+    Client,
+};
+use chrono::DateTime;
+
+use crate::domain::entity::{Actor, LedgerId, Tenant};
+use crate::domain::gateway::{TenantError, TenantRepository};
+use crate::gateway::ledger_entry_repository::key_prefix;
+use crate::gateway::Table;
+
+/// BatchWriteItem accepts at most 25 requests.
+const MAX_BATCH_WRITE_ITEMS: usize = 25;
+
+pub struct DynamoDbTenantRepository {
+    client: Client,
+    table_name: String,
+}
+
+impl From<Table> for DynamoDbTenantRepository {
+    fn from(table: Table) -> Self {
+        Self {
+            client: table.client,
+            table_name: table.name,
+        }
+    }
+}
+
+impl TenantRepository for DynamoDbTenantRepository {
+    async fn create_tenant(&self, tenant: &Tenant) -> Result<(), TenantError> {
+        let mut put_item = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("pk", tenant_pk(&tenant.ledger_id))
+            .item("sk", AttributeValue::S("|~".into()))
+            .item("ledger_id", AttributeValue::S(tenant.ledger_id.to_string()))
+            .item(
+                "metadata",
+                AttributeValue::S(
+                    serde_json::to_string(&tenant.metadata).map_err(anyhow::Error::from)?,
+                ),
+            )
+            .item("actor", AttributeValue::S(tenant.actor.to_string()))
+            .item(
+                "created_at",
+                AttributeValue::S(tenant.created_at.to_string()),
+            )
+            .condition_expression("attribute_not_exists(pk)");
+        if let Some(description) = &tenant.description {
+            put_item = put_item.item("description", AttributeValue::S(description.clone()));
+        }
+        match put_item.send().await {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if let Some(PutItemError::ConditionalCheckFailedException(_)) =
+                    error.as_service_error()
+                {
+                    return Err(TenantError::AlreadyExists(tenant.ledger_id.clone()));
+                }
+                Err(anyhow::Error::from(error).into())
+            }
+        }
+    }
+
+    async fn get_tenant(&self, ledger_id: &LedgerId) -> Result<Tenant, TenantError> {
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", tenant_pk(ledger_id))
+            .key("sk", AttributeValue::S("|~".into()))
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        match item.item() {
+            Some(item) => Ok(tenant_from_item(item)?),
+            None => Err(TenantError::NotFound(ledger_id.clone())),
+        }
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, TenantError> {
+        let mut tenants = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("begins_with(pk, :prefix) AND sk = :sk")
+                .expression_attribute_values(":prefix", AttributeValue::S("LEDGER:".into()))
+                .expression_attribute_values(":sk", AttributeValue::S("|~".into()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(anyhow::Error::from)?;
+            for item in output.items() {
+                // Rows of the ledgers also start with `LEDGER:`, only tenants have a ledger_id.
+                if item.contains_key("ledger_id") {
+                    tenants.push(tenant_from_item(item)?);
+                }
+            }
+            exclusive_start_key = output.last_evaluated_key().cloned();
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(tenants)
+    }
+
+    async fn delete_tenant(&self, ledger_id: &LedgerId) -> Result<(), TenantError> {
+        // The tenant is deleted first, so its ledger is not reachable while it is purged.
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", tenant_pk(ledger_id))
+            .key("sk", AttributeValue::S("|~".into()))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        if output.attributes().is_none() {
+            return Err(TenantError::NotFound(ledger_id.clone()));
+        }
+
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("begins_with(pk, :prefix)")
+                .expression_attribute_values(":prefix", AttributeValue::S(key_prefix(ledger_id)))
+                .projection_expression("pk, sk")
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(anyhow::Error::from)?;
+            for keys in output.items().chunks(MAX_BATCH_WRITE_ITEMS) {
+                let mut requests = keys
+                    .iter()
+                    .map(|key| -> anyhow::Result<WriteRequest> {
+                        Ok(WriteRequest::builder()
+                            .delete_request(
+                                DeleteRequest::builder()
+                                    .set_key(Some(key.clone()))
+                                    .build()?,
+                            )
+                            .build())
+                    })
+                    .collect::<anyhow::Result<Vec<WriteRequest>>>()?;
+                while !requests.is_empty() {
+                    requests = self
+                        .client
+                        .batch_write_item()
+                        .request_items(&self.table_name, requests)
+                        .send()
+                        .await
+                        .map_err(anyhow::Error::from)?
+                        .unprocessed_items
+                        .and_then(|mut unprocessed_items| {
+                            unprocessed_items.remove(&self.table_name)
+                        })
+                        .unwrap_or_default();
+                }
+            }
+            exclusive_start_key = output.last_evaluated_key().cloned();
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn tenant_pk(ledger_id: &LedgerId) -> AttributeValue {
+    AttributeValue::S(format!("LEDGER:{}", ledger_id))
+}
+
+fn tenant_from_item(item: &HashMap<String, AttributeValue>) -> anyhow::Result<Tenant> {
+    let string_field = |field: &str| -> anyhow::Result<&String> {
+        item.get(field)
+            .ok_or(anyhow!("Missing field {field} in tenant"))?
+            .as_s()
+            .map_err(|_| anyhow!("Error reading field {field} in tenant"))
+    };
+    Ok(Tenant {
+        ledger_id: LedgerId::new(string_field("ledger_id")?.clone())?,
+        description: item
+            .get("description")
+            .map(|_| string_field("description").cloned())
+            .transpose()?,
+        metadata: serde_json::from_str(string_field("metadata")?)?,
+        actor: Actor::new(string_field("actor")?.clone())?,
+        created_at: DateTime::from_str(string_field("created_at")?)?,
+    })
+}
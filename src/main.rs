@@ -1,14 +1,43 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use anyhow::Result;
 use aws_sdk_dynamodb as dynamodb;
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
 use dotenv::{dotenv, var};
 use dynamodb::Client;
+use itertools::Itertools;
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
+use tokio::sync::mpsc;
 use tower_http::{compression::CompressionLayer, trace::TraceLayer};
 use tracing::Level;
+use uuid::Uuid;
 
 use crate::app::build_app;
+use crate::controller::accruals::run_accruals;
+use crate::controller::attestation::{attestation_key_from_env, read_verifying_key};
+use crate::controller::auth::AuthConfig;
+use crate::controller::export::{write_export, ExportEncoder, ExportFormat};
+use crate::controller::import::{run_import, ImportFormat, ImportOptions, ImportReader};
+use crate::controller::recurring::run_recurring_entries;
+use crate::controller::verify::VerifyAccountResponse;
+use crate::domain::entity::{
+    AccountId, AccountScope, Actor, LedgerFieldName, LedgerId, Scope, SignedAttestation,
+};
+use crate::domain::use_case::{
+    create_api_key_use_case, create_tenant_use_case, delete_tenant_use_case,
+    export_entries_use_case, list_api_keys_use_case, list_tenants_use_case,
+    revoke_api_key_use_case, verify_account_use_case,
+};
+use crate::gateway::api_key_repository::DynamoDbApiKeyRepository;
+use crate::gateway::ledger_entry_repository::DynamoDbLedgerEntryRepository;
+use crate::gateway::migration::{migrate, migration_status, MIGRATIONS};
+use crate::gateway::table_config::{parse_tag, BillingMode, StreamViewType, TableConfig};
+use crate::gateway::tenant_repository::DynamoDbTenantRepository;
+use crate::gateway::Table;
 
 mod app;
 mod controller;
@@ -22,9 +51,218 @@ enum Args {
     /// Start the aldeger server
     Serve(ServerArgs),
     /// Create the dynamodb table
-    DbCreate,
+    DbCreate(TableArgs),
     /// Delete and recreate the dynamodb table
-    DbReset,
+    DbReset(TableArgs),
+    /// Apply the pending migrations to the dynamodb table
+    DbMigrate(MigrateArgs),
+    /// Export the entries created in a date range as JSON Lines, CSV or Parquet
+    Export(ExportArgs),
+    /// Import entries from JSON Lines or CSV files, in the format of the push endpoint
+    Import(ImportArgs),
+    /// Replay the rows of accounts and report what doesn't match. Exits with an error if any
+    /// account is corrupted
+    Verify(VerifyArgs),
+    /// Check the signature of a balance attestation offline, and print it if it is valid
+    VerifyAttestation(VerifyAttestationArgs),
+    /// Manage the api keys used when AUTH_MODE is api_key
+    #[command(subcommand)]
+    ApiKey(ApiKeyArgs),
+    /// Manage the isolated ledgers (tenants) of the deployment
+    #[command(subcommand)]
+    Ledger(LedgerArgs),
+}
+
+#[derive(Debug, Parser)]
+struct ExportArgs {
+    #[arg(long)]
+    start_date: DateTime<Utc>,
+    #[arg(long)]
+    end_date: DateTime<Utc>,
+    /// jsonl, csv or parquet
+    #[arg(long, default_value = "jsonl")]
+    format: ExportFormat,
+    /// Comma separated ledger fields exported as columns. Required by csv and parquet
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<String>,
+    /// Export only this account. Without it every account is exported
+    #[arg(long = "account")]
+    account_id: Option<Uuid>,
+    /// Export this ledger instead of the default one
+    #[arg(long = "ledger")]
+    ledger_id: Option<LedgerId>,
+    /// Parallel scan segments used to export every account
+    #[arg(long, default_value_t = 4)]
+    segments: i32,
+    /// File to write. Without it the export is written to stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct ImportArgs {
+    file: PathBuf,
+    /// jsonl or csv
+    #[arg(long, default_value = "jsonl")]
+    format: ImportFormat,
+    /// Import into this ledger instead of the default one
+    #[arg(long = "ledger")]
+    ledger_id: Option<LedgerId>,
+    /// Actor recorded on the imported entries
+    #[arg(long, default_value = "cli")]
+    actor: String,
+    /// Rows read and pushed at a time
+    #[arg(long, default_value_t = 10_000)]
+    batch_size: usize,
+    /// Accounts pushed at the same time
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Only validate the rows, without pushing them
+    #[arg(long)]
+    dry_run: bool,
+    /// File to save the progress to. If it exists, the import resumes from it
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// File to write the entries that were not applied to, as JSON Lines
+    #[arg(long)]
+    rejected: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct VerifyArgs {
+    /// Account to verify. Can be repeated
+    #[arg(long = "account", required = true)]
+    account_ids: Vec<Uuid>,
+    /// Verify accounts of this ledger instead of the default one
+    #[arg(long = "ledger")]
+    ledger_id: Option<LedgerId>,
+    /// Parallel scan segments used to read the rows of each account
+    #[arg(long, default_value_t = 4)]
+    segments: i32,
+    /// Only verify the rows after the last snapshot of each account, without a scan
+    #[arg(long)]
+    since_snapshot: bool,
+}
+
+#[derive(Debug, Parser)]
+struct VerifyAttestationArgs {
+    /// JSON file with the response of the attestation endpoint
+    file: PathBuf,
+    /// PEM file with the public key of the server
+    #[arg(long)]
+    public_key: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+struct MigrateArgs {
+    /// Only print the applied and pending migrations
+    #[arg(long)]
+    status: bool,
+    /// Parallel scan segments used by backfills
+    #[arg(long, default_value_t = 4)]
+    segments: i32,
+}
+
+/// Override the table config loaded from the config file and the TABLE_* envs
+#[derive(Debug, Parser)]
+struct TableArgs {
+    #[arg(long)]
+    table_name: Option<String>,
+    /// provisioned or on_demand
+    #[arg(long)]
+    billing_mode: Option<BillingMode>,
+    #[arg(long)]
+    read_capacity: Option<i64>,
+    #[arg(long)]
+    write_capacity: Option<i64>,
+    #[arg(long)]
+    index_read_capacity: Option<i64>,
+    #[arg(long)]
+    index_write_capacity: Option<i64>,
+    /// Enable point in time recovery
+    #[arg(long)]
+    point_in_time_recovery: bool,
+    /// Enable DynamoDB Streams: keys_only, new_image, old_image or new_and_old_images
+    #[arg(long)]
+    stream_view_type: Option<StreamViewType>,
+    /// Tag in the format key=value. Can be repeated
+    #[arg(long = "tag", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+}
+
+impl TableArgs {
+    fn apply(self, config: &mut TableConfig) {
+        if let Some(table_name) = self.table_name {
+            config.name = table_name;
+        }
+        if let Some(billing_mode) = self.billing_mode {
+            config.billing_mode = billing_mode;
+        }
+        for (arg, capacity) in [
+            (self.read_capacity, &mut config.read_capacity),
+            (self.write_capacity, &mut config.write_capacity),
+            (self.index_read_capacity, &mut config.index_read_capacity),
+            (self.index_write_capacity, &mut config.index_write_capacity),
+        ] {
+            if let Some(arg) = arg {
+                *capacity = arg;
+            }
+        }
+        config.point_in_time_recovery |= self.point_in_time_recovery;
+        if self.stream_view_type.is_some() {
+            config.stream_view_type = self.stream_view_type;
+        }
+        config.tags.extend(self.tags);
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum LedgerArgs {
+    /// Create a ledger. Requests use it with the x-ledger-id header
+    Create {
+        ledger_id: LedgerId,
+        #[arg(long)]
+        description: Option<String>,
+        /// JSON with the configuration of the ledger
+        #[arg(long)]
+        metadata: Option<String>,
+        /// Actor recorded as the creator of the ledger
+        #[arg(long, default_value = "cli")]
+        actor: String,
+    },
+    /// List the ledgers
+    List,
+    /// Delete a ledger and all its accounts
+    Delete { ledger_id: LedgerId },
+}
+
+#[derive(Debug, Subcommand)]
+enum ApiKeyArgs {
+    /// Create an api key and print its id and secret
+    Create(CreateApiKeyArgs),
+    /// List the api keys
+    List,
+    /// Delete an api key
+    Revoke { key_id: String },
+}
+
+#[derive(Debug, Parser)]
+struct CreateApiKeyArgs {
+    /// Actor recorded on every change made with the key
+    #[arg(long)]
+    actor: String,
+    /// Comma separated scopes: read, push, delete or admin
+    #[arg(long, value_delimiter = ',', required = true)]
+    scopes: Vec<Scope>,
+    /// Restrict the key to this account. Can be repeated
+    #[arg(long = "account")]
+    account_ids: Vec<Uuid>,
+    /// Restrict the key to accounts starting with this prefix. Can be repeated
+    #[arg(long = "account-prefix")]
+    account_prefixes: Vec<String>,
+    /// Restrict the key to this ledger. Without it the key can access every ledger
+    #[arg(long = "ledger")]
+    ledger_id: Option<LedgerId>,
 }
 
 #[derive(Debug, Parser)]
@@ -32,38 +270,277 @@ struct ServerArgs {
     /// Port to listen for. If not set it will try to load from ENV
     #[arg(short, long)]
     port: Option<u16>,
+    /// Minutes between the runs that post the accruals. If not set it will try to load from
+    /// the ACCRUAL_INTERVAL_MINUTES ENV, and defaults to 60. With 0 they are only posted on
+    /// request
+    #[arg(long)]
+    accrual_interval_minutes: Option<u64>,
+    /// Minutes between the runs that post the recurring entries. If not set it will try to
+    /// load from the RECURRING_INTERVAL_MINUTES ENV, and defaults to 60. With 0 they are only
+    /// posted on request
+    #[arg(long)]
+    recurring_interval_minutes: Option<u64>,
 }
 
+/// Minutes between the runs of a worker when neither its arg nor its ENV is set.
+const DEFAULT_WORKER_INTERVAL_MINUTES: u64 = 60;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv()?;
     tracing_setup()?;
 
     let client = dynamo_db_client().await;
+    let mut table_config = TableConfig::load()?;
+    let table = Table::new(client.clone(), &table_config);
     let args = Args::parse();
     match args {
         Args::Serve(serve_args) => {
+            if let Some(interval) = worker_interval(
+                serve_args.accrual_interval_minutes,
+                "ACCRUAL_INTERVAL_MINUTES",
+            )? {
+                tokio::spawn(run_accruals(table.clone(), interval));
+            }
+            if let Some(interval) = worker_interval(
+                serve_args.recurring_interval_minutes,
+                "RECURRING_INTERVAL_MINUTES",
+            )? {
+                tokio::spawn(run_recurring_entries(table.clone(), interval));
+            }
             let rng = SmallRng::from_entropy();
-            let app = build_app(client, rng)
-                .layer(CompressionLayer::new())
-                .layer(TraceLayer::new_for_http());
+            let app = build_app(
+                table.clone(),
+                rng,
+                AuthConfig::from_env(&table)?,
+                attestation_key_from_env()?,
+            )
+            .layer(CompressionLayer::new())
+            .layer(TraceLayer::new_for_http());
 
             let port = serve_args.port.unwrap_or(var("PORT")?.parse()?);
             let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
             tracing::info!("Listening to new connections at port {}", port);
             axum::serve(listener, app).await?;
         }
-        Args::DbCreate => {
-            gateway::create_database(&client).await?;
+        Args::DbCreate(table_args) => {
+            table_args.apply(&mut table_config);
+            gateway::create_database(&client, &table_config).await?;
+        }
+        Args::DbReset(table_args) => {
+            table_args.apply(&mut table_config);
+            gateway::delete_database(&client, &table_config).await?;
+            gateway::create_database(&client, &table_config).await?;
+        }
+        Args::DbMigrate(migrate_args) => {
+            if migrate_args.status {
+                let status = migration_status(&client, &table_config, MIGRATIONS).await?;
+                println!("version: {}", status.version);
+                for migration in status.applied {
+                    println!(
+                        "{}\t{}\tapplied at {}",
+                        migration.version, migration.name, migration.applied_at
+                    );
+                }
+                for (version, name) in status.pending {
+                    println!("{}\t{}\tpending", version, name);
+                }
+            } else {
+                let applied =
+                    migrate(&client, &table_config, MIGRATIONS, migrate_args.segments).await?;
+                tracing::info!("{} migrations applied", applied.len());
+            }
+        }
+        Args::Export(export_args) => {
+            let repository =
+                DynamoDbLedgerEntryRepository::new(table, export_args.ledger_id.as_ref());
+            let encoder = ExportEncoder::new(
+                export_args.format,
+                export_args
+                    .fields
+                    .into_iter()
+                    .map(LedgerFieldName::new)
+                    .collect::<Result<_>>()?,
+            )?;
+            let account_id = export_args.account_id.map(AccountId::new);
+            let (sender, receiver) = mpsc::channel(4);
+            let export = export_entries_use_case(
+                &repository,
+                account_id.as_ref(),
+                &export_args.start_date,
+                &export_args.end_date,
+                export_args.segments,
+                sender,
+            );
+            let exported = match export_args.output {
+                Some(path) => {
+                    let mut file = tokio::fs::File::create(path).await?;
+                    write_export(encoder, receiver, export, &mut file).await?
+                }
+                None => write_export(encoder, receiver, export, &mut tokio::io::stdout()).await?,
+            };
+            tracing::info!("{} entries exported", exported);
+        }
+        Args::Import(import_args) => {
+            let repository =
+                DynamoDbLedgerEntryRepository::new(table, import_args.ledger_id.as_ref());
+            let reader = ImportReader::open(&import_args.file, import_args.format)?;
+            let report = run_import(
+                &repository,
+                SmallRng::from_entropy(),
+                &Actor::new(import_args.actor)?,
+                reader,
+                &ImportOptions {
+                    batch_size: import_args.batch_size,
+                    concurrency: import_args.concurrency,
+                    dry_run: import_args.dry_run,
+                    checkpoint: import_args.checkpoint,
+                    rejected: import_args.rejected,
+                },
+            )
+            .await?;
+            println!("rows: {}", report.rows);
+            if import_args.dry_run {
+                println!("invalid: {}", report.invalid);
+                if report.invalid > 0 {
+                    anyhow::bail!("{} invalid rows", report.invalid);
+                }
+            } else {
+                println!("applied: {}", report.applied);
+                println!("non_applied: {}", report.non_applied.values().sum::<u64>());
+                for (error_code, count) in report.non_applied {
+                    println!("\t{}: {}", error_code, count);
+                }
+            }
         }
-        Args::DbReset => {
-            gateway::delete_database(&client).await?;
-            gateway::create_database(&client).await?;
+        Args::Verify(verify_args) => {
+            let repository =
+                DynamoDbLedgerEntryRepository::new(table, verify_args.ledger_id.as_ref());
+            let mut corrupted = 0;
+            for account_id in verify_args.account_ids.into_iter().map(AccountId::new) {
+                let report = verify_account_use_case(
+                    &repository,
+                    &account_id,
+                    verify_args.segments,
+                    verify_args.since_snapshot,
+                )
+                .await?;
+                if !report.is_valid() {
+                    corrupted += 1;
+                }
+                println!(
+                    "{}",
+                    serde_json::to_string(&VerifyAccountResponse::from(report))?
+                );
+            }
+            if corrupted > 0 {
+                anyhow::bail!("{} corrupted accounts", corrupted);
+            }
+        }
+        Args::VerifyAttestation(verify_attestation_args) => {
+            let verifying_key = read_verifying_key(&verify_attestation_args.public_key)?;
+            let signed: SignedAttestation =
+                serde_json::from_slice(&std::fs::read(&verify_attestation_args.file)?)?;
+            let attestation = signed.verify(&verifying_key)?;
+            println!("{}", serde_json::to_string_pretty(&attestation)?);
+        }
+        Args::ApiKey(api_key_args) => {
+            let repository = DynamoDbApiKeyRepository::from(table);
+            match api_key_args {
+                ApiKeyArgs::Create(create_args) => {
+                    let api_key = create_api_key_use_case(
+                        &repository,
+                        Actor::new(create_args.actor)?,
+                        HashSet::from_iter(create_args.scopes),
+                        AccountScope {
+                            account_ids: create_args
+                                .account_ids
+                                .into_iter()
+                                .map(AccountId::new)
+                                .collect(),
+                            account_prefixes: create_args.account_prefixes,
+                        },
+                        create_args.ledger_id,
+                    )
+                    .await?;
+                    println!("key_id: {}", api_key.key_id);
+                    println!("secret: {}", api_key.secret);
+                }
+                ApiKeyArgs::List => {
+                    for api_key in list_api_keys_use_case(&repository).await? {
+                        println!(
+                            "{}\t{}\t{}\t{}",
+                            api_key.key_id,
+                            api_key.actor,
+                            api_key
+                                .scopes
+                                .iter()
+                                .map(|scope| scope.to_string())
+                                .join(","),
+                            api_key.created_at
+                        );
+                    }
+                }
+                ApiKeyArgs::Revoke { key_id } => {
+                    revoke_api_key_use_case(&repository, &key_id).await?;
+                }
+            }
+        }
+        Args::Ledger(ledger_args) => {
+            let repository = DynamoDbTenantRepository::from(table);
+            match ledger_args {
+                LedgerArgs::Create {
+                    ledger_id,
+                    description,
+                    metadata,
+                    actor,
+                } => {
+                    let tenant = create_tenant_use_case(
+                        &repository,
+                        ledger_id,
+                        description,
+                        metadata
+                            .map(|metadata| serde_json::from_str(&metadata))
+                            .transpose()?
+                            .unwrap_or_default(),
+                        &Actor::new(actor)?,
+                    )
+                    .await?;
+                    println!("{}", serde_json::to_string_pretty(&tenant)?);
+                }
+                LedgerArgs::List => {
+                    for tenant in list_tenants_use_case(&repository).await? {
+                        println!(
+                            "{}\t{}\t{}",
+                            tenant.ledger_id,
+                            tenant.description.unwrap_or_default(),
+                            tenant.created_at
+                        );
+                    }
+                }
+                LedgerArgs::Delete { ledger_id } => {
+                    delete_tenant_use_case(&repository, &ledger_id).await?;
+                }
+            }
         }
     }
     Ok(())
 }
 
+/// The interval of a worker from its arg, or else its ENV, or else the default. It is `None`
+/// for 0, when the worker doesn't run.
+fn worker_interval(minutes: Option<u64>, env: &str) -> Result<Option<Duration>> {
+    let minutes = match minutes {
+        Some(minutes) => minutes,
+        None => var(env)
+            .ok()
+            .map(|minutes| minutes.parse())
+            .transpose()?
+            .unwrap_or(DEFAULT_WORKER_INTERVAL_MINUTES),
+    };
+    Ok((minutes > 0).then(|| Duration::from_secs(minutes * 60)))
+}
+
 async fn dynamo_db_client() -> Client {
     let config = aws_config::load_from_env().await;
     let mut builder = aws_sdk_dynamodb::config::Builder::from(&config);